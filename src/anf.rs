@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::cps::collect_names;
+use crate::messages::{self, Msg};
+
+/// A-정규형(administrative normal form) 변환. 함수 적용의 각 항은 변수나 람다뿐이고,
+/// 중간 결과는 `let t = e in M`에 해당하는 `(L t. M) (e)`로 묶인다.
+/// 값 호출 평가 순서가 그대로 유지되므로 결과를 다시 평가기에 넣을 수 있다.
+pub fn transform(program: &Expr) -> Result<Expr, String> {
+    let mut names = HashSet::new();
+    collect_names(program, &mut names);
+    let mut anf = Anf { names, counter: 0 };
+
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut result = Vec::new();
+    for statement in statements {
+        result.push(match statement {
+            Expr::Define(name, ty, body) => Expr::Define(name.clone(), ty.clone(), Box::new(anf.term(body)?)),
+            Expr::Assert(lhs, rhs) => Expr::Assert(Box::new(anf.term(lhs)?), Box::new(anf.term(rhs)?)),
+            other => anf.term(other)?,
+        });
+    }
+    Ok(match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Expr::Sequence(result),
+    })
+}
+
+type Cont<'a> = Box<dyn FnOnce(&mut Anf, Expr) -> Result<Expr, String> + 'a>;
+type AtomsCont<'a> = Box<dyn FnOnce(&mut Anf, Vec<Expr>) -> Result<Expr, String> + 'a>;
+
+struct Anf {
+    names: HashSet<String>,
+    counter: usize,
+}
+
+impl Anf {
+    fn term(&mut self, expr: &Expr) -> Result<Expr, String> {
+        self.convert(expr, Box::new(|_, e| Ok(e)))
+    }
+
+    fn convert<'a>(&mut self, expr: &'a Expr, cont: Cont<'a>) -> Result<Expr, String> {
+        match expr {
+            Expr::Word(_) => cont(self, expr.clone()),
+            Expr::Function(params, body) => {
+                let body = self.term(body)?;
+                cont(self, Expr::Function(params.clone(), Box::new(body)))
+            }
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                // 머리가 이미 적용이면 `(f a) b`를 `f a b`로 펼쳐서 이름을 줄임
+                if let Expr::Paren(inner) = head {
+                    if let Expr::Words(inner) = &**inner {
+                        let flat = inner.iter().chain(args).cloned().collect();
+                        return self.convert(&Expr::Words(flat), cont);
+                    }
+                }
+                self.atoms(words, Vec::new(), Box::new(|anf, atoms| cont(anf, Expr::Words(atoms))))
+            }
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.convert(inner, cont),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Definitions and asserts are only allowed at the top level".to_string())
+            }
+        }
+    }
+
+    /// 적용의 각 항을 왼쪽부터 차례로 원자로 만든다.
+    fn atoms<'a>(&mut self, words: &'a [Expr], mut done: Vec<Expr>, cont: AtomsCont<'a>) -> Result<Expr, String> {
+        match words.split_first() {
+            None => cont(self, done),
+            Some((first, rest)) => self.atom(first, Box::new(move |anf, atom| {
+                done.push(atom);
+                anf.atoms(rest, done, cont)
+            })),
+        }
+    }
+
+    /// 원자가 아닌 결과는 새 이름에 묶는다: `(L t. cont(t)) (e)`
+    fn atom<'a>(&mut self, expr: &'a Expr, cont: Cont<'a>) -> Result<Expr, String> {
+        self.convert(expr, Box::new(|anf, e| match e {
+            Expr::Word(_) | Expr::Function(..) => cont(anf, e),
+            _ => {
+                let t = anf.fresh("t");
+                let body = cont(anf, Expr::Word(t.clone()))?;
+                Ok(Expr::Words(vec![Expr::Function(vec![t], Box::new(body)), e]))
+            }
+        }))
+    }
+
+    /// 프로그램에 나오지 않는 이름만 쓰므로 사용자 변수를 가로채지 않음
+    fn fresh(&mut self, base: &str) -> String {
+        loop {
+            let name = format!("{}{}", base, self.counter);
+            self.counter += 1;
+            if !self.names.contains(&name) {
+                return name;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Config, Interpreter};
+    use crate::normalize;
+    use crate::parser::Parser;
+
+    fn normal_form(program: &Expr) -> Expr {
+        let mut interpreter = Interpreter::new(Config::default());
+        let value = interpreter.eval(program.clone()).unwrap();
+        interpreter.normal_form(&value).unwrap()
+    }
+
+    /// A-정규형으로 바꿔도 평가 결과가 그대로인지 봄
+    fn evaluates_like(source: &str, expected: &str) {
+        let program = transform(&Parser::new(source).parse().unwrap()).unwrap();
+        let expected = transform(&Parser::new(expected).parse().unwrap()).unwrap();
+        let (got, expected) = (normal_form(&program), normal_form(&expected));
+        assert!(normalize::alpha_eq(&got, &expected), "{} / {}", got, expected);
+    }
+
+    #[test]
+    fn transformed_programs_evaluate_to_the_same_result() {
+        evaluates_like("id = L x. x; k = L x y. x; k id (id k)", "L x. x");
+        evaluates_like("two = L f x. f (f x); two two", "L f x. f (f (f (f x)))");
+        evaluates_like("(L x y. x) (L a. a) (L b. b b)", "L a. a");
+        evaluates_like("id = L x. x; assert id id == id; id", "L x. x");
+    }
+
+    #[test]
+    fn nested_definitions_are_rejected() {
+        let nested = Expr::Words(vec![
+            Expr::Word("f".to_string()),
+            Expr::Define("x".to_string(), None, Box::new(Expr::Word("y".to_string()))),
+        ]);
+        assert!(transform(&nested).is_err());
+    }
+}
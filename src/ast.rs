@@ -1,9 +1,432 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::Parser;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Expr {
     Word(String),
     Words(Vec<Expr>),
     Function(Vec<String>, Box<Expr>),
-    Define(String, Box<Expr>),
+    // `name : T = e`의 타입 표기는 선택 사항
+    Define(String, Option<Type>, Box<Expr>),
     Sequence(Vec<Expr>),
     Paren(Box<Expr>),
+    Assert(Box<Expr>, Box<Expr>),
+    // `L (x : T) y. e`: 안쪽 Function의 매개변수마다 붙은 타입 표기
+    Annotated(Vec<Option<Type>>, Box<Expr>),
+    // System F: `/\a. e`와 `e [T]`. 타입이 없는 평가기는 무시함
+    TypeFunction(Vec<String>, Box<Expr>),
+    TypeApply(Box<Expr>, Type),
+    // 안쪽 식이 원문에서 차지하는 자리. 평가할 때 오류 위치를 알리려고 `Parser::spans`를 켰을 때만 붙음
+    Located(Span, Box<Expr>),
+}
+
+/// 원문의 한 구간. 줄과 칸은 1부터 세고, 끝은 마지막 글자의 다음 칸
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Base(String),
+    Arrow(Box<Type>, Box<Type>),
+    Forall(String, Box<Type>),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Base(name) => write!(f, "{}", name),
+            Type::Arrow(from, to) => match **from {
+                Type::Arrow(..) | Type::Forall(..) => write!(f, "({}) -> {}", from, to),
+                _ => write!(f, "{} -> {}", from, to),
+            },
+            Type::Forall(var, body) => write!(f, "forall {}. {}", var, body),
+        }
+    }
+}
+
+impl Expr {
+    /// 노드 수. 괄호와 타입 표기는 세지 않음
+    pub fn size(&self) -> usize {
+        self.fold(0, |n, e| match e {
+            Expr::Paren(_)
+            | Expr::Annotated(..)
+            | Expr::TypeFunction(..)
+            | Expr::TypeApply(..)
+            | Expr::Located(..) => n,
+            _ => n + 1,
+        })
+    }
+
+    /// 바로 아래 자식 식마다 `f`를 적용해서 같은 모양으로 다시 조립한다. 낱말은 그대로 둠
+    pub fn map_children(&self, mut f: impl FnMut(&Expr) -> Expr) -> Expr {
+        let mut child = |e: &Expr| Box::new(f(e));
+        match self {
+            Expr::Word(_) => self.clone(),
+            Expr::Words(words) => Expr::Words(words.iter().map(|e| *child(e)).collect()),
+            Expr::Sequence(exprs) => Expr::Sequence(exprs.iter().map(|e| *child(e)).collect()),
+            Expr::Function(params, body) => Expr::Function(params.clone(), child(body)),
+            Expr::Define(name, ty, body) => Expr::Define(name.clone(), ty.clone(), child(body)),
+            Expr::Paren(inner) => Expr::Paren(child(inner)),
+            Expr::Assert(lhs, rhs) => Expr::Assert(child(lhs), child(rhs)),
+            Expr::Annotated(types, inner) => Expr::Annotated(types.clone(), child(inner)),
+            Expr::TypeFunction(vars, inner) => Expr::TypeFunction(vars.clone(), child(inner)),
+            Expr::TypeApply(inner, ty) => Expr::TypeApply(child(inner), ty.clone()),
+            Expr::Located(span, inner) => Expr::Located(*span, child(inner)),
+        }
+    }
+
+    /// 이 식과 모든 부분 식을 위에서부터(전위 순서로) 돌며 `f`로 값을 모은다
+    pub fn fold<T>(&self, init: T, mut f: impl FnMut(T, &Expr) -> T) -> T {
+        self.fold_with(init, &mut f)
+    }
+
+    fn fold_with<T>(&self, init: T, f: &mut dyn FnMut(T, &Expr) -> T) -> T {
+        let acc = f(init, self);
+        match self {
+            Expr::Word(_) => acc,
+            Expr::Words(exprs) | Expr::Sequence(exprs) => exprs.iter().fold(acc, |acc, e| e.fold_with(acc, f)),
+            Expr::Assert(lhs, rhs) => {
+                let acc = lhs.fold_with(acc, f);
+                rhs.fold_with(acc, f)
+            }
+            Expr::Function(_, inner)
+            | Expr::Define(_, _, inner)
+            | Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => inner.fold_with(acc, f),
+        }
+    }
+
+    /// 자식 식부터 `f`로 바꾼 뒤, 바뀐 자식으로 다시 조립한 식에 `f`를 적용한다
+    pub fn rewrite_bottom_up(&self, mut f: impl FnMut(Expr) -> Expr) -> Expr {
+        self.rewrite_with(&mut f)
+    }
+
+    fn rewrite_with(&self, f: &mut dyn FnMut(Expr) -> Expr) -> Expr {
+        let rebuilt = self.map_children(|e| e.rewrite_with(f));
+        f(rebuilt)
+    }
+
+    /// 적용의 한 항으로 쓸 수 있게 낱말과 괄호가 아니면 괄호로 감쌈
+    pub fn into_arg(self) -> Expr {
+        match self.unlocated() {
+            Expr::Word(_) | Expr::Paren(_) => self,
+            _ => Expr::Paren(Box::new(self)),
+        }
+    }
+
+    /// 겉에 붙은 자리 정보를 떼어 낸 식
+    pub fn unlocated(&self) -> &Expr {
+        match self {
+            Expr::Located(_, inner) => inner.unlocated(),
+            other => other,
+        }
+    }
+
+    /// 앞에서부터 노드를 `limit`개까지만 남기고 나머지 부분 식은 `…`로 줄인다.
+    /// 출력용이라 결과는 다시 파싱할 수 없음
+    pub fn elide(&self, limit: usize) -> Expr {
+        let mut budget = limit;
+        self.elide_with(&mut budget)
+    }
+
+    fn elide_with(&self, budget: &mut usize) -> Expr {
+        if *budget == 0 {
+            return Expr::Word("…".to_string());
+        }
+        match self {
+            Expr::Word(_) => {
+                *budget -= 1;
+                self.clone()
+            }
+            Expr::Words(words) => {
+                *budget -= 1;
+                let mut kept = Vec::new();
+                for word in words {
+                    // 남은 인자가 여럿이어도 `…` 하나로
+                    if *budget == 0 {
+                        kept.push(Expr::Word("…".to_string()));
+                        break;
+                    }
+                    kept.push(word.elide_with(budget));
+                }
+                Expr::Words(kept)
+            }
+            Expr::Function(params, body) => {
+                *budget -= 1;
+                Expr::Function(params.clone(), Box::new(body.elide_with(budget)))
+            }
+            Expr::Paren(inner) => Expr::Paren(Box::new(inner.elide_with(budget))),
+            Expr::Annotated(types, inner) => Expr::Annotated(types.clone(), Box::new(inner.elide_with(budget))),
+            Expr::TypeFunction(vars, inner) => Expr::TypeFunction(vars.clone(), Box::new(inner.elide_with(budget))),
+            Expr::TypeApply(inner, ty) => Expr::TypeApply(Box::new(inner.elide_with(budget)), ty.clone()),
+            Expr::Located(span, inner) => Expr::Located(*span, Box::new(inner.elide_with(budget))),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => self.clone(),
+        }
+    }
+
+    /// 한 줄에 노드 하나씩 들여 써서 구조를 보여 준다.
+    /// 적용은 왼쪽으로 묶이므로 `f a b`는 `Apply(Apply(f, a), b)`로 나온다.
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        out
+    }
+
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let line = |out: &mut String, text: String| {
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&text);
+            out.push('\n');
+        };
+        match self {
+            Expr::Word(w) => line(out, format!("Word {}", w)),
+            Expr::Words(words) => match words.split_last() {
+                Some((last, init)) if !init.is_empty() => {
+                    line(out, "Apply".to_string());
+                    match init {
+                        [head] => head.write_tree(depth + 1, out),
+                        _ => Expr::Words(init.to_vec()).write_tree(depth + 1, out),
+                    }
+                    last.write_tree(depth + 1, out);
+                }
+                _ => {
+                    line(out, "Words".to_string());
+                    for word in words {
+                        word.write_tree(depth + 1, out);
+                    }
+                }
+            },
+            Expr::Function(params, body) => {
+                line(out, format!("Function {}", params.join(" ")));
+                body.write_tree(depth + 1, out);
+            }
+            Expr::Define(name, ty, body) => {
+                match ty {
+                    Some(ty) => line(out, format!("Define {} : {}", name, ty)),
+                    None => line(out, format!("Define {}", name)),
+                }
+                body.write_tree(depth + 1, out);
+            }
+            Expr::Sequence(exprs) => {
+                line(out, "Sequence".to_string());
+                for e in exprs {
+                    e.write_tree(depth + 1, out);
+                }
+            }
+            Expr::Paren(inner) => {
+                line(out, "Paren".to_string());
+                inner.write_tree(depth + 1, out);
+            }
+            Expr::Assert(lhs, rhs) => {
+                line(out, "Assert".to_string());
+                lhs.write_tree(depth + 1, out);
+                rhs.write_tree(depth + 1, out);
+            }
+            Expr::Annotated(types, inner) => {
+                let types = types.iter()
+                    .map(|t| t.as_ref().map_or("_".to_string(), ToString::to_string))
+                    .collect::<Vec<_>>();
+                line(out, format!("Annotated {}", types.join(", ")));
+                inner.write_tree(depth + 1, out);
+            }
+            Expr::TypeFunction(vars, body) => {
+                line(out, format!("TypeFunction {}", vars.join(" ")));
+                body.write_tree(depth + 1, out);
+            }
+            Expr::TypeApply(inner, ty) => {
+                line(out, format!("TypeApply [{}]", ty));
+                inner.write_tree(depth + 1, out);
+            }
+            Expr::Located(_, inner) => inner.write_tree(depth, out),
+        }
+    }
+}
+
+/// 다시 파싱할 수 있는 소스 형태로 출력한다.
+/// 파서가 만든 식이라면 `e.to_string().parse() == Ok(e)`가 항상 성립한다.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Word(w) => write!(f, "{}", w),
+            Expr::Words(words) => {
+                for (i, e) in words.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    match e.unlocated() {
+                        Expr::Word(_) | Expr::Paren(_) => write!(f, "{}", e)?,
+                        Expr::Words(_) | Expr::TypeApply(..) if i == 0 => write!(f, "{}", e)?,
+                        // 파서가 만들지 않는 모양이지만 의미가 유지되도록 괄호로 감쌈
+                        _ => write!(f, "({})", e)?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Function(params, body) => write!(f, "L {}. {}", params.join(" "), body),
+            Expr::Define(name, Some(ty), body) => write!(f, "{} : {} = {}", name, ty, body),
+            Expr::Define(name, None, body) => write!(f, "{} = {}", name, body),
+            Expr::Sequence(exprs) => {
+                for (i, e) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
+            Expr::Paren(inner) => write!(f, "({})", inner),
+            Expr::Assert(lhs, rhs) => write!(f, "assert {} == {}", lhs, rhs),
+            Expr::Annotated(types, inner) => match &**inner {
+                Expr::Function(params, body) => {
+                    let params = params.iter()
+                        .zip(types)
+                        .map(|(p, t)| match t {
+                            Some(t) => format!("({} : {})", p, t),
+                            None => p.clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    write!(f, "L {}. {}", params.join(" "), body)
+                }
+                _ => write!(f, "{}", inner),
+            },
+            Expr::TypeFunction(vars, body) => write!(f, "/\\{}. {}", vars.join(" "), body),
+            Expr::TypeApply(inner, ty) => match inner.unlocated() {
+                Expr::Word(_) | Expr::Words(_) | Expr::Paren(_) | Expr::TypeApply(..) => {
+                    write!(f, "{} [{}]", inner, ty)
+                }
+                _ => write!(f, "({}) [{}]", inner, ty),
+            },
+            Expr::Located(_, inner) => write!(f, "{}", inner),
+        }
+    }
+}
+
+impl Expr {
+    /// `Display`와 같은 식을 공백을 최소로 해서 출력한다. `L a b.a (b a)` 대신 `L a b.a(b a)`
+    pub fn compact(&self) -> String {
+        match self {
+            Expr::Word(w) => w.clone(),
+            Expr::Words(words) => {
+                let mut out = String::new();
+                for (i, e) in words.iter().enumerate() {
+                    let part = match e.unlocated() {
+                        Expr::Word(_) | Expr::Paren(_) => e.compact(),
+                        Expr::Words(_) | Expr::TypeApply(..) if i == 0 => e.compact(),
+                        _ => format!("({})", e.compact()),
+                    };
+                    // 괄호 옆에는 띄어 쓰지 않아도 적용으로 읽힘
+                    if i > 0 && !out.ends_with(')') && !part.starts_with('(') {
+                        out.push(' ');
+                    }
+                    out.push_str(&part);
+                }
+                out
+            }
+            Expr::Function(params, body) => format!("L {}.{}", params.join(" "), body.compact()),
+            Expr::Paren(inner) => format!("({})", inner.compact()),
+            Expr::Located(_, inner) => inner.compact(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Parser::new(source).parse()
+    }
+}
+
+/// 러스트 코드에서 식을 원문 문법 그대로 만든다: `expr!(L f x. f (f x))`.
+/// 파서를 거치지 않고 컴파일할 때 `Expr`로 펼쳐지며, 파서가 읽은 것과 같은 모양이 됨.
+/// 람다는 `L`로만 쓰고, 인자 자리의 람다는 괄호로 감싸야 함.
+/// `#name`이나 `#(rust 식)`은 그 자리에 실행 중에 만든 `Expr`를 넣음: `expr!(L x. #body)`.
+/// 값을 옮겨 오므로 뒤에서 또 쓸 값은 `#(body.clone())`로 넣음
+#[macro_export]
+macro_rules! expr {
+    (@splice ($value:expr)) => {
+        $crate::ast::Expr::from($value)
+    };
+    (@splice $value:ident) => {
+        $crate::ast::Expr::from($value)
+    };
+    (@atom ($($inner:tt)+)) => {
+        $crate::ast::Expr::Paren(Box::new($crate::expr!($($inner)+)))
+    };
+    (@atom $name:ident) => {
+        $crate::ast::Expr::Word(stringify!($name).to_string())
+    };
+    (@apply [$($done:expr),+] # $value:tt $($rest:tt)*) => {
+        $crate::expr!(@apply [$($done,)+ $crate::expr!(@splice $value).into_arg()] $($rest)*)
+    };
+    (@apply [$($done:expr),+] L $($rest:tt)*) => {
+        compile_error!("a lambda in argument position must be parenthesized")
+    };
+    (@apply [$($done:expr),+] $next:tt $($rest:tt)*) => {
+        $crate::expr!(@apply [$($done,)+ $crate::expr!(@atom $next)] $($rest)*)
+    };
+    (@apply [$($done:expr),+]) => {
+        $crate::ast::Expr::Words(vec![$($done),+])
+    };
+    (L $($param:ident)+ . $($body:tt)+) => {
+        $crate::ast::Expr::Function(vec![$(stringify!($param).to_string()),+], Box::new($crate::expr!($($body)+)))
+    };
+    (# $value:tt) => {
+        $crate::expr!(@splice $value)
+    };
+    (# $value:tt $($rest:tt)+) => {
+        $crate::expr!(@apply [$crate::expr!(@splice $value).into_arg()] $($rest)+)
+    };
+    ($atom:tt) => {
+        $crate::expr!(@atom $atom)
+    };
+    ($head:tt $($rest:tt)+) => {
+        $crate::expr!(@apply [$crate::expr!(@atom $head)] $($rest)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trips(source: &str) {
+        let expr: Expr = source.parse().unwrap();
+        let printed = expr.to_string();
+        assert_eq!(printed.parse::<Expr>(), Ok(expr), "{} → {}", source, printed);
+    }
+
+    #[test]
+    fn printed_programs_parse_back() {
+        round_trips("L f x. f (f x)");
+        round_trips("id = L x. x; k = L x y. x; k id (id k)");
+        round_trips("(L x. x x) (L y. y)");
+        round_trips("assert (L x. x) a == a");
+        round_trips("id : forall a. a -> a = /\\a. L (x : a). x; id [Nat]");
+        round_trips("L (f : Nat -> Nat) x. f x");
+        round_trips("pair = L a b f. f a b; pair (L x. x) b");
+    }
+
+    #[test]
+    fn printing_a_lambda_argument_keeps_its_parentheses() {
+        let expr: Expr = "f (L x. x) y".parse().unwrap();
+        assert_eq!(expr.to_string(), "f (L x. x) y");
+        assert!("f (".parse::<Expr>().is_err());
+    }
 }
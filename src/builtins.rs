@@ -0,0 +1,150 @@
+use std::io::{self, BufRead};
+
+use crate::ast::Expr;
+use crate::encode;
+use crate::error::{Error, ErrorKind};
+use crate::interpreter::{Decoded, Interpreter, Value, Verbosity};
+use crate::messages::{self, Msg};
+use crate::plugin;
+use crate::style::{self, Stream};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Print,
+    ReadNat,
+    ReadLine,
+    Trace,
+    Eq,
+    // 디코딩용 내부 마커: Church 숫자를 셀 때 쓰는 successor와 리스트의 cons
+    Succ,
+    Cons,
+    // `--plugin`으로 불러온 것. 번호는 `plugin`의 목록에서의 자리
+    Plugin(usize),
+}
+
+impl Builtin {
+    /// 프로그램에서 이름으로 부를 수 있는 것들
+    pub const NAMED: [Builtin; 5] = [Builtin::Print, Builtin::ReadNat, Builtin::ReadLine, Builtin::Trace, Builtin::Eq];
+
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "print" => Some(Builtin::Print),
+            "readNat" => Some(Builtin::ReadNat),
+            "readLine" => Some(Builtin::ReadLine),
+            "trace" => Some(Builtin::Trace),
+            "eq" => Some(Builtin::Eq),
+            _ => plugin::find(name).map(Builtin::Plugin),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::ReadNat => "readNat",
+            Builtin::ReadLine => "readLine",
+            Builtin::Trace => "trace",
+            Builtin::Eq => "eq",
+            Builtin::Succ => "#succ",
+            Builtin::Cons => "#cons",
+            Builtin::Plugin(index) => plugin::name(*index),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Builtin::Print => 1,
+            Builtin::ReadNat | Builtin::ReadLine => 0,
+            Builtin::Trace | Builtin::Eq => 2,
+            Builtin::Succ => 1,
+            Builtin::Cons => 2,
+            Builtin::Plugin(index) => plugin::arity(*index),
+        }
+    }
+
+    pub fn requires_io(&self) -> bool {
+        matches!(self, Builtin::Print | Builtin::ReadNat | Builtin::ReadLine)
+    }
+}
+
+impl Interpreter {
+    pub(crate) fn call_builtin(&mut self, builtin: Builtin, mut args: Vec<Value>) -> Result<Value, Error> {
+        match builtin {
+            Builtin::Print => {
+                let arg = args.remove(0);
+                let line = match self.decode(&arg) {
+                    Some(Decoded::Str(s)) => s,
+                    _ => self.display_value(&arg),
+                };
+                if let Some(sandbox) = self.sandbox() {
+                    sandbox.output(line.len() + 1)?;
+                }
+                self.emit(&line);
+                Ok(arg)
+            }
+            Builtin::ReadNat => {
+                let line = read_line()?;
+                let n = line.trim().parse::<usize>()
+                    .map_err(|_| messages::text(Msg::NotNatural, &[&line.trim()]))?;
+                self.eval(encode::church_numeral(n))
+            }
+            Builtin::ReadLine => {
+                let line = read_line()?;
+                self.eval(encode::church_string(&line))
+            }
+            Builtin::Trace => {
+                let value = args.pop().unwrap();
+                let label = match self.decode(&args[0]) {
+                    Some(Decoded::Str(s)) => s,
+                    _ => self.format_result(&args[0]),
+                };
+                if self.verbosity() > Verbosity::Quiet {
+                    eprintln!("{} {}: {}", style::dim(Stream::Stderr, "[trace]"), label, self.display_value(&value));
+                }
+                Ok(value)
+            }
+            Builtin::Eq => {
+                // 결과는 Church 부울 `L a b. a` / `L a b. b`
+                let equal = self.equal_values(&args[0], &args[1])?;
+                let chosen = if equal { "a" } else { "b" };
+                self.eval(Expr::Function(
+                    vec!["a".to_string(), "b".to_string()],
+                    Box::new(Expr::Word(chosen.to_string())),
+                ))
+            }
+            Builtin::Succ => match args.remove(0) {
+                Value::Word(w) => {
+                    let n: usize = w.strip_prefix('#')
+                        .and_then(|n| n.parse().ok())
+                        .ok_or_else(|| messages::text(Msg::NotNumeral, &[]))?;
+                    Ok(Value::Word(format!("#{}", n + 1)))
+                }
+                _ => Err(messages::text(Msg::NotNumeral, &[]).into()),
+            },
+            Builtin::Cons => {
+                let head = self.decode_nat(&args[0])
+                    .and_then(|n| char::from_u32(n as u32))
+                    .ok_or_else(|| messages::text(Msg::NotCharacter, &[]))?;
+                match &args[1] {
+                    Value::Word(w) if w.starts_with("#\"") => {
+                        Ok(Value::Word(format!("#\"{}{}", head, &w[2..])))
+                    }
+                    _ => Err(messages::text(Msg::NotString, &[]).into()),
+                }
+            }
+            Builtin::Plugin(index) => self.call_plugin(index, args),
+        }
+    }
+}
+
+fn read_line() -> Result<String, Error> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)
+        .map_err(|e| Error::new(ErrorKind::Io, messages::text(Msg::FailedToReadStdin, &[&e])))?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(line)
+}
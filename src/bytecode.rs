@@ -0,0 +1,236 @@
+use crate::ast::{Expr, Type};
+use crate::messages::{self, Msg};
+use crate::normalize;
+
+/// `.lbc` 파일의 맨 앞. 마지막 바이트는 형식 버전
+pub const MAGIC: &[u8; 4] = b"LBC\x01";
+
+/// 파싱이 끝난 프로그램을 그대로 바이트로 저장한다.
+/// 문자열과 개수는 little-endian u32 길이를 앞에 붙이고, 식과 타입은 태그 한 바이트로 시작한다.
+pub fn encode(program: &Expr) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    write_expr(&mut out, program);
+    out
+}
+
+/// `compile -o`로 만든 파일이면 읽은 프로그램, 소스 파일이면 `None`.
+/// `--save-env`로 저장한 값에는 평가하면서 붙은 `x$3` 같은 이름이 남아 있음. 그대로 평가하면
+/// 저장하고 읽을 때마다 `x$3$1`처럼 번호가 하나씩 늘어나므로 원래 이름으로 되돌림
+pub fn read(bytes: &[u8]) -> Option<Result<Expr, String>> {
+    bytes.starts_with(MAGIC).then(|| decode(bytes).map(|program| normalize::plain_names(&program)))
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Expr, String> {
+    let rest = bytes.strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| messages::text(Msg::NotBytecode, &[]))?;
+    let mut reader = Reader { bytes: rest, pos: 0 };
+    let expr = reader.expr()?;
+    if reader.pos != rest.len() {
+        return Err(messages::text(Msg::TrailingBytes, &[]));
+    }
+    Ok(expr)
+}
+
+fn write_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_strs(out: &mut Vec<u8>, strs: &[String]) {
+    write_u32(out, strs.len());
+    for s in strs {
+        write_str(out, s);
+    }
+}
+
+fn write_exprs(out: &mut Vec<u8>, exprs: &[Expr]) {
+    write_u32(out, exprs.len());
+    for e in exprs {
+        write_expr(out, e);
+    }
+}
+
+fn write_expr(out: &mut Vec<u8>, expr: &Expr) {
+    match expr {
+        Expr::Word(w) => {
+            out.push(0);
+            write_str(out, w);
+        }
+        Expr::Words(words) => {
+            out.push(1);
+            write_exprs(out, words);
+        }
+        Expr::Function(params, body) => {
+            out.push(2);
+            write_strs(out, params);
+            write_expr(out, body);
+        }
+        Expr::Define(name, ty, body) => {
+            out.push(3);
+            write_str(out, name);
+            write_opt_type(out, ty.as_ref());
+            write_expr(out, body);
+        }
+        Expr::Sequence(exprs) => {
+            out.push(4);
+            write_exprs(out, exprs);
+        }
+        Expr::Paren(inner) => {
+            out.push(5);
+            write_expr(out, inner);
+        }
+        // 자리 정보는 원문과 함께일 때만 뜻이 있으므로 저장하지 않음
+        Expr::Located(_, inner) => write_expr(out, inner),
+        Expr::Assert(lhs, rhs) => {
+            out.push(6);
+            write_expr(out, lhs);
+            write_expr(out, rhs);
+        }
+        Expr::Annotated(types, inner) => {
+            out.push(7);
+            write_u32(out, types.len());
+            for ty in types {
+                write_opt_type(out, ty.as_ref());
+            }
+            write_expr(out, inner);
+        }
+        Expr::TypeFunction(vars, inner) => {
+            out.push(8);
+            write_strs(out, vars);
+            write_expr(out, inner);
+        }
+        Expr::TypeApply(inner, ty) => {
+            out.push(9);
+            write_expr(out, inner);
+            write_type(out, ty);
+        }
+    }
+}
+
+fn write_opt_type(out: &mut Vec<u8>, ty: Option<&Type>) {
+    match ty {
+        Some(ty) => {
+            out.push(1);
+            write_type(out, ty);
+        }
+        None => out.push(0),
+    }
+}
+
+fn write_type(out: &mut Vec<u8>, ty: &Type) {
+    match ty {
+        Type::Base(name) => {
+            out.push(0);
+            write_str(out, name);
+        }
+        Type::Arrow(from, to) => {
+            out.push(1);
+            write_type(out, from);
+            write_type(out, to);
+        }
+        Type::Forall(var, body) => {
+            out.push(2);
+            write_str(out, var);
+            write_type(out, body);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.pos).ok_or_else(|| messages::text(Msg::BytecodeEnd, &[]))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn u32(&mut self) -> Result<usize, String> {
+        let bytes = self.bytes.get(self.pos..self.pos + 4).ok_or_else(|| messages::text(Msg::BytecodeEnd, &[]))?;
+        self.pos += 4;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.u32()?;
+        let bytes = self.bytes.get(self.pos..self.pos + len).ok_or_else(|| messages::text(Msg::BytecodeEnd, &[]))?;
+        self.pos += len;
+        String::from_utf8(bytes.to_vec()).map_err(|_| messages::text(Msg::BytecodeUtf8, &[]))
+    }
+
+    fn strs(&mut self) -> Result<Vec<String>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.str()).collect()
+    }
+
+    fn exprs(&mut self) -> Result<Vec<Expr>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.expr()).collect()
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        Ok(match self.byte()? {
+            0 => Expr::Word(self.str()?),
+            1 => Expr::Words(self.exprs()?),
+            2 => Expr::Function(self.strs()?, Box::new(self.expr()?)),
+            3 => Expr::Define(self.str()?, self.opt_type()?, Box::new(self.expr()?)),
+            4 => Expr::Sequence(self.exprs()?),
+            5 => Expr::Paren(Box::new(self.expr()?)),
+            6 => Expr::Assert(Box::new(self.expr()?), Box::new(self.expr()?)),
+            7 => {
+                let len = self.u32()?;
+                let types = (0..len).map(|_| self.opt_type()).collect::<Result<_, _>>()?;
+                Expr::Annotated(types, Box::new(self.expr()?))
+            }
+            8 => Expr::TypeFunction(self.strs()?, Box::new(self.expr()?)),
+            9 => Expr::TypeApply(Box::new(self.expr()?), self.ty()?),
+            tag => return Err(messages::text(Msg::ExpressionTag, &[&tag])),
+        })
+    }
+
+    fn opt_type(&mut self) -> Result<Option<Type>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => self.ty().map(Some),
+            tag => Err(messages::text(Msg::TypeTag, &[&tag])),
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type, String> {
+        Ok(match self.byte()? {
+            0 => Type::Base(self.str()?),
+            1 => Type::Arrow(Box::new(self.ty()?), Box::new(self.ty()?)),
+            2 => Type::Forall(self.str()?, Box::new(self.ty()?)),
+            tag => return Err(messages::text(Msg::TypeTag, &[&tag])),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn decoding_gives_back_the_encoded_program() {
+        let source = "id : forall a. a -> a = /\\a. L (x : a). x;\nassert id [Nat] == (L y. y);\nk = L x y. (x \"s\");\nk id";
+        let program = Parser::new(source).parse().unwrap();
+        assert_eq!(decode(&encode(&program)), Ok(program));
+    }
+
+    #[test]
+    fn truncated_or_foreign_bytes_are_errors() {
+        let bytes = encode(&Parser::new("L x. x").parse().unwrap());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(decode(b"L x. x").is_err());
+        assert!(read(b"L x. x").is_none());
+    }
+}
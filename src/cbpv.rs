@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::error::Error;
+use crate::highlight::{tokens, Class};
+use crate::interrupt;
+use crate::messages::{self, Msg};
+use crate::style::{self, Stream};
+
+const MAX_STEPS: usize = 1_000_000;
+
+/// 이 문법에서 쓰는 낱말. 이름으로 묶을 수 없음
+const KEYWORDS: [&str; 13] = [
+    "let", "in", "ifz", "then", "else", "to", "thunk", "force", "return", "print", "add", "sub", "mul",
+];
+
+/// 값. 계산하지 않고 그대로 넘길 수 있는 것
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Var(String),
+    Nat(usize),
+    Str(String),
+    Unit,
+    // `thunk M`: 계산을 값으로 감싸 둔 것. `force`로 다시 계산이 됨
+    Thunk(Rc<Comp>),
+}
+
+/// 계산. 스택을 보며 한 단계씩 진행함
+#[derive(Debug, Clone, PartialEq)]
+enum Comp {
+    Return(Value),
+    Force(Value),
+    // 스택에서 값 하나를 꺼내 묶음
+    Lam(String, Rc<Comp>),
+    // 값을 스택에 올리고 계산을 계속함
+    App(Rc<Comp>, Value),
+    // `M to x. N`: `M`이 돌려준 값을 `x`로 묶고 `N`을 계산함
+    To(Rc<Comp>, String, Rc<Comp>),
+    Let(String, Value, Rc<Comp>),
+    // `ifz V then M else N`: `V`가 0이면 `M`
+    Ifz(Value, Rc<Comp>, Rc<Comp>),
+    // 값을 출력하고 `()`를 돌려줌
+    Print(Value),
+    // 스택에서 자연수 둘을 꺼내 계산한 값을 돌려줌
+    Prim(Prim),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prim {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl Prim {
+    fn from_name(name: &str) -> Option<Prim> {
+        match name {
+            "add" => Some(Prim::Add),
+            "sub" => Some(Prim::Sub),
+            "mul" => Some(Prim::Mul),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Prim::Add => "add",
+            Prim::Sub => "sub",
+            Prim::Mul => "mul",
+        }
+    }
+
+    fn apply(self, a: usize, b: usize) -> Option<usize> {
+        match self {
+            Prim::Add => a.checked_add(b),
+            // 자연수이므로 0 밑으로는 내려가지 않음
+            Prim::Sub => Some(a.saturating_sub(b)),
+            Prim::Mul => a.checked_mul(b),
+        }
+    }
+}
+
+/// 파싱한 프로그램
+pub struct Program {
+    statements: Vec<Statement>,
+}
+
+enum Statement {
+    // `name = V`: 최상위 이름. 모든 계산에서 보이므로 `thunk` 안에서 자기 자신을 불러 재귀할 수 있음
+    Define(String, Value),
+    Run(Comp),
+}
+
+/// 실험용 call-by-push-value 프런트엔드. 값과 계산을 문법에서부터 나누고, `thunk`/`force`로 둘 사이를 오감.
+///
+/// ```text
+/// double = thunk L n. add n n;
+/// force double 21 to x. print x to _. return x
+/// ```
+/// 문장은 `;`로 나누고, 계산인 문장은 차례로 실행해서 마지막 계산이 돌려준 값을 결과로 함.
+/// 낱말은 본래 문법과 같은 토크나이저(`highlight::tokens`)로 나눔
+pub fn parse(source: &str) -> Result<Program, String> {
+    let tokens = tokens(source).into_iter()
+        .filter(|(_, class)| *class != Class::Space && *class != Class::Comment)
+        .collect();
+    let mut parser = Parser { source, tokens, pos: 0 };
+    parser.program().map(|statements| Program { statements }).map_err(|e| {
+        let offset = parser.tokens.get(parser.pos).map_or(source.len(), |(range, _)| range.start);
+        let line = source[..offset].matches('\n').count() + 1;
+        let column = source[..offset].rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        messages::text(Msg::LineColumn, &[&line, &column, &e])
+    })
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Range<usize>, Class)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn program(&mut self) -> Result<Vec<Statement>, String> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            let is_define = self.peek_class() == Some(Class::Word)
+                && self.tokens.get(self.pos + 1).is_some_and(|(range, _)| &self.source[range.clone()] == "=");
+            if is_define {
+                let name = self.binder()?;
+                self.expect("=")?;
+                statements.push(Statement::Define(name, self.value()?));
+            } else {
+                statements.push(Statement::Run(self.comp()?));
+            }
+            if !self.consume(";") && self.peek().is_some() {
+                return Err(self.expected(&["';'", &messages::text(Msg::EndOfInput, &[])]));
+            }
+        }
+        Ok(statements)
+    }
+
+    fn comp(&mut self) -> Result<Comp, String> {
+        match self.peek() {
+            Some("L") => {
+                self.pos += 1;
+                let mut params = vec![self.binder()?];
+                while !self.consume(".") {
+                    params.push(self.binder()?);
+                }
+                let body = self.comp()?;
+                Ok(params.into_iter().rev().fold(body, |body, param| Comp::Lam(param, Rc::new(body))))
+            }
+            Some("let") => {
+                self.pos += 1;
+                let name = self.binder()?;
+                self.expect("=")?;
+                let value = self.value()?;
+                self.expect("in")?;
+                Ok(Comp::Let(name, value, Rc::new(self.comp()?)))
+            }
+            Some("ifz") => {
+                self.pos += 1;
+                let value = self.value()?;
+                self.expect("then")?;
+                let zero = self.comp()?;
+                self.expect("else")?;
+                Ok(Comp::Ifz(value, Rc::new(zero), Rc::new(self.comp()?)))
+            }
+            _ => {
+                let first = self.app()?;
+                if !self.consume("to") {
+                    return Ok(first);
+                }
+                let name = self.binder()?;
+                self.expect(".")?;
+                Ok(Comp::To(Rc::new(first), name, Rc::new(self.comp()?)))
+            }
+        }
+    }
+
+    /// 계산 하나 뒤에 인자로 줄 값들
+    fn app(&mut self) -> Result<Comp, String> {
+        let mut comp = self.head()?;
+        while self.peek_value() {
+            comp = Comp::App(Rc::new(comp), self.atom()?);
+        }
+        Ok(comp)
+    }
+
+    fn head(&mut self) -> Result<Comp, String> {
+        let word = self.peek().unwrap_or_default();
+        if let Some(prim) = Prim::from_name(word) {
+            self.pos += 1;
+            return Ok(Comp::Prim(prim));
+        }
+        match word {
+            "return" | "force" | "print" => {
+                self.pos += 1;
+                let value = self.atom()?;
+                Ok(match word {
+                    "return" => Comp::Return(value),
+                    "force" => Comp::Force(value),
+                    _ => Comp::Print(value),
+                })
+            }
+            "(" => {
+                self.pos += 1;
+                let comp = self.comp()?;
+                self.expect(")")?;
+                Ok(comp)
+            }
+            _ if self.peek_value() => Err(messages::text(Msg::ExpectedComputation, &[&word])),
+            _ => Err(self.expected(&["'return'", "'force'", "'print'", "'L'", "'let'", "'ifz'", "'('"])),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.consume("thunk") {
+            true => Ok(Value::Thunk(Rc::new(self.comp()?))),
+            false => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Value, String> {
+        let Some(text) = self.peek() else {
+            return Err(self.expected(&[&messages::text(Msg::Value, &[])]));
+        };
+        match self.peek_class() {
+            Some(Class::Number) => {
+                self.pos += 1;
+                text.parse().map(Value::Nat).map_err(|_| messages::text(Msg::NumberTooLarge, &[&text]))
+            }
+            Some(Class::String) => {
+                self.pos += 1;
+                unescape(text).map(Value::Str)
+            }
+            Some(Class::Word) if !KEYWORDS.contains(&text) => {
+                self.pos += 1;
+                Ok(Value::Var(text.to_string()))
+            }
+            _ if self.consume("(") => {
+                if self.consume(")") {
+                    return Ok(Value::Unit);
+                }
+                let value = self.value()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            _ => Err(self.expected(&[&messages::text(Msg::Value, &[])])),
+        }
+    }
+
+    fn peek_value(&self) -> bool {
+        match (self.peek(), self.peek_class()) {
+            (Some(text), Some(Class::Word)) => !KEYWORDS.contains(&text),
+            (_, Some(Class::Number | Class::String)) => true,
+            // 계산 뒤의 괄호는 계산을 인자로 받을 수 없으므로 언제나 값
+            (Some(text), _) => text == "(",
+            _ => false,
+        }
+    }
+
+    fn binder(&mut self) -> Result<String, String> {
+        match (self.peek(), self.peek_class()) {
+            (Some(text), Some(Class::Word)) if KEYWORDS.contains(&text) => {
+                Err(messages::text(Msg::ReservedKeyword, &[&text]))
+            }
+            (Some(text), Some(Class::Word)) => {
+                self.pos += 1;
+                Ok(text.to_string())
+            }
+            _ => Err(self.expected(&[&messages::text(Msg::Identifier, &[])])),
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|(range, _)| &self.source[range.clone()])
+    }
+
+    fn peek_class(&self) -> Option<Class> {
+        self.tokens.get(self.pos).map(|(_, class)| *class)
+    }
+
+    fn consume(&mut self, text: &str) -> bool {
+        let found = self.peek() == Some(text);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        match self.consume(text) {
+            true => Ok(()),
+            false => Err(self.expected(&[&format!("'{}'", text)])),
+        }
+    }
+
+    /// 본래 파서와 같은 꼴의 오류: `Expected A, B, or C, found 'x'`
+    fn expected(&self, alternatives: &[&str]) -> String {
+        let found = self.peek().map_or_else(|| messages::text(Msg::EndOfInput, &[]), |text| format!("'{}'", text));
+        messages::text(Msg::Expected, &[&messages::either(alternatives), &found])
+    }
+}
+
+fn unescape(token: &str) -> Result<String, String> {
+    let inner = token.strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|_| token.len() >= 2)
+        .ok_or_else(|| messages::text(Msg::UnterminatedString, &[]))?;
+    let mut s = String::new();
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            s.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => s.push('\n'),
+            Some('t') => s.push('\t'),
+            Some('r') => s.push('\r'),
+            Some('0') => s.push('\0'),
+            Some(ch @ ('\\' | '"')) => s.push(ch),
+            Some(ch) => return Err(messages::text(Msg::UnknownEscape, &[&format!("\\{}", ch)])),
+            None => return Err(messages::text(Msg::UnterminatedString, &[])),
+        }
+    }
+    Ok(s)
+}
+
+/// 실행 중의 값. `thunk`는 만들어진 곳의 환경을 함께 들고 있음
+#[derive(Debug, Clone)]
+enum Val {
+    Nat(usize),
+    Str(String),
+    Unit,
+    Thunk(Rc<Comp>, Env),
+}
+
+// 안쪽 묶음이 앞에 오는 연결 리스트. 클로저마다 복사하지 않고 꼬리를 나눠 가짐
+type Env = Option<Rc<Binding>>;
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    value: Val,
+    next: Env,
+}
+
+fn bind(env: &Env, name: &str, value: Val) -> Env {
+    Some(Rc::new(Binding { name: name.to_string(), value, next: env.clone() }))
+}
+
+enum Frame {
+    // 다음 `L`이 꺼낼 인자
+    Arg(Val),
+    // `M to x. N`에서 `M`이 돌려줄 값을 기다리는 `x. N`
+    To(String, Rc<Comp>, Env),
+}
+
+/// 계산이 멈춘 모양
+pub enum Terminal {
+    Value(String),
+    // 스택이 빈 채로 닿은 `L`. 받을 인자가 없으므로 더 진행하지 않음
+    Function(String),
+}
+
+/// 스택 기계로 프로그램을 실행함. `trace`이면 단계마다 계산과 스택을 보여 줌
+pub fn run(program: &Program, trace: bool) -> Result<Option<Terminal>, Error> {
+    let mut machine = Machine { globals: HashMap::new(), trace, steps: 0 };
+    let mut last = None;
+    for statement in &program.statements {
+        match statement {
+            Statement::Define(name, value) => {
+                let value = machine.value(value, &None)?;
+                machine.globals.insert(name.clone(), value);
+            }
+            Statement::Run(comp) => last = Some(machine.execute(Rc::new(comp.clone()))?),
+        }
+    }
+    Ok(last)
+}
+
+struct Machine {
+    globals: HashMap<String, Val>,
+    trace: bool,
+    // 모든 문장에서 모두 센 단계 수
+    steps: usize,
+}
+
+impl Machine {
+    fn execute(&mut self, comp: Rc<Comp>) -> Result<Terminal, Error> {
+        let mut comp = comp;
+        let mut env: Env = None;
+        let mut stack: Vec<Frame> = Vec::new();
+        loop {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return Err(Error::step_limit(messages::text(Msg::CbpvSteps, &[&MAX_STEPS])));
+            }
+            interrupt::check(self.steps, || comp.to_string())?;
+            if self.trace {
+                self.print_state(&comp, &stack);
+            }
+            // 값을 돌려주는 계산은 모두 여기로 모임
+            let returned = match &*comp {
+                Comp::Return(value) => self.value(value, &env)?,
+                Comp::Print(value) => {
+                    // 문자열은 본래 `print`처럼 따옴표 없이
+                    match self.value(value, &env)? {
+                        Val::Str(s) => println!("{}", s),
+                        other => println!("{}", other),
+                    }
+                    Val::Unit
+                }
+                Comp::Prim(prim) => {
+                    let mut nat = || match stack.pop() {
+                        Some(Frame::Arg(Val::Nat(n))) => Ok(n),
+                        Some(Frame::Arg(other)) => Err(messages::text(Msg::PrimNatural, &[&prim.name(), &other])),
+                        _ => Err(messages::text(Msg::PrimArity, &[&prim.name()])),
+                    };
+                    let (a, b) = (nat()?, nat()?);
+                    Val::Nat(prim.apply(a, b).ok_or_else(|| messages::text(Msg::PrimOverflow, &[&prim.name(), &a, &b]))?)
+                }
+                Comp::Force(value) => match self.value(value, &env)? {
+                    Val::Thunk(inner, captured) => {
+                        comp = inner;
+                        env = captured;
+                        continue;
+                    }
+                    other => return Err(messages::text(Msg::NotThunk, &[&other]).into()),
+                },
+                Comp::Lam(param, body) => match stack.pop() {
+                    Some(Frame::Arg(arg)) => {
+                        env = bind(&env, param, arg);
+                        comp = body.clone();
+                        continue;
+                    }
+                    None => return Ok(Terminal::Function(comp.to_string())),
+                    Some(Frame::To(..)) => {
+                        return Err(messages::text(Msg::FunctionSequenced, &[]).into());
+                    }
+                },
+                Comp::App(func, arg) => {
+                    let arg = self.value(arg, &env)?;
+                    stack.push(Frame::Arg(arg));
+                    comp = func.clone();
+                    continue;
+                }
+                Comp::To(first, name, rest) => {
+                    stack.push(Frame::To(name.clone(), rest.clone(), env.clone()));
+                    comp = first.clone();
+                    continue;
+                }
+                Comp::Let(name, value, body) => {
+                    let value = self.value(value, &env)?;
+                    env = bind(&env, name, value);
+                    comp = body.clone();
+                    continue;
+                }
+                Comp::Ifz(value, zero, other) => match self.value(value, &env)? {
+                    Val::Nat(n) => {
+                        comp = if n == 0 { zero.clone() } else { other.clone() };
+                        continue;
+                    }
+                    other => return Err(messages::text(Msg::IfzNatural, &[&other]).into()),
+                },
+            };
+            match stack.pop() {
+                None => return Ok(Terminal::Value(returned.to_string())),
+                Some(Frame::To(name, rest, captured)) => {
+                    env = bind(&captured, &name, returned);
+                    comp = rest;
+                }
+                Some(Frame::Arg(arg)) => {
+                    return Err(messages::text(Msg::ReturnedArgument, &[&arg]).into());
+                }
+            }
+        }
+    }
+
+    fn value(&self, value: &Value, env: &Env) -> Result<Val, String> {
+        Ok(match value {
+            Value::Var(name) => lookup(env, name)
+                .or_else(|| self.globals.get(name))
+                .cloned()
+                .ok_or_else(|| messages::text(Msg::UnboundName, &[&name]))?,
+            Value::Nat(n) => Val::Nat(*n),
+            Value::Str(s) => Val::Str(s.clone()),
+            Value::Unit => Val::Unit,
+            Value::Thunk(comp) => Val::Thunk(comp.clone(), env.clone()),
+        })
+    }
+
+    fn print_state(&self, comp: &Comp, stack: &[Frame]) {
+        let frames = stack.iter().rev()
+            .map(|frame| match frame {
+                Frame::Arg(arg) => arg.to_string(),
+                Frame::To(name, rest, _) => format!("to {}. {}", name, rest),
+            })
+            .collect::<Vec<_>>();
+        println!("{} {}", style::dim(Stream::Stdout, &format!("{:>5}", self.steps)), style::redex(Stream::Stdout, &comp.to_string()));
+        if !frames.is_empty() {
+            println!("{}", style::dim(Stream::Stdout, &format!("      stack: {}", frames.join(" | "))));
+        }
+    }
+}
+
+fn lookup<'e>(env: &'e Env, name: &str) -> Option<&'e Val> {
+    let mut current = env.as_deref();
+    while let Some(binding) = current {
+        if binding.name == name {
+            return Some(&binding.value);
+        }
+        current = binding.next.as_deref();
+    }
+    None
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Val::Nat(n) => write!(f, "{}", n),
+            Val::Str(s) => write!(f, "{:?}", s),
+            Val::Unit => write!(f, "()"),
+            Val::Thunk(comp, _) => write!(f, "thunk {}", comp),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Var(name) => write!(f, "{}", name),
+            Value::Nat(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Unit => write!(f, "()"),
+            Value::Thunk(comp) => write!(f, "thunk {}", comp),
+        }
+    }
+}
+
+/// 인자 자리의 값. `thunk`는 뒤를 모두 삼키므로 괄호로 감쌈
+struct Atom<'a>(&'a Value);
+
+impl fmt::Display for Atom<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Value::Thunk(_) => write!(f, "({})", self.0),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+impl fmt::Display for Comp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Comp::Return(value) => write!(f, "return {}", Atom(value)),
+            Comp::Force(value) => write!(f, "force {}", Atom(value)),
+            Comp::Print(value) => write!(f, "print {}", Atom(value)),
+            Comp::Prim(prim) => write!(f, "{}", prim.name()),
+            Comp::Lam(param, body) => write!(f, "L {}. {}", param, body),
+            Comp::App(func, arg) => match **func {
+                Comp::Lam(..) | Comp::Let(..) | Comp::Ifz(..) | Comp::To(..) => write!(f, "({}) {}", func, Atom(arg)),
+                _ => write!(f, "{} {}", func, Atom(arg)),
+            },
+            Comp::To(first, name, rest) => match **first {
+                Comp::Lam(..) | Comp::Let(..) | Comp::Ifz(..) | Comp::To(..) => write!(f, "({}) to {}. {}", first, name, rest),
+                _ => write!(f, "{} to {}. {}", first, name, rest),
+            },
+            Comp::Let(name, value, body) => write!(f, "let {} = {} in {}", name, value, body),
+            Comp::Ifz(value, zero, other) => write!(f, "ifz {} then {} else {}", value, zero, other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(source: &str) -> Result<String, String> {
+        let program = parse(source)?;
+        match run(&program, false).map_err(|e| e.to_string())? {
+            Some(Terminal::Value(value) | Terminal::Function(value)) => Ok(value),
+            None => Ok(String::new()),
+        }
+    }
+
+    #[test]
+    fn thunks_are_forced_with_their_arguments() {
+        let source = "fact = thunk L n. ifz n then return 1 else sub n 1 to m. force fact m to r. mul n r;\nforce fact 5";
+        assert_eq!(result(source).unwrap(), "120");
+        assert_eq!(result("let x = 2 in add x x to y. return y").unwrap(), "4");
+    }
+
+    #[test]
+    fn values_in_computation_position_are_rejected() {
+        assert!(result("x = 1; x 2").is_err());
+        assert!(result("force 3").is_err());
+        assert!(result("add 1 (thunk return 2)").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::cps::collect_names;
+use crate::messages::{self, Msg};
+use crate::normalize::free_vars;
+
+/// 클로저 변환. 람다 하나는 코드와 환경의 Church 쌍 `L s. s code env`가 되고,
+/// 환경은 잡아 둔 변수들의 튜플 `L s. s y1 y2`이다. 코드는 닫힌 식 `L env x. env (L y1 y2. M)`.
+/// 적용 `f a`는 쌍을 풀어서 코드에 환경과 인자를 넘기는 `f (L code env. code env a)`가 된다.
+/// 평가기의 `Value::Closure(param, body, env)`가 바로 이 쌍에 해당한다.
+pub fn transform(program: &Expr) -> Result<Expr, String> {
+    let mut names = HashSet::new();
+    collect_names(program, &mut names);
+    let pick = |base: &str| {
+        (0..)
+            .map(|n| if n == 0 { base.to_string() } else { format!("{}{}", base, n) })
+            .find(|name| !names.contains(name))
+            .unwrap()
+    };
+    let mut conv = Convert {
+        selector: pick("s"),
+        env: pick("env"),
+        code: pick("code"),
+        bound: Vec::new(),
+    };
+
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    // 정의된 이름은 builtin을 가림
+    for statement in statements {
+        if let Expr::Define(name, _, _) = statement {
+            conv.bound.push(name.clone());
+        }
+    }
+    let mut result = Vec::new();
+    for statement in statements {
+        result.push(match statement {
+            Expr::Define(name, _, body) => Expr::Define(name.clone(), None, Box::new(conv.convert(body, &[])?)),
+            Expr::Assert(lhs, rhs) => Expr::Assert(
+                Box::new(conv.convert(lhs, &[])?),
+                Box::new(conv.convert(rhs, &[])?),
+            ),
+            other => conv.convert(other, &[])?,
+        });
+    }
+    Ok(match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Expr::Sequence(result),
+    })
+}
+
+struct Convert {
+    selector: String,
+    env: String,
+    code: String,
+    // 최상위 정의 이름
+    bound: Vec<String>,
+}
+
+impl Convert {
+    /// `scope`는 바깥 람다들이 묶은 변수 (안쪽이 뒤)
+    fn convert(&mut self, expr: &Expr, scope: &[String]) -> Result<Expr, String> {
+        match expr {
+            Expr::Word(name) => {
+                if scope.contains(name) || self.bound.contains(name) {
+                    return Ok(expr.clone());
+                }
+                match Builtin::from_name(name) {
+                    Some(builtin) => self.builtin(builtin),
+                    None => Ok(expr.clone()),
+                }
+            }
+            Expr::Function(params, body) => {
+                let (param, rest) = params.split_first().ok_or("Function without parameters")?;
+                let mut inner_scope = scope.to_vec();
+                inner_scope.push(param.clone());
+                let body = if rest.is_empty() {
+                    self.convert(body, &inner_scope)?
+                } else {
+                    self.convert(&Expr::Function(rest.to_vec(), body.clone()), &inner_scope)?
+                };
+
+                // 바깥에서 묶였고 본문에서 쓰이는 변수만 환경에 들어감
+                let used = free_vars(&body);
+                let mut captured = Vec::new();
+                for var in scope {
+                    if used.contains(var) && var != param && !captured.contains(var) {
+                        captured.push(var.clone());
+                    }
+                }
+                Ok(self.closure(&captured, param, body))
+            }
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                let mut func = self.convert(head, scope)?;
+                for arg in args {
+                    let arg = self.convert(arg, scope)?;
+                    func = self.apply(func, arg);
+                }
+                Ok(func)
+            }
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.convert(inner, scope),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Definitions and asserts are only allowed at the top level".to_string())
+            }
+        }
+    }
+
+    /// `L s. s (L env x. env (L y1 y2. body)) (L s. s y1 y2)`
+    fn closure(&self, captured: &[String], param: &str, body: Expr) -> Expr {
+        let body = match captured.is_empty() {
+            true => body,
+            false => Expr::Words(vec![
+                word(&self.env),
+                paren(Expr::Function(captured.to_vec(), Box::new(body))),
+            ]),
+        };
+        let code = Expr::Function(vec![self.env.clone(), param.to_string()], Box::new(body));
+        let env = Expr::Function(
+            vec![self.selector.clone()],
+            Box::new(Expr::Words(std::iter::once(word(&self.selector))
+                .chain(captured.iter().map(|v| word(v)))
+                .collect())),
+        );
+        Expr::Function(
+            vec![self.selector.clone()],
+            Box::new(Expr::Words(vec![word(&self.selector), paren(code), paren(env)])),
+        )
+    }
+
+    /// `f (L code env. code env a)`
+    fn apply(&self, func: Expr, arg: Expr) -> Expr {
+        let unpack = Expr::Function(
+            vec![self.code.clone(), self.env.clone()],
+            Box::new(Expr::Words(vec![word(&self.code), word(&self.env), paren(arg)])),
+        );
+        Expr::Words(vec![paren(func), paren(unpack)])
+    }
+
+    /// builtin은 직접 방식으로 호출하고, Church 값을 돌려주는 것은 변환된 값으로 바꿔 줌
+    fn builtin(&mut self, builtin: Builtin) -> Result<Expr, String> {
+        let name = builtin.name();
+        match builtin {
+            Builtin::Print => Ok(self.closure(&[], "x", Expr::Words(vec![word(name), word("x")]))),
+            Builtin::Trace | Builtin::Eq => {
+                let mut call = vec![word(name), word("a"), word("b")];
+                if builtin == Builtin::Eq {
+                    call.push(self.source("L t f. t")?);
+                    call.push(self.source("L t f. f")?);
+                }
+                let inner = self.closure(&["a".to_string()], "b", Expr::Words(call));
+                Ok(self.closure(&[], "a", inner))
+            }
+            Builtin::ReadNat => {
+                let succ = self.nat_succ()?;
+                Ok(Expr::Words(vec![word(name), paren(succ), self.source("L f x. x")?]))
+            }
+            Builtin::ReadLine => {
+                // 글자(직접 Church 숫자)도 변환된 숫자로 바꿈
+                let succ = self.nat_succ()?;
+                let zero = self.source("L f x. x")?;
+                let head = Expr::Words(vec![word("h"), paren(succ), zero]);
+                let cons = self.source("L h t c n. c h (t c n)")?;
+                let cons = self.apply(self.apply(cons, head), word("t"));
+                let cons = Expr::Function(vec!["h".to_string(), "t".to_string()], Box::new(cons));
+                Ok(Expr::Words(vec![word(name), paren(cons), self.source("L c n. n")?]))
+            }
+            Builtin::Succ | Builtin::Cons | Builtin::Plugin(_) => Ok(word(name)),
+        }
+    }
+
+    /// 변환된 숫자를 받아 변환된 다음 숫자를 돌려주는 직접 방식의 함수
+    fn nat_succ(&mut self) -> Result<Expr, String> {
+        let succ = self.source("L n f x. f (n f x)")?;
+        Ok(Expr::Function(vec!["m".to_string()], Box::new(self.apply(succ, word("m")))))
+    }
+
+    fn source(&mut self, source: &str) -> Result<Expr, String> {
+        let expr = source.parse()?;
+        self.convert(&expr, &[]).map(paren)
+    }
+}
+
+fn word(name: &str) -> Expr {
+    Expr::Word(name.to_string())
+}
+
+fn paren(expr: Expr) -> Expr {
+    match expr {
+        Expr::Word(_) | Expr::Paren(_) => expr,
+        _ => Expr::Paren(Box::new(expr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Config, Interpreter};
+    use crate::normalize;
+    use crate::parser::Parser;
+
+    fn normal_form(program: &Expr) -> Expr {
+        let mut interpreter = Interpreter::new(Config::default());
+        let value = interpreter.eval(program.clone()).unwrap();
+        interpreter.normal_form(&value).unwrap()
+    }
+
+    /// 클로저 쌍으로 바꾼 프로그램의 결과가 기대하는 값을 바꾼 것과 같은지 봄
+    fn evaluates_like(source: &str, expected: &str) {
+        let program = transform(&Parser::new(source).parse().unwrap()).unwrap();
+        let expected = transform(&Parser::new(expected).parse().unwrap()).unwrap();
+        let (got, expected) = (normal_form(&program), normal_form(&expected));
+        assert!(normalize::alpha_eq(&got, &expected), "{} / {}", got, expected);
+    }
+
+    #[test]
+    fn transformed_programs_evaluate_to_the_same_result() {
+        evaluates_like("id = L x. x; k = L x y. x; k id (id k)", "L x. x");
+        // 결과 클로저의 환경은 정규화되지 않으므로 환경이 빈 값으로 끝나는 프로그램만 비교함
+        evaluates_like("two = L f x. f (f x); two (L b. b) (L a. a)", "L a. a");
+        evaluates_like("(L x y. x) (L a. a) (L b. b b)", "L a. a");
+        evaluates_like("id = L x. x; assert id id == id; id", "L x. x");
+    }
+
+    #[test]
+    fn nested_definitions_are_rejected() {
+        let nested = Expr::Words(vec![
+            Expr::Word("f".to_string()),
+            Expr::Define("x".to_string(), None, Box::new(Expr::Word("y".to_string()))),
+        ]);
+        assert!(transform(&nested).is_err());
+    }
+}
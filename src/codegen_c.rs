@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::messages::{self, Msg};
+use crate::normalize::free_vars;
+
+/// 생성된 프로그램에 그대로 들어가는 런타임.
+/// 람다는 코드 포인터와 잡아 둔 값 배열을 가진 클로저 구조체이고, `apply`가 그 코드를 부른다.
+/// 정규형은 Rust 백엔드처럼 중립 값을 넣어서 읽어 내며 출력 형식은 `normalize::canonical`과 같다.
+/// 메모리는 해제하지 않는다.
+const RUNTIME: &str = r#"#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef struct Value Value;
+typedef Value *(*Code)(Value **env, Value *arg);
+
+enum { FUN, NEU };
+enum { LEVEL, SYM };
+
+struct Value {
+    int tag;
+    /* FUN: 코드와 환경 */
+    Code code;
+    Value **env;
+    /* NEU: 더 줄어들지 않는 적용. 머리(묶인 변수의 깊이나 자유 이름)와 인자들 */
+    int head;
+    size_t level;
+    const char *sym;
+    Value **args;
+    size_t argc;
+};
+
+typedef struct Term {
+    int lam;
+    struct Term *body;
+    int head;
+    size_t level;
+    const char *sym;
+    struct Term **args;
+    size_t argc;
+} Term;
+
+typedef struct {
+    char *data;
+    size_t len, cap;
+} Buf;
+
+static void *xmalloc(size_t n) {
+    void *p = malloc(n ? n : 1);
+    if (!p) {
+        fputs("out of memory\n", stderr);
+        exit(1);
+    }
+    return p;
+}
+
+static Value *mk_fun(Code code, size_t n, Value **env) {
+    Value *v = xmalloc(sizeof *v);
+    memset(v, 0, sizeof *v);
+    v->tag = FUN;
+    v->code = code;
+    v->env = xmalloc(n * sizeof *env);
+    if (n) memcpy(v->env, env, n * sizeof *env);
+    return v;
+}
+
+static Value *mk_neu(int head, size_t level, const char *sym) {
+    Value *v = xmalloc(sizeof *v);
+    memset(v, 0, sizeof *v);
+    v->tag = NEU;
+    v->head = head;
+    v->level = level;
+    v->sym = sym;
+    return v;
+}
+
+/* 쓰이지 않아도 경고가 나지 않도록 static이 아님 */
+Value *mk_sym(const char *name) {
+    return mk_neu(SYM, 0, name);
+}
+
+static Value *apply(Value *f, Value *a) {
+    Value *v;
+    if (f->tag == FUN) return f->code(f->env, a);
+    /* 중립 값은 공유될 수 있으므로 인자 배열을 복사해서 늘림 */
+    v = mk_neu(f->head, f->level, f->sym);
+    v->args = xmalloc((f->argc + 1) * sizeof *v->args);
+    if (f->argc) memcpy(v->args, f->args, f->argc * sizeof *v->args);
+    v->args[f->argc] = a;
+    v->argc = f->argc + 1;
+    return v;
+}
+
+static Term *quote(Value *v, size_t depth) {
+    size_t i;
+    Term *t = xmalloc(sizeof *t);
+    memset(t, 0, sizeof *t);
+    if (v->tag == FUN) {
+        t->lam = 1;
+        t->body = quote(apply(v, mk_neu(LEVEL, depth, NULL)), depth + 1);
+        return t;
+    }
+    t->head = v->head;
+    t->level = v->level;
+    t->sym = v->sym;
+    t->argc = v->argc;
+    t->args = xmalloc(v->argc * sizeof *t->args);
+    for (i = 0; i < v->argc; i++) t->args[i] = quote(v->args[i], depth);
+    return t;
+}
+
+static int is_free(Term *t, const char *name) {
+    size_t i;
+    if (t->lam) return is_free(t->body, name);
+    if (t->head == SYM && strcmp(t->sym, name) == 0) return 1;
+    for (i = 0; i < t->argc; i++) {
+        if (is_free(t->args[i], name)) return 1;
+    }
+    return 0;
+}
+
+static void put(Buf *b, const char *s) {
+    size_t n = strlen(s);
+    if (b->len + n + 1 > b->cap) {
+        char *data;
+        b->cap = (b->len + n + 1) * 2;
+        data = xmalloc(b->cap);
+        if (b->data) memcpy(data, b->data, b->len);
+        free(b->data);
+        b->data = data;
+    }
+    memcpy(b->data + b->len, s, n + 1);
+    b->len += n;
+}
+
+static void put_head(Buf *b, Term *t, size_t *names) {
+    char name[32];
+    if (t->head == SYM) {
+        put(b, t->sym);
+    } else {
+        sprintf(name, "v%lu", (unsigned long)names[t->level]);
+        put(b, name);
+    }
+}
+
+static void put_term(Buf *b, Term *t, Term *root, size_t *names, size_t depth, size_t *counter) {
+    char name[32];
+    size_t i;
+    if (t->lam) {
+        put(b, "L");
+        while (t->lam) {
+            sprintf(name, "v%lu", (unsigned long)*counter);
+            while (is_free(root, name)) {
+                *counter += 1;
+                sprintf(name, "v%lu", (unsigned long)*counter);
+            }
+            names[depth++] = (*counter)++;
+            put(b, " ");
+            put(b, name);
+            t = t->body;
+        }
+        put(b, ". ");
+        put_term(b, t, root, names, depth, counter);
+        return;
+    }
+    put_head(b, t, names);
+    for (i = 0; i < t->argc; i++) {
+        put(b, " ");
+        if (!t->args[i]->lam && t->args[i]->argc == 0) {
+            put_head(b, t->args[i], names);
+        } else {
+            put(b, "(");
+            put_term(b, t->args[i], root, names, depth, counter);
+            put(b, ")");
+        }
+    }
+}
+
+static size_t lam_depth(Term *t) {
+    size_t i, max = 0;
+    if (t->lam) return 1 + lam_depth(t->body);
+    for (i = 0; i < t->argc; i++) {
+        size_t d = lam_depth(t->args[i]);
+        if (d > max) max = d;
+    }
+    return max;
+}
+
+static char *show(Value *v) {
+    Buf b = { NULL, 0, 0 };
+    Term *t = quote(v, 0);
+    size_t counter = 0;
+    size_t *names = xmalloc(lam_depth(t) * sizeof *names);
+    put(&b, "");
+    put_term(&b, t, t, names, 0, &counter);
+    return b.data;
+}
+
+void check(Value *left, Value *right, const char *label) {
+    char *l = show(left), *r = show(right);
+    if (strcmp(l, r) != 0) {
+        fprintf(stderr, "%s\nAssertion failed:\n  left:  %s\n  right: %s\n", label, l, r);
+        exit(1);
+    }
+}
+"#;
+
+/// 프로그램을 독립된 C 소스로 옮긴다. 실행하면 마지막 식의 정규형을 출력한다.
+pub fn compile(program: &Expr) -> Result<String, String> {
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+
+    let mut gen = Gen { functions: Vec::new() };
+    // 최상위 정의는 main의 지역 변수. 다시 정의하면 새 변수가 됨
+    let mut scope = HashMap::new();
+    let mut lines = Vec::new();
+    for (i, statement) in statements.iter().enumerate() {
+        match statement {
+            Expr::Define(name, _, body) => {
+                let value = gen.expr(body, &scope)?;
+                let var = format!("d{}", i);
+                lines.push(format!("Value *{} = {};", var, value));
+                scope.insert(name.clone(), var);
+            }
+            Expr::Assert(lhs, rhs) => {
+                let label = format!("assert {} == {}", lhs, rhs);
+                let (lhs, rhs) = (gen.expr(lhs, &scope)?, gen.expr(rhs, &scope)?);
+                lines.push(format!("check({}, {}, {});", lhs, rhs, c_string(&label)));
+            }
+            _ => {}
+        }
+    }
+    // 마지막 식은 모든 정의가 끝난 뒤에 평가
+    let last = statements.iter()
+        .rfind(|e| !matches!(e, Expr::Define(..) | Expr::Assert(..)));
+    match last {
+        Some(last) => lines.push(format!("puts(show({}));", gen.expr(last, &scope)?)),
+        None => lines.push("puts(\"()\");".to_string()),
+    }
+
+    let mut out = String::from("/* Generated by lintre. */\n");
+    out.push_str(RUNTIME);
+    for function in &gen.functions {
+        out.push('\n');
+        out.push_str(function);
+    }
+    out.push_str("\nint main(void) {\n");
+    for line in lines {
+        out.push_str("    ");
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("    return 0;\n}\n");
+    Ok(out)
+}
+
+struct Gen {
+    functions: Vec<String>,
+}
+
+impl Gen {
+    /// `scope`는 이름에서 그 값을 담은 C 식으로 가는 표
+    fn expr(&mut self, e: &Expr, scope: &HashMap<String, String>) -> Result<String, String> {
+        match e {
+            Expr::Word(name) => {
+                if let Some(var) = scope.get(name) {
+                    Ok(var.clone())
+                } else if Builtin::from_name(name).is_some() {
+                    Err(format!("'{}' is not supported by the C backend.", name))
+                } else {
+                    Ok(format!("mk_sym({})", c_string(name)))
+                }
+            }
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                let mut code = self.expr(head, scope)?;
+                for arg in args {
+                    code = format!("apply({}, {})", code, self.expr(arg, scope)?);
+                }
+                Ok(code)
+            }
+            Expr::Function(params, body) => {
+                let (param, rest) = params.split_first().ok_or("Function without parameters")?;
+                let body_expr = match rest.is_empty() {
+                    true => (**body).clone(),
+                    false => Expr::Function(rest.to_vec(), body.clone()),
+                };
+
+                // 잡아 둘 변수는 환경 배열의 자리로 바뀜
+                let mut captured = free_vars(&body_expr).into_iter()
+                    .filter(|v| v != param && scope.contains_key(v))
+                    .collect::<Vec<_>>();
+                captured.sort();
+                let mut inner = captured.iter()
+                    .enumerate()
+                    .map(|(i, v)| (v.clone(), format!("env[{}]", i)))
+                    .collect::<HashMap<_, _>>();
+                inner.insert(param.clone(), "arg".to_string());
+                let body = self.expr(&body_expr, &inner)?;
+
+                let name = format!("lam{}", self.functions.len());
+                self.functions.push(format!(
+                    "static Value *{}(Value **env, Value *arg) {{\n    (void)env;\n    (void)arg;\n    return {};\n}}\n",
+                    name, body
+                ));
+                if captured.is_empty() {
+                    Ok(format!("mk_fun({}, 0, NULL)", name))
+                } else {
+                    let env = captured.iter()
+                        .map(|v| scope[v].clone())
+                        .collect::<Vec<_>>();
+                    Ok(format!("mk_fun({}, {}, (Value *[]){{ {} }})", name, captured.len(), env.join(", ")))
+                }
+            }
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.expr(inner, scope),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Definitions and asserts are only allowed at the top level".to_string())
+            }
+        }
+    }
+}
+
+/// ASCII가 아닌 바이트는 8진 이스케이프로 써서 어느 컴파일러에서나 같은 문자열이 되게 함
+fn c_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            b' '..=b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use std::process::Command;
+
+    fn compiled(source: &str) -> Result<String, String> {
+        compile(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn generated_program_prints_the_normal_form() {
+        let code = compiled("two = L f x. f (f x); id = L x. x; assert id id == id; two two").unwrap();
+        let dir = std::env::temp_dir().join(format!("lintre-codegen-c-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.c"), code).unwrap();
+        let status = Command::new("cc").arg("-o").arg(dir.join("main")).arg(dir.join("main.c")).status().unwrap();
+        assert!(status.success());
+        let output = Command::new(dir.join("main")).output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "L v0 v1. v0 (v0 (v0 (v0 v1)))\n");
+    }
+
+    #[test]
+    fn builtins_are_rejected() {
+        assert!(compiled("print (L x. x)").is_err());
+    }
+}
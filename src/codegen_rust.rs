@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::messages::{self, Msg};
+use crate::normalize::free_vars;
+
+/// 생성된 프로그램에 그대로 들어가는 런타임.
+/// 닫힌 값은 `Rc<dyn Fn>`이고, 정규형은 묶인 변수 자리에 중립 값을 넣어 읽어 낸다(normalization by evaluation).
+/// 출력 형식은 `normalize::canonical`과 같다.
+const RUNTIME: &str = r#"#![allow(unused, non_snake_case)]
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+#[derive(Clone)]
+enum V {
+    Fun(Rc<dyn Fn(V) -> V>),
+    // 더 줄어들지 않는 적용: 머리(묶인 변수의 깊이나 자유 이름)와 인자들
+    Neu(Head, Vec<V>),
+}
+
+#[derive(Clone)]
+enum Head {
+    Level(usize),
+    Sym(&'static str),
+}
+
+enum Term {
+    Lam(Box<Term>),
+    App(Head, Vec<Term>),
+}
+
+fn fun(f: impl Fn(V) -> V + 'static) -> V {
+    V::Fun(Rc::new(f))
+}
+
+fn sym(name: &'static str) -> V {
+    V::Neu(Head::Sym(name), Vec::new())
+}
+
+fn app(f: V, a: V) -> V {
+    match f {
+        V::Fun(f) => f(a),
+        V::Neu(head, mut args) => {
+            args.push(a);
+            V::Neu(head, args)
+        }
+    }
+}
+
+fn quote(v: &V, depth: usize) -> Term {
+    match v {
+        V::Fun(f) => Term::Lam(Box::new(quote(&f(V::Neu(Head::Level(depth), Vec::new())), depth + 1))),
+        V::Neu(head, args) => Term::App(head.clone(), args.iter().map(|a| quote(a, depth)).collect()),
+    }
+}
+
+fn collect_free(t: &Term, free: &mut HashSet<&'static str>) {
+    match t {
+        Term::Lam(body) => collect_free(body, free),
+        Term::App(head, args) => {
+            if let Head::Sym(name) = head {
+                free.insert(name);
+            }
+            for a in args {
+                collect_free(a, free);
+            }
+        }
+    }
+}
+
+fn write_head(head: &Head, names: &[String], out: &mut String) {
+    match head {
+        Head::Level(level) => out.push_str(&names[*level]),
+        Head::Sym(name) => out.push_str(name),
+    }
+}
+
+fn write_term(t: &Term, names: &mut Vec<String>, free: &HashSet<&'static str>, counter: &mut usize, out: &mut String) {
+    match t {
+        Term::Lam(_) => {
+            let depth = names.len();
+            let mut t = t;
+            out.push('L');
+            while let Term::Lam(body) = t {
+                let mut name = format!("v{}", counter);
+                while free.contains(name.as_str()) {
+                    *counter += 1;
+                    name = format!("v{}", counter);
+                }
+                *counter += 1;
+                out.push(' ');
+                out.push_str(&name);
+                names.push(name);
+                t = body;
+            }
+            out.push_str(". ");
+            write_term(t, names, free, counter, out);
+            names.truncate(depth);
+        }
+        Term::App(head, args) => {
+            write_head(head, names, out);
+            for a in args {
+                out.push(' ');
+                match a {
+                    Term::App(head, inner) if inner.is_empty() => write_head(head, names, out),
+                    _ => {
+                        out.push('(');
+                        write_term(a, names, free, counter, out);
+                        out.push(')');
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn show(v: &V) -> String {
+    let term = quote(v, 0);
+    let mut free = HashSet::new();
+    collect_free(&term, &mut free);
+    let mut out = String::new();
+    write_term(&term, &mut Vec::new(), &free, &mut 0, &mut out);
+    out
+}
+
+fn check(left: V, right: V, label: &str) {
+    let (left, right) = (show(&left), show(&right));
+    if left != right {
+        eprintln!("{}", label);
+        eprintln!("Assertion failed:\n  left:  {}\n  right: {}", left, right);
+        std::process::exit(1);
+    }
+}
+"#;
+
+/// 프로그램을 독립된 Rust 소스로 옮긴다. 실행하면 마지막 식의 정규형을 출력한다.
+pub fn compile(program: &Expr) -> Result<String, String> {
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+
+    let mut scope = HashSet::new();
+    let mut lines = Vec::new();
+    for statement in statements {
+        match statement {
+            Expr::Define(name, _, body) => {
+                let value = expr(body, &scope)?;
+                scope.insert(name.clone());
+                lines.push(format!("let {} = {};", mangle(name), value));
+            }
+            Expr::Assert(lhs, rhs) => {
+                let label = format!("assert {} == {}", lhs, rhs);
+                lines.push(format!("check({}, {}, {:?});", expr(lhs, &scope)?, expr(rhs, &scope)?, label));
+            }
+            _ => {}
+        }
+    }
+    // 마지막 식은 모든 정의가 끝난 뒤에 평가
+    let last = statements.iter()
+        .rfind(|e| !matches!(e, Expr::Define(..) | Expr::Assert(..)));
+    match last {
+        Some(last) => lines.push(format!("println!(\"{{}}\", show(&{}));", expr(last, &scope)?)),
+        None => lines.push("println!(\"()\");".to_string()),
+    }
+
+    let mut out = String::from("// Generated by lintre.\n");
+    out.push_str(RUNTIME);
+    out.push_str("\nfn main() {\n");
+    for line in lines {
+        out.push_str("    ");
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// `scope`는 Rust 지역 변수로 존재하는 이름들
+fn expr(e: &Expr, scope: &HashSet<String>) -> Result<String, String> {
+    match e {
+        Expr::Word(name) => {
+            if scope.contains(name) {
+                Ok(format!("{}.clone()", mangle(name)))
+            } else if Builtin::from_name(name).is_some() {
+                Err(format!("'{}' is not supported by the Rust backend.", name))
+            } else {
+                Ok(format!("sym({:?})", name))
+            }
+        }
+        Expr::Words(words) => {
+            let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+            let mut code = expr(head, scope)?;
+            for arg in args {
+                code = format!("app({}, {})", code, expr(arg, scope)?);
+            }
+            Ok(code)
+        }
+        Expr::Function(params, body) => {
+            let (param, rest) = params.split_first().ok_or("Function without parameters")?;
+            let body_expr = match rest.is_empty() {
+                true => (**body).clone(),
+                false => Expr::Function(rest.to_vec(), body.clone()),
+            };
+            let mut inner = scope.clone();
+            inner.insert(param.clone());
+            let body = expr(&body_expr, &inner)?;
+
+            // 클로저가 가져갈 변수는 미리 복제해 둠
+            let mut captured = free_vars(&body_expr).into_iter()
+                .filter(|v| v != param && scope.contains(v))
+                .collect::<Vec<_>>();
+            captured.sort();
+            let function = format!("fun(move |{}: V| {})", mangle(param), body);
+            if captured.is_empty() {
+                Ok(function)
+            } else {
+                let clones = captured.iter()
+                    .map(|v| format!("let {0} = {0}.clone(); ", mangle(v)))
+                    .collect::<String>();
+                Ok(format!("{{ {}{} }}", clones, function))
+            }
+        }
+        Expr::Paren(inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => expr(inner, scope),
+        Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+            Err("Definitions and asserts are only allowed at the top level".to_string())
+        }
+    }
+}
+
+/// 키워드나 Rust 식별자에 쓸 수 없는 글자와 겹치지 않도록 이름을 바꾼다.
+fn mangle(name: &str) -> String {
+    let mut out = String::from("u_");
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '_' {
+            out.push(ch);
+        } else {
+            out.push_str(&format!("_{:x}_", ch as u32));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use std::process::Command;
+
+    fn compiled(source: &str) -> Result<String, String> {
+        compile(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn generated_program_prints_the_normal_form() {
+        let code = compiled("two = L f x. f (f x); id = L x. x; assert id id == id; two two").unwrap();
+        let dir = std::env::temp_dir().join(format!("lintre-codegen-rust-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), code).unwrap();
+        let status = Command::new("rustc").arg("-o").arg(dir.join("main")).arg(dir.join("main.rs")).status().unwrap();
+        assert!(status.success());
+        let output = Command::new(dir.join("main")).output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "L v0 v1. v0 (v0 (v0 (v0 v1)))\n");
+    }
+
+    #[test]
+    fn builtins_are_rejected() {
+        assert!(compiled("print (L x. x)").is_err());
+    }
+}
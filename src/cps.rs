@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::messages::{self, Msg};
+
+// 값 수준의 builtin 래퍼. 직접 방식의 결과(Church 불리언, 숫자, 리스트)는 CPS 값으로 바꿔서 돌려줌
+const CPS_NAT_SUCC: &str = "(L acc f k. acc f (L g. k (L x k2. g x (L y. f y k2))))";
+const CPS_NAT_ZERO: &str = "(L f k. k (L x k2. k2 x))";
+
+/// 값 호출(call-by-value) CPS 변환. 관리용 redex가 생기지 않도록 한 번에 변환한다.
+/// 최상위 정의와 assert, 마지막 식은 항등 continuation으로 실행되므로 결과를 그대로 평가할 수 있다.
+pub fn transform(program: &Expr) -> Result<Expr, String> {
+    let mut names = HashSet::new();
+    collect_names(program, &mut names);
+    let mut cps = Cps { names, counter: 0, bound: Vec::new() };
+
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    // 정의된 이름은 builtin을 가림
+    for statement in statements {
+        if let Expr::Define(name, _, _) = statement {
+            cps.bound.push(name.clone());
+        }
+    }
+    let mut result = Vec::new();
+    for statement in statements {
+        result.push(match statement {
+            Expr::Define(name, _, body) => Expr::Define(name.clone(), None, Box::new(cps.run(body)?)),
+            Expr::Assert(lhs, rhs) => Expr::Assert(Box::new(cps.run(lhs)?), Box::new(cps.run(rhs)?)),
+            other => cps.run(other)?,
+        });
+    }
+    Ok(match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Expr::Sequence(result),
+    })
+}
+
+type Meta<'a> = Box<dyn FnOnce(&mut Cps, Expr) -> Result<Expr, String> + 'a>;
+
+enum Cont<'a> {
+    // 식 안의 continuation 변수
+    Var(String),
+    // 변환 중에만 존재하는 continuation. 적용하면 바로 식을 만들어냄
+    Meta(Meta<'a>),
+}
+
+struct Cps {
+    names: HashSet<String>,
+    counter: usize,
+    bound: Vec<String>,
+}
+
+impl Cps {
+    fn run(&mut self, expr: &Expr) -> Result<Expr, String> {
+        self.convert(expr, Cont::Meta(Box::new(|_, value| Ok(value))))
+    }
+
+    fn convert<'a>(&mut self, expr: &'a Expr, cont: Cont<'a>) -> Result<Expr, String> {
+        match expr {
+            Expr::Word(name) => {
+                let value = self.word(name)?;
+                self.resume(cont, value)
+            }
+            Expr::Function(params, body) => {
+                let value = self.function(params, body)?;
+                self.resume(cont, value)
+            }
+            Expr::Words(words) => self.application(words, cont),
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.convert(inner, cont),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Definitions and asserts are only allowed at the top level".to_string())
+            }
+        }
+    }
+
+    /// `f a b` = `(f a) b`
+    fn application<'a>(&mut self, words: &'a [Expr], cont: Cont<'a>) -> Result<Expr, String> {
+        let (last, init) = words.split_last().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+        if init.is_empty() {
+            return self.convert(last, cont);
+        }
+        self.application(init, Cont::Meta(Box::new(move |cps: &mut Cps, func| {
+            cps.convert(last, Cont::Meta(Box::new(move |cps: &mut Cps, arg| {
+                let k = cps.reify(cont)?;
+                Ok(Expr::Words(vec![func, to_arg(arg), to_arg(k)]))
+            })))
+        })))
+    }
+
+    /// `L x y. M` = `L x k1. k1 (L y k2. [M] k2)`
+    fn function(&mut self, params: &[String], body: &Expr) -> Result<Expr, String> {
+        let (param, rest) = params.split_first().ok_or("Function without parameters")?;
+        let k = self.fresh("k");
+        self.bound.push(param.clone());
+        let converted = if rest.is_empty() {
+            self.convert(body, Cont::Var(k.clone()))
+        } else {
+            self.function(rest, body)
+                .map(|inner| Expr::Words(vec![Expr::Word(k.clone()), to_arg(inner)]))
+        };
+        self.bound.pop();
+        Ok(Expr::Function(vec![param.clone(), k], Box::new(converted?)))
+    }
+
+    fn word(&mut self, name: &str) -> Result<Expr, String> {
+        if self.bound.iter().any(|b| b == name) {
+            return Ok(Expr::Word(name.to_string()));
+        }
+        let source = match Builtin::from_name(name) {
+            Some(Builtin::Print) => "L x k. k (print x)".to_string(),
+            Some(Builtin::Trace) => "L a k. k (L b k. k (trace a b))".to_string(),
+            Some(Builtin::Eq) => {
+                "L a k. k (L b k. k (eq a b (L t k. k (L f k. k t)) (L t k. k (L f k. k f))))".to_string()
+            }
+            Some(Builtin::ReadNat) => format!("readNat {} {}", CPS_NAT_SUCC, CPS_NAT_ZERO),
+            Some(Builtin::ReadLine) => format!(
+                "readLine (L h t c k. k (L n k2. c (h {} {}) (L m. t c (L g. g n (L r. m r k2))))) (L c k. k (L n k2. k2 n))",
+                CPS_NAT_SUCC, CPS_NAT_ZERO
+            ),
+            _ => return Ok(Expr::Word(name.to_string())),
+        };
+        source.parse()
+    }
+
+    fn resume(&mut self, cont: Cont, value: Expr) -> Result<Expr, String> {
+        match cont {
+            Cont::Var(k) => Ok(Expr::Words(vec![Expr::Word(k), to_arg(value)])),
+            Cont::Meta(f) => f(self, value),
+        }
+    }
+
+    /// continuation을 식으로 만든다: `Meta(f)` = `L v. f(v)`
+    fn reify(&mut self, cont: Cont) -> Result<Expr, String> {
+        match cont {
+            Cont::Var(k) => Ok(Expr::Word(k)),
+            Cont::Meta(f) => {
+                let v = self.fresh("v");
+                let body = f(self, Expr::Word(v.clone()))?;
+                Ok(Expr::Function(vec![v], Box::new(body)))
+            }
+        }
+    }
+
+    /// 프로그램에 나오지 않는 이름만 쓰므로 사용자 변수를 가로채지 않음
+    fn fresh(&mut self, base: &str) -> String {
+        loop {
+            let name = format!("{}{}", base, self.counter);
+            self.counter += 1;
+            if !self.names.contains(&name) {
+                return name;
+            }
+        }
+    }
+}
+
+pub(crate) fn collect_names(expr: &Expr, names: &mut HashSet<String>) {
+    match expr {
+        Expr::Word(w) => {
+            names.insert(w.clone());
+        }
+        Expr::Words(words) | Expr::Sequence(words) => {
+            for e in words {
+                collect_names(e, names);
+            }
+        }
+        Expr::Function(params, body) => {
+            names.extend(params.iter().cloned());
+            collect_names(body, names);
+        }
+        Expr::Define(name, _, inner) => {
+            names.insert(name.clone());
+            collect_names(inner, names);
+        }
+        Expr::Paren(inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => collect_names(inner, names),
+        Expr::Assert(lhs, rhs) => {
+            collect_names(lhs, names);
+            collect_names(rhs, names);
+        }
+    }
+}
+
+fn to_arg(expr: Expr) -> Expr {
+    match expr {
+        Expr::Word(_) => expr,
+        _ => Expr::Paren(Box::new(expr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Config, Interpreter};
+    use crate::normalize;
+    use crate::parser::Parser;
+
+    fn normal_form(program: &Expr) -> Expr {
+        let mut interpreter = Interpreter::new(Config::default());
+        let value = interpreter.eval(program.clone()).unwrap();
+        interpreter.normal_form(&value).unwrap()
+    }
+
+    /// CPS로 바꾼 프로그램을 항등 continuation으로 실행한 결과가 기대하는 값을 바꾼 것과 같은지 봄
+    fn evaluates_like(source: &str, expected: &str) {
+        let program = transform(&Parser::new(source).parse().unwrap()).unwrap();
+        let expected = transform(&Parser::new(expected).parse().unwrap()).unwrap();
+        let (got, expected) = (normal_form(&program), normal_form(&expected));
+        assert!(normalize::alpha_eq(&got, &expected), "{} / {}", got, expected);
+    }
+
+    #[test]
+    fn transformed_programs_evaluate_to_the_same_result() {
+        evaluates_like("id = L x. x; k = L x y. x; k id (id k)", "L x. x");
+        evaluates_like("two = L f x. f (f x); two two", "L f x. f (f (f (f x)))");
+        evaluates_like("(L x y. x) (L a. a) (L b. b b)", "L a. a");
+        evaluates_like("id = L x. x; assert id id == id; id", "L x. x");
+    }
+
+    #[test]
+    fn nested_definitions_are_rejected() {
+        let nested = Expr::Words(vec![
+            Expr::Word("f".to_string()),
+            Expr::Define("x".to_string(), None, Box::new(Expr::Word("y".to_string()))),
+        ]);
+        assert!(transform(&nested).is_err());
+    }
+}
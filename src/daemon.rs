@@ -0,0 +1,289 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use crate::ast::Expr;
+use crate::error::{self, Error};
+use crate::interpreter::{Config, Interpreter, Output};
+use crate::json;
+use crate::log::{self, Target};
+use crate::messages::{self, Msg};
+use crate::parser::{Context, Parser};
+use crate::sandbox::{Limits, Sandbox};
+use crate::scope;
+use crate::server;
+use crate::trace;
+
+// 요청 하나의 최대 크기
+const MAX_FRAME: usize = 1 << 20;
+// 연결마다 도는 스레드의 스택. `serve`와 같은 까닭
+const STACK: usize = 512 << 20;
+
+/// `daemon`의 설정. 연결마다 스레드를 띄우므로 인터프리터 대신 만들 재료만 들고 있음
+pub struct Daemon {
+    // 연결마다 한 번 평가해 두고 요청마다 그 환경에서 시작함
+    pub prelude: Option<Arc<Expr>>,
+    // prelude가 남긴 `data` 선언과 레코드. 요청을 읽을 때 이어받음
+    pub context: Context,
+    pub allow_io: bool,
+    pub limits: Limits,
+}
+
+/// 유닉스 소켓 데몬. 편집기 플러그인이 요청마다 프로세스를 띄우지 않고 이미 읽어 둔 prelude를 다시 쓰게 함.
+///
+/// 주고받는 틀은 4바이트 빅 엔디언 길이 뒤에 그만큼의 UTF-8. 한 연결에서 요청을 여러 번 보낼 수 있음.
+/// 요청은 첫 줄이 종류(`eval`, `trace`, `check`)이고 나머지가 소스. 응답은 JSON 객체 하나
+/// - `eval`: `{"result":…,"output":…,"error":…}`. `result`는 `json::result`의 객체
+/// - `trace`: `{"steps":[{"rule":…,"term":…},…],"complete":…,"name":…,"error":…}`
+/// - `check`: `{"problems":[{"line":…,"column":…,"message":…},…],"error":…}`
+///
+/// 요청마다 prelude만 정의된 환경에서 시작하므로 앞 요청의 정의는 남지 않음
+impl Daemon {
+    pub fn serve(self, path: &str) -> Result<(), Error> {
+        // 앞서 죽은 데몬이 남긴 소켓 파일은 지우고, 다른 파일이면 건드리지 않음
+        if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) && UnixStream::connect(path).is_err() {
+            fs::remove_file(path).map_err(|e| Error::new(error::ErrorKind::Io, format!("Failed to remove the stale socket {}: {}", path, e)))?;
+        }
+        // prelude에 오류가 있으면 연결을 받기 전에 알림
+        self.base().map_err(|e| e.map(|e| format!("Failed to load the prelude: {}", e)))?;
+        let listener = UnixListener::bind(path).map_err(|e| Error::new(error::ErrorKind::Io, format!("Failed to listen on {}: {}", path, e)))?;
+        eprintln!("Listening on {}", path);
+        let daemon = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn(Target::Server, || format!("Failed to accept a connection: {}", e));
+                    continue;
+                }
+            };
+            let daemon = daemon.clone();
+            let spawned = thread::Builder::new()
+                .stack_size(STACK)
+                .spawn(move || daemon.handle(stream));
+            if let Err(e) = spawned {
+                log::warn(Target::Server, || format!("Failed to start a connection thread: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&self, mut stream: UnixStream) {
+        log::debug(Target::Server, || "connection opened".to_string());
+        let base = match self.base() {
+            Ok(base) => base,
+            Err(e) => {
+                let _ = write_frame(&mut stream, &error(&format!("Failed to load the prelude: {}", e)));
+                return;
+            }
+        };
+        loop {
+            let response = match read_frame(&mut stream) {
+                Ok(Some(request)) => self.respond(&base, &request),
+                Ok(None) => {
+                    log::debug(Target::Server, || "connection closed".to_string());
+                    return;
+                }
+                Err(e) => {
+                    log::info(Target::Server, || format!("rejected a request: {}", e));
+                    // 틀이 어긋나면 다음 요청의 시작을 알 수 없으므로 연결을 닫음
+                    let _ = write_frame(&mut stream, &error(&e));
+                    return;
+                }
+            };
+            if write_frame(&mut stream, &response).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn config(&self) -> Config {
+        Config { allow_io: self.allow_io, ..Config::default() }
+    }
+
+    /// prelude를 평가한 인터프리터. prelude가 `print`로 낸 출력은 버림
+    fn base(&self) -> Result<Interpreter, Error> {
+        let config = Config { output: Output::Capture(Rc::default()), ..self.config() };
+        let mut base = Interpreter::new(config);
+        if let Some(prelude) = &self.prelude {
+            base.eval(Expr::clone(prelude))?;
+        }
+        Ok(base)
+    }
+
+    fn respond(&self, base: &Interpreter, request: &str) -> String {
+        let started = Instant::now();
+        let (kind, source) = request.split_once('\n').unwrap_or((request, ""));
+        let response = match kind.trim() {
+            "eval" => self.eval(base, source),
+            "trace" => self.trace(source),
+            "check" => self.check(source),
+            other => error(&format!("Unknown request '{}' (expected eval, trace or check)", other)),
+        };
+        log::info(Target::Server, || format!("{} in {:.1} ms", kind.trim(), started.elapsed().as_secs_f64() * 1000.0));
+        response
+    }
+
+    fn eval(&self, base: &Interpreter, source: &str) -> String {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut interpreter = base.fork(Config { output: Output::Capture(output.clone()), ..self.config() });
+        let sandbox = Rc::new(Sandbox::new(self.limits));
+        interpreter.set_sandbox(Some(sandbox.clone()));
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| server::evaluate(&mut interpreter, &mut self.context.clone(), source, &sandbox)));
+        let (result, error) = match outcome {
+            Ok(Ok(result)) => (result, "null".to_string()),
+            Ok(Err(e)) => ("null".to_string(), json::string(&e)),
+            Err(_) => {
+                log::error(Target::Server, || "an evaluation panicked".to_string());
+                ("null".to_string(), json::string("The evaluation panicked."))
+            }
+        };
+        let output = output.borrow();
+        format!("{{\"result\":{},\"output\":{},\"error\":{}}}", result, json::string(&output), error)
+    }
+
+    /// prelude의 정의를 앞에 붙인 프로그램으로 유도 과정을 만듦. `eval`과 같은 제한을 걸고,
+    /// 그래도 패닉하면 연결 스레드가 죽지 않게 오류로 돌려줌
+    fn trace(&self, source: &str) -> String {
+        let sandbox = Sandbox::new(self.limits);
+        let derivation = self.parse(source, false)
+            .map(|ast| Expr::Sequence([self.statements(), statements(ast)].concat()))
+            .and_then(|program| {
+                panic::catch_unwind(AssertUnwindSafe(|| trace::derivation_within(&program, Some(&sandbox)).map_err(|e| e.to_string())))
+                    .unwrap_or_else(|_| {
+                        log::error(Target::Server, || "a trace panicked".to_string());
+                        Err("The trace panicked.".to_string())
+                    })
+            });
+        let derivation = match derivation {
+            Ok(derivation) => derivation,
+            Err(e) => return error(&e),
+        };
+        let steps = derivation.steps.iter()
+            .map(|(rule, term)| format!(
+                "{{\"rule\":{},\"term\":{}}}",
+                rule.as_ref().map_or("null".to_string(), |rule| json::string(&rule.summary())),
+                json::string(&term.to_string()),
+            ))
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"steps\":[{}],\"complete\":{},\"name\":{},\"error\":null}}",
+            steps.join(","),
+            derivation.complete,
+            derivation.name.as_deref().map_or("null".to_string(), json::string),
+        )
+    }
+
+    /// `lintre check`처럼 평가하지 않고 이름을 확인함. prelude의 정의는 정의된 것으로 봄.
+    /// 식을 줄이지 않으므로 걸리는 제한은 파싱할 때의 `--max-depth`뿐
+    fn check(&self, source: &str) -> String {
+        let ast = match self.parse(source, true) {
+            Ok(ast) => ast,
+            Err(e) => return error(&e),
+        };
+        let prelude = Expr::Sequence(self.statements());
+        let found = scope::unresolved(&[prelude, ast]).pop().unwrap_or_default();
+        let problems = found.iter()
+            .map(|unresolved| {
+                let message = match unresolved.early {
+                    true => format!("'{}' is used before its definition is evaluated", unresolved.name),
+                    false => format!("unbound name '{}'", unresolved.name),
+                };
+                let (line, column) = unresolved.span
+                    .map_or(("null".to_string(), "null".to_string()), |s| (s.line.to_string(), s.column.to_string()));
+                format!("{{\"line\":{},\"column\":{},\"message\":{}}}", line, column, json::string(&message))
+            })
+            .collect::<Vec<_>>();
+        format!("{{\"problems\":[{}],\"error\":null}}", problems.join(","))
+    }
+
+    fn parse(&self, source: &str, spans: bool) -> Result<Expr, String> {
+        Parser::new(source).max_depth(self.limits.depth).spans(spans).with_context(self.context.clone()).parse()
+            .map_err(|e| messages::text(Msg::ParseError, &[&e]))
+    }
+
+    fn statements(&self) -> Vec<Expr> {
+        self.prelude.as_deref().cloned().map_or_else(Vec::new, statements)
+    }
+}
+
+fn statements(program: Expr) -> Vec<Expr> {
+    match program {
+        Expr::Sequence(exprs) => exprs,
+        other => vec![other],
+    }
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\":{}}}", json::string(message))
+}
+
+/// 틀 하나를 읽음. 연결이 틀 사이에서 닫혔으면 `None`
+fn read_frame(stream: &mut UnixStream) -> Result<Option<String>, String> {
+    let mut length = [0; 4];
+    match stream.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    }
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(format!("The request is larger than {} bytes", MAX_FRAME));
+    }
+    let mut payload = vec![0; length];
+    stream.read_exact(&mut payload).map_err(|e| e.to_string())?;
+    String::from_utf8(payload).map(Some).map_err(|_| "The request is not valid UTF-8".to_string())
+}
+
+fn write_frame(stream: &mut UnixStream, payload: &str) -> std::io::Result<()> {
+    stream.write_all(&(payload.len() as u32).to_be_bytes())?;
+    stream.write_all(payload.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const GROWING: &str = "d = L x. x x x x;\nd (L y. y y y y)";
+
+    fn respond(limits: Limits, request: &str) -> String {
+        let daemon = Daemon { prelude: None, context: Context::default(), allow_io: false, limits };
+        let base = daemon.base().unwrap();
+        daemon.respond(&base, request)
+    }
+
+    #[test]
+    fn trace_honors_the_term_size_limit() {
+        let limits = Limits { term_size: 200, ..Limits::default() };
+        let response = respond(limits, &format!("trace\n{}", GROWING));
+        assert!(response.contains("did not finish within 200 nodes"), "{}", response);
+    }
+
+    #[test]
+    fn trace_honors_the_step_limit() {
+        let limits = Limits { steps: 10, ..Limits::default() };
+        let response = respond(limits, &format!("trace\n{}", GROWING));
+        assert!(response.contains("did not finish within 10 steps"), "{}", response);
+    }
+
+    #[test]
+    fn trace_honors_the_time_limit() {
+        let limits = Limits { time: Duration::ZERO, ..Limits::default() };
+        let response = respond(limits, &format!("trace\n{}", GROWING));
+        assert!(response.contains("did not finish within 0 ms"), "{}", response);
+    }
+
+    #[test]
+    fn trace_of_a_growing_term_returns_an_error_instead_of_crashing() {
+        let response = respond(Limits::default(), &format!("trace\n{}", GROWING));
+        assert!(response.starts_with("{\"error\":"), "{}", response);
+    }
+}
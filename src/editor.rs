@@ -0,0 +1,332 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::parser::is_name_char;
+
+const HISTORY_FILE: &str = ".lintre_history";
+const HISTORY_LIMIT: usize = 1000;
+
+/// REPL의 한 줄 편집기. 화살표로 커서와 기록을 옮기고, Ctrl-K/U/W로 지운 글자를 Ctrl-Y로 되살린다.
+/// Tab은 커서 앞 낱말을 주어진 이름들로 완성한다.
+/// 기록은 `~/.lintre_history`에 남아 다음 실행에서도 쓸 수 있다.
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>,
+    killed: Vec<char>,
+}
+
+/// 한 줄을 읽는 동안만 터미널을 raw 모드로 바꿔 두고, 끝나면(패닉이어도) 원래대로 돌려놓음
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        let output = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other("stty -g failed"));
+        }
+        let saved = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        stty(&["raw", "-echo"])?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.saved.as_str()]);
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<()> {
+    match Command::new("stty").args(args).stdin(Stdio::inherit()).status()?.success() {
+        true => Ok(()),
+        false => Err(io::Error::other("stty failed")),
+    }
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    // Ctrl와 함께 누른 글자
+    Ctrl(u8),
+    Unknown,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        let history = path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let skip = history.len().saturating_sub(HISTORY_LIMIT);
+        Editor { history: history[skip..].to_vec(), path, killed: Vec::new() }
+    }
+
+    /// 입력이 끝났으면(Ctrl-D, EOF) `None`. Ctrl-C는 쓰던 줄을 버리고 빈 줄을 돌려준다.
+    /// `completions`는 Tab으로 완성할 후보 이름들
+    pub fn read_line(&mut self, prompt: &str, completions: &[String]) -> io::Result<Option<String>> {
+        if !io::stdin().is_terminal() {
+            return read_plain_line(prompt);
+        }
+        let raw = match RawMode::enter() {
+            Ok(raw) => raw,
+            // stty가 없으면 편집 없이 읽음
+            Err(_) => return read_plain_line(prompt),
+        };
+        let result = self.edit(prompt, completions);
+        drop(raw);
+        result
+    }
+
+    /// 기록 끝에 붙이고 파일에도 바로 덧붙임. 바로 앞과 같은 줄은 한 번만 남김
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) || line.contains('\n') {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn edit(&mut self, prompt: &str, completions: &[String]) -> io::Result<Option<String>> {
+        let mut line: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // 기록을 위아래로 오가는 동안 쓰던 줄은 맨 아래 칸으로 보관
+        let mut index = self.history.len();
+        let mut draft = Vec::new();
+        let mut out = io::stdout();
+        redraw(&mut out, prompt, &line, cursor)?;
+
+        loop {
+            match read_key()? {
+                None => return Ok(None),
+                Some(Key::Enter) => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Some(Key::Char(ch)) => {
+                    line.insert(cursor, ch);
+                    cursor += 1;
+                }
+                Some(Key::Tab) => {
+                    let mut start = cursor;
+                    while start > 0 && is_name_char(line[start - 1]) {
+                        start -= 1;
+                    }
+                    // `:quit` 같은 REPL 명령
+                    if start > 0 && line[start - 1] == ':' {
+                        start -= 1;
+                    }
+                    let prefix = line[start..cursor].iter().collect::<String>();
+                    let matches = completions.iter()
+                        .filter(|name| name.starts_with(&prefix))
+                        .collect::<Vec<_>>();
+                    let common = common_prefix(&matches);
+                    if common.chars().count() > prefix.chars().count() {
+                        for ch in common.chars().skip(prefix.chars().count()) {
+                            line.insert(cursor, ch);
+                            cursor += 1;
+                        }
+                    } else if matches.len() > 1 {
+                        // 더 채울 글자가 없으면 후보를 보여 줌
+                        let list = matches.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+                        write!(out, "\r\n{}\r\n", list.join("  "))?;
+                    }
+                }
+                Some(Key::Backspace) | Some(Key::Ctrl(b'H')) if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Some(Key::Delete) if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Some(Key::Left) | Some(Key::Ctrl(b'B')) => cursor = cursor.saturating_sub(1),
+                Some(Key::Right) | Some(Key::Ctrl(b'F')) => cursor = (cursor + 1).min(line.len()),
+                Some(Key::Home) | Some(Key::Ctrl(b'A')) => cursor = 0,
+                Some(Key::End) | Some(Key::Ctrl(b'E')) => cursor = line.len(),
+                Some(Key::Up) | Some(Key::Ctrl(b'P')) if index > 0 => {
+                    if index == self.history.len() {
+                        draft = line.clone();
+                    }
+                    index -= 1;
+                    line = self.history[index].chars().collect();
+                    cursor = line.len();
+                }
+                Some(Key::Down) | Some(Key::Ctrl(b'N')) if index < self.history.len() => {
+                    index += 1;
+                    line = match self.history.get(index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => draft.clone(),
+                    };
+                    cursor = line.len();
+                }
+                Some(Key::Ctrl(b'K')) => {
+                    self.killed = line.split_off(cursor);
+                }
+                Some(Key::Ctrl(b'U')) => {
+                    self.killed = line.drain(..cursor).collect();
+                    cursor = 0;
+                }
+                Some(Key::Ctrl(b'W')) => {
+                    // 커서 앞의 공백과 그 앞 낱말 하나
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    self.killed = line.drain(start..cursor).collect();
+                    cursor = start;
+                }
+                Some(Key::Ctrl(b'Y')) => {
+                    for &ch in &self.killed {
+                        line.insert(cursor, ch);
+                        cursor += 1;
+                    }
+                }
+                Some(Key::Ctrl(b'C')) => {
+                    write!(out, "^C\r\n")?;
+                    out.flush()?;
+                    return Ok(Some(String::new()));
+                }
+                Some(Key::Ctrl(b'D')) => {
+                    if line.is_empty() {
+                        write!(out, "\r\n")?;
+                        out.flush()?;
+                        return Ok(None);
+                    }
+                    if cursor < line.len() {
+                        line.remove(cursor);
+                    }
+                }
+                _ => {}
+            }
+            redraw(&mut out, prompt, &line, cursor)?;
+        }
+    }
+}
+
+fn common_prefix(names: &[&String]) -> String {
+    let mut common = match names.first() {
+        Some(first) => first.to_string(),
+        None => return String::new(),
+    };
+    for name in &names[1..] {
+        let len = common.chars()
+            .zip(name.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        common.truncate(len);
+    }
+    common
+}
+
+fn read_plain_line(prompt: &str) -> io::Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+/// 줄 전체를 다시 쓰고 커서를 제자리로 옮김. 글자 하나의 폭을 한 칸으로 봄
+fn redraw(out: &mut impl Write, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+    let text = line.iter().collect::<String>();
+    write!(out, "\r{}{}\x1b[K", prompt, text)?;
+    if cursor < line.len() {
+        write!(out, "\x1b[{}D", line.len() - cursor)?;
+    }
+    out.flush()
+}
+
+fn read_byte() -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match io::stdin().lock().read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+fn read_key() -> io::Result<Option<Key>> {
+    let byte = match read_byte()? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    Ok(Some(match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        127 => Key::Backspace,
+        0x1b => read_escape()?,
+        1..=26 => Key::Ctrl(b'A' + byte - 1),
+        0..=31 => Key::Unknown,
+        _ => {
+            // UTF-8의 첫 바이트로 남은 바이트 수를 앎
+            let len = match byte {
+                0xc0..=0xdf => 1,
+                0xe0..=0xef => 2,
+                0xf0..=0xf7 => 3,
+                _ => 0,
+            };
+            let mut bytes = vec![byte];
+            for _ in 0..len {
+                bytes.extend(read_byte()?);
+            }
+            match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+                Some(ch) => Key::Char(ch),
+                None => Key::Unknown,
+            }
+        }
+    }))
+}
+
+/// `ESC [ A`, `ESC [ 3 ~`, `ESC O H` 같은 방향키·기능키
+fn read_escape() -> io::Result<Key> {
+    match read_byte()? {
+        Some(b'[') | Some(b'O') => {}
+        _ => return Ok(Key::Unknown),
+    }
+    let mut params = Vec::new();
+    loop {
+        match read_byte()? {
+            Some(byte @ b'0'..=b'9') | Some(byte @ b';') => params.push(byte),
+            Some(b'A') => return Ok(Key::Up),
+            Some(b'B') => return Ok(Key::Down),
+            Some(b'C') => return Ok(Key::Right),
+            Some(b'D') => return Ok(Key::Left),
+            Some(b'H') => return Ok(Key::Home),
+            Some(b'F') => return Ok(Key::End),
+            Some(b'~') => return Ok(match params.as_slice() {
+                b"1" | b"7" => Key::Home,
+                b"4" | b"8" => Key::End,
+                b"3" => Key::Delete,
+                _ => Key::Unknown,
+            }),
+            _ => return Ok(Key::Unknown),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::ast::Expr;
+use crate::expr;
+
+/// `L f x. f (f (... x))`
+pub fn church_numeral(n: usize) -> Expr {
+    let mut body = expr!(x);
+    for _ in 0..n {
+        body = expr!(f #body);
+    }
+    expr!(L f x. #body)
+}
+
+/// `L c n. c e1 (c e2 (... n))`
+pub fn church_list(items: Vec<Expr>) -> Expr {
+    let mut body = expr!(n);
+    for item in items.into_iter().rev() {
+        body = expr!(c #item #body);
+    }
+    expr!(L c n. #body)
+}
+
+/// 문자열을 문자 코드(Church 숫자)의 리스트로 인코딩한다.
+pub fn church_string(s: &str) -> Expr {
+    church_list(s.chars().map(|ch| church_numeral(ch as usize)).collect())
+}
@@ -0,0 +1,73 @@
+use crate::ast::Expr;
+
+/// 노드 수가 `size`인 닫힌 항을 모두 만든다. 변수 이름은 묶는 깊이에 따라 x0, x1, …
+pub fn closed_terms(size: usize) -> Vec<Expr> {
+    terms(size, 0)
+}
+
+fn terms(size: usize, depth: usize) -> Vec<Expr> {
+    match size {
+        0 => Vec::new(),
+        1 => (0..depth).map(|i| Expr::Word(var(i))).collect(),
+        _ => {
+            let mut result = terms(size - 1, depth + 1).into_iter()
+                .map(|body| Expr::Function(vec![var(depth)], Box::new(body)))
+                .collect::<Vec<_>>();
+            for left in 1..size - 1 {
+                let funcs = terms(left, depth);
+                if funcs.is_empty() {
+                    continue;
+                }
+                let args = terms(size - 1 - left, depth);
+                for func in &funcs {
+                    for arg in &args {
+                        result.push(Expr::Words(vec![func.clone(), arg.clone()]));
+                    }
+                }
+            }
+            result
+        }
+    }
+}
+
+fn var(index: usize) -> String {
+    format!("x{}", index)
+}
+
+/// de Bruijn 표기: `λ λ 1 0`
+pub fn to_de_bruijn(expr: &Expr) -> String {
+    de_bruijn_with(expr, &mut Vec::new())
+}
+
+fn de_bruijn_with(expr: &Expr, binders: &mut Vec<String>) -> String {
+    match expr {
+        Expr::Word(w) => match binders.iter().rev().position(|b| b == w) {
+            Some(index) => index.to_string(),
+            None => w.clone(),
+        },
+        Expr::Function(params, body) => {
+            binders.extend(params.iter().cloned());
+            let body = de_bruijn_with(body, binders);
+            binders.truncate(binders.len() - params.len());
+            format!("{}{}", "λ ".repeat(params.len()), body)
+        }
+        Expr::Words(words) => words.iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let source = de_bruijn_with(e, binders);
+                match e {
+                    Expr::Word(_) => source,
+                    Expr::Words(_) if i == 0 => source,
+                    _ => format!("({})", source),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Expr::Paren(inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => de_bruijn_with(inner, binders),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::ast::Span;
+use crate::messages::{self, Msg};
+
+/// 평가가 실패한 까닭의 종류. 종료 코드를 고를 때 씀
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// 평가되기 전에 쓰인 정의
+    Unbound,
+    /// 무한 루프로 보이거나 정해진 단계, 크기, 깊이, 시간 안에 끝나지 않음
+    StepLimit,
+    /// 입출력 실패나 `--allow-io` 없이 부른 입출력
+    Io,
+    /// Ctrl-C로 멈춤
+    Interrupted,
+    Other,
+}
+
+/// 오류가 난 곳에서 종류를 정해 둔 오류. 종료 코드는 메시지의 글이 아니라 `kind`로 고르므로
+/// 메시지를 고치거나 옮겨도 달라지지 않음
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    // 오류가 난 식의 원문 자리. 안쪽 식부터 붙으므로 가장 좁은 자리가 남음
+    pub span: Option<Span>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+        Error { kind, message: message.into(), span: None }
+    }
+
+    pub fn step_limit(message: impl Into<String>) -> Error {
+        Error::new(ErrorKind::StepLimit, message)
+    }
+
+    /// 자리가 없으면 `span`을 붙임. 멈춤 요청과 단계 제한은 어느 한 식의 탓이 아니므로 그대로 둠
+    pub fn at(mut self, span: Span) -> Error {
+        if self.span.is_none() && !matches!(self.kind, ErrorKind::Interrupted | ErrorKind::StepLimit) {
+            self.span = Some(span);
+        }
+        self
+    }
+
+    /// 종류와 자리는 그대로 두고 메시지만 바꿈. 앞에 파일 이름을 붙일 때 등
+    pub fn map(mut self, f: impl FnOnce(String) -> String) -> Error {
+        self.message = f(self.message);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(span) = self.span {
+            write!(f, "\n{}", messages::text(Msg::At, &[&span.line, &span.column]))?;
+        }
+        Ok(())
+    }
+}
+
+/// 종류를 따로 정하지 않은 오류
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::new(ErrorKind::Other, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::new(ErrorKind::Other, message)
+    }
+}
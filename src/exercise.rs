@@ -0,0 +1,185 @@
+use std::fs;
+
+use crate::interpreter::{Config, Interpreter};
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::parser::Parser;
+use crate::style::{self, Stream};
+
+/// 과제 파일의 `[[exercise]]` 하나. 학생 파일의 정의로 `prompt`를 평가한 정규형이 `expect`와 α-동치여야 함
+pub struct Exercise {
+    pub name: String,
+    pub prompt: String,
+    pub expect: String,
+    // 틀렸을 때 보여 줄 말. 정답 항은 보여 주지 않음
+    pub hint: Option<String>,
+    // `define = "add, mul"`: 학생 파일이 정의해야 하는 이름. 틀렸을 때 이 중 빠진 것을 알려 줌
+    pub define: Vec<String>,
+}
+
+/// 과제 파일을 읽는다. TOML 중 이 용도에 필요한 것만 받음:
+/// `[[exercise]]` 머리, `키 = "문자열"`(또는 `'문자열'`), `#` 주석
+pub fn parse_spec(text: &str) -> Result<Vec<Exercise>, String> {
+    let mut exercises = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+    for (n, line) in text.lines().enumerate() {
+        let at = |message: &str| format!("line {}: {}", n + 1, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "[[exercise]]" {
+            if let Some(fields) = current.take() {
+                exercises.push(exercise(fields, exercises.len())?);
+            }
+            current = Some(Vec::new());
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(at("expected '[[exercise]]' or 'key = \"value\"'"));
+        };
+        let key = key.trim();
+        if !matches!(key, "name" | "prompt" | "expect" | "hint" | "define") {
+            return Err(at(&format!("unknown key '{}' (keys: name, prompt, expect, hint, define)", key)));
+        }
+        let fields = current.as_mut().ok_or_else(|| at("keys must come after '[[exercise]]'"))?;
+        if fields.iter().any(|(k, _)| k == key) {
+            return Err(at(&format!("duplicate key '{}'", key)));
+        }
+        fields.push((key.to_string(), string_value(value.trim()).map_err(|e| at(&e))?));
+    }
+    if let Some(fields) = current {
+        exercises.push(exercise(fields, exercises.len())?);
+    }
+    Ok(exercises)
+}
+
+fn exercise(mut fields: Vec<(String, String)>, index: usize) -> Result<Exercise, String> {
+    let mut take = |key: &str| fields.iter()
+        .position(|(k, _)| k == key)
+        .map(|i| fields.remove(i).1);
+    let name = take("name").unwrap_or_else(|| format!("#{}", index + 1));
+    let missing = |key: &str| format!("{}: missing '{}'", name, key);
+    Ok(Exercise {
+        prompt: take("prompt").ok_or_else(|| missing("prompt"))?,
+        expect: take("expect").ok_or_else(|| missing("expect"))?,
+        hint: take("hint"),
+        define: take("define").map_or_else(Vec::new, |names| names.split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect()),
+        name,
+    })
+}
+
+/// 따옴표로 감싼 값. 큰따옴표 안에서는 `\"`, `\\`, `\n`, `\t`를 풀고, 작은따옴표 안은 그대로 씀.
+/// 닫는 따옴표 뒤에는 주석만 올 수 있음
+fn string_value(text: &str) -> Result<String, String> {
+    let mut chars = text.chars();
+    let quote = chars.next().filter(|c| *c == '"' || *c == '\'')
+        .ok_or("values must be quoted strings")?;
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some(c) if c == quote => break,
+            Some('\\') if quote == '"' => match chars.next() {
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                _ => return Err("unknown escape in string".to_string()),
+            },
+            Some(c) => value.push(c),
+        }
+    }
+    let rest = chars.as_str().trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected '{}' after the string", rest));
+    }
+    Ok(value)
+}
+
+/// 학생 파일을 평가한 뒤 과제마다 통과했는지 출력하고, 모두 통과했는지 돌려준다.
+/// 파일이 중간에 실패해도 그 앞까지의 정의로 과제를 확인함
+pub fn run_exercises(exercises: &[Exercise], file: &str, config: Config) -> bool {
+    let mut interpreter = Interpreter::new(config);
+    let loaded = fs::read_to_string(file)
+        .map_err(|e| format!("Failed to read: {}", e))
+        .and_then(|source| Parser::new(&source).parse().map_err(|e| messages::text(Msg::ParseError, &[&e])))
+        .and_then(|ast| interpreter.eval(ast).map_err(|e| e.to_string()));
+    if let Err(e) = &loaded {
+        println!("{}: {}", file, style::error(Stream::Stdout, e));
+    }
+
+    let mut passed = 0;
+    for exercise in exercises {
+        match check(&mut interpreter, exercise) {
+            Ok(()) => {
+                println!("exercise {} ... {}", exercise.name, style::result(Stream::Stdout, "ok"));
+                passed += 1;
+            }
+            Err(notes) => {
+                println!("exercise {} ... {}", exercise.name, style::error(Stream::Stdout, "FAILED"));
+                for note in notes {
+                    println!("    {}", note);
+                }
+            }
+        }
+    }
+
+    let status = match passed == exercises.len() {
+        true => style::result(Stream::Stdout, "ok"),
+        false => style::error(Stream::Stdout, "FAILED"),
+    };
+    println!();
+    println!("exercises: {}. {} of {} passed", status, passed, exercises.len());
+    passed == exercises.len()
+}
+
+/// 틀렸으면 학생에게 보여 줄 줄들을 돌려줌: 학생 결과나 오류, `define`에 있지만 정의하지 않은 이름, 과제의 힌트
+fn check(interpreter: &mut Interpreter, exercise: &Exercise) -> Result<(), Vec<String>> {
+    let parse = |text: &str| Parser::new(text).parse().map_err(|e| vec![format!("error in the exercise: {}", e)]);
+    let prompt = parse(&exercise.prompt)?;
+    let expected = normalize::normalize(&parse(&exercise.expect)?)
+        .map_err(|e| vec![format!("error in the exercise: {}", e)])?;
+
+    let mut notes = match interpreter.eval(prompt).and_then(|value| interpreter.normal_form(&value)) {
+        Ok(actual) if normalize::alpha_eq(&actual, &expected) => return Ok(()),
+        Ok(actual) => vec![format!("your result: {}", normalize::canonical_letters(&actual))],
+        Err(e) => vec![format!("error: {}", e)],
+    };
+    // 프롬프트의 자유 변수 중에는 `a`처럼 일부러 열어 둔 자리도 있으므로, 과제가 정의하라고 한 이름만 살핌
+    let defined = interpreter.names();
+    for name in exercise.define.iter().filter(|name| !defined.contains(name)) {
+        notes.push(format!("hint: '{}' is not defined in your file", name));
+    }
+    if let Some(hint) = &exercise.hint {
+        notes.push(format!("hint: {}", hint));
+    }
+    Err(notes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn student(source: &str) -> Interpreter {
+        let mut interpreter = Interpreter::new(Config::default());
+        interpreter.eval(Parser::new(source).parse().unwrap()).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn hints_only_name_the_definitions_the_exercise_asks_for() {
+        let spec = "[[exercise]]\nname = \"k\"\nprompt = \"k a\"\nexpect = \"L x. x\"\ndefine = \"k, id\"\n";
+        let exercises = parse_spec(spec).unwrap();
+        assert_eq!(exercises[0].define, ["k", "id"]);
+
+        let notes = check(&mut student("k = L x y. x;"), &exercises[0]).unwrap_err();
+        assert!(notes.contains(&"hint: 'id' is not defined in your file".to_string()), "{:?}", notes);
+        // 프롬프트에 열어 둔 `a`는 학생이 정의할 이름이 아님
+        assert!(!notes.iter().any(|note| note.contains("'a'")), "{:?}", notes);
+    }
+}
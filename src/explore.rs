@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::interrupt;
+use crate::messages::{self, Msg};
+use crate::normalize;
+
+/// `explore`가 찾은 것. 정규형마다 가장 짧은 축약 길이가 붙음
+pub struct Exploration {
+    pub normal_forms: Vec<(Expr, usize)>,
+    // 서로 다른(α-동치가 아닌) 항을 몇 개 봤는지
+    pub explored: usize,
+    // 닿을 수 있는 항을 모두 봤는지. `limit`에 걸리거나 너무 커서 건너뛴 항이 있으면 거짓
+    pub complete: bool,
+    // `MAX_TERM_SIZE`보다 커서 더 줄여 보지 않은 항의 수
+    pub oversized: usize,
+}
+
+// 이보다 노드가 많은 항은 더 줄여 보지 않음. 항이 커질수록 축약 자리와 비교할 노드가 함께 늘어서,
+// 자라기만 하는 항에서는 항 하나를 보는 데 드는 시간이 끝없이 늘어남
+pub const MAX_TERM_SIZE: usize = 500;
+
+/// 프로그램의 마지막 식에 앞의 정의를 모두 대입해서 탐색할 항 하나로 만든다.
+/// 내장 함수는 자유 변수로 남음
+pub fn program_term(program: &Expr) -> Result<Expr, Error> {
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut defined = HashMap::new();
+    let mut term = None;
+    for statement in statements {
+        match statement.unlocated() {
+            Expr::Define(name, _, body) => {
+                let body = normalize::substitute(body, &defined);
+                defined.insert(name.clone(), body);
+            }
+            Expr::Assert(..) => {}
+            other => term = Some(normalize::substitute(other, &defined)),
+        }
+    }
+    term.ok_or_else(|| "No expression to explore.".into())
+}
+
+/// 모든 β-축약 자리를 너비 우선으로 골라 가며 닿을 수 있는 정규형을 모두 찾는다.
+/// 처치-로서 정리대로라면 정규형은 많아야 하나. 너비 우선이므로 처음 만난 길이가 가장 짧은 길이임.
+/// α-동치인 항은 한 번만 보고, 서로 다른 항을 `limit`개 보면 멈춤. `MAX_TERM_SIZE`보다 큰 항은 세기만 하고 건너뜀
+pub fn explore(term: &Expr, limit: usize) -> Result<Exploration, Error> {
+    let start = normalize::canonical(&core(term)?);
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    let mut normal_forms = Vec::new();
+    let mut oversized = 0;
+    while let Some((term, length)) = queue.pop_front() {
+        interrupt::check(seen.len(), || sugar(&term).to_string())?;
+        if term.size() > MAX_TERM_SIZE {
+            oversized += 1;
+            continue;
+        }
+        let next = reducts(&term)?;
+        if next.is_empty() {
+            normal_forms.push((sugar(&term), length));
+            continue;
+        }
+        for reduct in next {
+            let reduct = normalize::canonical(&reduct);
+            if seen.contains(&reduct) {
+                continue;
+            }
+            if seen.len() >= limit {
+                return Ok(Exploration { normal_forms, explored: seen.len(), complete: false, oversized });
+            }
+            seen.insert(reduct.clone());
+            queue.push_back((reduct, length + 1));
+        }
+    }
+    Ok(Exploration { normal_forms, explored: seen.len(), complete: oversized == 0, oversized })
+}
+
+/// 괄호와 자리 정보, 타입을 떼고 람다는 매개변수 하나씩, 적용은 두 항씩으로 펼친 식
+pub(crate) fn core(expr: &Expr) -> Result<Expr, String> {
+    Ok(match expr {
+        Expr::Word(_) => expr.clone(),
+        Expr::Paren(inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => core(inner)?,
+        Expr::Function(params, body) => params.iter().rev()
+            .fold(core(body)?, |body, p| Expr::Function(vec![p.clone()], Box::new(body))),
+        Expr::Words(words) => {
+            let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+            let mut term = core(head)?;
+            for arg in args {
+                term = Expr::Words(vec![term, core(arg)?]);
+            }
+            term
+        }
+        Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+            return Err("Expected a single expression, not a definition or statement.".to_string());
+        }
+    })
+}
+
+/// β-축약 한 번으로 갈 수 있는 항을 모두. 정규형이면 비어 있음
+fn reducts(term: &Expr) -> Result<Vec<Expr>, String> {
+    let mut result = Vec::new();
+    match term {
+        Expr::Function(params, body) => {
+            for body in reducts(body)? {
+                result.push(Expr::Function(params.clone(), Box::new(body)));
+            }
+        }
+        Expr::Words(pair) => {
+            let [func, arg] = pair.as_slice() else {
+                return Err(messages::text(Msg::EmptyWords, &[]));
+            };
+            if let Expr::Function(params, body) = func {
+                let mapping = HashMap::from([(params[0].clone(), arg.clone())]);
+                // 대입하면서 붙은 괄호를 다시 떼어 냄
+                result.push(core(&normalize::substitute(body, &mapping))?);
+            }
+            for func in reducts(func)? {
+                result.push(Expr::Words(vec![func, arg.clone()]));
+            }
+            for arg in reducts(arg)? {
+                result.push(Expr::Words(vec![func.clone(), arg]));
+            }
+        }
+        _ => {}
+    }
+    Ok(result)
+}
+
+/// `core`로 펼친 식을 다시 여러 매개변수의 람다와 여러 항의 적용으로 모음
+pub(crate) fn sugar(term: &Expr) -> Expr {
+    match term {
+        Expr::Function(params, body) => match sugar(body) {
+            Expr::Function(inner, body) => Expr::Function([params.clone(), inner].concat(), body),
+            body => Expr::Function(params.clone(), Box::new(body)),
+        },
+        Expr::Words(pair) => {
+            let arg = sugar(&pair[1]).into_arg();
+            match sugar(&pair[0]) {
+                Expr::Words(mut words) => {
+                    words.push(arg);
+                    Expr::Words(words)
+                }
+                func => Expr::Words(vec![func, arg]),
+            }
+        }
+        _ => term.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn term(source: &str) -> Expr {
+        program_term(&Parser::new(source).parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn finds_the_single_normal_form() {
+        let found = explore(&term("(L x y. x) (L a. a) ((L b. b) (L c. c))"), 10_000).unwrap();
+        assert!(found.complete);
+        assert_eq!(found.normal_forms.len(), 1);
+    }
+
+    #[test]
+    fn growing_terms_are_skipped_instead_of_explored() {
+        let found = explore(&term("d = L x. x x x x; d (L y. y y y y)"), 10_000).unwrap();
+        assert!(!found.complete);
+        assert!(found.oversized > 0);
+        assert!(found.normal_forms.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, ErrorKind};
+
+const EXTENSION: &str = "ltr";
+
+/// 명령줄의 입력들을 평가할 파일 목록으로 펼친다.
+/// 디렉터리는 그 아래의 모든 `.ltr` 파일로, `*`/`?`/`**` 패턴은 일치하는 파일로 바뀌고,
+/// 각 입력 안에서는 경로 순으로 정렬된다.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<String>, Error> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let mut matched = if input == "-" {
+            vec![PathBuf::from(input)]
+        } else if is_pattern(input) {
+            expand_pattern(input)?
+        } else if Path::new(input).is_dir() {
+            let mut found = Vec::new();
+            collect_dir(Path::new(input), &mut found)?;
+            found
+        } else {
+            vec![PathBuf::from(input)]
+        };
+        if matched.is_empty() {
+            return Err(Error::from(format!("No files match '{}'", input)));
+        }
+        matched.sort();
+        for path in matched {
+            let path = path.display().to_string();
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?'])
+}
+
+fn collect_dir(dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for path in read_dir(dir)? {
+        if is_real_dir(&path) {
+            collect_dir(&path, found)?;
+        } else if path.extension().is_some_and(|e| e == EXTENSION) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to read directory '{}': {}", dir.display(), e)))?;
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn expand_pattern(pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let components = pattern.split('/').collect::<Vec<_>>();
+    let (base, rest) = if pattern.starts_with('/') {
+        (PathBuf::from("/"), &components[1..])
+    } else {
+        (PathBuf::new(), &components[..])
+    };
+    let mut found = Vec::new();
+    match_components(&base, rest, &mut found)?;
+    Ok(found)
+}
+
+fn match_components(base: &Path, components: &[&str], found: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let Some((&first, rest)) = components.split_first() else {
+        if base.is_file() {
+            found.push(base.to_path_buf());
+        }
+        return Ok(());
+    };
+    let dir = if base.as_os_str().is_empty() { Path::new(".") } else { base };
+
+    if first == "**" {
+        // `**`는 0개 이상의 디렉터리
+        match_components(base, rest, found)?;
+        for path in read_dir(dir)? {
+            if is_real_dir(&path) && !is_hidden(&path) {
+                match_components(&base.join(file_name(&path)), components, found)?;
+            }
+        }
+    } else if is_pattern(first) {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        for path in read_dir(dir)? {
+            let name = file_name(&path);
+            if wildcard_match(first, &name) && (first.starts_with('.') || !name.starts_with('.')) {
+                match_components(&base.join(name), rest, found)?;
+            }
+        }
+    } else if !first.is_empty() {
+        match_components(&base.join(first), rest, found)?;
+    } else {
+        match_components(base, rest, found)?;
+    }
+    Ok(())
+}
+
+/// 심볼릭 링크가 아닌 디렉터리. 링크를 따라 내려가면 자기를 가리키는 링크에서 끝없이 돌 수 있음
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_dir())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    file_name(path).starts_with('.')
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_directories_are_not_followed() {
+        let root = std::env::temp_dir().join(format!("lintre-files-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/a.ltr"), "L x. x\n").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+        let root_name = root.display().to_string();
+        let expected = vec![root.join("sub/a.ltr").display().to_string()];
+        assert_eq!(expand_inputs(std::slice::from_ref(&root_name)).unwrap(), expected);
+        assert_eq!(expand_inputs(&[format!("{}/**/*.ltr", root_name)]).unwrap(), expected);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
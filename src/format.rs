@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::highlight::{self, Class};
+use crate::lint;
+use crate::normalize;
+use crate::parser::Parser;
+
+/// `fmt --sort-defs`: 최상위 문장을 원문 그대로 두고 순서만 바꿔서, 모든 문장이 자기가 쓰는
+/// 정의보다 뒤에 오게 한다. 순서가 상관없는 문장끼리는 원래 순서를 지킴.
+/// 서로를 쓰는 문장들처럼 그렇게 놓을 수 없으면 그 문장들은 원래 순서대로 둠
+pub fn sort_definitions(source: &str) -> Result<String, String> {
+    let items = Parser::new(source).collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = items.first() else {
+        return Ok(source.to_string());
+    };
+    let defines = items.iter()
+        .map(|item| {
+            item.exprs.iter()
+                .filter_map(|e| match e {
+                    Expr::Define(name, _, _) => Some(name.clone()),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // 문장마다 먼저 와야 하는 문장들
+    let mut before = vec![HashSet::new(); items.len()];
+    for (i, item) in items.iter().enumerate() {
+        let mut free = HashSet::new();
+        for expr in &item.exprs {
+            let body = match expr {
+                Expr::Define(_, _, body) => body,
+                other => other,
+            };
+            free.extend(normalize::free_vars(body));
+        }
+        for name in free.iter().filter(|name| !defines[i].contains(*name)) {
+            // 앞의 정의 중 가장 가까운 것, 없으면 뒤의 정의 중 첫 번째를 씀
+            let earlier = (0..i).rev().find(|&j| defines[j].contains(name));
+            if let Some(j) = earlier.or_else(|| (i + 1..items.len()).find(|&j| defines[j].contains(name))) {
+                before[i].insert(j);
+            }
+        }
+        // 같은 이름을 다시 정의하는 문장끼리는 순서를 지킴
+        for j in 0..i {
+            if !defines[i].is_disjoint(&defines[j]) {
+                before[i].insert(j);
+            }
+        }
+    }
+
+    let mut placed = vec![false; items.len()];
+    let mut order = Vec::new();
+    while order.len() < items.len() {
+        let ready = (0..items.len())
+            .find(|&i| !placed[i] && before[i].iter().all(|&j| placed[j]))
+            .unwrap_or_else(|| placed.iter().position(|p| !p).unwrap());
+        placed[ready] = true;
+        order.push(ready);
+    }
+
+    let mut out = source[..first.range.start].to_string();
+    for (n, &i) in order.iter().enumerate() {
+        let text = code(&source[items[i].range.clone()]);
+        out.push_str(text);
+        // 마지막이 아니게 된 문장에는 `;`를 붙여야 다음 문장과 이어짐
+        if n + 1 < order.len() && !text.ends_with(';') {
+            out.push(';');
+        }
+        let end = items[i].range.end;
+        let text_end = items[i].range.start + text.len();
+        out.push_str(match &source[text_end..end] {
+            "" if n + 1 < order.len() => "\n",
+            space => space,
+        });
+    }
+    Ok(out)
+}
+
+/// `lint --eta --fix`: η-축약할 수 있는 람다가 있는 최상위 문장만 줄인 식으로 다시 쓴다.
+/// 다시 쓴 문장은 파싱한 식을 출력한 것이므로 그 문장 안의 줄바꿈과 문법 설탕은 풀려 나옴. 나머지 문장은 원문 그대로 둠
+pub fn eta_reduce(source: &str) -> Result<String, String> {
+    let items = Parser::new(source).collect::<Result<Vec<_>, _>>()?;
+    let mut out = String::new();
+    let mut copied = 0;
+    for item in items {
+        let fixed = item.exprs.iter().map(lint::eta_fixed).collect::<Vec<_>>();
+        if fixed == item.exprs {
+            continue;
+        }
+        let text = code(&source[item.range.clone()]);
+        out.push_str(&source[copied..item.range.start]);
+        out.push_str(&fixed.iter().map(Expr::to_string).collect::<Vec<_>>().join("; "));
+        if text.ends_with(';') {
+            out.push(';');
+        }
+        copied = item.range.start + text.len();
+    }
+    out.push_str(&source[copied..]);
+    Ok(out)
+}
+
+/// 문장 원문에서 뒤따르는 공백과 주석을 뺀 부분
+fn code(text: &str) -> &str {
+    let end = highlight::tokens(text).into_iter()
+        .rfind(|(_, class)| *class != Class::Space && *class != Class::Comment)
+        .map_or(0, |(range, _)| range.end);
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_fix_rewrites_only_reducible_statements() {
+        let source = "id   =   L x. x;\nf = L y. id y;\nf id\n";
+        assert_eq!(eta_reduce(source).unwrap(), "id   =   L x. x;\nf = id;\nf id\n");
+    }
+
+    #[test]
+    fn eta_fix_keeps_delaying_lambdas() {
+        let source = "g = L v. f f v;\n";
+        assert_eq!(eta_reduce(source).unwrap(), source);
+    }
+
+    #[test]
+    fn sorting_keeps_trailing_comments_out_of_the_separator() {
+        let source = "main = f; # uses f\nf = L x. x # identity\n";
+        assert_eq!(sort_definitions(source).unwrap(), "f = L x. x; # identity\nmain = f; # uses f\n");
+    }
+}
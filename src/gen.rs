@@ -0,0 +1,69 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ast::Expr;
+
+const FREE_NAMES: [&str; 4] = ["a", "b", "c", "d"];
+
+/// xorshift64* 의사 난수 생성기
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// 노드 수가 정확히 `size`인 무작위 람다 항을 만든다.
+/// `closed`이면 자유 변수가 없도록 만들며, 이때 `size`는 2 이상이어야 함
+pub fn random_term(rng: &mut Rng, size: usize, closed: bool) -> Result<Expr, String> {
+    if size == 0 {
+        return Err("Term size must be at least 1.".to_string());
+    }
+    if closed && size < 2 {
+        return Err("Closed terms need a size of at least 2.".to_string());
+    }
+    Ok(generate(rng, size, 0, closed))
+}
+
+fn generate(rng: &mut Rng, size: usize, depth: usize, closed: bool) -> Expr {
+    if size == 1 {
+        return if depth > 0 && (closed || rng.below(4) != 0) {
+            Expr::Word(var(rng.below(depth)))
+        } else {
+            Expr::Word(FREE_NAMES[rng.below(FREE_NAMES.len())].to_string())
+        };
+    }
+    // 닫힌 항에서 변수가 하나도 없으면 람다로 시작해야 함
+    let must_bind = closed && depth == 0;
+    if must_bind || size == 2 || rng.below(2) == 0 {
+        let body = generate(rng, size - 1, depth + 1, closed);
+        return Expr::Function(vec![var(depth)], Box::new(body));
+    }
+    let left = 1 + rng.below(size - 2);
+    let func = generate(rng, left, depth, closed);
+    let arg = generate(rng, size - 1 - left, depth, closed);
+    Expr::Words(vec![func, arg])
+}
+
+fn var(index: usize) -> String {
+    format!("x{}", index)
+}
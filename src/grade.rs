@@ -0,0 +1,179 @@
+use std::fs;
+
+use crate::ast::Expr;
+use crate::error::{Error, ErrorKind};
+use crate::interpreter::{Config, Interpreter};
+use crate::json;
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::parser::Parser;
+
+/// 채점한 정의 하나의 결과
+pub enum Outcome {
+    Pass,
+    // 정의는 있지만 정규형이 다름. 학생의 βη-정규형을 담음
+    Fail(Expr),
+    Missing,
+    Error(String),
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail(_) => "fail",
+            Outcome::Missing => "missing",
+            Outcome::Error(_) => "error",
+        }
+    }
+
+    fn detail(&self) -> Option<String> {
+        match self {
+            Outcome::Pass => None,
+            Outcome::Fail(actual) => Some(format!("got {}", normalize::canonical_letters(actual))),
+            Outcome::Missing => Some("not defined".to_string()),
+            Outcome::Error(e) => Some(e.clone()),
+        }
+    }
+}
+
+/// 제출 파일 하나의 채점 결과. 순서는 채점한 정의의 순서와 같음
+pub struct Report {
+    pub student: String,
+    // 파일을 끝까지 평가하지 못했으면 그 오류. 그 앞까지의 정의는 채점함
+    pub error: Option<String>,
+    pub outcomes: Vec<(String, Outcome)>,
+}
+
+impl Report {
+    pub fn score(&self) -> usize {
+        self.outcomes.iter().filter(|(_, o)| matches!(o, Outcome::Pass)).count()
+    }
+}
+
+/// 정답 파일을 평가해서 채점할 정의마다 βη-정규형을 구한다.
+/// `names`가 비어 있으면 정답 파일의 최상위 정의를 모두 채점함
+pub fn reference(file: &str, names: &[String], config: Config) -> Result<Vec<(String, Expr)>, Error> {
+    let mut interpreter = Interpreter::new(config);
+    let ast = load(file, &mut interpreter)?;
+    let names = match names.is_empty() {
+        true => defines(&ast),
+        false => names.to_vec(),
+    };
+    names.into_iter()
+        .map(|name| {
+            let value = interpreter.lookup(&name)
+                .ok_or_else(|| format!("'{}' is not defined in {}", name, file))?;
+            let normal = interpreter.normal_form(value).map_err(|e| e.map(|e| format!("{} in {}: {}", name, file, e)))?;
+            Ok((name, normalize::eta_normal(&normal)))
+        })
+        .collect()
+}
+
+/// 제출 파일을 평가하고 정의마다 정답과 βηα-동치인지 본다
+pub fn grade(file: &str, reference: &[(String, Expr)], config: Config) -> Report {
+    let mut interpreter = Interpreter::new(config);
+    let error = load(file, &mut interpreter).err().map(|e| e.to_string());
+    let outcomes = reference.iter()
+        .map(|(name, expected)| {
+            let outcome = match interpreter.lookup(name) {
+                None => Outcome::Missing,
+                Some(value) => match interpreter.normal_form(value) {
+                    Ok(actual) => {
+                        let actual = normalize::eta_normal(&actual);
+                        match normalize::alpha_eq(&actual, expected) {
+                            true => Outcome::Pass,
+                            false => Outcome::Fail(actual),
+                        }
+                    }
+                    Err(e) => Outcome::Error(e.to_string()),
+                },
+            };
+            (name.clone(), outcome)
+        })
+        .collect();
+    Report { student: file.to_string(), error, outcomes }
+}
+
+fn load(file: &str, interpreter: &mut Interpreter) -> Result<Expr, Error> {
+    let source = fs::read_to_string(file)
+        .map_err(|e| Error::new(ErrorKind::Io, format!("Failed to read '{}': {}", file, e)))?;
+    let ast = Parser::new(&source).parse().map_err(|e| messages::text(Msg::ParseError, &[&e]))?;
+    interpreter.eval(ast.clone())?;
+    Ok(ast)
+}
+
+fn defines(ast: &Expr) -> Vec<String> {
+    let statements = match ast {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut names = Vec::new();
+    for statement in statements {
+        if let Expr::Define(name, _, _) = statement.unlocated() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names
+}
+
+/// 학생마다 한 줄: `student,score,total,<정의마다 결과>,notes`. notes에는 틀린 이유를 `; `로 이어 붙임
+pub fn csv(reports: &[Report], names: &[String]) -> String {
+    let mut header = vec!["student".to_string(), "score".to_string(), "total".to_string()];
+    header.extend(names.iter().cloned());
+    header.push("notes".to_string());
+    let mut out = row(&header);
+    for report in reports {
+        let mut cells = vec![report.student.clone(), report.score().to_string(), names.len().to_string()];
+        cells.extend(report.outcomes.iter().map(|(_, o)| o.label().to_string()));
+        let notes = report.error.iter()
+            .map(|e| format!("file: {}", e))
+            .chain(report.outcomes.iter().filter_map(|(name, o)| o.detail().map(|d| format!("{}: {}", name, d))))
+            .collect::<Vec<_>>();
+        cells.push(notes.join("; "));
+        out.push_str(&row(&cells));
+    }
+    out
+}
+
+fn row(cells: &[String]) -> String {
+    let cells = cells.iter()
+        .map(|cell| match cell.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", cell.replace('"', "\"\"")),
+            false => cell.clone(),
+        })
+        .collect::<Vec<_>>();
+    format!("{}\n", cells.join(","))
+}
+
+/// 학생마다 객체 하나를 담은 JSON 배열. 한 줄에 학생 하나
+/// `{"student":"a.lc","score":1,"total":2,"error":null,"definitions":[{"name":"not","result":"pass","detail":null},…]}`
+pub fn json(reports: &[Report]) -> String {
+    let optional = |s: Option<String>| s.map_or("null".to_string(), |s| json::string(&s));
+    let lines = reports.iter()
+        .map(|report| {
+            let definitions = report.outcomes.iter()
+                .map(|(name, o)| format!(
+                    "{{\"name\":{},\"result\":{},\"detail\":{}}}",
+                    json::string(name),
+                    json::string(o.label()),
+                    optional(o.detail()),
+                ))
+                .collect::<Vec<_>>();
+            format!(
+                "{{\"student\":{},\"score\":{},\"total\":{},\"error\":{},\"definitions\":[{}]}}",
+                json::string(&report.student),
+                report.score(),
+                report.outcomes.len(),
+                optional(report.error.clone()),
+                definitions.join(","),
+            )
+        })
+        .collect::<Vec<_>>();
+    match lines.is_empty() {
+        true => "[]\n".to_string(),
+        false => format!("[\n{}\n]\n", lines.join(",\n")),
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::interpreter::{Config, Interpreter, Output, Value, Verbosity};
+use crate::normalize;
+use crate::parser::{is_name_char, is_name_start, Parser, RESERVED};
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>lintre</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+pre { background: #fafafa; border: 1px solid #ddd; padding: 1em; line-height: 1.4; }
+.keyword { color: #a626a4; font-weight: bold; }
+.lambda { color: #4078f2; font-weight: bold; }
+.string { color: #50a14f; }
+.number { color: #986801; }
+.symbol { color: #777; }
+.comment { color: #a0a1a7; font-style: italic; }
+.def { color: #e45649; font-weight: bold; border-bottom: 1px dotted; cursor: help; }
+</style>
+</head>
+<body>
+"#;
+
+const FOOTER: &str = "</body>\n</html>\n";
+
+// 설명에 보여 줄 정규형의 최대 노드 수. 나머지는 `…`
+const MAX_NOTE_NODES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    Keyword,
+    Lambda,
+    Word,
+    Number,
+    String,
+    Symbol,
+    Comment,
+    Space,
+}
+
+impl Class {
+    fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::Lambda => "lambda",
+            Class::Word => "word",
+            Class::Number => "number",
+            Class::String => "string",
+            Class::Symbol => "symbol",
+            Class::Comment => "comment",
+            Class::Space => "space",
+        }
+    }
+}
+
+/// `highlight --format html`: 파일마다 원문을 색만 입혀 그대로 보여 주고, 정의하는 이름에
+/// 마우스를 올리면 그 정의의 β-정규형과 자유 변수가 뜨는 HTML 문서를 만든다.
+/// 여러 파일은 `run`처럼 하나의 프로그램으로 보므로 뒤 파일에서 앞 파일의 정의를 쓸 수 있음
+pub fn html(files: &[(String, String)]) -> Result<String, String> {
+    // 정의를 평가하는 동안의 `print` 같은 출력은 문서에 섞이지 않도록 버림
+    let config = Config {
+        verbosity: Verbosity::Quiet,
+        output: Output::Capture(Rc::default()),
+        ..Config::default()
+    };
+    let mut interpreter = Interpreter::new(config);
+    let mut out = String::from(HEADER);
+    for (file, source) in files {
+        let notes = annotate(&mut interpreter, source)
+            .map_err(|e| format!("Parse error in {}: {}", file, e))?;
+        out.push_str(&format!("<h2>{}</h2>\n<pre>", escape(file)));
+        for (range, class) in tokens(source) {
+            let text = escape(&source[range.clone()]);
+            match (notes.get(&range.start), class) {
+                (Some(note), _) => out.push_str(&format!("<span class=\"def\" title=\"{}\">{}</span>", escape(note), text)),
+                (None, Class::Space) => out.push_str(&text),
+                (None, class) => out.push_str(&format!("<span class=\"{}\">{}</span>", class.name(), text)),
+            }
+        }
+        out.push_str("</pre>\n");
+    }
+    out.push_str(FOOTER);
+    Ok(out)
+}
+
+/// 정의를 차례로 평가하고, 정의하는 이름이 나온 바이트 위치마다 보여 줄 설명을 돌려준다.
+fn annotate(interpreter: &mut Interpreter, source: &str) -> Result<HashMap<usize, String>, String> {
+    let tokens = tokens(source);
+    let mut notes = HashMap::new();
+    for item in Parser::new(source) {
+        let item = item?;
+        let mut sites = definition_sites(source, &tokens, &item.range);
+        for expr in item.exprs {
+            let Expr::Define(name, _, body) = &expr else {
+                continue;
+            };
+            let mut free = normalize::free_vars(body).into_iter().collect::<Vec<_>>();
+            free.sort();
+            let free = match free.is_empty() {
+                true => "(none)".to_string(),
+                false => free.join(", "),
+            };
+            let normal = interpreter.eval(Expr::Sequence(vec![expr.clone()]))
+                .and_then(|_| interpreter.normal_form(&interpreter.lookup(name).cloned().unwrap_or(Value::Word(name.clone()))))
+                .map(|normal| normalize::canonical_letters(&normal).elide(MAX_NOTE_NODES).to_string())
+                .unwrap_or_else(|e| format!("error: {}", e));
+            let note = format!("{} = {}\nfree variables: {}", name, normal, free);
+            if let Some(i) = sites.iter().position(|(site, _)| site == name) {
+                notes.insert(sites.remove(i).1, note);
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// 문장 안에서 이름을 정의하는 낱말과 그 위치: `=`나 `:` 앞의 낱말, `data` 선언의 생성자
+fn definition_sites(source: &str, tokens: &[(Range<usize>, Class)], range: &Range<usize>) -> Vec<(String, usize)> {
+    let tokens = tokens.iter()
+        .filter(|(r, class)| range.contains(&r.start) && *class != Class::Space)
+        .map(|(r, class)| (&source[r.clone()], *class, r.start))
+        .collect::<Vec<_>>();
+    let data = tokens.first().is_some_and(|(text, _, _)| *text == "data");
+    let mut sites = Vec::new();
+    for (i, &(text, class, start)) in tokens.iter().enumerate() {
+        if class != Class::Word {
+            continue;
+        }
+        let next = tokens.get(i + 1).map(|(text, _, _)| *text);
+        let previous = i.checked_sub(1).map(|j| tokens[j].0);
+        let defined = matches!(next, Some("=" | ":"))
+            || (data && matches!(previous, Some("=" | "|")));
+        if defined {
+            sites.push((text.to_string(), start));
+        }
+    }
+    sites
+}
+
+/// 파서와 같은 규칙으로 원문을 나눈 조각. 모두 이어 붙이면 원문과 같음
+pub(crate) fn tokens(source: &str) -> Vec<(Range<usize>, Class)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    while let Some(ch) = source[start..].chars().next() {
+        let rest = &source[start..];
+        let end = if ch.is_whitespace() {
+            start + rest.len() - rest.trim_start().len()
+        } else if ch == '#' {
+            // 줄 끝까지 주석. 스크립트로 실행할 때의 `#!` 줄도 여기에 들어감
+            start + rest.find('\n').unwrap_or(rest.len())
+        } else if lambda(rest) {
+            start + 1
+        } else if is_name_start(ch) {
+            start + rest.find(|ch| !is_name_char(ch)).unwrap_or(rest.len())
+        } else if ch == '"' {
+            string_end(rest).map_or(source.len(), |end| start + end)
+        } else if ["==", "->", "/\\"].iter().any(|symbol| rest.starts_with(symbol)) {
+            start + 2
+        } else {
+            start + ch.len_utf8()
+        };
+        let text = &source[start..end];
+        let class = if ch.is_whitespace() {
+            Class::Space
+        } else if ch == '#' {
+            Class::Comment
+        } else if lambda(rest) {
+            Class::Lambda
+        } else if ch == '"' {
+            Class::String
+        } else if RESERVED.contains(&text) {
+            Class::Keyword
+        } else if text.chars().all(|ch| ch.is_ascii_digit()) {
+            Class::Number
+        } else if is_name_start(ch) {
+            Class::Word
+        } else {
+            Class::Symbol
+        };
+        tokens.push((start..end, class));
+        start = end;
+    }
+    tokens
+}
+
+/// 홀로 선 `L`만 람다로 봄. `List`처럼 `L`로 시작하는 이름은 `data` 선언 같은 곳에서 이름으로 쓰임
+fn lambda(rest: &str) -> bool {
+    let mut chars = rest.chars();
+    chars.next() == Some('L') && !chars.next().is_some_and(is_name_char)
+}
+
+/// 여는 `"`로 시작하는 문자열 리터럴의 끝(닫는 `"` 다음) 바이트 위치
+fn string_end(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => return Some(i + 1),
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
@@ -0,0 +1,208 @@
+use std::ops::Range;
+
+use crate::ast::Expr;
+use crate::parser::{Context, Item, Parser};
+
+/// 편집할 때마다 바뀐 부분의 최상위 문장만 다시 읽는 문서. watch처럼 같은 파일을 계속 다시 읽을 때 씀.
+/// 편집 범위 뒤의 문장은 위치만 옮겨서 그대로 쓰되, 앞 문장이 남긴 `data`·레코드 정보가 바뀌었으면 다시 읽음
+pub struct Document {
+    source: String,
+    // 앞 파일들이 남긴 `Context`. 첫 문장을 이것으로 읽음
+    base: Context,
+    // 문장과 그 문장까지 읽은 뒤의 `Context`
+    items: Vec<(Item, Context)>,
+    // 마지막 편집에서 난 파싱 오류. 있으면 `items`는 오류가 난 문장 앞까지만 맞음
+    error: Option<String>,
+}
+
+impl Document {
+    pub fn new(source: String) -> Self {
+        let mut document = Document { source: String::new(), base: Context::default(), items: Vec::new(), error: None };
+        document.edit(0..0, &source);
+        document
+    }
+
+    /// 모든 문장을 이어 붙인 프로그램. `Parser::parse`의 결과와 같음
+    pub fn program(&self) -> Result<Expr, String> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let mut exprs = self.items.iter()
+            .flat_map(|(item, _)| item.exprs.iter().cloned())
+            .collect::<Vec<_>>();
+        match exprs.len() {
+            1 => Ok(exprs.remove(0)),
+            _ => Ok(Expr::Sequence(exprs)),
+        }
+    }
+
+    /// 이 문서를 `base`를 남긴 파일들 뒤에서 읽는 것으로 함. 달라졌으면 처음부터 다시 읽음
+    pub fn rebase(&mut self, base: &Context) {
+        if self.base == *base {
+            return;
+        }
+        self.base = base.clone();
+        let source = std::mem::take(&mut self.source);
+        self.items.clear();
+        self.error = None;
+        self.edit(0..0, &source);
+    }
+
+    /// 마지막으로 읽은 문장까지의 `Context`. 다음 파일을 읽을 때 넘김
+    pub fn context(&self) -> &Context {
+        self.items.last().map_or(&self.base, |(_, context)| context)
+    }
+
+    /// 새 원문으로 바꿈. 앞뒤로 같은 부분을 뺀 가운데만 편집한 것으로 봄
+    pub fn update(&mut self, source: &str) -> usize {
+        let old = self.source.as_bytes();
+        let new = source.as_bytes();
+        let mut start = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let max_suffix = old.len().min(new.len()) - start;
+        let mut suffix = old.iter().rev().zip(new.iter().rev()).take(max_suffix).take_while(|(a, b)| a == b).count();
+        // 여러 바이트 글자의 중간에서 자르지 않도록
+        while !source.is_char_boundary(start) || !self.source.is_char_boundary(start) {
+            start -= 1;
+        }
+        while !source.is_char_boundary(new.len() - suffix) || !self.source.is_char_boundary(old.len() - suffix) {
+            suffix -= 1;
+        }
+        let text = source[start..new.len() - suffix].to_string();
+        self.edit(start..old.len() - suffix, &text)
+    }
+
+    /// 원문의 `range` 바이트를 `text`로 바꾸고, 다시 읽은 문장 수를 돌려준다.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> usize {
+        self.source.replace_range(range.clone(), text);
+        let shift = |offset: usize| offset + text.len() - range.len();
+
+        // 편집 범위에 닿지 않은 앞 문장은 그대로. 다만 바로 앞 문장은 `where`나 `rec` 묶음이
+        // 뒤의 정의를 끌어갈 수 있으므로 다시 읽음
+        let old = std::mem::take(&mut self.items);
+        let kept = old.iter()
+            .take_while(|(item, _)| item.range.end < range.start)
+            .count()
+            .saturating_sub(1);
+        // 편집 범위보다 뒤에서 시작한 문장은 다시 쓸 수 있는 후보. 그 문장을 읽기 전의 Context도 함께 둠
+        let mut candidates = Vec::new();
+        for (i, (item, context)) in old.iter().enumerate().skip(kept) {
+            if item.range.start > range.end {
+                let before = i.checked_sub(1).map_or_else(|| self.base.clone(), |j| old[j].1.clone());
+                let range = shift(item.range.start)..shift(item.range.end);
+                candidates.push((Item { range, exprs: item.exprs.clone() }, context.clone(), before));
+            }
+        }
+        let mut candidates = candidates.into_iter().peekable();
+        self.items = old.into_iter().take(kept).collect();
+
+        let (offset, context) = match self.items.last() {
+            Some((item, context)) => (item.range.end, context.clone()),
+            None => (0, self.base.clone()),
+        };
+        let mut parser = Parser::resume(&self.source, offset, context);
+        let mut reparsed = 0;
+        // 지난번에 오류가 났으면 후보 뒤로도 아직 읽지 않은 부분이 남아 있음
+        let incomplete = self.error.take().is_some();
+        loop {
+            // 다음 문장이 후보의 시작에서 같은 Context로 시작하면 나머지는 그대로 씀
+            let start = parser.offset() + leading_whitespace(&self.source[parser.offset()..]);
+            while candidates.next_if(|(item, _, _)| item.range.start < start).is_some() {}
+            if candidates.peek().is_some_and(|(item, _, before)| item.range.start == start && before == parser.context()) {
+                self.items.extend(candidates.by_ref().map(|(item, context, _)| (item, context)));
+                if !incomplete {
+                    break;
+                }
+                let (item, context) = self.items.last().unwrap();
+                parser = Parser::resume(&self.source, item.range.end, context.clone());
+            }
+            match parser.next() {
+                Some(Ok(item)) => {
+                    reparsed += 1;
+                    self.items.push((item, parser.context().clone()));
+                }
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    break;
+                }
+                None => break,
+            }
+        }
+        reparsed
+    }
+}
+
+/// `Parser`가 문장 사이에서 건너뛰는 공백과 `#` 주석의 바이트 수
+fn leading_whitespace(source: &str) -> usize {
+    let mut rest = source;
+    loop {
+        rest = rest.trim_start_matches([' ', '\n', '\t', '\r']);
+        match rest.strip_prefix('#') {
+            Some(comment) => rest = &comment[comment.find('\n').unwrap_or(comment.len())..],
+            None => return source.len() - rest.len(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "a = L x. x;\nb = L y. y;\nc = L z. z;\na b c\n";
+
+    // 편집한 문서가 처음부터 읽은 것과 문장 위치까지 같은지 보고, 다시 읽은 문장 수를 돌려줌
+    fn updated(document: &mut Document, source: &str) -> usize {
+        let reparsed = document.update(source);
+        assert_eq!(document.program(), Parser::new(source).parse(), "{}", source);
+        if document.error.is_none() {
+            let ranges = document.items.iter().map(|(item, _)| item.range.clone()).collect::<Vec<_>>();
+            let fresh = Parser::new(source).map(|item| item.unwrap().range).collect::<Vec<_>>();
+            assert_eq!(ranges, fresh, "{}", source);
+        }
+        reparsed
+    }
+
+    #[test]
+    fn edits_reparse_the_changed_item_and_the_one_before() {
+        let mut document = Document::new(SOURCE.to_string());
+        assert_eq!(updated(&mut document, &SOURCE.replace("L y. y", "L y. a")), 2);
+        // 맨 앞 문장은 앞에 다시 읽을 문장이 없음
+        assert_eq!(updated(&mut document, &SOURCE.replace("L y. y", "L y. a").replace("L x. x", "L x. c")), 1);
+    }
+
+    #[test]
+    fn later_items_are_shifted_instead_of_reparsed() {
+        let mut document = Document::new(SOURCE.to_string());
+        assert_eq!(updated(&mut document, &SOURCE.replace("L x. x", "L x w. x w")), 1);
+        assert_eq!(updated(&mut document, SOURCE), 1);
+    }
+
+    #[test]
+    fn edits_inside_a_multibyte_character_keep_char_boundaries() {
+        // '가'와 '각'은 UTF-8의 앞 두 바이트가 같음
+        let mut document = Document::new("가 = L x. x;\nt = L y. y;\n가 t\n".to_string());
+        assert_eq!(updated(&mut document, "각 = L x. x;\nt = L y. y;\n가 t\n"), 1);
+    }
+
+    #[test]
+    fn items_read_with_a_different_context_are_reparsed() {
+        let source = "data T = A | B;\nf = L t. case t of A -> t | B -> t;\nf A\n";
+        let mut document = Document::new(source.to_string());
+        // 생성자가 늘어나면 뒤의 `case`는 같은 원문이라도 빠진 갈래가 생김
+        updated(&mut document, &source.replace("A | B;", "A | B | C;"));
+        assert!(document.program().is_err());
+        assert_eq!(updated(&mut document, source), 3);
+    }
+
+    #[test]
+    fn a_sequence_of_edits_recovers_from_parse_errors() {
+        let mut document = Document::new(SOURCE.to_string());
+        let broken = SOURCE.replace("L y. y", "L y. ");
+        updated(&mut document, &broken);
+        assert!(document.program().is_err());
+        updated(&mut document, &broken.replace("L z. z", "L z. b"));
+        // 오류 뒤로는 읽은 문장이 없으므로 모두 다시 읽음
+        assert_eq!(updated(&mut document, &SOURCE.replace("L z. z", "L z. b")), 4);
+        assert_eq!(updated(&mut document, &SOURCE.replace("a b c\n", "# done\nc b a\n")), 3);
+    }
+}
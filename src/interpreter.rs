@@ -1,51 +1,318 @@
 use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::cps::collect_names;
+use crate::error::{Error, ErrorKind};
+use crate::interrupt;
+use crate::log::{self, Target};
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::progress::Progress;
+use crate::sandbox::Sandbox;
+use crate::style::{self, Stream};
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
-    Closure(Vec<String>, Box<Expr>, Env),
+    // 매개변수는 하나. `L x y. e`는 `x`를 받아 `L y. e`를 돌려주는 클로저로 펼쳐 둠
+    Closure(String, Box<Expr>, Env),
+    // `--args lazy`로 평가하지 않고 넘긴 인자. 쓰일 때마다 잡아 둔 환경에서 다시 평가함(이름 호출)
+    Thunk(Box<Expr>, Env),
     Word(String),
+    Builtin(Builtin, Vec<Value>),
 }
 
 type Env = HashMap<String, Value>;
 
+/// 환경을 본문에 대입하고 묶인 변수를 v0, v1, …로 바꿔서, 다시 파싱할 수 있는 닫힌 식으로 출력
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", normalize::canonical(&normalize::readback(self)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    Bool(bool),
+    Nat(usize),
+    Str(String),
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Decoded::Bool(b) => write!(f, "{}", b),
+            Decoded::Nat(n) => write!(f, "{}", n),
+            Decoded::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// 프로그램 출력(`print`와 최종 결과)이 나가는 곳
+#[derive(Debug, Clone, Default)]
+pub enum Output {
+    #[default]
+    Stdout,
+    Capture(Rc<RefCell<String>>),
+}
+
+impl Output {
+    pub fn println(&self, line: &str) {
+        match self {
+            Output::Stdout => println!("{}", line),
+            Output::Capture(buffer) => {
+                let mut buffer = buffer.borrow_mut();
+                buffer.push_str(line);
+                buffer.push('\n');
+            }
+        }
+    }
+
+    /// 최종 결과. 스냅샷으로 모을 때는 색을 넣지 않음
+    pub fn result(&self, line: &str) {
+        match self {
+            Output::Stdout => println!("{}", style::result(Stream::Stdout, line)),
+            Output::Capture(_) => self.println(line),
+        }
+    }
+}
+
+/// `--dump-env raw|normal|decoded`에서 각 정의의 값을 보여 주는 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvDump {
+    /// 평가한 값 그대로. 클로저는 줄이지 않은 본문을 보여 줌
+    Raw,
+    /// β-정규형. 정규화에 실패하면 그대로
+    Normal,
+    /// 불리언·자연수·문자열로 읽히면 그 값, 아니면 정규형
+    Decoded,
+}
+
+impl EnvDump {
+    pub fn from_name(name: &str) -> Option<EnvDump> {
+        match name {
+            "raw" => Some(EnvDump::Raw),
+            "normal" => Some(EnvDump::Normal),
+            "decoded" => Some(EnvDump::Decoded),
+            _ => None,
+        }
+    }
+}
+
+// `--opt prenorm`이 정의 하나를 정규형으로 줄일 때 쓰는 β-축약 수. 넘으면 정규형이 없다고 봄
+const PRENORM_STEPS: usize = 1_000;
+
+/// 평가하면서 바꾼 이름(`x$3`)의 원래 이름. `fresh_name`은 원래 이름 뒤에 `$`와 번호만 붙이고
+/// 사용자가 쓰는 이름에는 `$`가 들어갈 수 없으므로, 첫 `$` 앞이 곧 소스에 쓴 이름
+pub fn origin(name: &str) -> &str {
+    name.split('$').next().unwrap_or(name)
+}
+
+/// (보여 준 이름, 그 전 이름) 쌍에서 소스와 달라진 이름을 `x$3 (renamed from x)`처럼 모은 줄.
+/// 달라진 이름이 없으면 `None`
+fn provenance(pairs: Vec<(String, String)>) -> Option<String> {
+    let mut hints = Vec::new();
+    for (shown, before) in pairs {
+        let hint = format!("{} (renamed from {})", shown, origin(&before));
+        if shown != origin(&before) && !hints.contains(&hint) {
+            hints.push(hint);
+        }
+    }
+    (!hints.is_empty()).then(|| hints.join(", "))
+}
+
+/// 식에 나오는 이름 중 평가하면서 바꾼 것
+fn renamed_names(expr: &Expr) -> Vec<(String, String)> {
+    let mut names = HashSet::new();
+    collect_names(expr, &mut names);
+    let mut names = names.into_iter()
+        .filter(|name| name.contains('$'))
+        .map(|name| (name.clone(), name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// 테스트 실행기가 assert 결과를 모을 때 쓰는 기록
+pub type AssertLog = Rc<RefCell<Vec<(String, Result<(), String>)>>>;
+
+/// `-q`, 기본, `-v`, `-vv`. 단계가 오를수록 결과 뒤에 더 많은 정보를 stderr로 냄
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// 최종 결과만. `print`와 `trace`의 출력도 숨김
+    Quiet,
+    #[default]
+    Normal,
+    /// β-축약 횟수와 걸린 시간
+    Verbose,
+    /// 그에 더해 최상위 환경 전체
+    VeryVerbose,
+}
+
+/// 텍스트로 결과를 보여 주는 방식
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// 같은 값의 이름이 있으면 이름, 없으면 평가한 값 그대로
+    #[default]
+    Pretty,
+    /// `--compact`: 이름을 찾지 않고 변수를 한 글자로 바꿔 공백 없이
+    Compact,
+    /// `--canonical`: 이름을 찾지 않고 변수를 v0, v1, …로. 입력의 이름과 상관없이 같은 출력이 나옴
+    Canonical,
+}
+
+/// 람다를 평가해 클로저를 만드는 방식
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvalMode {
+    /// 매개변수를 새 이름으로 바꾼 본문과 환경 전체를 잡음. 추적에서 클로저마다 이름이 달라 구별하기 쉬움
+    #[default]
+    Substitution,
+    /// `--eval env`: 본문은 그대로 두고 환경에서 본문이 쓰는 변수만 잡음.
+    /// 본문을 다시 쓰지 않고 잡는 환경도 작아서, 클로저를 많이 만드는 프로그램에서 훨씬 빠름
+    Environment,
+}
+
+/// 함수에 넘기는 인자를 언제 평가하는지. 클로저를 만드는 방식(`EvalMode`)과는 따로 고름
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArgMode {
+    /// 적용하기 전에 인자를 값으로 만듦(값 호출)
+    #[default]
+    Strict,
+    /// `--args lazy`: 인자를 식 그대로 넘기고, 본문에서 쓰일 때마다 평가함(이름 호출).
+    /// 쓰이지 않는 인자는 평가하지 않으므로 값 호출에서 끝나지 않는 프로그램도 끝날 수 있음
+    Lazy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub debug: bool,
+    pub verbosity: Verbosity,
+    pub format: ResultFormat,
+    // `--print-depth N`: 클로저가 잡은 값을 몇 단계까지 함께 보여 줄지. 0이면 본문만
+    pub print_depth: usize,
+    // `--max-print-nodes N`: 결과와 추적에서 식을 이만큼의 노드까지만 보여 주고 나머지는 `…`
+    pub max_print_nodes: Option<usize>,
+    // 하위 인터프리터도 같은 설정을 복제해 쓰므로 모두 같은 칸을 셈
+    pub steps: Rc<Cell<usize>>,
+    // 있으면 평가가 오래 걸릴 때 진행 상황을 stderr로 알림
+    pub progress: Option<Rc<Progress>>,
+    // `--names`로 읽은 이름과 β-정규형. 환경에 같은 값이 없을 때 결과의 이름을 찾는 데 씀
+    pub names: Rc<Vec<(String, Expr)>>,
+    pub allow_io: bool,
+    pub output: Output,
+    // 있으면 실패한 assert에서 멈추지 않고 여기에 기록
+    pub assertions: Option<AssertLog>,
+    // `--opt prenorm`: 최상위 정의를 평가할 때 한 번 β-정규형까지 줄여서 저장
+    pub prenorm: bool,
+    pub eval_mode: EvalMode,
+    pub args: ArgMode,
+    // `--deterministic`: 출력이 평가 이력이나 해시 순서에 따라 달라지지 않게 함.
+    // 결과의 `x$17` 같은 이름은 결과마다 `x$1`부터 다시 매기고, 환경은 이름 순서로 훑음
+    pub deterministic: bool,
+    // `serve`에서 요청마다 새로 만들어 거는 제한. 넘으면 평가를 멈추고 오류로 돌려줌
+    pub sandbox: Option<Rc<Sandbox>>,
+}
+
+/// 최상위 정의. 앞에 있는 식이 뒤에 정의된 이름을 부를 수 있도록 모든 인스턴스가 공유함
+#[derive(Debug, Clone, Default)]
+struct Globals {
+    values: Env,
+    // 문서에 정의가 있지만 아직 평가되지 않은 이름
+    pending: HashSet<String>,
+    // 처음 정의된 순서. 같은 값인 이름이 여럿일 때 결과에 보여 줄 이름을 고르는 데 씀
+    order: Vec<String>,
+}
+
 pub struct Interpreter {
     env: Env,
-    debug: bool,
+    globals: Rc<RefCell<Globals>>,
+    config: Config,
     previous_states: HashSet<String>,
     name_counter: HashMap<String, usize>,
 }
 
 impl Interpreter {
-    pub fn new(debug: bool) -> Self {
+    pub fn new(config: Config) -> Self {
         Self {
             env: HashMap::new(),
-            debug,
+            globals: Rc::default(),
+            config,
             previous_states: HashSet::new(),
             name_counter: HashMap::new(),
         }
     }
 
-    pub fn eval(&mut self, expr: Expr) -> Result<Value, String> {
+    /// 지금 환경을 이어받은 새 인터프리터. 최상위 정의는 복사하므로 새 인터프리터에서 정의한 이름은 이쪽에 보이지 않음
+    pub fn fork(&self, config: Config) -> Self {
+        Self {
+            env: self.env.clone(),
+            globals: Rc::new(RefCell::new(self.globals.borrow().clone())),
+            config,
+            previous_states: HashSet::new(),
+            name_counter: self.name_counter.clone(),
+        }
+    }
+
+    pub fn eval(&mut self, expr: Expr) -> Result<Value, Error> {
+        let Some(sandbox) = self.config.sandbox.clone() else {
+            return self.evaluated(expr);
+        };
+        sandbox.enter()?;
+        let result = self.evaluated(expr);
+        sandbox.leave();
+        result
+    }
+
+    fn evaluated(&mut self, expr: Expr) -> Result<Value, Error> {
         match expr {
             Expr::Word(name) => {
+                let global = self.globals.borrow().values.get(&name).cloned();
                 if let Some(v) = self.env.get(&name) {
-                    Ok(v.clone())
+                    self.forced(v.clone())
+                } else if let Some(v) = global {
+                    Ok(v)
+                } else if self.globals.borrow().pending.contains(&name) {
+                    Err(Error::new(ErrorKind::Unbound, messages::text(Msg::UsedBeforeDefinition, &[&name])))
+                } else if let Some(builtin) = Builtin::from_name(&name) {
+                    if builtin.requires_io() && !self.config.allow_io {
+                        return Err(Error::new(ErrorKind::Io, messages::text(Msg::RequiresIo, &[&name])));
+                    }
+                    if builtin.arity() == 0 {
+                        self.call_builtin(builtin, Vec::new())
+                    } else {
+                        Ok(Value::Builtin(builtin, Vec::new()))
+                    }
                 } else {
                     Ok(Value::Word(name))
                 }
             }
             Expr::Words(mut words) => {
                 if words.is_empty() {
-                    return Err("Empty Words expression.".to_string());
+                    return Err(messages::text(Msg::EmptyWords, &[]).into());
                 }
                 let mut func = self.eval(words.remove(0))?;
+                // 적용한 결과가 다시 함수면 남은 인자를 이어서 적용함: `((L x. x) (L y. y)) z`는 `z`
                 for word in words {
-                    let arg = self.eval(word)?;
+                    let arg = match self.config.args {
+                        ArgMode::Strict => self.eval(word)?,
+                        ArgMode::Lazy => self.delayed(word)?,
+                    };
                     func = self.apply(func, arg)?;
                 }
                 Ok(func)
             }
+            Expr::Function(params, body) if self.config.eval_mode == EvalMode::Environment => {
+                // 변수는 적용할 때 클로저의 환경에서 찾으므로 이름을 바꿀 필요가 없음
+                let free = normalize::free_vars(&body);
+                let env = self.env.iter()
+                    .filter(|(name, _)| free.contains(*name) && !params.contains(name))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                let (param, body) = curried(params, body)?;
+                Ok(Value::Closure(param, body, env))
+            }
             Expr::Function(params, body) => {
                 let fresh_params = params.iter()
                     .map(|p| self.fresh_name(p))
@@ -58,26 +325,57 @@ impl Interpreter {
 
                 let renamed_body = self.rename(*body, &mapping);
 
-                Ok(Value::Closure(fresh_params, Box::new(renamed_body), self.env.clone()))
+                let (param, body) = curried(fresh_params, Box::new(renamed_body))?;
+                Ok(Value::Closure(param, body, self.env.clone()))
             }
-            Expr::Define(name, body) => {
-                let mut sub_interpreter = Interpreter::new(self.debug);
+            Expr::Define(name, _, body) => {
+                let mut sub_interpreter = Interpreter::new(self.config.clone());
                 sub_interpreter.env = self.env.clone(); // 기존 env를 복사해서 새 인스턴스에 넣어줌
+                sub_interpreter.globals = self.globals.clone();
                 let val = sub_interpreter.eval(*body)?; // fresh_name 카운터는 새로 초기화
                 self.env.insert(name.clone(), val.clone());
                 Ok(val)
             }
             Expr::Sequence(exprs) => {
+                // 마지막 식만 모든 정의가 끝난 뒤에 평가하고, 나머지는 순서대로 평가
+                let last_index = exprs.iter()
+                    .rposition(|e| !matches!(e, Expr::Define(..) | Expr::Assert(..)));
+                // 뒤에 올 정의의 이름을 미리 모아 둠
+                for expr in &exprs {
+                    if let Expr::Define(name, _, _) = expr {
+                        if !self.env.contains_key(name) {
+                            self.globals.borrow_mut().pending.insert(name.clone());
+                        }
+                    }
+                }
                 let mut last_expr = None;
-                for expr in exprs {
+                for (i, expr) in exprs.into_iter().enumerate() {
                     match expr {
-                        Expr::Define(name, body) => {
+                        Expr::Define(name, _, body) => {
                             let val = self.eval(*body)?;
+                            let val = match self.config.prenorm {
+                                true => self.prenormalized(val),
+                                false => val,
+                            };
+                            log::debug(Target::Interp, || format!(
+                                "defined '{}' after {} β-reductions in total",
+                                name,
+                                self.config.steps.get(),
+                            ));
+                            let mut globals = self.globals.borrow_mut();
+                            globals.pending.remove(&name);
+                            if !globals.order.contains(&name) {
+                                globals.order.push(name.clone());
+                            }
+                            globals.values.insert(name.clone(), val.clone());
                             self.env.insert(name, val);
                         }
-                        _ => {
+                        _ if Some(i) == last_index => {
                             last_expr = Some(expr);
                         }
+                        _ => {
+                            self.eval(expr)?;
+                        }
                     }
                 }
                 if let Some(e) = last_expr {
@@ -86,73 +384,459 @@ impl Interpreter {
                     Ok(Value::Word("()".to_string()))
                 }
             }
-            Expr::Paren(inner) => self.eval(*inner),
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _) => self.eval(*inner),
+            Expr::Located(span, inner) => self.eval(*inner).map_err(|e| e.at(span)),
+            Expr::Assert(lhs, rhs) => {
+                let label = format!("assert {} == {}", self.pretty_expr(&lhs), self.pretty_expr(&rhs));
+                let left = self.eval(*lhs)?;
+                let right = self.eval(*rhs)?;
+                let outcome = self.check_equal(&left, &right);
+                match &self.config.assertions {
+                    Some(log) => log.borrow_mut().push((label, outcome.map_err(|e| e.to_string()))),
+                    None => outcome.map_err(|e| e.map(|message| format!("{}\n{}", label, message)))?,
+                }
+                Ok(Value::Word("()".to_string()))
+            }
         }
     }
 
-    fn apply(&mut self, func: Value, arg: Value) -> Result<Value, String> {
-        match func {
-            Value::Closure(mut params, body, mut closure_env) => {
-                if params.is_empty() {
-                    return Err("No parameter left to apply!".to_string());
+    pub fn normal_form(&self, value: &Value) -> Result<Expr, Error> {
+        normalize::normalize(&normalize::readback(value))
+    }
+
+    /// 정의의 값을 β-정규형에서 다시 만든 클로저로 바꿔서, 그 정의를 쓸 때마다 같은 축약을 되풀이하지 않게 함.
+    /// 정규형은 잡은 환경을 모두 대입한 식이므로 빈 환경에서 만듦. 정해진 단계 안에 정규형이 나오지 않으면 그대로 둠
+    fn prenormalized(&self, value: Value) -> Value {
+        if !matches!(value, Value::Closure(..)) {
+            return value;
+        }
+        let Ok(normal) = normalize::normalize_within(&normalize::readback(&value), PRENORM_STEPS) else {
+            log::debug(Target::Interp, || format!("no normal form within {} steps; kept as is", PRENORM_STEPS));
+            return value;
+        };
+        // 축약하면서 붙은 `x$1` 같은 이름이 다시 평가할 때마다 길어지지 않도록 원래 이름으로 되돌림
+        let normal = normalize::plain_names(&normal);
+        let mut fresh = Interpreter::new(self.config.clone());
+        fresh.globals = self.globals.clone();
+        fresh.name_counter = self.name_counter.clone();
+        fresh.eval(normal).unwrap_or(value)
+    }
+
+    /// 두 값의 정규형이 α-동치인지 확인
+    pub(crate) fn equal_values(&self, left: &Value, right: &Value) -> Result<bool, Error> {
+        Ok(normalize::alpha_eq(&self.normal_form(left)?, &self.normal_form(right)?))
+    }
+
+    /// 두 값의 정규형이 α-동치인지 확인하고, 다르면 두 정규형을 보여준다.
+    fn check_equal(&self, left: &Value, right: &Value) -> Result<(), Error> {
+        let left = self.normal_form(left)?;
+        let right = self.normal_form(right)?;
+        if normalize::alpha_eq(&left, &right) {
+            Ok(())
+        } else {
+            let mut message = messages::text(Msg::AssertionFailed, &[]);
+            for (side, normal) in [(Msg::AssertionLeft, &left), (Msg::AssertionRight, &right)] {
+                let shown = normalize::canonical(normal);
+                message.push_str(&format!("\n  {:<6} {}", messages::text(side, &[]), self.pretty_expr(&shown)));
+                if let Some(hint) = provenance(normalize::renamings(normal, &shown)) {
+                    message.push_str(&format!("\n         {}", hint));
                 }
-                let param = params.remove(0);
+            }
+            Err(message.into())
+        }
+    }
 
+    fn apply(&mut self, func: Value, arg: Value) -> Result<Value, Error> {
+        match func {
+            Value::Closure(param, body, mut closure_env) => {
                 closure_env.insert(param, arg);
 
-                if self.debug {
-                    println!("--- β-reduction step ---");
-                    println!("Applying: {}", self.pretty_expr(&body));
+                self.config.steps.set(self.config.steps.get() + 1);
+                interrupt::check(self.config.steps.get(), || {
+                    let hint = provenance(renamed_names(&body)).map_or(String::new(), |hint| format!("\n  {}", hint));
+                    format!("{}{}", self.shown_expr(&body), hint)
+                })?;
+                if let Some(progress) = &self.config.progress {
+                    progress.tick(self.config.steps.get(), || body.size());
+                }
+                if let Some(sandbox) = &self.config.sandbox {
+                    sandbox.step(|| body.size())?;
+                }
+                log::trace(Target::Interp, || format!("β #{}: {}", self.config.steps.get(), self.shown_expr(&body)));
+                if self.config.debug {
+                    println!("{}", style::dim(Stream::Stdout, "--- β-reduction step ---"));
+                    println!("Applying: {}", style::redex(Stream::Stdout, &self.shown_expr(&body)));
+                    if let Some(hint) = provenance(renamed_names(&body)) {
+                        println!("{}", style::dim(Stream::Stdout, &format!("Names: {}", hint)));
+                    }
                     println!("With environment:");
-                    for (k, v) in &closure_env {
+                    let mut bindings = closure_env.iter().collect::<Vec<_>>();
+                    if self.config.deterministic {
+                        bindings.sort_by_key(|(k, _)| *k);
+                    }
+                    for (k, v) in bindings {
                         println!("  {} = {}", k, self.pretty_value(v));
                     }
                     println!();
                 }
 
-                let state_key = format!("{} {:?}", self.pretty_expr(&body), closure_env.keys());
+                // 본문이 람다면 평가할 것 없이 다음 매개변수를 받는 클로저가 됨
+                if let Expr::Function(params, inner) = *body {
+                    let (param, body) = curried(params, inner)?;
+                    return Ok(Value::Closure(param, body, closure_env));
+                }
+
+                // 같은 본문을 같은 환경으로 평가하는 도중에 다시 만나면 무한 루프
+                let state_key = self.state_key(&body, &closure_env);
                 if self.previous_states.contains(&state_key) {
-                    return Err("무한 β-축약 루프 감지!".to_string());
+                    log::debug(Target::Interp, || format!("loop detected: {}", state_key));
+                    return Err(Error::step_limit(messages::text(Msg::LoopDetected, &[])));
                 }
-                self.previous_states.insert(state_key);
 
-                if params.is_empty() {
-                    let mut next = Interpreter::new(self.debug);
-                    next.env = closure_env;
-                    next.name_counter = self.name_counter.clone();
-                    next.previous_states = self.previous_states.clone();
-                    next.eval(*body)
-                } else{
-                    Ok(Value::Closure(params, body, closure_env))
+                let mut next = Interpreter::new(self.config.clone());
+                next.env = closure_env;
+                next.globals = self.globals.clone();
+                next.name_counter = self.name_counter.clone();
+                next.previous_states = self.previous_states.clone();
+                next.previous_states.insert(state_key);
+                next.eval(*body)
+            }
+            Value::Builtin(builtin, mut args) => {
+                // 내장 함수는 인자의 값을 봐야 하므로 미뤄 둔 인자도 여기서 평가함
+                args.push(self.forced(arg)?);
+                if args.len() < builtin.arity() {
+                    Ok(Value::Builtin(builtin, args))
+                } else if builtin.requires_io() && !self.config.allow_io {
+                    Err(Error::new(ErrorKind::Io, messages::text(Msg::RequiresIo, &[&builtin.name()])))
+                } else {
+                    self.call_builtin(builtin, args)
                 }
             }
-            _ => Err("Trying to apply non-function!".to_string()),
+            Value::Thunk(..) => {
+                let func = self.forced(func)?;
+                self.apply(func, arg)
+            }
+            _ => Err(messages::text(Msg::NotFunction, &[]).into()),
         }
     }
 
-    pub fn format_result(&self, value: &Value) -> String {
-        for (name, v) in &self.env {
-            if v == value {
-                return name.clone();
+    /// `--args lazy`에서 인자를 평가하지 않고 넘길 값. 이미 값인 람다와 환경의 변수는 그대로 쓰고,
+    /// 나머지는 지금 환경을 잡은 `Value::Thunk`로 미룸
+    fn delayed(&mut self, expr: Expr) -> Result<Value, Error> {
+        match expr.unlocated() {
+            Expr::Function(..) => self.eval(expr),
+            Expr::Word(name) if self.env.contains_key(name) => Ok(self.env[name].clone()),
+            _ => {
+                // 인자에서 쓰는 변수만 잡음. 미뤄 둔 인자가 다시 미뤄 둔 인자를 잡으므로 전부 잡으면 금방 커짐
+                let free = normalize::free_vars(&expr);
+                let env = self.env.iter()
+                    .filter(|(name, _)| free.contains(*name))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect();
+                Ok(Value::Thunk(Box::new(expr), env))
             }
         }
+    }
+
+    /// 미뤄 둔 인자면 잡아 둔 환경에서 평가하고, 아니면 그대로
+    fn forced(&mut self, value: Value) -> Result<Value, Error> {
+        let Value::Thunk(expr, env) = value else {
+            return Ok(value);
+        };
+        let mut next = Interpreter::new(self.config.clone());
+        next.env = env;
+        next.globals = self.globals.clone();
+        next.name_counter = self.name_counter.clone();
+        next.previous_states = self.previous_states.clone();
+        next.eval(*expr)
+    }
+
+    /// 본문이 실제로 쓰는 변수만, 클로저의 환경까지 펼친 모양으로 비교한다.
+    /// 본문만 같고 환경이 다른 클로저(예: 재귀 호출마다 새로 만드는 인자)를 같은 상태로 보지 않기 위함
+    fn state_key(&self, body: &Expr, env: &Env) -> String {
+        let free = normalize::free_vars(body);
+        let mut bindings = env.iter()
+            .filter(|(k, _)| free.contains(*k))
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>();
+        bindings.sort();
+        format!("{} [{}]", self.pretty_expr(body), bindings.join(", "))
+    }
+
+    /// Church 부울/숫자/문자열로 해석할 수 있으면 그 값을 돌려준다.
+    pub fn decode(&self, value: &Value) -> Option<Decoded> {
+        // 인코딩은 모두 두 인자를 받는 함수이므로, 그 외의 값은 해석하지 않음
         match value {
-            Value::Word(w) => w.clone(),
-            Value::Closure(params, body, _) => {
-                format!("(λ{} . {})", params.join(" "), self.pretty_expr(body))
+            Value::Closure(_, body, _) if matches!(&**body, Expr::Function(params, inner)
+                if params.len() == 1 && !matches!(**inner, Expr::Function(..))) => {}
+            _ => return None,
+        }
+
+        // 해석해 보는 동안에도 같은 제한을 걺. 넘으면 해석하지 못한 것으로 봄
+        let mut probe = Interpreter::new(Config { sandbox: self.config.sandbox.clone(), ..Config::default() });
+        probe.env = self.env.clone();
+        probe.globals = self.globals.clone();
+        probe.name_counter = self.name_counter.clone();
+
+        let t = Value::Word("#t".to_string());
+        let f = Value::Word("#f".to_string());
+        let applied = probe.apply(value.clone(), t)
+            .and_then(|v| probe.apply(v, f));
+        match applied {
+            Ok(Value::Word(w)) if w == "#t" => return Some(Decoded::Bool(true)),
+            Ok(Value::Word(w)) if w == "#f" => return Some(Decoded::Bool(false)),
+            _ => {}
+        }
+
+        if let Some(n) = probe.decode_nat(value) {
+            return Some(Decoded::Nat(n));
+        }
+
+        let cons = Value::Builtin(Builtin::Cons, Vec::new());
+        let nil = Value::Word("#\"".to_string());
+        let applied = probe.apply(value.clone(), cons)
+            .and_then(|v| probe.apply(v, nil));
+        match applied {
+            Ok(Value::Word(w)) => w.strip_prefix("#\"")
+                .map(|s| Decoded::Str(s.to_string())),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn decode_nat(&mut self, value: &Value) -> Option<usize> {
+        let succ = Value::Builtin(Builtin::Succ, Vec::new());
+        let zero = Value::Word("#0".to_string());
+        let applied = self.apply(value.clone(), succ)
+            .and_then(|v| self.apply(v, zero));
+        match applied {
+            Ok(Value::Word(w)) => w.strip_prefix('#')?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// 지금 환경에서 부를 수 있는 정의 이름들
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.env.keys().cloned().collect::<Vec<_>>();
+        names.extend(self.globals.borrow().values.keys().cloned());
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        self.env.get(name)
+    }
+
+    /// 프로그램이 내는 출력(`print`). `-q`이면 버림
+    pub fn emit(&self, line: &str) {
+        if self.config.verbosity > Verbosity::Quiet {
+            self.config.output.println(line);
+        }
+    }
+
+    pub fn deterministic(&self) -> bool {
+        self.config.deterministic
+    }
+
+    pub fn sandbox(&self) -> Option<&Rc<Sandbox>> {
+        self.config.sandbox.as_ref()
+    }
+
+    /// 다음 평가부터 걸 제한. 세션의 인터프리터는 요청마다 새 제한으로 바꿈
+    pub fn set_sandbox(&mut self, sandbox: Option<Rc<Sandbox>>) {
+        self.config.sandbox = sandbox;
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.config.verbosity
+    }
+
+    /// 이름순으로 정렬한 최상위 환경
+    pub fn bindings(&self) -> Vec<(String, Value)> {
+        let globals = self.globals.borrow();
+        self.names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.env.get(&name).or_else(|| globals.values.get(&name))?.clone();
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    /// 최상위 정의마다 `name = value` 한 줄씩, 이름 순서로
+    pub fn dump_env(&self, mode: EnvDump) -> Vec<String> {
+        self.bindings()
+            .into_iter()
+            .map(|(name, value)| {
+                let raw = || self.pretty_value(&value);
+                let normal = || self.normal_form(&value)
+                    .map_or_else(|_| raw(), |e| normalize::canonical(&e).to_string());
+                let shown = match mode {
+                    EnvDump::Raw => raw(),
+                    EnvDump::Normal => normal(),
+                    EnvDump::Decoded => self.decode(&value).map_or_else(normal, |d| d.to_string()),
+                };
+                self.stable(format!("{} = {}", name, shown))
+            })
+            .collect()
+    }
+
+    /// 최상위 정의를 `name = value` 정의들로 되돌림. 클로저가 잡은 환경은 본문에 대입되므로
+    /// 이 프로그램을 다시 평가하면 무거운 계산 없이 같은 환경이 만들어짐
+    pub fn env_program(&self) -> Expr {
+        Expr::Sequence(self.bindings()
+            .into_iter()
+            .map(|(name, value)| Expr::Define(name, None, Box::new(normalize::readback(&value))))
+            .collect())
+    }
+
+    pub fn emit_result(&self, line: &str) {
+        self.config.output.result(line);
+    }
+
+    pub fn display_value(&self, value: &Value) -> String {
+        match self.decode(value) {
+            Some(decoded) => decoded.to_string(),
+            None => self.format_result(value),
+        }
+    }
+
+    pub fn name_of(&self, value: &Value) -> Option<String> {
+        let found = self.first_defined(self.env.iter()
+            .filter(|(_, v)| *v == value)
+            .map(|(name, _)| name));
+        if found.is_some() || !matches!(value, Value::Closure(..)) {
+            return found;
+        }
+        // `--opt prenorm`은 정의의 값을 새로 만들므로 원래 이름으로 되돌린 정규형이 같은지 비교함.
+        // 이름까지 같아야 하므로 `L x y. x`인 `k`를 `true`로 부르지는 않음
+        if self.config.prenorm {
+            let plain = |v: &Value| self.normal_form(v).map(|normal| normalize::plain_names(&normal));
+            let normal = plain(value).ok()?;
+            let found = self.first_defined(self.env.iter()
+                .filter(|(_, v)| matches!(v, Value::Closure(..)))
+                .filter(|(_, v)| plain(v).is_ok_and(|known| known == normal))
+                .map(|(name, _)| name));
+            if found.is_some() {
+                return found;
             }
         }
+        if self.config.names.is_empty() {
+            return None;
+        }
+        let normal = self.normal_form(value).ok()?;
+        self.config.names.iter()
+            .find(|(_, known)| normalize::alpha_eq(known, &normal))
+            .map(|(name, _)| name.clone())
+    }
+
+    /// 같은 값인 이름이 여럿이면 먼저 정의된 이름, 최상위 정의가 아니면 이름순으로 가장 앞선 이름.
+    /// 해시 순서를 따르면 `true = L a b. a; assert_t = true`의 결과가 실행할 때마다 달라짐
+    fn first_defined<'a>(&self, names: impl Iterator<Item = &'a String>) -> Option<String> {
+        let globals = self.globals.borrow();
+        let rank = |name: &String| globals.order.iter().position(|n| n == name).unwrap_or(usize::MAX);
+        names.min_by_key(|name| (rank(name), *name)).cloned()
+    }
+
+    pub fn format_result(&self, value: &Value) -> String {
+        let shown = match self.config.format {
+            ResultFormat::Pretty => self.name_of(value).unwrap_or_else(|| self.pretty_value(value)),
+            ResultFormat::Compact => self.elided(normalize::canonical_letters(&normalize::readback(value))).compact(),
+            ResultFormat::Canonical => self.elided(normalize::canonical(&normalize::readback(value))).to_string(),
+        };
+        self.stable(shown)
+    }
+
+    /// `--deterministic`이면 축약하면서 붙은 `x$17` 같은 번호를 이 글 안에서 나온 순서대로 `x$1`, `x$2`, …로 다시 매김.
+    /// 번호는 그 전까지 몇 번 축약했는지에 따라 달라지므로, 상관없는 정의를 고쳐도 결과가 바뀌지 않게 함
+    fn stable(&self, text: String) -> String {
+        if !self.config.deterministic || !text.contains('$') {
+            return text;
+        }
+        let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+        let mut numbers: HashMap<(String, String), usize> = HashMap::new();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut out = String::new();
+        let mut rest = text.as_str();
+        while let Some(at) = rest.find('$') {
+            let (before, after) = rest.split_at(at);
+            let digits = after[1..].chars().take_while(char::is_ascii_digit).collect::<String>();
+            let base = &before[before.trim_end_matches(is_name).len()..];
+            out.push_str(before);
+            if digits.is_empty() || base.is_empty() {
+                out.push('$');
+                rest = &after[1..];
+                continue;
+            }
+            let number = *numbers.entry((base.to_string(), digits.clone())).or_insert_with(|| {
+                let count = counts.entry(base.to_string()).or_insert(0);
+                *count += 1;
+                *count
+            });
+            out.push_str(&format!("${}", number));
+            rest = &after[1 + digits.len()..];
+        }
+        out.push_str(rest);
+        out
     }
 
     fn pretty_value(&self, v: &Value) -> String {
+        self.pretty_value_at(v, self.config.print_depth)
+    }
+
+    /// `depth`가 남아 있으면 본문이 쓰는 잡힌 값을 `[n = four, m = (λ…)]`처럼 덧붙인다.
+    /// 이름이 있는 값은 이름으로, 없으면 한 단계 얕게 펼쳐서 보여 줌
+    fn pretty_value_at(&self, v: &Value, depth: usize) -> String {
         match v {
             Value::Word(w) => w.clone(),
-            Value::Closure(params, body, _) => {
-                format!("(λ{} . {})", params.join(" "), self.pretty_expr(body))
+            Value::Closure(param, body, env) => {
+                let (params, body) = uncurried(param, body);
+                let shown = format!("(λ{} . {})", params.join(" "), self.shown_expr(body));
+                if depth == 0 {
+                    return shown;
+                }
+                let mut captured = normalize::free_vars(body).into_iter()
+                    .filter(|name| !params.contains(&name.as_str()))
+                    .filter_map(|name| {
+                        let value = env.get(&name)?;
+                        let value = self.name_of(value).unwrap_or_else(|| self.pretty_value_at(value, depth - 1));
+                        Some(format!("{} = {}", name, value))
+                    })
+                    .collect::<Vec<_>>();
+                if captured.is_empty() {
+                    return shown;
+                }
+                captured.sort();
+                format!("{} [{}]", shown, captured.join(", "))
+            }
+            Value::Thunk(expr, _) => self.shown_expr(expr),
+            Value::Builtin(builtin, args) => {
+                std::iter::once(builtin.name().to_string())
+                    .chain(args.iter().map(|a| self.pretty_value_at(a, depth)))
+                    .collect::<Vec<_>>()
+                    .join(" ")
             }
         }
     }
 
+    fn elided(&self, expr: Expr) -> Expr {
+        match self.config.max_print_nodes {
+            Some(limit) => expr.elide(limit),
+            None => expr,
+        }
+    }
+
+    /// 사용자에게 보여 줄 식. 상태 비교에 쓰는 `pretty_expr`와 달리 `--max-print-nodes`를 따름
+    fn shown_expr(&self, expr: &Expr) -> String {
+        match self.config.max_print_nodes {
+            Some(limit) => self.pretty_expr(&expr.elide(limit)),
+            None => self.pretty_expr(expr),
+        }
+    }
+
     fn pretty_expr(&self, expr: &Expr) -> String {
         match expr {
             Expr::Word(w) => w.clone(),
@@ -163,7 +847,7 @@ impl Interpreter {
             Expr::Function(params, body) => {
                 format!("(λ{} . {})", params.join(" "), self.pretty_expr(body))
             }
-            Expr::Define(name, body) => {
+            Expr::Define(name, _, body) => {
                 format!("{} = {}", name, self.pretty_expr(body))
             }
             Expr::Sequence(seq) => {
@@ -175,6 +859,13 @@ impl Interpreter {
             Expr::Paren(inner) => {
                 format!("({})", self.pretty_expr(inner))
             }
+            Expr::Assert(lhs, rhs) => {
+                format!("assert {} == {}", self.pretty_expr(lhs), self.pretty_expr(rhs))
+            }
+            Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.pretty_expr(inner),
         }
     }
 
@@ -210,8 +901,8 @@ impl Interpreter {
                 let new_body = self.rename(*body, &new_mapping);
                 Expr::Function(new_params, Box::new(new_body))
             }
-            Expr::Define(name, body) => {
-                Expr::Define(name, Box::new(self.rename(*body, mapping)))
+            Expr::Define(name, ty, body) => {
+                Expr::Define(name, ty, Box::new(self.rename(*body, mapping)))
             }
             Expr::Sequence(seq) => {
                 Expr::Sequence(seq.into_iter()
@@ -221,6 +912,148 @@ impl Interpreter {
             Expr::Paren(inner) => {
                 Expr::Paren(Box::new(self.rename(*inner, mapping)))
             }
+            Expr::Assert(lhs, rhs) => {
+                Expr::Assert(
+                    Box::new(self.rename(*lhs, mapping)),
+                    Box::new(self.rename(*rhs, mapping)),
+                )
+            }
+            Expr::Annotated(types, inner) => {
+                Expr::Annotated(types, Box::new(self.rename(*inner, mapping)))
+            }
+            Expr::TypeFunction(vars, inner) => {
+                Expr::TypeFunction(vars, Box::new(self.rename(*inner, mapping)))
+            }
+            Expr::TypeApply(inner, ty) => {
+                Expr::TypeApply(Box::new(self.rename(*inner, mapping)), ty)
+            }
+            Expr::Located(span, inner) => {
+                Expr::Located(span, Box::new(self.rename(*inner, mapping)))
+            }
         }
     }
 }
+
+/// 매개변수가 여럿인 람다를 첫 매개변수와 나머지를 받는 람다로 나눔
+fn curried(mut params: Vec<String>, body: Box<Expr>) -> Result<(String, Box<Expr>), String> {
+    if params.is_empty() {
+        return Err(messages::text(Msg::NoParameterLeft, &[]));
+    }
+    let param = params.remove(0);
+    match params.is_empty() {
+        true => Ok((param, body)),
+        false => Ok((param, Box::new(Expr::Function(params, body)))),
+    }
+}
+
+/// `curried`를 되돌려 보여 줄 때 씀: 본문이 람다인 동안 매개변수를 모음
+fn uncurried<'a>(param: &'a str, mut body: &'a Expr) -> (Vec<&'a str>, &'a Expr) {
+    let mut params = vec![param];
+    while let Expr::Function(inner, next) = body {
+        params.extend(inner.iter().map(String::as_str));
+        body = next;
+    }
+    (params, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn failed(source: &str) -> Error {
+        let mut interpreter = Interpreter::new(Config::default());
+        interpreter.eval(Parser::new(source).spans(true).parse().unwrap()).unwrap_err()
+    }
+
+    /// 모든 평가 방식으로 마지막 식의 β-정규형을 구해 `expected`와 α-동치인지 봄
+    fn reduces_to(source: &str, expected: &str) {
+        let expected = Parser::new(expected).parse().unwrap();
+        for eval_mode in [EvalMode::Substitution, EvalMode::Environment] {
+            for args in [ArgMode::Strict, ArgMode::Lazy] {
+                let mut interpreter = Interpreter::new(Config { eval_mode, args, ..Config::default() });
+                let value = interpreter.eval(Parser::new(source).parse().unwrap()).unwrap();
+                let normal = interpreter.normal_form(&value).unwrap();
+                assert!(normalize::alpha_eq(&normal, &expected), "{:?} {:?}: {}", eval_mode, args, normal);
+            }
+        }
+    }
+
+    #[test]
+    fn evaluates_church_arithmetic_in_every_mode() {
+        reduces_to(
+            "two = L f x. f (f x); plus = L m n f x. m f (n f x); plus two two",
+            "L f x. f (f (f (f x)))",
+        );
+    }
+
+    #[test]
+    fn closures_applied_to_more_arguments_than_parameters_keep_applying() {
+        reduces_to("((L x. x) (L y. y)) (L z. z)", "L z. z");
+        reduces_to("id = L x. x; k = L x y. x; id k (L a. a) (L b. b)", "L a. a");
+    }
+
+    #[test]
+    fn lazy_arguments_are_not_evaluated_when_unused() {
+        let mut interpreter = Interpreter::new(Config { args: ArgMode::Lazy, ..Config::default() });
+        let source = "k = L x y. x; k (L a. a) ((L x. x x) (L x. x x))";
+        assert!(interpreter.eval(Parser::new(source).parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn passing_assertions_evaluate_quietly() {
+        reduces_to("id = L x. x; assert id id == id; id", "L x. x");
+    }
+
+    #[test]
+    fn errors_carry_the_kind_they_were_raised_with() {
+        assert_eq!(failed("x = y; y = L a. a; x").kind, ErrorKind::Unbound);
+        assert_eq!(failed("(L x. x x) (L x. x x)").kind, ErrorKind::StepLimit);
+        assert_eq!(failed("print \"hi\"").kind, ErrorKind::Io);
+        assert_eq!(failed("x = L a. a; assert x == L a b. a").kind, ErrorKind::Other);
+    }
+
+    #[test]
+    fn the_innermost_location_is_kept() {
+        let e = failed("x = y; y = L a. a; x");
+        assert_eq!(e.to_string(), "'y' is used before its definition is evaluated.\n  at line 1, column 5");
+    }
+
+    #[test]
+    fn rec_definitions_call_themselves() {
+        reduces_to(
+            "true = L t f. t; false = L t f. f; rec loop = L b. b (L _. loop false) (L _. L a. a) (L _. _); loop true",
+            "L a. a",
+        );
+        assert!(Parser::new("rec x = x;").parse().is_err());
+    }
+
+    #[test]
+    fn consecutive_rec_definitions_call_each_other() {
+        reduces_to(
+            "true = L t f. t; false = L t f. f; not = L b. b false true;
+             rec even = L b. b (L _. odd (not b)) (L _. true) (L _. _);
+             rec odd = L b. b (L _. even (not b)) (L _. false) (L _. _);
+             even true",
+            "L t f. f",
+        );
+        assert!(Parser::new("rec f = g; rec g = L x. x;").parse().is_err());
+    }
+
+    #[test]
+    fn functions_may_refer_to_later_definitions() {
+        reduces_to("twice = L x. id (id x); id = L a. a; twice (L b. b)", "L b. b");
+        assert_eq!(failed("x = y; y = L a. a; x").kind, ErrorKind::Unbound);
+    }
+
+    #[test]
+    fn if_and_match_pick_only_the_chosen_branch() {
+        let prelude = "true = L t f. t; false = L t f. f; pair = L a b s. s a b; none = L n s. n; some = L x n s. s x; \
+                       omega = L _. (L x. x x) (L x. x x);";
+        reduces_to(&format!("{} if false then (omega omega) else (L a. a)", prelude), "L a. a");
+        reduces_to(&format!("{} match (pair (L a. a) (L b. b b)) with (x, y) -> x", prelude), "L a. a");
+        reduces_to(&format!("{} match (some (L a. a)) with none -> (omega omega) | some v -> v", prelude), "L a. a");
+        assert!(Parser::new("match b with true -> x").parse().is_err());
+        assert!(Parser::new("match o with none -> x | true -> y").parse().is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::{Error, ErrorKind};
+use crate::messages::{self, Msg};
+
+// 시그널 처리기 안에서는 이 칸에 표시만 하고, 축약 루프가 다음 단계에서 확인해서 멈춤
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" {
+    fn signal(signum: i32, handler: usize) -> usize;
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: i32) {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Ctrl-C(SIGINT)로 프로세스를 바로 죽이지 않고 평가를 멈추도록 요청만 남김.
+/// 유닉스가 아니면 아무 일도 하지 않음
+pub fn install() {
+    #[cfg(unix)]
+    {
+        const SIGINT: i32 = 2;
+        // SAFETY: 처리기는 원자적 쓰기만 하므로 시그널 안전함
+        unsafe {
+            signal(SIGINT, on_sigint as extern "C" fn(i32) as usize);
+        }
+    }
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// REPL에서 다음 입력을 평가하기 전에 지난 요청을 지움
+pub fn clear() {
+    REQUESTED.store(false, Ordering::SeqCst);
+}
+
+/// 요청이 있으면 지금까지의 단계 수와 줄이던 식을 담은 오류를 돌려줌.
+/// 식은 멈출 때만 만들도록 함수로 받음
+pub fn check(steps: usize, term: impl FnOnce() -> String) -> Result<(), Error> {
+    match requested() {
+        true => Err(Error::new(ErrorKind::Interrupted, format!("{}\n  at: {}", messages::text(Msg::Interrupted, &[&steps]), term()))),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,67 @@
+use crate::ast::Expr;
+use crate::interpreter::{Decoded, Interpreter, Value};
+use crate::normalize;
+
+/// 최종 결과를 한 줄짜리 JSON 객체로 바꾼다.
+/// `kind`는 `closure`, `symbol`, `builtin`, `thunk`(`--args lazy`에서 미뤄 둔 인자) 중 하나이고, 식은 모두 `normalize::canonical` 형태의 문자열이다.
+/// `{"kind":"closure","params":["v0","v1"],"body":"v0","term":"L v0 v1. v0","name":"true","decoded":true}`
+pub fn result(interpreter: &Interpreter, value: &Value) -> String {
+    let mut fields = Vec::new();
+    match value {
+        Value::Closure(..) => {
+            let term = normalize::canonical(&normalize::readback(value));
+            fields.push(("kind", string("closure")));
+            if let Expr::Function(params, body) = &term {
+                let params = params.iter().map(|p| string(p)).collect::<Vec<_>>();
+                fields.push(("params", format!("[{}]", params.join(","))));
+                fields.push(("body", string(&body.to_string())));
+            }
+            fields.push(("term", string(&term.to_string())));
+        }
+        Value::Thunk(..) => {
+            let term = normalize::canonical(&normalize::readback(value));
+            fields.push(("kind", string("thunk")));
+            fields.push(("term", string(&term.to_string())));
+        }
+        Value::Word(w) => {
+            fields.push(("kind", string("symbol")));
+            fields.push(("term", string(w)));
+        }
+        Value::Builtin(builtin, args) => {
+            fields.push(("kind", string("builtin")));
+            fields.push(("builtin", string(builtin.name())));
+            fields.push(("args", args.len().to_string()));
+        }
+    }
+    let name = interpreter.name_of(value).map_or("null".to_string(), |name| string(&name));
+    fields.push(("name", name));
+    let decoded = match interpreter.decode(value) {
+        Some(Decoded::Bool(b)) => b.to_string(),
+        Some(Decoded::Nat(n)) => n.to_string(),
+        Some(Decoded::Str(s)) => string(&s),
+        None => "null".to_string(),
+    };
+    fields.push(("decoded", decoded));
+
+    let fields = fields.into_iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+pub fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
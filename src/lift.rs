@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use crate::ast::Expr;
+use crate::cps::collect_names;
+use crate::normalize::free_vars;
+
+/// 람다 끌어올리기(lambda lifting). 모든 람다를 최상위 정의(supercombinator)로 옮기고,
+/// 바깥 람다에서 가져다 쓰던 변수는 앞쪽 매개변수로 넘긴다: `L x. L y. x y` => `f0 = L x y. x y; f1 = L x. f0 x; f1`.
+/// 끌어올린 정의는 그것을 쓰는 문장 바로 앞에 놓이므로 결과를 그대로 평가할 수 있다.
+pub fn transform(program: &Expr) -> Result<Expr, String> {
+    let mut names = HashSet::new();
+    collect_names(program, &mut names);
+    let mut lift = Lift { names, counter: 0, lifted: Vec::new() };
+
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut result = Vec::new();
+    for statement in statements {
+        let statement = match statement {
+            // 최상위 람다는 이미 supercombinator이므로 이름을 그대로 씀
+            Expr::Define(name, _, body) => match strip(body) {
+                Expr::Function(params, inner) => {
+                    let mut scope = params.clone();
+                    let inner = lift.lift(inner, &mut scope)?;
+                    Expr::Define(name.clone(), None, Box::new(Expr::Function(params.clone(), Box::new(inner))))
+                }
+                body => Expr::Define(name.clone(), None, Box::new(lift.lift(body, &mut Vec::new())?)),
+            },
+            Expr::Assert(lhs, rhs) => Expr::Assert(
+                Box::new(lift.lift(lhs, &mut Vec::new())?),
+                Box::new(lift.lift(rhs, &mut Vec::new())?),
+            ),
+            other => lift.lift(other, &mut Vec::new())?,
+        };
+        result.append(&mut lift.lifted);
+        result.push(statement);
+    }
+    Ok(match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Expr::Sequence(result),
+    })
+}
+
+struct Lift {
+    names: HashSet<String>,
+    counter: usize,
+    lifted: Vec<Expr>,
+}
+
+impl Lift {
+    /// `scope`는 바깥 람다들이 묶은 변수 (안쪽이 뒤)
+    fn lift(&mut self, expr: &Expr, scope: &mut Vec<String>) -> Result<Expr, String> {
+        match expr {
+            Expr::Word(_) => Ok(expr.clone()),
+            Expr::Words(words) => words.iter()
+                .map(|e| self.lift(e, scope))
+                .collect::<Result<_, _>>()
+                .map(Expr::Words),
+            Expr::Paren(inner) => match self.lift(inner, scope)? {
+                lifted @ Expr::Word(_) => Ok(lifted),
+                lifted => Ok(Expr::Paren(Box::new(lifted))),
+            },
+            Expr::Function(params, body) => {
+                let depth = scope.len();
+                scope.extend(params.iter().cloned());
+                let body = self.lift(body, scope);
+                scope.truncate(depth);
+                let body = body?;
+
+                // 바깥에서 묶였고 본문에서 쓰이는 변수만 추가 매개변수가 됨
+                let used = free_vars(&body);
+                let mut captured = Vec::new();
+                for var in scope.iter() {
+                    if used.contains(var) && !params.contains(var) && !captured.contains(var) {
+                        captured.push(var.clone());
+                    }
+                }
+                let name = self.fresh("f");
+                let all_params = captured.iter().chain(params).cloned().collect();
+                self.lifted.push(Expr::Define(name.clone(), None, Box::new(Expr::Function(all_params, Box::new(body)))));
+
+                let head = Expr::Word(name);
+                if captured.is_empty() {
+                    Ok(head)
+                } else {
+                    Ok(Expr::Words(std::iter::once(head).chain(captured.into_iter().map(Expr::Word)).collect()))
+                }
+            }
+            Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.lift(inner, scope),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Definitions and asserts are only allowed at the top level".to_string())
+            }
+        }
+    }
+
+    /// 프로그램에 나오지 않는 이름만 쓰므로 사용자 변수를 가로채지 않음
+    fn fresh(&mut self, base: &str) -> String {
+        loop {
+            let name = format!("{}{}", base, self.counter);
+            self.counter += 1;
+            if !self.names.contains(&name) {
+                return name;
+            }
+        }
+    }
+}
+
+/// 괄호와 타입 표기를 벗긴다.
+fn strip(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => strip(inner),
+        _ => expr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Config, Interpreter};
+    use crate::normalize;
+    use crate::parser::Parser;
+
+    fn normal_form(program: &Expr) -> Expr {
+        let mut interpreter = Interpreter::new(Config::default());
+        let value = interpreter.eval(program.clone()).unwrap();
+        interpreter.normal_form(&value).unwrap()
+    }
+
+    /// 끌어올린 정의들을 거쳐도 마지막 식의 결과가 그대로인지 봄
+    fn evaluates_like(source: &str, expected: &str) {
+        let program = transform(&Parser::new(source).parse().unwrap()).unwrap();
+        let expected = transform(&Parser::new(expected).parse().unwrap()).unwrap();
+        let (got, expected) = (normal_form(&program), normal_form(&expected));
+        assert!(normalize::alpha_eq(&got, &expected), "{} / {}", got, expected);
+    }
+
+    #[test]
+    fn transformed_programs_evaluate_to_the_same_result() {
+        evaluates_like("id = L x. x; k = L x y. x; k id (id k)", "L x. x");
+        evaluates_like("two = L f x. f (f x); two two", "L f x. f (f (f (f x)))");
+        evaluates_like("(L x y. x) (L a. a) (L b. b b)", "L a. a");
+        evaluates_like("id = L x. x; assert id id == id; id", "L x. x");
+    }
+
+    #[test]
+    fn nested_definitions_are_rejected() {
+        let nested = Expr::Words(vec![
+            Expr::Word("f".to_string()),
+            Expr::Define("x".to_string(), None, Box::new(Expr::Word("y".to_string()))),
+        ]);
+        assert!(transform(&nested).is_err());
+    }
+}
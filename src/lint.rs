@@ -0,0 +1,297 @@
+use crate::ast::Expr;
+use crate::normalize;
+
+/// 매개변수가 몇 번 쓰이는지 검사한다.
+/// 한 번도 쓰지 않으면 선형(linear)이 아니고, 두 번 이상 쓰면 아핀(affine)도 아니다.
+pub fn linearity(program: &Expr) -> Vec<String> {
+    each_statement(program, check)
+}
+
+/// `L x. f x`처럼 마지막 인자를 그대로 넘기기만 하는 람다를 찾아 η-축약한 모양을 알려 준다.
+/// `x`가 `f`에 자유 변수로 나오면 줄일 수 없음
+pub fn eta(program: &Expr) -> Vec<String> {
+    each_statement(program, check_eta)
+}
+
+/// `lint --eta --fix`: `eta`가 알리는 람다를 모두 η-축약한 식
+pub fn eta_fixed(expr: &Expr) -> Expr {
+    expr.rewrite_bottom_up(|e| match &e {
+        Expr::Function(params, body) => eta_reduce(params, body).unwrap_or(e),
+        _ => e,
+    })
+}
+
+/// 본문에 한 번도 나오지 않는 매개변수를 찾는다. Church 인코딩에서는 인자 순서를 바꿔 쓴 실수일 때가 많음.
+/// 일부러 버리는 매개변수는 `_`로 시작하는 이름을 쓰면 알리지 않음
+pub fn unused(program: &Expr) -> Vec<String> {
+    each_statement(program, check_unused)
+}
+
+/// `L x x. x`처럼 한 람다에서 같은 이름을 두 번 이상 쓴 매개변수를 찾는다.
+/// 뒤의 매개변수가 앞의 것을 가리므로 앞의 인자는 쓸 수 없음
+pub fn duplicates(program: &Expr) -> Vec<String> {
+    each_statement(program, check_duplicates)
+}
+
+/// 평가해 보기 전에 알 수 있는 무한 루프를 찾는다. 지금은 `(L x. x x) (L x. x x)`나
+/// `(L x. f (x x)) (L x. f (x x))`처럼 자기 자신에 적용하는 함수를 자기에게 적용하는 모양만 봄.
+/// `x x`가 안쪽 람다 밑에 있으면(`L v. x x v`) 평가가 늦춰지므로 알리지 않음
+pub fn divergence(program: &Expr) -> Vec<String> {
+    each_statement(program, check_divergence)
+}
+
+fn each_statement(program: &Expr, check: fn(&Expr, &str, &mut Vec<String>)) -> Vec<String> {
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut warnings = Vec::new();
+    for statement in statements {
+        match statement {
+            Expr::Define(name, _, body) => check(body, &format!("'{}'", name), &mut warnings),
+            Expr::Assert(lhs, rhs) => {
+                check(lhs, "assert", &mut warnings);
+                check(rhs, "assert", &mut warnings);
+            }
+            other => check(other, "<eval>", &mut warnings),
+        }
+    }
+    warnings
+}
+
+fn check(expr: &Expr, context: &str, warnings: &mut Vec<String>) {
+    match expr {
+        Expr::Word(_) => {}
+        Expr::Words(words) | Expr::Sequence(words) => {
+            for e in words {
+                check(e, context, warnings);
+            }
+        }
+        Expr::Function(params, body) => {
+            for (i, param) in params.iter().enumerate() {
+                // 뒤쪽 매개변수가 같은 이름이면 그쪽이 가림
+                let uses = if params[i + 1..].contains(param) { 0 } else { count_uses(body, param) };
+                match uses {
+                    0 => warnings.push(format!("in {}: parameter '{}' is never used (not linear)", context, param)),
+                    1 => {}
+                    n => warnings.push(format!("in {}: parameter '{}' is used {} times (not affine)", context, param, n)),
+                }
+            }
+            check(body, context, warnings);
+        }
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => check(inner, context, warnings),
+        Expr::Assert(lhs, rhs) => {
+            check(lhs, context, warnings);
+            check(rhs, context, warnings);
+        }
+    }
+}
+
+fn check_unused(expr: &Expr, context: &str, warnings: &mut Vec<String>) {
+    match expr {
+        Expr::Word(_) => {}
+        Expr::Words(words) | Expr::Sequence(words) => {
+            for e in words {
+                check_unused(e, context, warnings);
+            }
+        }
+        Expr::Function(params, body) => {
+            for (i, param) in params.iter().enumerate() {
+                let shadowed = params[i + 1..].contains(param);
+                if !param.starts_with('_') && (shadowed || count_uses(body, param) == 0) {
+                    warnings.push(format!("in {}: parameter '{}' is never used", context, param));
+                }
+            }
+            check_unused(body, context, warnings);
+        }
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => check_unused(inner, context, warnings),
+        Expr::Assert(lhs, rhs) => {
+            check_unused(lhs, context, warnings);
+            check_unused(rhs, context, warnings);
+        }
+    }
+}
+
+fn check_duplicates(expr: &Expr, context: &str, warnings: &mut Vec<String>) {
+    match expr {
+        Expr::Word(_) => {}
+        Expr::Words(words) | Expr::Sequence(words) => {
+            for e in words {
+                check_duplicates(e, context, warnings);
+            }
+        }
+        Expr::Function(params, body) => {
+            for (i, param) in params.iter().enumerate() {
+                let count = params.iter().filter(|p| *p == param).count();
+                // 이름마다 처음 나온 곳에서 한 번만 알림
+                if count > 1 && !params[..i].contains(param) {
+                    warnings.push(format!(
+                        "in {}: parameter '{}' is bound {} times in 'L {}.'; only the last one can be used",
+                        context, param, count, params.join(" "),
+                    ));
+                }
+            }
+            check_duplicates(body, context, warnings);
+        }
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => check_duplicates(inner, context, warnings),
+        Expr::Assert(lhs, rhs) => {
+            check_duplicates(lhs, context, warnings);
+            check_duplicates(rhs, context, warnings);
+        }
+    }
+}
+
+fn check_divergence(expr: &Expr, context: &str, warnings: &mut Vec<String>) {
+    match expr {
+        Expr::Word(_) => {}
+        Expr::Words(words) | Expr::Sequence(words) => {
+            if let (Expr::Words(_), [f, arg, ..]) = (expr, words.as_slice()) {
+                if self_applying(strip(f)) && self_applying(strip(arg)) {
+                    let redex = Expr::Words(vec![f.clone(), arg.clone()]);
+                    warnings.push(format!(
+                        "in {}: '{}' applies a self-applying function to itself and never terminates when evaluated",
+                        context, redex,
+                    ));
+                }
+            }
+            for e in words {
+                check_divergence(e, context, warnings);
+            }
+        }
+        Expr::Function(_, inner)
+        | Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => check_divergence(inner, context, warnings),
+        Expr::Assert(lhs, rhs) => {
+            check_divergence(lhs, context, warnings);
+            check_divergence(rhs, context, warnings);
+        }
+    }
+}
+
+/// 인자 하나를 받아 그 인자를 자기 자신에 바로 적용하는 람다: `L x. ... (x x) ...`
+fn self_applying(expr: &Expr) -> bool {
+    match expr {
+        Expr::Function(params, body) if params.len() == 1 => applies_self(body, &params[0]),
+        _ => false,
+    }
+}
+
+fn applies_self(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Words(words) => {
+            let is_name = |e: &Expr| matches!(strip(e), Expr::Word(w) if w == name);
+            (words.len() >= 2 && is_name(&words[0]) && is_name(&words[1]))
+                || words.iter().any(|e| applies_self(e, name))
+        }
+        Expr::Paren(inner) | Expr::Located(_, inner) => applies_self(inner, name),
+        _ => false,
+    }
+}
+
+fn count_uses(expr: &Expr, name: &str) -> usize {
+    match expr {
+        Expr::Word(w) => usize::from(w == name),
+        Expr::Words(words) | Expr::Sequence(words) => words.iter().map(|e| count_uses(e, name)).sum(),
+        Expr::Function(params, _) if params.iter().any(|p| p == name) => 0,
+        Expr::Function(_, inner)
+        | Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => count_uses(inner, name),
+        Expr::Assert(lhs, rhs) => count_uses(lhs, name) + count_uses(rhs, name),
+    }
+}
+
+fn check_eta(expr: &Expr, context: &str, warnings: &mut Vec<String>) {
+    match expr {
+        Expr::Word(_) => {}
+        Expr::Words(words) | Expr::Sequence(words) => {
+            for e in words {
+                check_eta(e, context, warnings);
+            }
+        }
+        Expr::Function(params, body) => match eta_reduce(params, body) {
+            Some(reduced) => {
+                warnings.push(format!("in {}: '{}' can be eta-reduced to '{}'", context, expr, reduced));
+                check_eta(&reduced, context, warnings);
+            }
+            None => check_eta(body, context, warnings),
+        },
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => check_eta(inner, context, warnings),
+        Expr::Assert(lhs, rhs) => {
+            check_eta(lhs, context, warnings);
+            check_eta(rhs, context, warnings);
+        }
+    }
+}
+
+/// 뒤쪽 매개변수부터 더 줄일 수 없을 때까지 η-축약한 식. 하나도 줄일 수 없으면 `None`
+fn eta_reduce(params: &[String], body: &Expr) -> Option<Expr> {
+    let mut params = params.to_vec();
+    let mut body = body.clone();
+    let mut reduced = false;
+    while let Some(param) = params.last() {
+        let Expr::Words(words) = strip(&body) else {
+            break;
+        };
+        let Some((last, init)) = words.split_last() else {
+            break;
+        };
+        // 함수 부분에서도 매개변수를 쓰면 줄일 수 없음
+        if strip(last) != &Expr::Word(param.clone())
+            || init.iter().any(|e| normalize::free_vars(e).contains(param))
+        {
+            break;
+        }
+        let head = match init {
+            [single] => strip(single).clone(),
+            _ => Expr::Words(init.to_vec()),
+        };
+        // 마지막 람다까지 벗기면 함수 부분을 바로 평가하게 되므로 값일 때만 줄임.
+        // `rec`이 만드는 `L v. x x v`처럼 평가를 늦추려고 감싼 람다는 그대로 둠
+        if params.len() == 1 && !matches!(head, Expr::Word(_) | Expr::Function(..)) {
+            break;
+        }
+        body = head;
+        params.pop();
+        reduced = true;
+    }
+    match (reduced, params.is_empty()) {
+        (false, _) => None,
+        (true, true) => Some(body),
+        (true, false) => Some(Expr::Function(params, Box::new(body))),
+    }
+}
+
+fn strip(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(inner) | Expr::Located(_, inner) => strip(inner),
+        other => other,
+    }
+}
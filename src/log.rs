@@ -0,0 +1,189 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::json;
+use crate::style::{self, Stream};
+
+/// 기록의 중요도. 뒤로 갈수록 자세함
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+/// 기록을 낸 곳. `--log`에서 이 이름으로 따로 켬
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Parser,
+    Interp,
+    Repl,
+    // `serve`와 `daemon`
+    Server,
+}
+
+impl Target {
+    const ALL: [Target; 4] = [Target::Parser, Target::Interp, Target::Repl, Target::Server];
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        Target::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Target::Parser => "parser",
+            Target::Interp => "interp",
+            Target::Repl => "repl",
+            Target::Server => "server",
+        }
+    }
+}
+
+/// `--log-format text|json`. `json`이면 한 줄에 객체 하나(JSON Lines)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+struct Logger {
+    // `Target::ALL`의 순서로, 대상마다 낼 가장 자세한 중요도
+    levels: [Level; 4],
+    format: Format,
+    // 없으면 stderr
+    file: Option<Mutex<File>>,
+}
+
+// `serve`와 `daemon`의 스레드도 같은 설정을 봐야 하므로 프로그램 전체에 하나.
+// 설정하지 않으면 모든 대상에서 경고 이상만 stderr로
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+const DEFAULT: Level = Level::Warn;
+
+/// `--log`, `--log-file`, `--log-format`을 적용한다. `spec`은 `interp=debug,parser=info`처럼
+/// 쉼표로 나눈 `대상=중요도`이고, 대상 없이 `debug`라고만 쓰면 모든 대상에 적용됨
+pub fn init(spec: Option<&str>, file: Option<&str>, format: Format) -> Result<(), String> {
+    let mut levels = [DEFAULT; 4];
+    for item in spec.unwrap_or_default().split(',').filter(|item| !item.is_empty()) {
+        let (target, level) = match item.split_once('=') {
+            Some((target, level)) => (Some(target), level),
+            None => (None, item),
+        };
+        let level = Level::from_name(level)
+            .ok_or_else(|| format!("Unknown log level '{}' (levels: error, warn, info, debug, trace)", level))?;
+        match target {
+            None => levels = [level; 4],
+            Some(name) => {
+                let target = Target::from_name(name)
+                    .ok_or_else(|| format!("Unknown log target '{}' (targets: parser, interp, repl, server)", name))?;
+                levels[target as usize] = level;
+            }
+        }
+    }
+    let file = file
+        .map(|path| OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("Failed to open the log file '{}': {}", path, e)))
+        .transpose()?;
+    let logger = Logger { levels, format, file: file.map(Mutex::new) };
+    LOGGER.set(logger).map_err(|_| "The log is already set up.".to_string())
+}
+
+pub fn enabled(target: Target, level: Level) -> bool {
+    let most = LOGGER.get().map_or(DEFAULT, |logger| logger.levels[target as usize]);
+    level <= most
+}
+
+/// 켠 대상과 중요도면 기록함. 메시지는 기록할 때만 만들도록 함수로 받음
+pub fn log(target: Target, level: Level, message: impl FnOnce() -> String) {
+    if !enabled(target, level) {
+        return;
+    }
+    let message = message();
+    let logger = LOGGER.get();
+    let line = match logger.map_or(Format::Text, |logger| logger.format) {
+        Format::Text => format!("[{} {}] {}", level.name(), target.name(), message),
+        Format::Json => {
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            format!(
+                "{{\"time\":{:.3},\"level\":{},\"target\":{},\"message\":{}}}",
+                time.as_secs_f64(),
+                json::string(level.name()),
+                json::string(target.name()),
+                json::string(&message),
+            )
+        }
+    };
+    match logger.and_then(|logger| logger.file.as_ref()) {
+        Some(file) => {
+            let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(file, "{}", line);
+        }
+        None if logger.is_some_and(|logger| logger.format == Format::Json) => {
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+        None => {
+            let line = match level {
+                Level::Error => style::error(Stream::Stderr, &line),
+                Level::Warn => style::warning(Stream::Stderr, &line),
+                _ => style::dim(Stream::Stderr, &line),
+            };
+            let _ = writeln!(io::stderr(), "{}", line);
+        }
+    }
+}
+
+pub fn error(target: Target, message: impl FnOnce() -> String) {
+    log(target, Level::Error, message);
+}
+
+pub fn warn(target: Target, message: impl FnOnce() -> String) {
+    log(target, Level::Warn, message);
+}
+
+pub fn info(target: Target, message: impl FnOnce() -> String) {
+    log(target, Level::Info, message);
+}
+
+pub fn debug(target: Target, message: impl FnOnce() -> String) {
+    log(target, Level::Debug, message);
+}
+
+pub fn trace(target: Target, message: impl FnOnce() -> String) {
+    log(target, Level::Trace, message);
+}
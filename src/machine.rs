@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::error::Error;
+use crate::interrupt;
+use crate::lift;
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::style::{self, Stream};
+
+const MAX_STEPS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+enum Node {
+    App(usize, usize),
+    // 최상위 supercombinator. 매개변수가 없으면 한 번만 계산되는 CAF
+    Global(String, Vec<String>, Expr),
+    // 묶이지 않은 이름. 더 줄어들지 않음
+    Symbol(String),
+    // 계산이 끝난 노드는 결과를 가리키도록 덮어써서 공유함
+    Ind(usize),
+}
+
+/// 템플릿 인스턴스화 방식의 그래프 환원기.
+/// 프로그램을 람다 끌어올리기로 supercombinator들로 바꾼 뒤, 마지막 식을 약한 머리 정규형까지 줄인다.
+pub struct Machine {
+    heap: Vec<Node>,
+    globals: HashMap<String, usize>,
+    // 끌어올리기 전부터 있던 정의 이름
+    defines: HashSet<String>,
+    trace: bool,
+}
+
+impl Machine {
+    pub fn new(trace: bool) -> Self {
+        Machine { heap: Vec::new(), globals: HashMap::new(), defines: HashSet::new(), trace }
+    }
+
+    /// 결과를 정의 이름이나 다시 파싱할 수 있는 정규형으로 돌려준다.
+    pub fn run(&mut self, program: &Expr) -> Result<String, Error> {
+        if let Expr::Sequence(exprs) = program {
+            for expr in exprs {
+                if let Expr::Define(name, _, _) = expr {
+                    self.defines.insert(name.clone());
+                }
+            }
+        }
+        let program = lift::transform(program)?;
+        let statements = match program {
+            Expr::Sequence(exprs) => exprs,
+            other => vec![other],
+        };
+
+        let mut result = None;
+        for statement in statements {
+            match statement {
+                Expr::Define(name, _, body) => {
+                    let (params, body) = match *body {
+                        Expr::Function(params, body) => (params, *body),
+                        body => (Vec::new(), body),
+                    };
+                    let addr = self.alloc(Node::Global(name.clone(), params, body));
+                    self.globals.insert(name, addr);
+                }
+                Expr::Assert(lhs, rhs) => {
+                    let left = self.normal_form(&lhs)?;
+                    let right = self.normal_form(&rhs)?;
+                    if !normalize::alpha_eq(&left, &right) {
+                        return Err(format!(
+                            "{}\n  {:<6} {}\n  {:<6} {}",
+                            messages::text(Msg::AssertionFailed, &[]),
+                            messages::text(Msg::AssertionLeft, &[]),
+                            normalize::canonical(&left),
+                            messages::text(Msg::AssertionRight, &[]),
+                            normalize::canonical(&right),
+                        ).into());
+                    }
+                }
+                other => result = Some(other),
+            }
+        }
+
+        let expr = match result {
+            Some(expr) => expr,
+            None => return Ok("()".to_string()),
+        };
+        let root = self.instantiate(&expr, &HashMap::new())?;
+        let root = self.eval(root)?;
+        match &self.heap[self.resolve(root)] {
+            Node::Global(name, _, _) if self.defines.contains(name) => Ok(name.clone()),
+            _ => Ok(normalize::canonical(&self.strong(root)?).to_string()),
+        }
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        self.heap.push(node);
+        self.heap.len() - 1
+    }
+
+    fn normal_form(&mut self, expr: &Expr) -> Result<Expr, Error> {
+        let root = self.instantiate(expr, &HashMap::new())?;
+        self.strong(root)
+    }
+
+    /// 정규형까지 줄인다. 인자가 모자란 supercombinator에는 새 이름을 넣어 람다 안쪽도 줄이고,
+    /// 묶이지 않은 이름이 머리이면 인자들을 각각 줄인다.
+    fn strong(&mut self, addr: usize) -> Result<Expr, Error> {
+        let root = self.eval(addr)?;
+        let mut args = Vec::new();
+        let mut head = self.resolve(root);
+        while let Node::App(func, arg) = self.heap[head] {
+            args.push(arg);
+            head = self.resolve(func);
+        }
+        args.reverse();
+
+        match self.heap[head].clone() {
+            Node::Global(_, params, _) => {
+                // `#`로 시작하는 이름은 파서가 만들 수 없으므로 사용자 이름과 겹치지 않음
+                let fresh = (args.len()..params.len())
+                    .map(|i| format!("#{}", self.heap.len() + i))
+                    .collect::<Vec<_>>();
+                let mut applied = root;
+                for name in &fresh {
+                    let symbol = self.alloc(Node::Symbol(name.clone()));
+                    applied = self.alloc(Node::App(applied, symbol));
+                }
+                match self.strong(applied)? {
+                    Expr::Function(mut params, body) => {
+                        let mut all = fresh;
+                        all.append(&mut params);
+                        Ok(Expr::Function(all, body))
+                    }
+                    body => Ok(Expr::Function(fresh, Box::new(body))),
+                }
+            }
+            Node::Symbol(name) => {
+                let mut words = vec![Expr::Word(name)];
+                for arg in args {
+                    words.push(match self.strong(arg)? {
+                        arg @ Expr::Word(_) => arg,
+                        arg => Expr::Paren(Box::new(arg)),
+                    });
+                }
+                Ok(match words.len() {
+                    1 => words.pop().unwrap(),
+                    _ => Expr::Words(words),
+                })
+            }
+            _ => Err(messages::text(Msg::BrokenSpine, &[]).into()),
+        }
+    }
+
+    /// 식의 그래프를 만든다. 같은 매개변수는 같은 노드를 가리키므로 인자의 계산이 공유된다.
+    fn instantiate(&mut self, expr: &Expr, env: &HashMap<String, usize>) -> Result<usize, String> {
+        match expr {
+            Expr::Word(name) => {
+                if let Some(&addr) = env.get(name).or_else(|| self.globals.get(name)) {
+                    Ok(addr)
+                } else if Builtin::from_name(name).is_some() {
+                    Err(messages::text(Msg::MachineUnsupported, &[name]))
+                } else {
+                    Ok(self.alloc(Node::Symbol(name.clone())))
+                }
+            }
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                let mut func = self.instantiate(head, env)?;
+                for arg in args {
+                    let arg = self.instantiate(arg, env)?;
+                    func = self.alloc(Node::App(func, arg));
+                }
+                Ok(func)
+            }
+            Expr::Paren(inner) | Expr::Located(_, inner) => self.instantiate(inner, env),
+            _ => Err(messages::text(Msg::UnexpectedLifted, &[expr])),
+        }
+    }
+
+    /// 스택으로 척추(spine)를 따라 내려가며 약한 머리 정규형이 될 때까지 줄인다.
+    fn eval(&mut self, root: usize) -> Result<usize, Error> {
+        let mut stack = vec![root];
+        for step in 0..MAX_STEPS {
+            if self.trace {
+                self.print_state(step, &stack);
+            }
+            let top = *stack.last().unwrap();
+            interrupt::check(step, || self.describe(top))?;
+            match self.heap[top].clone() {
+                Node::App(func, _) => stack.push(func),
+                Node::Ind(target) => {
+                    stack.pop();
+                    stack.push(target);
+                }
+                Node::Symbol(_) => return Ok(stack[0]),
+                Node::Global(_, params, body) => {
+                    if stack.len() <= params.len() {
+                        // 부분 적용도 약한 머리 정규형
+                        return Ok(stack[0]);
+                    }
+                    let mut env = HashMap::new();
+                    for (i, param) in params.iter().enumerate() {
+                        let app = stack[stack.len() - 2 - i];
+                        match self.heap[app] {
+                            Node::App(_, arg) => env.insert(param.clone(), arg),
+                            _ => return Err(messages::text(Msg::BrokenSpine, &[]).into()),
+                        };
+                    }
+                    let redex = stack[stack.len() - 1 - params.len()];
+                    let result = self.instantiate(&body, &env)?;
+                    // 환원한 자리를 결과로 덮어써서 다른 곳에서도 다시 계산하지 않음
+                    self.heap[redex] = Node::Ind(result);
+                    stack.truncate(stack.len() - 1 - params.len());
+                    stack.push(result);
+                }
+            }
+        }
+        Err(Error::step_limit(messages::text(Msg::GraphSteps, &[&MAX_STEPS])))
+    }
+
+    fn print_state(&self, step: usize, stack: &[usize]) {
+        println!("{}", style::dim(Stream::Stdout, &format!("--- step {} ---", step)));
+        for (i, &addr) in stack.iter().rev().enumerate() {
+            // 스택 맨 위가 다음에 줄일 노드
+            let node = format!("#{} = {}", addr, self.describe(addr));
+            match i {
+                0 => println!("  {}", style::redex(Stream::Stdout, &node)),
+                _ => println!("  {}", node),
+            }
+        }
+        println!();
+    }
+
+    fn describe(&self, addr: usize) -> String {
+        match &self.heap[addr] {
+            Node::App(func, arg) => format!("@ #{} #{}", func, arg),
+            Node::Global(name, params, _) => format!("{} (arity {})", name, params.len()),
+            Node::Symbol(name) => format!("symbol {}", name),
+            Node::Ind(target) => format!("-> #{}", target),
+        }
+    }
+
+    fn resolve(&self, addr: usize) -> usize {
+        match self.heap[addr] {
+            Node::Ind(target) => self.resolve(target),
+            _ => addr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn run(source: &str) -> Result<String, Error> {
+        Machine::new(false).run(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn reduces_to_a_definition_name_or_normal_form() {
+        assert_eq!(run("id = L x. x; k = L x y. x; k id (id k)").unwrap(), "id");
+        assert_eq!(run("two = L f x. f (f x); two (L b. b) (L a. a)").unwrap(), "L v0. v0");
+    }
+
+    #[test]
+    fn divergent_programs_hit_the_step_limit() {
+        let error = run("(L x. x x) (L x. x x)").unwrap_err();
+        assert_eq!(error.kind, crate::error::ErrorKind::StepLimit);
+    }
+}
@@ -1,38 +1,1445 @@
+use std::cell::RefCell;
 use std::env;
 use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::rc::Rc;
+use std::time::Duration;
 
+use crate::messages::Msg;
+
+mod anf;
 mod ast;
+mod builtins;
+mod bytecode;
+mod cbpv;
+mod closure;
+mod codegen_c;
+mod codegen_rust;
+mod cps;
+#[cfg(unix)]
+mod daemon;
+mod diff;
+mod editor;
+mod encode;
+mod enumerate;
+mod error;
+mod exercise;
+mod explore;
+mod files;
+mod format;
+mod gen;
+mod grade;
+mod highlight;
+mod incremental;
+mod lift;
+mod lint;
+mod log;
+mod machine;
+mod messages;
 mod parser;
+mod plugin;
+mod progress;
+mod stlc;
+mod style;
+mod systemf;
 mod interpreter;
+mod interrupt;
+mod json;
+mod normalize;
+mod opt;
+mod repl;
+mod sandbox;
+mod scope;
+mod server;
+mod sigma;
+mod test_runner;
+mod trace;
+mod visit;
+mod watch;
+
+struct Options {
+    config: interpreter::Config,
+    files: Vec<String>,
+    snapshot: Option<String>,
+    update: bool,
+    machine: Option<String>,
+    // `--output json`
+    json: bool,
+    dump_env: Option<interpreter::EnvDump>,
+    // `--save-env`/`--load-env`의 `.lenv` 파일
+    save_env: Option<String>,
+    load_env: Option<String>,
+    // `--opt`이나 `--passes`로 켠 변환. 준 순서대로 적용
+    opts: Vec<opt::Pass>,
+    // `--dump-after`: 이 패스를 적용한 프로그램을 stderr로
+    dump_after: Option<opt::Pass>,
+    // `--trace-format`: 평가하는 대신 정규 순서로 줄이는 과정을 이 모양으로 출력
+    trace: Option<trace::TraceFormat>,
+    // `--explain`: 그 과정의 단계마다 설명 한 줄
+    explain: bool,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    take_color_option(&mut args);
+    take_log_options(&mut args);
+    take_lang_option(&mut args);
+    take_plugin_options(&mut args);
+
+    match args.get(1).map(String::as_str) {
+        Some("run") => run(&args[0], parse_options(&args[0], &args[2..], false)),
+        Some("repl") => {
+            let options = parse_options(&args[0], &args[2..], false);
+            if !options.files.is_empty() {
+                usage(&args[0]);
+            }
+            repl::run(options.config);
+        }
+        // 인자 없이 터미널에서 실행하면 REPL
+        None if io::stdin().is_terminal() => repl::run(interpreter::Config::default()),
+        Some("watch") => {
+            let options = parse_options(&args[0], &args[2..], false);
+            if options.files.is_empty() || options.files.iter().any(|f| f == "-") {
+                usage(&args[0]);
+            }
+            let files = expand_inputs(&options.files);
+            watch::watch(&files, options.config);
+        }
+        Some("test") => {
+            let options = parse_options(&args[0], &args[2..], false);
+            let inputs = match options.files.is_empty() {
+                true => vec![".".to_string()],
+                false => options.files,
+            };
+            let files = expand_inputs(&inputs);
+            if !test_runner::run_tests(&files, options.config) {
+                std::process::exit(1);
+            }
+        }
+        Some("parse") => parse_command(&args[0], &args[2..]),
+        Some("check") => check_command(&args[0], &args[2..]),
+        Some("lint") => lint_command(&args[0], &args[2..]),
+        Some("transform") => transform_command(&args[0], &args[2..]),
+        Some("compile") => compile_command(&args[0], &args[2..]),
+        Some("gen") => gen_command(&args[0], &args[2..]),
+        Some("highlight") => highlight_command(&args[0], &args[2..]),
+        Some("graph") => graph_command(&args[0], &args[2..]),
+        Some("fmt") => fmt_command(&args[0], &args[2..]),
+        Some("enumerate") => enumerate_command(&args[0], &args[2..]),
+        Some("explore") => explore_command(&args[0], &args[2..]),
+        Some("cbpv") => cbpv_command(&args[0], &args[2..]),
+        Some("confluence") => confluence_command(&args[0], &args[2..]),
+        Some("grade") => grade_command(&args[0], &args[2..]),
+        Some("serve") => serve_command(&args[0], &args[2..]),
+        Some("daemon") => daemon_command(&args[0], &args[2..]),
+        Some("exercise") => {
+            let options = parse_options(&args[0], &args[2..], false);
+            let [spec, file] = options.files.as_slice() else {
+                usage(&args[0]);
+            };
+            let text = fs::read_to_string(spec)
+                .unwrap_or_else(|e| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&spec, &e])));
+            let exercises = exercise::parse_spec(&text)
+                .unwrap_or_else(|e| fail_as(Failure::Parse, &messages::text(Msg::ErrorIn, &[&spec, &e])));
+            if !exercise::run_exercises(&exercises, file, options.config) {
+                std::process::exit(1);
+            }
+        }
+        _ => run(&args[0], parse_options(&args[0], &args[1..], true)),
+    }
+}
 
-    let mut debug = false;
-    let filename: &str;
+/// 어느 명령에서나 쓸 수 있는 `--color always|never|auto`를 꺼내서 적용
+fn take_color_option(args: &mut Vec<String>) {
+    let Some(i) = args.iter().position(|a| a == "--color") else {
+        return;
+    };
+    let choice = args.get(i + 1)
+        .and_then(|name| style::ColorChoice::from_name(name))
+        .unwrap_or_else(|| usage(&args[0]));
+    style::set_choice(choice);
+    args.drain(i..i + 2);
+}
 
-    if args.len() == 2 {
-        filename = &args[1];
-    } else if args.len() == 3 && args[1] == "-b" {
-        debug = true;
-        filename = &args[2];
-    } else {
-        eprintln!("Usage: {} [-b] <source-file>", args[0]);
+/// 어느 명령에서나 쓸 수 있는 `--lang en|ko`를 꺼내서 적용. 없으면 로케일 환경 변수를 따름
+fn take_lang_option(args: &mut Vec<String>) {
+    let lang = match args.iter().position(|a| a == "--lang") {
+        Some(i) => {
+            let lang = args.get(i + 1)
+                .and_then(|name| messages::Lang::from_name(name))
+                .unwrap_or_else(|| usage(&args[0]));
+            args.drain(i..i + 2);
+            Some(lang)
+        }
+        None => messages::Lang::from_env(),
+    };
+    if let Some(lang) = lang {
+        messages::set_lang(lang);
+    }
+}
+
+/// 어느 명령에서나 쓸 수 있는 `--plugin <lib>`를 모두 꺼내서 그 내장 함수를 불러옴. 여러 번 줄 수 있음
+fn take_plugin_options(args: &mut Vec<String>) {
+    let mut paths = Vec::new();
+    while let Some(i) = args.iter().position(|a| a == "--plugin") {
+        let path = args.get(i + 1).cloned().unwrap_or_else(|| usage(&args[0]));
+        args.drain(i..i + 2);
+        paths.push(path);
+    }
+    if !paths.is_empty() {
+        plugin::load(&paths).unwrap_or_else(|e| fail(&e));
+    }
+}
+
+/// 어느 명령에서나 쓸 수 있는 `--log SPEC`, `--log-file <path>`, `--log-format text|json`을 꺼내서 적용
+fn take_log_options(args: &mut Vec<String>) {
+    let mut take = |flag: &str| {
+        let i = args.iter().position(|a| a == flag)?;
+        let value = args.get(i + 1).cloned().unwrap_or_else(|| usage(&args[0]));
+        args.drain(i..i + 2);
+        Some(value)
+    };
+    let spec = take("--log");
+    let file = take("--log-file");
+    let format = take("--log-format");
+    if spec.is_none() && file.is_none() && format.is_none() {
+        return;
+    }
+    let format = match format {
+        Some(name) => log::Format::from_name(&name).unwrap_or_else(|| fail(&format!("Unknown log format '{}'", name))),
+        None => log::Format::Text,
+    };
+    log::init(spec.as_deref(), file.as_deref(), format)
+        .unwrap_or_else(|e| fail(&e));
+}
+
+/// `script`이면 첫 파일 이름 뒤의 인자는 스크립트 인자로 남겨둠
+fn parse_options(program: &str, args: &[String], script: bool) -> Options {
+    let mut options = Options {
+        config: interpreter::Config::default(),
+        files: Vec::new(),
+        snapshot: None,
+        update: false,
+        machine: None,
+        json: false,
+        dump_env: None,
+        save_env: None,
+        load_env: None,
+        opts: Vec::new(),
+        dump_after: None,
+        trace: None,
+        explain: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if script && !options.files.is_empty() {
+            break;
+        }
+        match arg.as_str() {
+            "-b" => options.config.debug = true,
+            "-q" => options.config.verbosity = interpreter::Verbosity::Quiet,
+            "-v" => options.config.verbosity = interpreter::Verbosity::Verbose,
+            "-vv" => options.config.verbosity = interpreter::Verbosity::VeryVerbose,
+            "--allow-io" => options.config.allow_io = true,
+            "--print-depth" => {
+                let depth = args.next().and_then(|n| n.parse().ok());
+                options.config.print_depth = depth.unwrap_or_else(|| usage(program));
+            }
+            "--max-print-nodes" => {
+                let limit = args.next().and_then(|n| n.parse().ok());
+                options.config.max_print_nodes = Some(limit.unwrap_or_else(|| usage(program)));
+            }
+            "--eval" => match args.next().map(String::as_str) {
+                Some("subst") => options.config.eval_mode = interpreter::EvalMode::Substitution,
+                Some("env") => options.config.eval_mode = interpreter::EvalMode::Environment,
+                _ => usage(program),
+            },
+            "--args" => match args.next().map(String::as_str) {
+                Some("strict") => options.config.args = interpreter::ArgMode::Strict,
+                Some("lazy") => options.config.args = interpreter::ArgMode::Lazy,
+                _ => usage(program),
+            },
+            "--deterministic" => options.config.deterministic = true,
+            "--explain" => options.explain = true,
+            "--trace-format" => {
+                let format = args.next().and_then(|name| trace::TraceFormat::from_name(name));
+                options.trace = Some(format.unwrap_or_else(|| usage(program)));
+            }
+            "--compact" => options.config.format = interpreter::ResultFormat::Compact,
+            "--canonical" => options.config.format = interpreter::ResultFormat::Canonical,
+            "--snapshot" => {
+                let path = args.next().unwrap_or_else(|| usage(program));
+                options.snapshot = Some(path.clone());
+            }
+            "--update" => options.update = true,
+            "--output" => match args.next().map(String::as_str) {
+                Some("json") => options.json = true,
+                Some("text") => options.json = false,
+                _ => usage(program),
+            },
+            "--dump-env" => {
+                let mode = args.next().and_then(|name| interpreter::EnvDump::from_name(name));
+                options.dump_env = Some(mode.unwrap_or_else(|| usage(program)));
+            }
+            "--save-env" => {
+                let path = args.next().unwrap_or_else(|| usage(program));
+                options.save_env = Some(path.clone());
+            }
+            "--load-env" => {
+                let path = args.next().unwrap_or_else(|| usage(program));
+                options.load_env = Some(path.clone());
+            }
+            "--names" => {
+                let path = args.next().unwrap_or_else(|| usage(program));
+                options.config.names = Rc::new(load_names(path));
+            }
+            "--opt" => {
+                let pass = args.next().and_then(|name| opt::Pass::from_name(name));
+                options.opts.push(pass.unwrap_or_else(|| usage(program)));
+            }
+            "--passes" => {
+                let list = args.next().unwrap_or_else(|| usage(program));
+                options.opts.extend(opt::parse_pipeline(list).unwrap_or_else(|e| fail(&e)));
+            }
+            "--dump-after" => {
+                let pass = args.next().and_then(|name| opt::Pass::from_name(name));
+                options.dump_after = Some(pass.unwrap_or_else(|| usage(program)));
+            }
+            "--machine" => {
+                let machine = args.next().unwrap_or_else(|| usage(program));
+                options.machine = Some(machine.clone());
+            }
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    if options.update && options.snapshot.is_none() {
+        usage(program);
+    }
+    options
+}
+
+/// 파일들을 순서대로 같은 환경에 평가하고 마지막 결과를 출력
+fn run(program: &str, options: Options) {
+    // 파일을 생략했는데 stdin이 파이프이면 stdin에서 읽음
+    let mut files = match options.files.is_empty() {
+        true if !io::stdin().is_terminal() => vec!["-".to_string()],
+        true => usage(program),
+        false => expand_inputs(&options.files),
+    };
+    // 저장해 둔 환경은 정의만 담긴 바이트코드 프로그램이므로 맨 앞 파일로 평가하면 됨
+    if let Some(path) = options.load_env {
+        files.insert(0, path);
+    }
+
+    interrupt::install();
+    let mut config = options.config;
+    let quiet = config.verbosity == interpreter::Verbosity::Quiet;
+    let captured = Rc::new(RefCell::new(String::new()));
+    if options.snapshot.is_some() {
+        config.output = interpreter::Output::Capture(captured.clone());
+    }
+
+    if options.trace.is_some() || options.explain {
+        if options.machine.is_some() || !options.opts.is_empty() || options.json {
+            fail(&messages::text(Msg::TraceCombination, &[]));
+        }
+        let derivation = trace::derivation(&parse_program(&files))
+            .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &messages::text(Msg::Error, &[&e])));
+        let text = match options.trace {
+            Some(trace::TraceFormat::Markdown) => trace::markdown(&derivation, options.explain),
+            Some(trace::TraceFormat::Mermaid) => trace::mermaid(&derivation, options.explain),
+            None => trace::explained(&derivation),
+        };
+        config.output.println(text.trim_end());
+        if let Some(snapshot) = options.snapshot {
+            check_snapshot(&snapshot, &captured.borrow(), options.update, quiet);
+        }
+        if !derivation.complete {
+            std::process::exit(Failure::StepLimit as i32);
+        }
+        return;
+    }
+
+    match options.machine.as_deref() {
+        Some(_) if options.json => fail("--output json is not supported with --machine"),
+        Some(_) if options.dump_env.is_some() => fail("--dump-env is not supported with --machine"),
+        Some(_) if options.save_env.is_some() => fail("--save-env is not supported with --machine"),
+        Some(_) if !options.opts.is_empty() => fail("--opt and --passes are not supported with --machine"),
+        Some("g") => {
+            let started = std::time::Instant::now();
+            let result = machine::Machine::new(config.debug).run(&parse_program(&files))
+                .unwrap_or_else(|e| match interrupt::requested() {
+                    true => stop_interrupted(&e, started),
+                    false => fail_as(Failure::of(e.kind), &messages::text(Msg::Error, &[&e])),
+                });
+            config.output.result(&result);
+        }
+        Some("sigma") => {
+            let started = std::time::Instant::now();
+            let result = sigma::Machine::new(config.debug).sandbox(config.sandbox.clone()).run(&parse_program(&files))
+                .unwrap_or_else(|e| match interrupt::requested() {
+                    true => stop_interrupted(&e, started),
+                    false => fail_as(Failure::of(e.kind), &messages::text(Msg::Error, &[&e])),
+                });
+            config.output.result(&result);
+        }
+        Some(other) => fail(&messages::text(Msg::UnknownMachine, &[&other])),
+        None => {
+            match options.dump_after {
+                Some(opt::Pass::Prenorm) => fail("--dump-after prenorm is not supported; prenorm runs while evaluating (try --dump-env normal)"),
+                Some(pass) if !options.opts.contains(&pass) => fail(&format!("--dump-after {}: the pass is not in --passes", pass.name())),
+                _ => {}
+            }
+            // 평가하면서 하는 변환이라 인터프리터 설정으로 넘김
+            config.prenorm |= options.opts.contains(&opt::Pass::Prenorm);
+            let passes = Pipeline { passes: &options.opts, dump_after: options.dump_after };
+            run_interpreter(&files, config, passes, options.json, options.dump_env, options.save_env.as_deref())
+        }
+    }
+
+    if let Some(snapshot) = options.snapshot {
+        check_snapshot(&snapshot, &captured.borrow(), options.update, quiet);
+    }
+}
+
+/// `run`이 평가하기 전에 파일마다 적용할 변환들
+struct Pipeline<'a> {
+    passes: &'a [opt::Pass],
+    dump_after: Option<opt::Pass>,
+}
+
+fn run_interpreter(
+    files: &[String],
+    mut config: interpreter::Config,
+    pipeline: Pipeline,
+    json: bool,
+    dump_env: Option<interpreter::EnvDump>,
+    save_env: Option<&str>,
+) {
+    // 진행 상황에는 걸린 시간이 들어가므로 `--deterministic`이면 알리지 않음.
+    // 표준 입력은 다음 줄을 기다리는 시간까지 들어가므로 알리지 않음
+    if config.verbosity > interpreter::Verbosity::Quiet && !config.deterministic && !files.iter().any(|file| file == "-") {
+        config.progress = Some(Rc::new(progress::Progress::new()));
+    }
+    let steps = config.steps.clone();
+    let output = config.output.clone();
+    let started = std::time::Instant::now();
+    let mut interpreter = interpreter::Interpreter::new(config);
+    let mut result = None;
+    let mut context = parser::Context::default();
+
+    for file in files {
+        // 변환 단계는 프로그램 전체를 보므로 없을 때만 표준 입력을 문장마다 평가함
+        if file == "-" && pipeline.passes.is_empty()
+            && stream_stdin(&mut context, |ast| result = Some(evaluate(&mut interpreter, file, ast, started)))
+        {
+            continue;
+        }
+        // 평가 중 오류가 난 식의 자리를 알릴 수 있도록 자리를 붙여 읽음
+        let mut ast = parse_file_in(file, false, true, &mut context);
+        for &pass in pipeline.passes {
+            let stats;
+            (ast, stats) = pass.run(&ast).unwrap_or_else(|e| fail(&messages::text(Msg::ErrorIn, &[&file, &e])));
+            if interpreter.verbosity() >= interpreter::Verbosity::Verbose {
+                eprintln!("{}", stats);
+            }
+            if pipeline.dump_after == Some(pass) {
+                eprintln!("-- {} after {} --\n{}", file, pass.name(), program_text(&ast));
+            }
+        }
+        result = Some(evaluate(&mut interpreter, file, ast, started));
+    }
+
+    match result {
+        Some(result) if json => interpreter.emit_result(&json::result(&interpreter, &result)),
+        Some(result) => interpreter.emit_result(&interpreter.format_result(&result)),
+        None => {}
+    }
+    // 요청한 출력이므로 결과처럼 stdout(또는 스냅샷)으로
+    if let Some(mode) = dump_env {
+        for line in interpreter.dump_env(mode) {
+            output.println(&line);
+        }
+    }
+
+    if let Some(path) = save_env {
+        fs::write(path, bytecode::encode(&interpreter.env_program()))
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e)));
+    }
+
+    // 부가 정보는 결과와 섞이지 않도록 stderr로
+    let verbosity = interpreter.verbosity();
+    if verbosity >= interpreter::Verbosity::Verbose {
+        eprintln!("β-reductions: {}", steps.get());
+        if !interpreter.deterministic() {
+            eprintln!("time: {:.3} ms", started.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+    if verbosity >= interpreter::Verbosity::VeryVerbose {
+        eprintln!("environment:");
+        for (name, value) in interpreter.bindings() {
+            // 자기 이름으로 출력되지 않도록 정의 이름 대신 식을 보여 줌
+            let shown = interpreter.decode(&value).map_or_else(|| value.to_string(), |d| d.to_string());
+            eprintln!("  {} = {}", name, shown);
+        }
+    }
+}
+
+/// `--names` 파일의 정의를 평가해서 이름마다 β-정규형을 구해 둠
+fn load_names(path: &str) -> Vec<(String, ast::Expr)> {
+    let mut interpreter = interpreter::Interpreter::new(interpreter::Config::default());
+    interpreter.eval(parse_file(path))
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &messages::text(Msg::ErrorIn, &[&path, &e])));
+    interpreter.bindings()
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, interpreter.normal_form(&value).ok()?)))
+        .collect()
+}
+
+/// Ctrl-C로 멈춘 곳의 식과 단계 수, 걸린 시간을 알리고 끝냄
+fn stop_interrupted(error: &error::Error, started: std::time::Instant) -> ! {
+    eprintln!("{}", style::warning(style::Stream::Stderr, &error.to_string()));
+    eprintln!("time: {:.3} ms", started.elapsed().as_secs_f64() * 1000.0);
+    std::process::exit(Failure::Interrupted as i32);
+}
+
+/// 출력을 저장된 스냅샷과 비교. 스냅샷이 없거나 `update`이면 새로 기록
+fn check_snapshot(path: &str, actual: &str, update: bool, quiet: bool) {
+    let expected = fs::read_to_string(path).ok();
+    match expected {
+        Some(expected) if !update => {
+            if expected == actual {
+                if !quiet {
+                    println!("Snapshot {} matches.", path);
+                }
+            } else {
+                eprintln!("Snapshot {} differs:", path);
+                eprint!("{}", diff::line_diff(&expected, actual));
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            fs::write(path, actual)
+                .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e)));
+            if !quiet {
+                println!("Snapshot {} written.", path);
+            }
+        }
+    }
+}
+
+/// `parse [--binary-application] <file>...`: 파싱한 AST를 트리로 출력
+fn parse_command(program: &str, args: &[String]) {
+    let mut binary_application = false;
+    let mut inputs = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--binary-application" => binary_application = true,
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    for file in &files {
+        print!("{}", parse_file_with(file, binary_application, false).tree());
+    }
+}
+
+/// `check [--typed stlc|systemf | --free-vars] <file>...`
+fn check_command(program: &str, args: &[String]) {
+    let mut typed = None;
+    let mut free_vars = false;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--typed" => typed = args.next().cloned(),
+            "--free-vars" => free_vars = true,
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+    if free_vars {
+        return print_free_vars(&files);
+    }
+
+    match typed.as_deref() {
+        Some("stlc") => {
+            let mut checker = stlc::Checker::new();
+            let mut last = None;
+            for file in &files {
+                let ast = parse_file(file);
+                if let Some(ty) = checker.check(&ast)
+                    .unwrap_or_else(|e| fail(&messages::text(Msg::TypeErrorIn, &[file, &e])))
+                {
+                    last = Some(ty);
+                }
+            }
+            match last {
+                Some(ty) => println!("{}", stlc::rename_vars(&ty)),
+                None => println!("ok"),
+            }
+        }
+        Some("systemf") => {
+            let mut checker = systemf::Checker::new();
+            let mut last = None;
+            for file in &files {
+                let ast = parse_file(file);
+                if let Some(ty) = checker.check(&ast)
+                    .unwrap_or_else(|e| fail(&messages::text(Msg::TypeErrorIn, &[file, &e])))
+                {
+                    last = Some(ty);
+                }
+            }
+            match last {
+                Some(ty) => println!("{}", ty),
+                None => println!("ok"),
+            }
+        }
+        Some(other) => fail(&format!("Unknown type system '{}'", other)),
+        None => check_scope(&files),
+    }
+}
+
+/// `check --free-vars <file>...`: 최상위 정의마다 바깥에서 가져다 쓰는 이름을 표로 보여 줌
+fn print_free_vars(files: &[String]) {
+    for file in files {
+        let table = scope::free_variables(&parse_file(file));
+        let width = table.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        println!("{}", style::dim(style::Stream::Stdout, file));
+        for (name, free) in table {
+            let free = match free.is_empty() {
+                true => "(none)".to_string(),
+                false => free.join(", "),
+            };
+            println!("  {:width$}  {}", name, free, width = width);
+        }
+    }
+}
+
+/// `check <file>...`: 평가하지 않고 이름을 찾을 수 없는 낱말을 자리와 함께 모두 알림
+fn check_scope(files: &[String]) {
+    let programs = files.iter()
+        .map(|file| parse_file_with(file, false, true))
+        .collect::<Vec<_>>();
+    let mut clean = true;
+    for (file, found) in files.iter().zip(scope::unresolved(&programs)) {
+        for unresolved in found {
+            let location = unresolved.span.map_or(String::new(), |span| format!("{}: ", span));
+            let problem = match unresolved.early {
+                true => format!("'{}' is used before its definition is evaluated", unresolved.name),
+                false => format!("unbound name '{}'", unresolved.name),
+            };
+            println!("{}: {}{}", style::dim(style::Stream::Stdout, file), location, style::warning(style::Stream::Stdout, &problem));
+            clean = false;
+        }
+    }
+    if !clean {
+        std::process::exit(Failure::Unbound as i32);
+    }
+    println!("ok");
+}
+
+/// `lint [--linearity] [--eta [--fix]] [--unused] [--duplicates] [--divergence] <file>...`
+fn lint_command(program: &str, args: &[String]) {
+    let mut linearity = false;
+    let mut eta = false;
+    let mut unused = false;
+    let mut duplicates = false;
+    let mut divergence = false;
+    let mut fix = false;
+    let mut inputs = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--linearity" => linearity = true,
+            "--eta" => eta = true,
+            "--unused" => unused = true,
+            "--duplicates" => duplicates = true,
+            "--divergence" => divergence = true,
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if !(linearity || eta || unused || duplicates || divergence) || inputs.is_empty() || (fix && !eta) {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let mut clean = true;
+    for file in &files {
+        if fix {
+            fix_eta(file);
+        }
+        let ast = parse_file(file);
+        let mut warnings = Vec::new();
+        if linearity {
+            warnings.extend(lint::linearity(&ast));
+        }
+        if eta {
+            warnings.extend(lint::eta(&ast));
+        }
+        if unused {
+            warnings.extend(lint::unused(&ast));
+        }
+        if duplicates {
+            warnings.extend(lint::duplicates(&ast));
+        }
+        if divergence {
+            warnings.extend(lint::divergence(&ast));
+        }
+        for warning in warnings {
+            println!("{}: {}", style::dim(style::Stream::Stdout, file), style::warning(style::Stream::Stdout, &warning));
+            clean = false;
+        }
+    }
+    if !clean {
         std::process::exit(1);
     }
+    println!("ok");
+}
+
+/// `lint --eta --fix`: η-축약할 수 있는 람다를 줄여서 파일을 고쳐 씀. 고친 파일은 다시 읽어 나머지 검사를 함
+fn fix_eta(file: &str) {
+    if file == "-" {
+        fail("--fix cannot rewrite stdin");
+    }
+    let source = String::from_utf8(read_source(file))
+        .unwrap_or_else(|_| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &"not valid UTF-8"])));
+    let fixed = format::eta_reduce(&source)
+        .unwrap_or_else(|e| fail_as(Failure::Parse, &messages::text(Msg::ParseErrorIn, &[&file, &e])));
+    if fixed != source {
+        fs::write(file, fixed)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", file, e)));
+        println!("{}: eta-reduced", style::dim(style::Stream::Stdout, file));
+    }
+}
 
-    let code = fs::read_to_string(filename)
-        .expect("Failed to read source file.");
+/// `transform --cps|--anf|--lift|--closure-convert <file>...`
+fn transform_command(program: &str, args: &[String]) {
+    let mut mode = None;
+    let mut inputs = Vec::new();
 
-    let mut parser = parser::Parser::new(&code);
-    let ast = parser.parse().expect("Parse error");
+    for arg in args {
+        match arg.as_str() {
+            "--cps" | "--anf" | "--lift" | "--closure-convert" if mode.is_none() => mode = Some(arg.clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if mode.is_none() || inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
 
-    let mut interpreter = interpreter::Interpreter::new(debug);
+    // 여러 파일은 `run`처럼 하나의 프로그램으로 이어 붙여 변환
+    let program_ast = parse_program(&files);
+    let transformed = match mode.as_deref() {
+        Some("--cps") => cps::transform(&program_ast),
+        Some("--anf") => anf::transform(&program_ast),
+        Some("--lift") => lift::transform(&program_ast),
+        Some("--closure-convert") => closure::transform(&program_ast),
+        _ => usage(program),
+    };
+    match transformed {
+        Ok(ast) => println!("{}", program_text(&ast)),
+        Err(e) => fail(&e),
+    }
+}
+
+/// 변환한 프로그램을 다시 읽을 수 있는 소스로. 한 줄에 문장 하나씩
+fn program_text(program: &ast::Expr) -> String {
+    match program {
+        ast::Expr::Sequence(statements) => statements.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(";\n"),
+        other => other.to_string(),
+    }
+}
+
+/// `compile --to rust|c [-o <out>] <file>...` 또는 `compile -o <out.lbc> <file>...`
+fn compile_command(program: &str, args: &[String]) {
+    let mut target = None;
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => target = args.next().cloned(),
+            "-o" => output = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let program_ast = parse_program(&files);
+    let compiled = match target.as_deref() {
+        Some("rust") => codegen_rust::compile(&program_ast).map(String::into_bytes),
+        Some("c") => codegen_c::compile(&program_ast).map(String::into_bytes),
+        Some(other) => fail(&format!("Unknown compile target '{}'", other)),
+        // 대상 없이 출력 파일만 주면 바이트코드
+        None if output.is_some() => Ok(bytecode::encode(&program_ast)),
+        None => usage(program),
+    };
+    let compiled = compiled.unwrap_or_else(|e| fail(&e));
+    match output {
+        Some(path) => fs::write(&path, compiled)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e))),
+        None => io::stdout().write_all(&compiled)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write stdout: {}", e))),
+    }
+}
 
+/// `highlight --format html [-o <out.html>] <file>...`
+fn highlight_command(program: &str, args: &[String]) {
+    let mut format = None;
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = args.next().cloned(),
+            "-o" => output = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    match format.as_deref() {
+        Some("html") => {}
+        Some(other) => fail(&format!("Unknown highlight format '{}'", other)),
+        None => usage(program),
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let sources = files.iter()
+        .map(|file| {
+            let source = String::from_utf8(read_source(file))
+                .unwrap_or_else(|_| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &"not valid UTF-8"])));
+            (file.clone(), source)
+        })
+        .collect::<Vec<_>>();
+    let page = highlight::html(&sources).unwrap_or_else(|e| fail_as(Failure::Parse, &e));
+    match output {
+        Some(path) => fs::write(&path, page)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e))),
+        None => print!("{}", page),
+    }
+}
+
+/// `graph --what deps [-o <out.dot>] <file>...`
+fn graph_command(program: &str, args: &[String]) {
+    let mut what = None;
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--what" => what = args.next().cloned(),
+            "-o" => output = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    match what.as_deref() {
+        Some("deps") => {}
+        Some(other) => fail(&format!("Unknown graph '{}'", other)),
+        None => usage(program),
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let graph = scope::dot(&scope::dependencies(&parse_program(&files)));
+    match output {
+        Some(path) => fs::write(&path, graph)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e))),
+        None => print!("{}", graph),
+    }
+}
+
+/// `fmt --sort-defs [-o <out>] <file>`
+fn fmt_command(program: &str, args: &[String]) {
+    let mut sort_defs = false;
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sort-defs" => sort_defs = true,
+            "-o" => output = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    let [file] = inputs.as_slice() else {
+        usage(program);
+    };
+    if !sort_defs {
+        usage(program);
+    }
+    let source = String::from_utf8(read_source(file))
+        .unwrap_or_else(|_| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &"not valid UTF-8"])));
+    let sorted = format::sort_definitions(&source)
+        .unwrap_or_else(|e| fail_as(Failure::Parse, &messages::text(Msg::ParseErrorIn, &[&file, &e])));
+    match output {
+        Some(path) => fs::write(&path, sorted)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e))),
+        None => print!("{}", sorted),
+    }
+}
+
+/// 여러 파일의 문장을 이어 붙여 하나의 프로그램으로 만든다.
+fn parse_program(files: &[String]) -> ast::Expr {
+    let mut statements = Vec::new();
+    let mut context = parser::Context::default();
+    for file in files {
+        match parse_file_in(file, false, false, &mut context) {
+            ast::Expr::Sequence(exprs) => statements.extend(exprs),
+            other => statements.push(other),
+        }
+    }
+    ast::Expr::Sequence(statements)
+}
+
+fn parse_file(file: &str) -> ast::Expr {
+    parse_file_with(file, false, false)
+}
+
+fn parse_file_with(file: &str, binary_application: bool, spans: bool) -> ast::Expr {
+    parse_file_in(file, binary_application, spans, &mut parser::Context::default())
+}
+
+/// 앞 파일들이 남긴 `data` 선언과 레코드 모양을 `context`로 이어받아 읽고, 이 파일의 것을 더해 둠
+fn parse_file_in(file: &str, binary_application: bool, spans: bool, context: &mut parser::Context) -> ast::Expr {
+    let source = read_source(file);
+    // `compile -o`로 만든 파일은 파싱하지 않고 바로 읽음
+    if let Some(program) = bytecode::read(&source) {
+        return program.unwrap_or_else(|e| fail_as(Failure::Parse, &format!("Bytecode error in {}: {}", file, e)));
+    }
+    let source = String::from_utf8(source)
+        .unwrap_or_else(|_| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &"not valid UTF-8"])));
+    let mut parser = parser::Parser::new(&source)
+        .binary_application(binary_application)
+        .spans(spans)
+        .with_context(std::mem::take(context));
+    let parsed = parser.parse_all();
+    *context = parser.context().clone();
+    parsed.unwrap_or_else(|errors| {
+        let messages = errors.iter()
+            .map(|e| messages::text(Msg::ParseErrorIn, &[&file, &e]))
+            .collect::<Vec<_>>();
+        fail_as(Failure::Parse, &messages.join("\n"))
+    })
+}
+
+/// `file`에서 읽은 식을 평가함. 실패하면 그 종류의 종료 코드로 끝냄
+fn evaluate(interpreter: &mut interpreter::Interpreter, file: &str, ast: ast::Expr, started: std::time::Instant) -> interpreter::Value {
+    // 단계 제한에 걸리면 미리 찾아 둔 무한 루프 모양을 함께 알려 줌
+    let hints = lint::divergence(&ast);
     match interpreter.eval(ast) {
-        Ok(result) => {
-            println!("{}", interpreter.format_result(&result));
+        Ok(value) => value,
+        Err(e) if interrupt::requested() => stop_interrupted(&e, started),
+        Err(e) => {
+            let failure = Failure::of(e.kind);
+            let mut message = messages::text(Msg::ErrorIn, &[&file, &e]);
+            if matches!(failure, Failure::StepLimit) {
+                for hint in hints {
+                    message.push_str(&format!("\n  hint: {}", hint));
+                }
+            }
+            fail_as(failure, &message)
+        }
+    }
+}
+
+/// 표준 입력을 끝까지 기다리지 않고 줄마다 읽어, 끝난 최상위 문장을 그때그때 `eval`에 넘김.
+/// 바이트코드면 읽지 않고 `false`
+fn stream_stdin(context: &mut parser::Context, mut eval: impl FnMut(ast::Expr)) -> bool {
+    let read_failed = |e: io::Error| -> ! { fail_as(Failure::Io, &messages::text(Msg::FailedToReadStdin, &[&e])) };
+    let mut stdin = io::stdin().lock();
+    if stdin.fill_buf().unwrap_or_else(|e| read_failed(e)).starts_with(bytecode::MAGIC) {
+        return false;
+    }
+    let mut source = String::new();
+    let mut offset = 0;
+    let mut evaluated = false;
+    while stdin.read_line(&mut source).unwrap_or_else(|e| read_failed(e)) > 0 {
+        let mut parser = parser::Parser::resume(&source, offset, context.clone()).spans(true);
+        while let Some(Ok(item)) = parser.next() {
+            if item.range.end == source.len() && !finished(&source[item.range.clone()]) {
+                break;
+            }
+            offset = item.range.end;
+            *context = parser.context().clone();
+            eval(ast::Expr::Sequence(item.exprs));
+            evaluated = true;
+        }
+    }
+    // 남은 부분은 파일처럼 읽어서 오류를 모두 알림
+    let mut parser = parser::Parser::resume(&source, offset, std::mem::take(context)).spans(true);
+    let rest = parser.parse_all().unwrap_or_else(|errors| {
+        let messages = errors.iter()
+            .map(|e| messages::text(Msg::ParseErrorIn, &[&"-", &e]))
+            .collect::<Vec<_>>();
+        fail_as(Failure::Parse, &messages.join("\n"))
+    });
+    *context = parser.context().clone();
+    if !evaluated || !matches!(&rest, ast::Expr::Sequence(exprs) if exprs.is_empty()) {
+        eval(rest);
+    }
+    true
+}
+
+/// 뒤에 글이 더 와도 이어지지 않는 문장: `;`로 끝났고, 마지막 문장이 다음 `rec` 정의와 한 묶음이 될 `rec` 정의가 아님
+fn finished(item: &str) -> bool {
+    let tokens = highlight::tokens(item).into_iter()
+        .filter(|(_, class)| !matches!(class, highlight::Class::Space | highlight::Class::Comment))
+        .map(|(range, _)| &item[range])
+        .collect::<Vec<_>>();
+    match tokens.split_last() {
+        Some((&";", rest)) => rest.rsplit(|&token| token == ";").next().and_then(|last| last.first()) != Some(&"rec"),
+        _ => false,
+    }
+}
+
+/// `gen [--size N] [--count K] [--seed S] [--closed]`
+fn gen_command(program: &str, args: &[String]) {
+    let mut size = 10;
+    let mut count = 1;
+    let mut seed = None;
+    let mut closed = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => size = parse_number(program, args.next()),
+            "--count" => count = parse_number(program, args.next()),
+            "--seed" => seed = Some(parse_number(program, args.next()) as u64),
+            "--closed" => closed = true,
+            _ => usage(program),
+        }
+    }
+
+    let mut rng = seed.map_or_else(gen::Rng::from_time, gen::Rng::new);
+    for _ in 0..count {
+        let term = gen::random_term(&mut rng, size, closed)
+            .unwrap_or_else(|e| fail(&e));
+        println!("{}", term);
+    }
+}
+
+/// `enumerate --max-size N [--de-bruijn]`
+fn enumerate_command(program: &str, args: &[String]) {
+    let mut max_size = None;
+    let mut de_bruijn = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-size" => max_size = Some(parse_number(program, args.next())),
+            "--de-bruijn" => de_bruijn = true,
+            _ => usage(program),
         }
-        Err(e) => eprintln!("Error: {}", e),
     }
+    let max_size = max_size.unwrap_or_else(|| usage(program));
+
+    for size in 1..=max_size {
+        for term in enumerate::closed_terms(size) {
+            if de_bruijn {
+                println!("{}", enumerate::to_de_bruijn(&term));
+            } else {
+                println!("{}", term);
+            }
+        }
+    }
+}
+
+/// `explore [--max-terms N] <file>...`
+fn explore_command(program: &str, args: &[String]) {
+    let mut max_terms = 10_000;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-terms" => max_terms = parse_number(program, args.next()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let found = explore::program_term(&parse_program(&files))
+        .and_then(|term| explore::explore(&term, max_terms))
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &e.to_string()));
+    for (term, length) in &found.normal_forms {
+        println!("normal form: {} ({} steps)", term, length);
+    }
+    if found.normal_forms.len() > 1 {
+        println!("warning: {} different normal forms; reduction is not confluent here", found.normal_forms.len());
+    }
+    if found.oversized > 0 {
+        fail_as(Failure::StepLimit, &format!(
+            "Exploration skipped {} terms larger than {} nodes; more normal forms may be reachable",
+            found.oversized,
+            explore::MAX_TERM_SIZE,
+        ));
+    }
+    if !found.complete {
+        fail_as(Failure::StepLimit, &format!(
+            "Exploration did not finish within {} terms; more normal forms may be reachable (raise --max-terms)",
+            max_terms,
+        ));
+    }
+    if found.normal_forms.is_empty() {
+        println!("no normal form: every one of the {} reachable terms reduces further", found.explored);
+    } else {
+        println!("explored {} terms", found.explored);
+    }
+}
+
+/// `cbpv [-b] <file>...`: 실험용 call-by-push-value 문법으로 읽고 실행함
+fn cbpv_command(program: &str, args: &[String]) {
+    let mut trace = false;
+    let mut inputs = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "-b" => trace = true,
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let mut last = None;
+    for file in &files {
+        let source = String::from_utf8(read_source(file))
+            .unwrap_or_else(|_| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &"not valid UTF-8"])));
+        let parsed = cbpv::parse(&source)
+            .unwrap_or_else(|e| fail_as(Failure::Parse, &messages::text(Msg::ParseErrorIn, &[&file, &e])));
+        // 파일마다 따로 실행하므로 앞 파일의 정의는 뒤 파일에서 보이지 않음
+        if let Some(terminal) = cbpv::run(&parsed, trace).unwrap_or_else(|e| fail_as(Failure::of(e.kind), &messages::text(Msg::ErrorIn, &[&file, &e]))) {
+            last = Some(terminal);
+        }
+    }
+    match last {
+        Some(cbpv::Terminal::Value(value)) => println!("{}", style::result(style::Stream::Stdout, &value)),
+        Some(cbpv::Terminal::Function(function)) => println!("{}", style::result(style::Stream::Stdout, &function)),
+        None => {}
+    }
+}
+
+/// `confluence [--eval subst|env] [--args strict|lazy] <file>...`
+fn confluence_command(program: &str, args: &[String]) {
+    let mut config = interpreter::Config::default();
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--eval" => match args.next().map(String::as_str) {
+                Some("subst") => config.eval_mode = interpreter::EvalMode::Substitution,
+                Some("env") => config.eval_mode = interpreter::EvalMode::Environment,
+                _ => usage(program),
+            },
+            "--args" => match args.next().map(String::as_str) {
+                Some("strict") => config.args = interpreter::ArgMode::Strict,
+                Some("lazy") => config.args = interpreter::ArgMode::Lazy,
+                _ => usage(program),
+            },
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+    let ast = parse_program(&files);
+
+    // 기준이 되는 정규 순서 축약과, 인터프리터로 값을 구한 뒤 정규형까지 마저 줄인 것을 비교함
+    let reference = explore::program_term(&ast)
+        .and_then(|term| normalize::normalize(&term))
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &format!("normal order: {}", e)));
+    let evaluator = format!(
+        "evaluator (--eval {}, --args {})",
+        match config.eval_mode {
+            interpreter::EvalMode::Substitution => "subst",
+            interpreter::EvalMode::Environment => "env",
+        },
+        match config.args {
+            interpreter::ArgMode::Strict => "strict",
+            interpreter::ArgMode::Lazy => "lazy",
+        },
+    );
+    let mut interpreter = interpreter::Interpreter::new(config);
+    let evaluated = interpreter.eval(ast)
+        .and_then(|value| interpreter.normal_form(&value))
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &format!("{}: {}", evaluator, e)));
+
+    if !normalize::alpha_eq(&reference, &evaluated) {
+        fail(&format!(
+            "The strategies reach different normal forms; this is a bug in the evaluator\n  normal order: {}\n  {}: {}",
+            normalize::canonical(&reference),
+            evaluator,
+            normalize::canonical(&evaluated),
+        ));
+    }
+    println!("confluent: {}", normalize::canonical(&reference));
+}
+
+/// `grade --reference <ref> [--defs a,b] [--format csv|json] [-o <out>] <submission>...`
+fn grade_command(program: &str, args: &[String]) {
+    let mut reference = None;
+    let mut names = Vec::new();
+    let mut json = false;
+    let mut output = None;
+    let mut inputs = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--reference" => reference = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            "--defs" => names = args.next().unwrap_or_else(|| usage(program))
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            "--format" => match args.next().map(String::as_str) {
+                Some("csv") => json = false,
+                Some("json") => json = true,
+                _ => usage(program),
+            },
+            "-o" => output = Some(args.next().unwrap_or_else(|| usage(program)).clone()),
+            _ if arg.starts_with('-') && arg != "-" => usage(program),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    let Some(reference) = reference else {
+        usage(program);
+    };
+    if inputs.is_empty() {
+        usage(program);
+    }
+    let files = expand_inputs(&inputs);
+
+    let config = interpreter::Config::default();
+    let expected = grade::reference(&reference, &names, config.clone())
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &messages::text(Msg::ErrorIn, &[&"reference", &e])));
+    let names = expected.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+    let reports = files.iter()
+        .map(|file| grade::grade(file, &expected, config.clone()))
+        .collect::<Vec<_>>();
+    let report = match json {
+        true => grade::json(&reports),
+        false => grade::csv(&reports, &names),
+    };
+    match output {
+        Some(path) => fs::write(&path, report)
+            .unwrap_or_else(|e| fail_as(Failure::Io, &format!("Failed to write '{}': {}", path, e))),
+        None => print!("{}", report),
+    }
+}
+
+// `serve`의 스레드 스택. `--max-depth`의 기본값만큼 겹쳐도 넘치지 않을 만큼
+const SERVER_STACK: usize = 512 << 20;
+
+/// `serve [--bind ADDR] [--port N] [--session-ttl SECS] [--allow-io] [limits]`
+fn serve_command(program: &str, args: &[String]) {
+    let mut bind = "127.0.0.1".to_string();
+    let mut port = 8080;
+    let mut ttl = 600;
+    let mut allow_io = false;
+    let mut limits = sandbox::Limits::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => bind = args.next().unwrap_or_else(|| usage(program)).clone(),
+            "--port" => port = parse_number(program, args.next()),
+            "--session-ttl" => ttl = parse_number(program, args.next()),
+            "--allow-io" => allow_io = true,
+            _ if limit_flag(program, arg, &mut args, &mut limits) => {}
+            _ => usage(program),
+        }
+    }
+    // 깊게 겹친 평가도 `--max-depth`에 걸리기 전에 스택이 넘치지 않도록 큰 스택의 스레드에서 돌림.
+    // 인터프리터는 스레드 사이에 옮길 수 없으므로 설정도 그 스레드에서 만듦
+    let address = format!("{}:{}", bind, port);
+    let served = std::thread::Builder::new()
+        .stack_size(SERVER_STACK)
+        .spawn(move || {
+            let config = interpreter::Config { allow_io, ..Default::default() };
+            server::Server::new(config, Duration::from_secs(ttl as u64), limits).serve(&address)
+        })
+        .unwrap_or_else(|e| fail(&e.to_string()))
+        .join()
+        .unwrap_or_else(|_| fail("The server thread panicked."));
+    served.unwrap_or_else(|e| fail_as(Failure::Io, &e));
+}
+
+/// `daemon --socket PATH [--prelude FILE] [--allow-io] [limits]`
+#[cfg(unix)]
+fn daemon_command(program: &str, args: &[String]) {
+    let mut socket = None;
+    let mut prelude = None;
+    let mut context = parser::Context::default();
+    let mut allow_io = false;
+    let mut limits = sandbox::Limits::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--socket" => socket = args.next().cloned(),
+            "--prelude" => prelude = Some(parse_file_in(args.next().unwrap_or_else(|| usage(program)), false, false, &mut context)),
+            "--allow-io" => allow_io = true,
+            _ if limit_flag(program, arg, &mut args, &mut limits) => {}
+            _ => usage(program),
+        }
+    }
+    let socket = socket.unwrap_or_else(|| usage(program));
+    let prelude = prelude.map(std::sync::Arc::new);
+    daemon::Daemon { prelude, context, allow_io, limits }.serve(&socket)
+        .unwrap_or_else(|e| fail_as(Failure::of(e.kind), &e.to_string()));
+}
+
+#[cfg(not(unix))]
+fn daemon_command(_: &str, _: &[String]) {
+    fail("daemon needs Unix domain sockets, which this platform does not have");
+}
+
+/// `serve`와 `daemon`이 함께 받는 요청마다의 제한:
+/// `--max-steps N`, `--timeout-ms N`, `--max-output BYTES`, `--max-term-size N`, `--max-depth N`
+fn limit_flag(program: &str, arg: &str, args: &mut std::slice::Iter<String>, limits: &mut sandbox::Limits) -> bool {
+    match arg {
+        "--max-steps" => limits.steps = parse_number(program, args.next()),
+        "--timeout-ms" => limits.time = Duration::from_millis(parse_number(program, args.next()) as u64),
+        "--max-output" => limits.output = parse_number(program, args.next()),
+        "--max-term-size" => limits.term_size = parse_number(program, args.next()),
+        "--max-depth" => limits.depth = parse_number(program, args.next()),
+        _ => return false,
+    }
+    true
+}
+
+fn parse_number(program: &str, arg: Option<&String>) -> usize {
+    arg.and_then(|a| a.parse().ok())
+        .unwrap_or_else(|| usage(program))
+}
+
+fn read_source(file: &str) -> Vec<u8> {
+    if file == "-" {
+        return read_stdin();
+    }
+    fs::read(file)
+        .unwrap_or_else(|e| fail_as(Failure::Io, &messages::text(Msg::FailedToRead, &[&file, &e])))
+}
+
+fn read_stdin() -> Vec<u8> {
+    let mut code = Vec::new();
+    io::stdin().read_to_end(&mut code)
+        .unwrap_or_else(|e| fail_as(Failure::Io, &messages::text(Msg::FailedToReadStdin, &[&e])));
+    code
+}
+
+/// 실패 종류마다 다른 종료 코드. 스크립트가 `$?`로 구별할 수 있음
+#[derive(Debug, Clone, Copy)]
+enum Failure {
+    // 그 밖의 오류, 실패한 assert, 잘못된 사용법
+    Error = 1,
+    Parse = 2,
+    Unbound = 3,
+    StepLimit = 4,
+    Io = 5,
+    // 셸이 SIGINT로 끝난 프로세스에 주는 값과 같음
+    Interrupted = 130,
+}
+
+impl Failure {
+    fn of(kind: error::ErrorKind) -> Failure {
+        match kind {
+            error::ErrorKind::Unbound => Failure::Unbound,
+            error::ErrorKind::StepLimit => Failure::StepLimit,
+            error::ErrorKind::Io => Failure::Io,
+            error::ErrorKind::Interrupted => Failure::Interrupted,
+            error::ErrorKind::Other => Failure::Error,
+        }
+    }
+}
+
+/// 명령줄의 입력을 파일 목록으로 펼침. 디렉터리를 읽지 못했을 때만 입출력 실패
+fn expand_inputs(inputs: &[String]) -> Vec<String> {
+    files::expand_inputs(inputs).unwrap_or_else(|e| fail_as(Failure::of(e.kind), &e.to_string()))
+}
+
+fn fail(message: &str) -> ! {
+    fail_as(Failure::Error, message)
+}
+
+fn fail_as(failure: Failure, message: &str) -> ! {
+    eprintln!("{}", style::error(style::Stream::Stderr, message));
+    std::process::exit(failure as i32);
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} [-b] [-q | -v | -vv] [--allow-io] [--eval subst|env] [--args strict|lazy] [--deterministic] [--trace-format markdown|mermaid] [--explain] [--output text|json] [--compact | --canonical] [--print-depth N] [--max-print-nodes N] [--dump-env raw|normal|decoded] [--save-env <out.lenv>] [--load-env <in.lenv>] [--names <file>] <source-file | -> [script-args...]", program);
+    eprintln!("       {} run [-b] [-q | -v | -vv] [--allow-io] [--eval subst|env] [--args strict|lazy] [--deterministic] [--trace-format markdown|mermaid] [--explain] [--output text|json] [--compact | --canonical] [--print-depth N] [--max-print-nodes N] [--dump-env raw|normal|decoded] [--save-env <out.lenv>] [--load-env <in.lenv>] [--names <file>] [--opt inline|cse|prenorm | --passes desugar,lift,inline,cse,prenorm] [--dump-after PASS] [--machine g|sigma] [--snapshot <expected.out> [--update]] <source-file | directory | glob>...", program);
+    eprintln!("       {} repl [-b] [-q | -v | -vv] [--allow-io] [--eval subst|env] [--args strict|lazy] [--deterministic] [--compact | --canonical] [--print-depth N] [--max-print-nodes N] [--names <file>]", program);
+    eprintln!("       {} parse [--binary-application] <source-file | directory | glob>...", program);
+    eprintln!("       {} check [--typed stlc|systemf | --free-vars] <source-file | directory | glob>...", program);
+    eprintln!("       {} lint [--linearity] [--eta [--fix]] [--unused] [--duplicates] [--divergence] <source-file | directory | glob>...", program);
+    eprintln!("       {} transform --cps|--anf|--lift|--closure-convert <source-file | directory | glob>...", program);
+    eprintln!("       {} compile --to rust|c [-o <out>] <source-file | directory | glob>...", program);
+    eprintln!("       {} compile -o <out.lbc> <source-file | directory | glob>...", program);
+    eprintln!("       {} highlight --format html [-o <out.html>] <source-file | directory | glob>...", program);
+    eprintln!("       {} fmt --sort-defs [-o <out>] <source-file>", program);
+    eprintln!("       {} graph --what deps [-o <out.dot>] <source-file | directory | glob>...", program);
+    eprintln!("       {} gen [--size N] [--count K] [--seed S] [--closed]", program);
+    eprintln!("       {} enumerate --max-size N [--de-bruijn]", program);
+    eprintln!("       {} explore [--max-terms N] <source-file | directory | glob>...", program);
+    eprintln!("       {} cbpv [-b] <source-file | directory | glob>...", program);
+    eprintln!("       {} confluence [--eval subst|env] [--args strict|lazy] <source-file | directory | glob>...", program);
+    eprintln!("       {} test [--allow-io] [source-file | directory | glob]...", program);
+    eprintln!("       {} grade --reference <ref-file> [--defs a,b] [--format csv|json] [-o <out>] <source-file | directory | glob>...", program);
+    eprintln!("       {} exercise [--eval subst|env] [--args strict|lazy] <spec.toml> <source-file>", program);
+    eprintln!("       {} serve [--bind ADDR] [--port N] [--session-ttl SECS] [--allow-io] [--max-steps N] [--timeout-ms N] [--max-output BYTES] [--max-term-size N] [--max-depth N]", program);
+    eprintln!("       {} daemon --socket <path> [--prelude <file>] [--allow-io] [--max-steps N] [--timeout-ms N] [--max-output BYTES] [--max-term-size N] [--max-depth N]", program);
+    eprintln!("       {} watch [-b] [--allow-io] <source-file | directory | glob>...", program);
+    eprintln!("Every command also accepts --color always|never|auto, --lang en|ko, --plugin <lib.so> (repeatable) and --log [target=]level,... [--log-file <path>] [--log-format text|json].");
+    eprintln!("Log targets: parser, interp, repl, server. Levels: error, warn, info, debug, trace.");
+    eprintln!("Exit codes: 1 error, 2 parse error, 3 name used before its definition, 4 step limit, 5 I/O error, 130 interrupted.");
+    std::process::exit(1);
 }
@@ -0,0 +1,430 @@
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// `--lang en|ko`. 오류 메시지를 보여 줄 말
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En = 1,
+    Ko = 2,
+}
+
+impl Lang {
+    pub fn from_name(name: &str) -> Option<Lang> {
+        match name {
+            "en" => Some(Lang::En),
+            "ko" => Some(Lang::Ko),
+            _ => None,
+        }
+    }
+
+    /// `LC_ALL`, `LC_MESSAGES`, `LANG` 중 처음 설정된 값. `ko_KR.UTF-8`처럼 말 이름으로 시작해야 함
+    pub fn from_env() -> Option<Lang> {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .find_map(|key| env::var(key).ok().filter(|value| !value.is_empty()))?;
+        Lang::from_name(locale.split(['_', '.', '@']).next().unwrap_or_default())
+    }
+}
+
+// 0이면 고르지 않음: 틀의 첫 번째 말로 만듦
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+fn lang() -> Option<Lang> {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Some(Lang::En),
+        2 => Some(Lang::Ko),
+        _ => None,
+    }
+}
+
+/// 보여 줄 메시지 하나. 만드는 곳에서 `text`에 인자와 함께 넘겨 고른 말로 만듦
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // 앞에 붙는 말
+    Error,
+    ParseError,
+    ErrorIn,
+    ParseErrorIn,
+    LineColumn,
+    At,
+    // 파서
+    ExpectedComputation,
+    ExpectedAfter,
+    Expected,
+    EndOfInput,
+    Identifier,
+    ParameterName,
+    TypeName,
+    ReservedKeyword,
+    NotDefinable,
+    NestedTooDeep,
+    UnterminatedString,
+    UnknownEscape,
+    DuplicateField,
+    DuplicateConstructor,
+    DuplicateMatchCase,
+    DuplicateCase,
+    MissingCase,
+    UnknownPattern,
+    UnknownConstructor,
+    ForeignConstructor,
+    PatternArity,
+    EmptyMatch,
+    MixedCases,
+    UnknownField,
+    ExpectedWhereDefinition,
+    AmbiguousField,
+    UnresolvedRecursion,
+    PairMatchCases,
+    OptionCases,
+    BooleanCases,
+    // 평가
+    UsedBeforeDefinition,
+    RequiresIo,
+    LoopDetected,
+    EmptyWords,
+    NotFunction,
+    NoParameterLeft,
+    AssertionFailed,
+    Interrupted,
+    NormalizationSteps,
+    NormalizationNesting,
+    EvaluationSteps,
+    EvaluationTime,
+    TermSize,
+    OutputSize,
+    EvaluationDepth,
+    SubstitutionSteps,
+    NotThunk,
+    UnboundName,
+    CbpvSteps,
+    NotNumeral,
+    NotCharacter,
+    NotString,
+    NotNatural,
+    FailedToRead,
+    FailedToReadStdin,
+    AssertionLeft,
+    AssertionRight,
+    SubstitutionNesting,
+    SubstitutionSize,
+    BrokenSpine,
+    MachineUnsupported,
+    SigmaUnsupported,
+    UnexpectedLifted,
+    GraphSteps,
+    Value,
+    NumberTooLarge,
+    PrimNatural,
+    PrimArity,
+    PrimOverflow,
+    FunctionSequenced,
+    IfzNatural,
+    ReturnedArgument,
+    // 타입 검사
+    UnboundVariable,
+    InDefinition,
+    InAssert,
+    InExpression,
+    TopLevelOnly,
+    TypeAbstractionNeedsSystemF,
+    InfiniteType,
+    TypeMismatch,
+    PolymorphicNeedsSystemF,
+    DeclaredType,
+    AssertTypeMismatch,
+    ArgumentType,
+    NeedsTypeArgument,
+    ApplyNonFunction,
+    ParameterNeedsType,
+    TypeVariableEscapes,
+    NoTypeArgument,
+    // 바이트코드
+    NotBytecode,
+    TrailingBytes,
+    BytecodeEnd,
+    BytecodeUtf8,
+    ExpressionTag,
+    TypeTag,
+    // 플러그인
+    PluginError,
+    PluginsLoaded,
+    PluginNameUtf8,
+    PluginNameInvalid,
+    PluginDuplicate,
+    UnknownError,
+    PluginPath,
+    PluginLoad,
+    PluginExport,
+    #[cfg(not(unix))]
+    PluginsUnix,
+    NoPlugins,
+    PluginNul,
+    PluginStatus,
+    PluginNoTerm,
+    PluginBadTerm,
+    PluginValueKind,
+    // 명령줄
+    TraceCombination,
+    UnknownMachine,
+    TypeErrorIn,
+}
+
+impl Msg {
+    /// (고르지 않았을 때, 영어, 한국어) 틀. `{0}`, `{1}`, …에 인자가 차례로 들어감
+    fn templates(self) -> (&'static str, &'static str, &'static str) {
+        let same = |en, ko| (en, en, ko);
+        match self {
+            Msg::Error => same("Error: {0}", "오류: {0}"),
+            Msg::ParseError => same("Parse error: {0}", "구문 오류: {0}"),
+            Msg::ErrorIn => same("Error in {0}: {1}", "{0}의 오류: {1}"),
+            Msg::ParseErrorIn => same("Parse error in {0}: {1}", "{0}의 구문 오류: {1}"),
+            Msg::LineColumn => same("line {0}, column {1}: {2}", "{0}번째 줄 {1}번째 칸: {2}"),
+            Msg::At => same("  at line {0}, column {1}", "  위치: {0}번째 줄 {1}번째 칸"),
+            Msg::ExpectedComputation => same(
+                "Expected a computation, found the value '{0}' (use 'force {0}' or 'return {0}')",
+                "계산이 와야 하는데 값 '{0}'이(가) 있습니다 ('force {0}'나 'return {0}'을(를) 쓰세요)",
+            ),
+            Msg::ExpectedAfter => same("Expected {0} after {1}, found {2}", "{1} 뒤에 {0}이(가) 와야 하는데 {2}이(가) 있습니다"),
+            Msg::Expected => same("Expected {0}, found {1}", "{0}이(가) 와야 하는데 {1}이(가) 있습니다"),
+            Msg::EndOfInput => same("end of input", "입력의 끝"),
+            Msg::Identifier => same("identifier", "이름"),
+            Msg::ParameterName => same("parameter name", "매개변수 이름"),
+            Msg::TypeName => same("type name", "타입 이름"),
+            Msg::ReservedKeyword => same(
+                "'{0}' is a reserved keyword and cannot be used as a name",
+                "'{0}'은(는) 예약어라서 이름으로 쓸 수 없습니다",
+            ),
+            Msg::NotDefinable => same("Only a name can be defined, not '{0}'", "이름만 정의할 수 있는데 '{0}'을(를) 정의하려고 했습니다"),
+            Msg::NestedTooDeep => same("Expression nested deeper than {0} levels.", "식이 {0}단계보다 깊게 겹쳐 있습니다."),
+            Msg::UnterminatedString => same("Unterminated string literal", "문자열이 닫히지 않았습니다"),
+            Msg::UnknownEscape => same("Unknown escape sequence '{0}'", "알 수 없는 이스케이프 '{0}'"),
+            Msg::DuplicateField => same("Field '{0}' appears twice in a record", "레코드에 필드 '{0}'이(가) 두 번 나옵니다"),
+            Msg::DuplicateConstructor => same("Constructor '{0}' appears twice in '{1}'", "'{1}'에 생성자 '{0}'이(가) 두 번 나옵니다"),
+            Msg::DuplicateMatchCase => same("Duplicate '{0}' case in match", "match에 '{0}' 갈래가 두 번 나옵니다"),
+            Msg::DuplicateCase => same("Duplicate '{0}' case", "'{0}' 갈래가 두 번 나옵니다"),
+            Msg::MissingCase => same("Missing case for '{0}'", "'{0}' 갈래가 없습니다"),
+            Msg::UnknownPattern => same("Unknown match pattern '{0}'", "알 수 없는 match 패턴 '{0}'"),
+            Msg::UnknownConstructor => same("Unknown constructor '{0}'", "알 수 없는 생성자 '{0}'"),
+            Msg::ForeignConstructor => same(
+                "'{0}' is not a constructor of the same type as '{1}'",
+                "'{0}'은(는) '{1}'과(와) 같은 타입의 생성자가 아닙니다",
+            ),
+            Msg::PatternArity => same(
+                "Constructor '{0}' has {1} field(s) but the pattern binds {2}",
+                "생성자 '{0}'의 필드는 {1}개인데 패턴은 {2}개를 묶습니다",
+            ),
+            Msg::EmptyMatch => same("Empty match", "갈래가 없는 match"),
+            Msg::MixedCases => same(
+                "Boolean and option cases cannot be mixed in one match",
+                "한 match에 불리언 갈래와 옵션 갈래를 섞을 수 없습니다",
+            ),
+            Msg::UnknownField => same(
+                "Unknown record field '{0}'; it must appear in a record literal first",
+                "알 수 없는 레코드 필드 '{0}'. 먼저 레코드 리터럴에 나와야 합니다",
+            ),
+            Msg::ExpectedWhereDefinition => same("Expected a definition after 'where'", "'where' 뒤에 정의가 와야 합니다"),
+            Msg::AmbiguousField => same(
+                "Field '{0}' is ambiguous between records {1} and {2}",
+                "필드 '{0}'이(가) 레코드 {1}와(과) {2} 중 어느 쪽의 것인지 알 수 없습니다",
+            ),
+            Msg::UnresolvedRecursion => same(
+                "'{0}' uses '{1}' before it can be resolved; recursive references must be inside a function",
+                "'{0}'이(가) 아직 정해지지 않은 '{1}'을(를) 씁니다. 재귀 참조는 함수 안에 있어야 합니다",
+            ),
+            Msg::PairMatchCases => same("A pair match has exactly one case", "쌍 match에는 갈래가 하나만 있어야 합니다"),
+            Msg::OptionCases => same(
+                "A match on an option needs both 'none' and 'some' cases",
+                "옵션 match에는 'none'과 'some' 갈래가 모두 있어야 합니다",
+            ),
+            Msg::BooleanCases => same(
+                "A match on a boolean needs both 'true' and 'false' cases",
+                "불리언 match에는 'true'와 'false' 갈래가 모두 있어야 합니다",
+            ),
+            Msg::UsedBeforeDefinition => same("'{0}' is used before its definition is evaluated.", "'{0}'이(가) 정의를 평가하기 전에 쓰였습니다."),
+            Msg::RequiresIo => same("'{0}' requires --allow-io.", "'{0}'을(를) 쓰려면 --allow-io가 필요합니다."),
+            Msg::LoopDetected => ("무한 β-축약 루프 감지!", "Infinite β-reduction loop detected!", "무한 β-축약 루프 감지!"),
+            Msg::EmptyWords => same("Empty Words expression.", "빈 Words 식입니다."),
+            Msg::NotFunction => same("Trying to apply non-function!", "함수가 아닌 값을 적용하려고 했습니다!"),
+            Msg::NoParameterLeft => same("No parameter left to apply!", "적용할 매개변수가 남아 있지 않습니다!"),
+            Msg::AssertionFailed => same("Assertion failed:", "단언 실패:"),
+            Msg::Interrupted => same("Interrupted after {0} reduction steps.", "β-축약 {0}번째에서 멈췄습니다."),
+            Msg::NormalizationSteps => same("Normalization did not finish within {0} steps.", "정규화가 {0}단계 안에 끝나지 않았습니다."),
+            Msg::NormalizationNesting => same(
+                "Normalization did not finish within {0} levels of nesting.",
+                "정규화가 {0}단계의 겹침 안에 끝나지 않았습니다.",
+            ),
+            Msg::EvaluationSteps => same(
+                "Evaluation did not finish within {0} steps (--max-steps).",
+                "평가가 {0}단계 안에 끝나지 않았습니다 (--max-steps).",
+            ),
+            Msg::EvaluationTime => same(
+                "Evaluation did not finish within {0} ms (--timeout-ms).",
+                "평가가 {0} ms 안에 끝나지 않았습니다 (--timeout-ms).",
+            ),
+            Msg::TermSize => same(
+                "Term grew to {0} nodes, more than {1} (--max-term-size).",
+                "식이 노드 {0}개로 자라 {1}개를 넘었습니다 (--max-term-size).",
+            ),
+            Msg::OutputSize => same("Output exceeded {0} bytes (--max-output).", "출력이 {0}바이트를 넘었습니다 (--max-output)."),
+            Msg::EvaluationDepth => same(
+                "Evaluation nested deeper than {0} levels (--max-depth).",
+                "평가가 {0}단계보다 깊게 겹쳐 들어갔습니다 (--max-depth).",
+            ),
+            Msg::SubstitutionSteps => same(
+                "Explicit substitution did not finish within {0} steps.",
+                "명시적 대입이 {0}단계 안에 끝나지 않았습니다.",
+            ),
+            Msg::NotThunk => same("Only a thunk can be forced, not {0}", "thunk만 force할 수 있는데 {0}을(를) force했습니다"),
+            Msg::UnboundName => same("Unbound name '{0}'", "묶이지 않은 이름 '{0}'"),
+            Msg::CbpvSteps => same("CBPV evaluation did not finish within {0} steps.", "CBPV 평가가 {0}단계 안에 끝나지 않았습니다."),
+            Msg::NotNumeral => same("Not a numeral.", "숫자가 아닙니다."),
+            Msg::NotCharacter => same("Not a character.", "글자가 아닙니다."),
+            Msg::NotString => same("Not a string.", "문자열이 아닙니다."),
+            Msg::NotNatural => same(
+                "readNat: expected a natural number, got '{0}'.",
+                "readNat: 자연수가 와야 하는데 '{0}'이(가) 들어왔습니다.",
+            ),
+            Msg::FailedToRead => same("Failed to read '{0}': {1}", "'{0}'을(를) 읽지 못했습니다: {1}"),
+            Msg::FailedToReadStdin => same("Failed to read stdin: {0}", "표준 입력을 읽지 못했습니다: {0}"),
+            Msg::AssertionLeft => same("left:", "왼쪽:"),
+            Msg::AssertionRight => same("right:", "오른쪽:"),
+            Msg::SubstitutionNesting => same(
+                "Explicit substitution did not finish within {0} levels of nesting.",
+                "명시적 대입이 {0}단계의 겹침 안에 끝나지 않았습니다.",
+            ),
+            Msg::SubstitutionSize => same(
+                "Explicit substitution did not finish within {0} nodes of term size.",
+                "명시적 대입이 식의 노드 {0}개 안에 끝나지 않았습니다.",
+            ),
+            Msg::BrokenSpine => same("Broken application spine.", "적용 스파인이 깨졌습니다."),
+            Msg::MachineUnsupported => same(
+                "'{0}' is not supported by the graph reduction machine.",
+                "그래프 축약 기계는 '{0}'을(를) 지원하지 않습니다.",
+            ),
+            Msg::SigmaUnsupported => same(
+                "'{0}' is not supported by the explicit substitution machine.",
+                "명시적 대입 기계는 '{0}'을(를) 지원하지 않습니다.",
+            ),
+            Msg::UnexpectedLifted => same("Unexpected expression after lambda lifting: {0}", "람다 끌어올리기 뒤에 예상하지 못한 식이 있습니다: {0}"),
+            Msg::GraphSteps => same("Graph reduction did not finish within {0} steps.", "그래프 축약이 {0}단계 안에 끝나지 않았습니다."),
+            Msg::Value => same("value", "값"),
+            Msg::NumberTooLarge => same("Number '{0}' is too large", "수 '{0}'이(가) 너무 큽니다"),
+            Msg::PrimNatural => same("'{0}' expects natural numbers, not {1}", "'{0}'에는 자연수가 와야 하는데 {1}이(가) 왔습니다"),
+            Msg::PrimArity => same("'{0}' needs two arguments", "'{0}'에는 인자가 두 개 필요합니다"),
+            Msg::PrimOverflow => same("'{0}' overflowed on {1} and {2}", "{1}와(과) {2}의 '{0}'이(가) 넘쳤습니다"),
+            Msg::FunctionSequenced => same(
+                "A function cannot be sequenced with 'to'; return a thunk of it instead",
+                "함수는 'to'로 이어 붙일 수 없습니다. 대신 그 thunk를 return하세요",
+            ),
+            Msg::IfzNatural => same("'ifz' expects a natural number, not {0}", "'ifz'에는 자연수가 와야 하는데 {0}이(가) 왔습니다"),
+            Msg::ReturnedArgument => same(
+                "A returned value cannot take the argument {0}; only 'L' can",
+                "return한 값은 인자 {0}을(를) 받을 수 없습니다. 'L'만 받을 수 있습니다",
+            ),
+            Msg::UnboundVariable => same("Unbound variable '{0}'", "묶이지 않은 변수 '{0}'"),
+            Msg::InDefinition => same("{0} (in definition '{1}')", "{0} (정의 '{1}' 안)"),
+            Msg::InAssert => same("{0} (in assert)", "{0} (assert 안)"),
+            Msg::InExpression => same("{0} in '{1}'", "{0} ('{1}' 안)"),
+            Msg::TopLevelOnly => same(
+                "Definitions and asserts are only allowed at the top level",
+                "정의와 assert는 맨 바깥에서만 쓸 수 있습니다",
+            ),
+            Msg::TypeAbstractionNeedsSystemF => same(
+                "Type abstraction and application need --typed systemf",
+                "타입 추상화와 적용에는 --typed systemf가 필요합니다",
+            ),
+            Msg::InfiniteType => same("Infinite type: {0} = {1}", "무한 타입: {0} = {1}"),
+            Msg::TypeMismatch => same("Type mismatch: {0} vs {1}", "타입이 맞지 않습니다: {0}와(과) {1}"),
+            Msg::PolymorphicNeedsSystemF => same(
+                "Polymorphic type '{0}' needs --typed systemf",
+                "다형 타입 '{0}'에는 --typed systemf가 필요합니다",
+            ),
+            Msg::DeclaredType => same("'{0}' is declared as {1} but has type {2}", "'{0}'은(는) {1}(으)로 선언됐지만 타입이 {2}입니다"),
+            Msg::AssertTypeMismatch => same("Type mismatch in assert: {0} vs {1}", "assert의 타입이 맞지 않습니다: {0}와(과) {1}"),
+            Msg::ArgumentType => same(
+                "Argument '{0}' has type {1}, expected {2} in '{3}'",
+                "'{3}'에서 인자 '{0}'의 타입은 {2}여야 하는데 {1}입니다",
+            ),
+            Msg::NeedsTypeArgument => same(
+                "'{0}' is polymorphic and needs a type argument [T] in '{1}'",
+                "'{1}'에서 '{0}'은(는) 다형이라 타입 인자 [T]가 필요합니다",
+            ),
+            Msg::ApplyNonFunction => same("Applying a non-function of type {0} in '{1}'", "'{1}'에서 함수가 아닌 타입 {0}의 값을 적용했습니다"),
+            Msg::ParameterNeedsType => same("Parameter '{0}' needs a type annotation", "매개변수 '{0}'에 타입 표기가 필요합니다"),
+            Msg::TypeVariableEscapes => same(
+                "Type variable '{0}' would escape through '{1}'",
+                "타입 변수 '{0}'이(가) '{1}'을(를) 통해 밖으로 나갑니다",
+            ),
+            Msg::NoTypeArgument => same("'{0}' of type {1} cannot take a type argument", "타입이 {1}인 '{0}'은(는) 타입 인자를 받을 수 없습니다"),
+            Msg::NotBytecode => same(
+                "Not a lintre bytecode file (or an unsupported version).",
+                "lintre 바이트코드 파일이 아니거나 지원하지 않는 버전입니다.",
+            ),
+            Msg::TrailingBytes => same("Trailing bytes after the program.", "프로그램 뒤에 바이트가 남아 있습니다."),
+            Msg::BytecodeEnd => same("Unexpected end of bytecode.", "바이트코드가 중간에 끝났습니다."),
+            Msg::BytecodeUtf8 => same("Invalid UTF-8 in bytecode.", "바이트코드에 잘못된 UTF-8이 있습니다."),
+            Msg::ExpressionTag => same("Unknown expression tag {0} in bytecode.", "바이트코드에 알 수 없는 식 태그 {0}이(가) 있습니다."),
+            Msg::TypeTag => same("Unknown type tag {0} in bytecode.", "바이트코드에 알 수 없는 타입 태그 {0}이(가) 있습니다."),
+            Msg::PluginError => same("Plugin {0}: {1}", "플러그인 {0}: {1}"),
+            Msg::PluginsLoaded => same("Plugins are already loaded.", "플러그인을 이미 불러왔습니다."),
+            Msg::PluginNameUtf8 => same("a builtin name is not valid UTF-8", "내장 함수 이름이 올바른 UTF-8이 아닙니다"),
+            Msg::PluginNameInvalid => same("'{0}' is not a valid builtin name", "'{0}'은(는) 내장 함수 이름으로 쓸 수 없습니다"),
+            Msg::PluginDuplicate => same("builtin '{0}' is already defined", "내장 함수 '{0}'은(는) 이미 정의돼 있습니다"),
+            Msg::UnknownError => same("unknown error", "알 수 없는 오류"),
+            Msg::PluginPath => same("Invalid plugin path '{0}'", "잘못된 플러그인 경로 '{0}'"),
+            Msg::PluginLoad => same("Failed to load plugin '{0}': {1}", "플러그인 '{0}'을(를) 불러오지 못했습니다: {1}"),
+            Msg::PluginExport => same("Plugin '{0}' does not export {1}: {2}", "플러그인 '{0}'이(가) {1}을(를) 내보내지 않습니다: {2}"),
+            #[cfg(not(unix))]
+            Msg::PluginsUnix => same("plugins are only supported on Unix", "플러그인은 Unix에서만 쓸 수 있습니다"),
+            Msg::NoPlugins => same("No plugins are loaded.", "불러온 플러그인이 없습니다."),
+            Msg::PluginNul => same("{0}: an argument contains a NUL character", "{0}: 인자에 NUL 문자가 있습니다"),
+            Msg::PluginStatus => same("{0} failed with status {1}.", "{0}이(가) 상태 {1}(으)로 실패했습니다."),
+            Msg::PluginNoTerm => same("{0} returned no term.", "{0}이(가) 식을 돌려주지 않았습니다."),
+            Msg::PluginBadTerm => same("{0} returned a term that does not parse: {1}", "{0}이(가) 돌려준 식을 읽을 수 없습니다: {1}"),
+            Msg::PluginValueKind => same("{0} returned an unknown value kind {1}.", "{0}이(가) 알 수 없는 값 종류 {1}을(를) 돌려주었습니다."),
+            Msg::TraceCombination => same(
+                "--trace-format and --explain cannot be combined with --machine, --opt, --passes or --output json",
+                "--trace-format과 --explain은 --machine, --opt, --passes, --output json과 함께 쓸 수 없습니다",
+            ),
+            Msg::UnknownMachine => same("Unknown machine '{0}'", "알 수 없는 기계 '{0}'"),
+            Msg::TypeErrorIn => same("Type error in {0}: {1}", "{0}의 타입 오류: {1}"),
+        }
+    }
+}
+
+/// 고른 말로 만든 메시지. 말은 만들 때 정해지므로, 보여 주는 곳에서 글을 다시 읽어 옮길 일이 없음
+pub fn text(msg: Msg, args: &[&dyn fmt::Display]) -> String {
+    let (source, en, ko) = msg.templates();
+    let template = match lang() {
+        None => source,
+        Some(Lang::En) => en,
+        Some(Lang::Ko) => ko,
+    };
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = open + rest[open..].find('}').expect("unclosed placeholder");
+        let slot: usize = rest[open + 1..close].parse().expect("placeholder is not a number");
+        out.push_str(&rest[..open]);
+        out.push_str(&args[slot].to_string());
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `Expected` 오류에 쓰는 나열: `A or B`, `A, B, or C`
+pub fn either(items: &[&str]) -> String {
+    match (lang(), items) {
+        (_, []) => String::new(),
+        (_, [one]) => one.to_string(),
+        (Some(Lang::Ko), [init @ .., last]) => format!("{} 또는 {}", init.join(", "), last),
+        (_, [first, second]) => format!("{} or {}", first, second),
+        (_, [init @ .., last]) => format!("{}, or {}", init.join(", "), last),
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::interpreter::Value;
+use crate::interrupt;
+use crate::messages::{self, Msg};
+use crate::visit::{fold_children, Folder, Visitor};
+
+const MAX_STEPS: usize = 100_000;
+// 정규형이 없는 식은 줄일수록 깊어지므로, 스택이 넘치기 전에 멈춤
+pub const MAX_DEPTH: usize = 1_000;
+
+/// 값을 다시 식으로 되돌린다. 클로저가 잡고 있는 환경은 본문에 대입된다.
+pub fn readback(value: &Value) -> Expr {
+    match value {
+        Value::Word(w) => Expr::Word(w.clone()),
+        Value::Closure(param, body, env) => {
+            let mapping = captured(body, env, Some(param));
+            // 펼쳐 둔 매개변수는 다시 한 람다로 모음
+            function(vec![param.clone()], substitute(body, &mapping))
+        }
+        Value::Thunk(expr, env) => substitute(expr, &captured(expr, env, None)),
+        Value::Builtin(builtin, args) => {
+            let head = Expr::Word(builtin.name().to_string());
+            if args.is_empty() {
+                head
+            } else {
+                Expr::Words(std::iter::once(head)
+                    .chain(args.iter().map(|a| to_arg(readback(a))))
+                    .collect())
+            }
+        }
+    }
+}
+
+/// 본문의 자유 변수 중 환경에 있는 것을 되돌린 식으로. `param`은 본문에서 묶이므로 뺌
+fn captured(body: &Expr, env: &HashMap<String, Value>, param: Option<&String>) -> HashMap<String, Expr> {
+    free_vars(body).into_iter()
+        .filter(|v| Some(v) != param)
+        .filter_map(|v| env.get(&v).map(|val| (v, readback(val))))
+        .collect()
+}
+
+/// 정규 순서(normal order)로 β-정규형까지 줄인다.
+pub fn normalize(expr: &Expr) -> Result<Expr, Error> {
+    normalize_within(expr, MAX_STEPS)
+}
+
+/// `normalize`와 같지만 β-축약을 `limit`번까지만 함
+pub fn normalize_within(expr: &Expr, limit: usize) -> Result<Expr, Error> {
+    Normalizer { steps: 0, limit, depth: 0 }.fold_expr(expr)
+}
+
+struct Normalizer {
+    steps: usize,
+    limit: usize,
+    depth: usize,
+}
+
+impl Folder for Normalizer {
+    type Error = Error;
+
+    fn fold_expr(&mut self, expr: &Expr) -> Result<Expr, Error> {
+        if self.depth > MAX_DEPTH {
+            return Err(Error::step_limit(messages::text(Msg::NormalizationNesting, &[&MAX_DEPTH])));
+        }
+        self.depth += 1;
+        let result = match expr {
+            Expr::Paren(inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _)
+            | Expr::Located(_, inner) => self.fold_expr(inner),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err("Cannot normalize a definition or statement.".into())
+            }
+            _ => fold_children(self, expr),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    fn fold_function(&mut self, params: &[String], body: &Expr) -> Result<Expr, Error> {
+        let body = self.fold_expr(body)?;
+        Ok(function(params.to_vec(), body))
+    }
+
+    fn fold_words(&mut self, words: &[Expr]) -> Result<Expr, Error> {
+        let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+        let mut head = head.clone();
+        let mut args = args.to_vec();
+        loop {
+            match head {
+                Expr::Paren(inner)
+                | Expr::Annotated(_, inner)
+                | Expr::TypeFunction(_, inner)
+                | Expr::TypeApply(inner, _)
+                | Expr::Located(_, inner) => head = *inner,
+                Expr::Words(inner) => {
+                    let mut inner = inner.into_iter();
+                    head = inner.next().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                    args.splice(0..0, inner);
+                }
+                Expr::Function(mut params, body) if !args.is_empty() => {
+                    self.steps += 1;
+                    interrupt::check(self.steps, || Expr::Function(params.clone(), body.clone()).to_string())?;
+                    if self.steps > self.limit {
+                        return Err(Error::step_limit(messages::text(Msg::NormalizationSteps, &[&self.limit])));
+                    }
+                    let param = params.remove(0);
+                    let arg = args.remove(0);
+                    let mapping = HashMap::from([(param, arg)]);
+                    head = if params.is_empty() {
+                        substitute_owned(*body, &mapping)
+                    } else {
+                        substitute_owned(Expr::Function(params, body), &mapping)
+                    };
+                }
+                _ => break,
+            }
+        }
+        let head = self.fold_expr(&head)?;
+        if args.is_empty() {
+            return Ok(head);
+        }
+        let mut words = vec![head];
+        for arg in &args {
+            words.push(to_arg(self.fold_expr(arg)?));
+        }
+        Ok(Expr::Words(words))
+    }
+}
+
+/// β-정규형을 η-축약까지 한 정규형으로. `L x. f x`처럼 마지막 매개변수를 넘기기만 하는 람다를 `f`로 줄임.
+/// β-정규형을 η-축약해도 새 β-축약 자리는 생기지 않음
+pub fn eta_normal(expr: &Expr) -> Expr {
+    expr.rewrite_bottom_up(|e| {
+        let Expr::Function(mut params, mut body) = e else {
+            return e;
+        };
+        while let Some(param) = params.last() {
+            let Expr::Words(words) = body.unlocated() else {
+                break;
+            };
+            let Some((last, init)) = words.split_last() else {
+                break;
+            };
+            if last.unlocated() != &Expr::Word(param.clone()) || init.iter().any(|e| free_vars(e).contains(param)) {
+                break;
+            }
+            body = Box::new(match init {
+                [single] => match single.unlocated() {
+                    Expr::Paren(inner) => (**inner).clone(),
+                    other => other.clone(),
+                },
+                _ => Expr::Words(init.to_vec()),
+            });
+            params.pop();
+        }
+        match params.is_empty() {
+            true => *body,
+            false => Expr::Function(params, body),
+        }
+    })
+}
+
+/// 여러 변수를 동시에 대입한다. 묶인 변수와 겹치면 이름을 바꿔 포획을 피함
+pub fn substitute(expr: &Expr, mapping: &HashMap<String, Expr>) -> Expr {
+    match mapping.is_empty() {
+        true => expr.clone(),
+        false => substitute_owned(expr.clone(), mapping),
+    }
+}
+
+/// `substitute`와 같지만 식을 넘겨받아 바뀌는 자리만 고친다. 바뀌지 않은 부분 식은 새로 만들지 않고,
+/// 재귀 대신 직접 쌓는 스택으로 내려가므로 아주 깊은 식에서도 스택이 넘치지 않음
+pub fn substitute_owned(mut expr: Expr, mapping: &HashMap<String, Expr>) -> Expr {
+    if mapping.is_empty() {
+        return expr;
+    }
+    // 고칠 자리, 그 자리에서 보이는 대입, 적용의 항인지(그러면 넣는 식을 괄호로 감쌈)
+    let mut stack = vec![(&mut expr, Mapping::Given(mapping), false)];
+    while let Some((node, mapping, arg)) = stack.pop() {
+        match node {
+            Expr::Word(w) => {
+                if let Some(value) = mapping.get().get(w) {
+                    *node = match arg {
+                        true => to_arg(value.clone()),
+                        false => value.clone(),
+                    };
+                }
+            }
+            Expr::Words(words) => stack.extend(words.iter_mut().map(|e| (e, mapping.clone(), true))),
+            Expr::Sequence(exprs) => stack.extend(exprs.iter_mut().map(|e| (e, mapping.clone(), false))),
+            Expr::Located(_, inner) => stack.push((inner, mapping, arg)),
+            Expr::Paren(inner)
+            | Expr::Define(_, _, inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _) => stack.push((inner, mapping, false)),
+            Expr::Assert(lhs, rhs) => {
+                stack.push((lhs, mapping.clone(), false));
+                stack.push((rhs, mapping, false));
+            }
+            Expr::Function(params, body) => {
+                let body_free = free_vars(body);
+                let mut inner = mapping.get().iter()
+                    .filter(|(k, _)| !params.contains(k) && body_free.contains(*k))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<HashMap<_, _>>();
+                if inner.is_empty() {
+                    continue;
+                }
+                let incoming = inner.values()
+                    .flat_map(free_vars)
+                    .collect::<HashSet<_>>();
+                let mut taken = incoming.iter()
+                    .chain(body_free.iter())
+                    .chain(params.iter())
+                    .cloned()
+                    .collect::<HashSet<_>>();
+                for p in params.iter_mut().filter(|p| incoming.contains(*p)) {
+                    let fresh = fresh_name(p, &taken);
+                    taken.insert(fresh.clone());
+                    inner.insert(std::mem::replace(p, fresh.clone()), Expr::Word(fresh));
+                }
+                stack.push((body, Mapping::Narrowed(Rc::new(inner)), false));
+            }
+        }
+    }
+    expr
+}
+
+/// `substitute_owned`의 스택에 쌓이는 대입. 람다 안으로 들어가며 좁힌 대입은 그 람다의 부분 식들이 나눠 가짐
+#[derive(Clone)]
+enum Mapping<'a> {
+    Given(&'a HashMap<String, Expr>),
+    Narrowed(Rc<HashMap<String, Expr>>),
+}
+
+impl Mapping<'_> {
+    fn get(&self) -> &HashMap<String, Expr> {
+        match self {
+            Mapping::Given(mapping) => mapping,
+            Mapping::Narrowed(mapping) => mapping,
+        }
+    }
+}
+
+pub fn free_vars(expr: &Expr) -> HashSet<String> {
+    let mut vars = FreeVars { bound: Vec::new(), free: HashSet::new() };
+    vars.visit_expr(expr);
+    vars.free
+}
+
+struct FreeVars {
+    bound: Vec<String>,
+    free: HashSet<String>,
+}
+
+impl Visitor for FreeVars {
+    fn visit_word(&mut self, word: &str) {
+        if !self.bound.iter().any(|b| b == word) {
+            self.free.insert(word.to_string());
+        }
+    }
+
+    fn visit_function(&mut self, params: &[String], body: &Expr) {
+        let depth = self.bound.len();
+        self.bound.extend(params.iter().cloned());
+        self.visit_expr(body);
+        self.bound.truncate(depth);
+    }
+}
+
+/// 묶인 변수를 등장 순서대로 v0, v1, …로 바꾼다.
+pub fn canonical(expr: &Expr) -> Expr {
+    let free = free_vars(expr);
+    let mut counter = 0;
+    canonical_with(expr, &HashMap::new(), &free, &mut counter, &|n| format!("v{}", n))
+}
+
+/// `canonical`과 같지만 a, b, …, z, a1, b1, …처럼 되도록 짧은 이름을 씀
+pub fn canonical_letters(expr: &Expr) -> Expr {
+    let free = free_vars(expr);
+    let mut counter = 0;
+    let letter = |n: usize| {
+        let ch = (b'a' + (n % 26) as u8) as char;
+        match n / 26 {
+            0 => ch.to_string(),
+            round => format!("{}{}", ch, round),
+        }
+    };
+    canonical_with(expr, &HashMap::new(), &free, &mut counter, &letter)
+}
+
+/// 축약하면서 붙은 `x$3` 같은 이름을 원래 이름 `x`로 되돌림. 되돌린 이름이 본문의 다른 변수와 겹치면 그대로 둠
+pub fn plain_names(expr: &Expr) -> Expr {
+    plain_names_with(expr, &HashMap::new())
+}
+
+fn plain_names_with(expr: &Expr, names: &HashMap<String, String>) -> Expr {
+    let recurse = |e: &Expr| Box::new(plain_names_with(e, names));
+    match expr {
+        Expr::Word(w) => Expr::Word(names.get(w).cloned().unwrap_or_else(|| w.clone())),
+        Expr::Words(words) => Expr::Words(words.iter().map(|e| plain_names_with(e, names)).collect()),
+        Expr::Sequence(exprs) => Expr::Sequence(exprs.iter().map(|e| plain_names_with(e, names)).collect()),
+        Expr::Define(name, ty, body) => Expr::Define(name.clone(), ty.clone(), recurse(body)),
+        Expr::Paren(inner) => Expr::Paren(recurse(inner)),
+        Expr::Located(span, inner) => Expr::Located(*span, recurse(inner)),
+        Expr::Annotated(types, inner) => Expr::Annotated(types.clone(), recurse(inner)),
+        Expr::TypeFunction(vars, inner) => Expr::TypeFunction(vars.clone(), recurse(inner)),
+        Expr::TypeApply(inner, ty) => Expr::TypeApply(recurse(inner), ty.clone()),
+        Expr::Function(params, body) => {
+            let mut names = names.clone();
+            // 본문에서 이 람다 바깥을 가리키는 변수들이 바뀐 뒤의 이름
+            let outside = free_vars(body).into_iter()
+                .filter(|v| !params.contains(v))
+                .map(|v| names.get(&v).cloned().unwrap_or(v))
+                .collect::<HashSet<_>>();
+            let mut taken = HashSet::new();
+            let params = params.iter()
+                .map(|p| {
+                    let plain = p.split('$').next().unwrap_or(p).to_string();
+                    let name = match outside.contains(&plain) || taken.contains(&plain) {
+                        true => p.clone(),
+                        false => plain,
+                    };
+                    taken.insert(name.clone());
+                    names.insert(p.clone(), name.clone());
+                    name
+                })
+                .collect();
+            Expr::Function(params, Box::new(plain_names_with(body, &names)))
+        }
+        _ => expr.clone(),
+    }
+}
+
+fn canonical_with(
+    expr: &Expr,
+    names: &HashMap<String, String>,
+    free: &HashSet<String>,
+    counter: &mut usize,
+    fresh: &dyn Fn(usize) -> String,
+) -> Expr {
+    match expr {
+        Expr::Word(w) => Expr::Word(names.get(w).cloned().unwrap_or_else(|| w.clone())),
+        Expr::Words(words) => Expr::Words(words.iter()
+            .map(|e| canonical_with(e, names, free, counter, fresh))
+            .collect()),
+        Expr::Paren(inner) => Expr::Paren(Box::new(canonical_with(inner, names, free, counter, fresh))),
+        Expr::Located(span, inner) => Expr::Located(*span, Box::new(canonical_with(inner, names, free, counter, fresh))),
+        Expr::Annotated(types, inner) => {
+            Expr::Annotated(types.clone(), Box::new(canonical_with(inner, names, free, counter, fresh)))
+        }
+        Expr::TypeFunction(vars, inner) => {
+            Expr::TypeFunction(vars.clone(), Box::new(canonical_with(inner, names, free, counter, fresh)))
+        }
+        Expr::TypeApply(inner, ty) => {
+            Expr::TypeApply(Box::new(canonical_with(inner, names, free, counter, fresh)), ty.clone())
+        }
+        Expr::Function(params, body) => {
+            let mut names = names.clone();
+            let params = params.iter()
+                .map(|p| {
+                    let mut name = fresh(*counter);
+                    while free.contains(&name) {
+                        *counter += 1;
+                        name = fresh(*counter);
+                    }
+                    *counter += 1;
+                    names.insert(p.clone(), name.clone());
+                    name
+                })
+                .collect();
+            Expr::Function(params, Box::new(canonical_with(body, &names, free, counter, fresh)))
+        }
+        _ => expr.clone(),
+    }
+}
+
+/// 이름만 다른 두 식에서 (바뀐 이름, 원래 이름)을 묶인 자리의 순서대로 모은다.
+/// `canonical`이 `v0`으로 바꾼 변수가 원래 무엇이었는지 알려 줄 때 씀
+pub fn renamings(original: &Expr, renamed: &Expr) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    collect_renamings(original, renamed, &mut pairs);
+    pairs
+}
+
+fn collect_renamings(original: &Expr, renamed: &Expr, pairs: &mut Vec<(String, String)>) {
+    match (original, renamed) {
+        (Expr::Function(old, old_body), Expr::Function(new, new_body)) => {
+            pairs.extend(new.iter().cloned().zip(old.iter().cloned()));
+            collect_renamings(old_body, new_body, pairs);
+        }
+        (Expr::Words(old), Expr::Words(new)) => {
+            for (old, new) in old.iter().zip(new) {
+                collect_renamings(old, new, pairs);
+            }
+        }
+        (Expr::Paren(old), Expr::Paren(new))
+        | (Expr::Annotated(_, old), Expr::Annotated(_, new))
+        | (Expr::TypeFunction(_, old), Expr::TypeFunction(_, new))
+        | (Expr::TypeApply(old, _), Expr::TypeApply(new, _))
+        | (Expr::Located(_, old), Expr::Located(_, new)) => collect_renamings(old, new, pairs),
+        _ => {}
+    }
+}
+
+/// α-동치: 묶인 변수의 이름만 다른 두 식을 같다고 본다.
+pub fn alpha_eq(a: &Expr, b: &Expr) -> bool {
+    canonical(a) == canonical(b)
+}
+
+fn function(mut params: Vec<String>, body: Expr) -> Expr {
+    match body {
+        Expr::Function(inner, body) => {
+            params.extend(inner);
+            Expr::Function(params, body)
+        }
+        _ => Expr::Function(params, Box::new(body)),
+    }
+}
+
+fn to_arg(expr: Expr) -> Expr {
+    match expr.unlocated() {
+        Expr::Words(_) => Expr::Paren(Box::new(expr)),
+        _ => expr,
+    }
+}
+
+fn fresh_name(base: &str, taken: &HashSet<String>) -> String {
+    let base = base.split('$').next().unwrap_or(base);
+    (1..)
+        .map(|n| format!("{}${}", base, n))
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode;
+    use crate::interpreter::{Config, Interpreter};
+    use crate::parser::Parser;
+
+    /// `--save-env`로 저장하고 `--load-env`로 읽는 과정을 한 번
+    fn round_trip(program: Expr) -> Expr {
+        let mut interpreter = Interpreter::new(Config::default());
+        interpreter.eval(program).unwrap();
+        let saved = bytecode::encode(&interpreter.env_program());
+        plain_names(&bytecode::decode(&saved).unwrap())
+    }
+
+    #[test]
+    fn substitution_renames_binders_that_would_capture() {
+        let mapping = HashMap::from([("y".to_string(), Expr::Word("x".to_string()))]);
+        let expr = Parser::new("L x. x y").parse().unwrap();
+        let substituted = substitute(&expr, &mapping);
+        assert!(alpha_eq(&substituted, &Parser::new("L z. z x").parse().unwrap()), "{}", substituted);
+    }
+
+    #[test]
+    fn substitution_handles_terms_deeper_than_the_call_stack() {
+        let mut expr = Expr::Word("x".to_string());
+        for _ in 0..200_000 {
+            expr = Expr::Paren(Box::new(expr));
+        }
+        let mapping = HashMap::from([("x".to_string(), Expr::Word("y".to_string()))]);
+        let mut expr = substitute_owned(expr, &mapping);
+        // 풀어서 버려야 `Drop`도 재귀하지 않음
+        let mut depth = 0;
+        while let Expr::Paren(inner) = expr {
+            expr = *inner;
+            depth += 1;
+        }
+        assert_eq!((depth, expr), (200_000, Expr::Word("y".to_string())));
+    }
+
+    #[test]
+    fn saved_environment_names_stay_stable_across_round_trips() {
+        let program = Parser::new("id = L x. x; k = L x y. x; f = k id;").parse().unwrap();
+        let once = round_trip(program);
+        let twice = round_trip(once.clone());
+        assert_eq!(once, twice);
+        assert!(!format!("{:?}", twice).contains('$'), "{:?}", twice);
+    }
+}
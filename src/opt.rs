@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::Expr;
+use crate::cps::collect_names;
+use crate::lift;
+use crate::normalize;
+
+// 이 노드 수 이하인 정의는 여러 번 쓰여도 펼침
+const SMALL_SIZE: usize = 8;
+// 이보다 작은 식은 여러 번 나와도 따로 정의하지 않음
+const MIN_SHARED_SIZE: usize = 5;
+
+/// 평가하기 전에 프로그램에 적용하는 변환. `--opt <name>`으로 하나씩 켜거나 `--passes a,b,...`로 순서를 정해 켬
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    // 문법 설탕은 파서가 읽으면서 풀기 때문에 프로그램을 그대로 둠. `--dump-after desugar`로 풀린 모양을 볼 수 있음
+    Desugar,
+    Lift,
+    Inline,
+    Cse,
+    // 평가하면서 정의를 정규형으로 줄이므로 인터프리터 설정으로 넘기고, 파이프라인의 마지막에만 올 수 있음
+    Prenorm,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 5] = [Pass::Desugar, Pass::Lift, Pass::Inline, Pass::Cse, Pass::Prenorm];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Desugar => "desugar",
+            Pass::Lift => "lift",
+            Pass::Inline => "inline",
+            Pass::Cse => "cse",
+            Pass::Prenorm => "prenorm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|pass| pass.name() == name)
+    }
+
+    /// 바꾼 프로그램과 `-v`로 보여 줄 한 줄 요약
+    pub fn run(self, program: &Expr) -> Result<(Expr, String), String> {
+        Ok(match self {
+            Pass::Desugar => (program.clone(), "desugar: done while parsing".to_string()),
+            Pass::Lift => {
+                let lifted = lift::transform(program)?;
+                let count = |e: &Expr| match e {
+                    Expr::Sequence(exprs) => exprs.len(),
+                    _ => 1,
+                };
+                let stats = format!(
+                    "lift: {} lambdas lifted, {} -> {} nodes",
+                    count(&lifted) - count(program),
+                    program.size(),
+                    lifted.size()
+                );
+                (lifted, stats)
+            }
+            Pass::Inline => inline(program),
+            Pass::Cse => cse(program),
+            Pass::Prenorm => (program.clone(), "prenorm: definitions are normalized while evaluating".to_string()),
+        })
+    }
+}
+
+/// `--passes`의 쉼표로 나눈 패스 이름들. `prenorm`은 평가하면서 하므로 마지막에만 둘 수 있음
+pub fn parse_pipeline(list: &str) -> Result<Vec<Pass>, String> {
+    let passes = list.split(',')
+        .map(|name| {
+            let names = Pass::ALL.map(Pass::name).join(", ");
+            Pass::from_name(name.trim()).ok_or_else(|| format!("Unknown pass '{}' (passes: {})", name, names))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    check_pipeline(&passes)?;
+    Ok(passes)
+}
+
+/// `prenorm` 뒤에 다른 패스가 있으면 그 패스가 실제로는 먼저 돌게 되므로 거부함
+pub fn check_pipeline(passes: &[Pass]) -> Result<(), String> {
+    match passes.iter().position(|&pass| pass == Pass::Prenorm) {
+        Some(i) if i + 1 < passes.len() => Err("prenorm runs while evaluating, so it must be the last pass".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// 람다를 값으로 갖는 작은 정의나 한 번만 쓰이는 정의를 뒤 문장의 쓰는 자리에 펼친다.
+/// 식이 커지는 대신 이름을 찾는 횟수가 줄어듦. 정의 자체는 `--dump-env`나 다음 파일에서 쓸 수 있도록 남겨 둠.
+/// 클로저는 만들어진 곳의 환경을 모두 잡으므로, 람다 안에 펼치면 불릴 때마다 큰 환경을 잡은 클로저가
+/// 새로 생김. 그래서 람다 밖의 쓰는 자리에만 펼치고, 정의가 본 이름이 다시 정의되면 그 뒤로는 펼치지 않음
+pub fn inline(program: &Expr) -> (Expr, String) {
+    let mut statements = match program {
+        Expr::Sequence(exprs) => exprs.clone(),
+        other => vec![other.clone()],
+    };
+    let before = program.size();
+    // 인터프리터는 마지막 식을 모든 정의를 평가한 뒤에 평가함
+    let last = statements.iter().rposition(|e| !matches!(e, Expr::Define(..) | Expr::Assert(..)));
+    let mut inlined = 0;
+    let mut sites = 0;
+    for i in 0..statements.len() {
+        let Expr::Define(name, _, body) = &statements[i] else {
+            continue;
+        };
+        let (name, value) = (name.clone(), strip(body).clone());
+        let free = normalize::free_vars(&value);
+        if !matches!(value, Expr::Function(..)) || free.contains(&name) {
+            continue;
+        }
+        // 이 정의를 그대로 볼 수 있는 뒤 문장들
+        let end = (i + 1..statements.len())
+            .find(|&j| matches!(&statements[j], Expr::Define(n, _, _) if *n == name || free.contains(n)))
+            .map_or(statements.len(), |j| j + 1);
+        let targets = (i + 1..end)
+            .filter(|&j| Some(j) != last || end == statements.len())
+            .collect::<Vec<_>>();
+        let uses = targets.iter().map(|&j| count_sites(&statements[j], &name)).sum::<usize>();
+        if uses == 0 || (uses > 1 && value.size() > SMALL_SIZE) {
+            continue;
+        }
+        let value = Expr::Paren(Box::new(value));
+        for j in targets {
+            // 다시 정의하는 문장은 본문만 바꿈
+            statements[j] = replace_sites(&statements[j], &name, &value);
+        }
+        inlined += 1;
+        sites += uses;
+    }
+    let program = match statements.len() {
+        1 => statements.remove(0),
+        _ => Expr::Sequence(statements),
+    };
+    let stats = format!(
+        "inline: {} definitions at {} sites, {} -> {} nodes",
+        inlined, sites, before, program.size(),
+    );
+    (program, stats)
+}
+
+fn strip(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(inner) | Expr::Located(_, inner) => strip(inner),
+        other => other,
+    }
+}
+
+/// 람다 밖에서 `name`이 나오는 횟수
+fn count_sites(expr: &Expr, name: &str) -> usize {
+    match expr {
+        Expr::Word(w) => usize::from(w == name),
+        Expr::Words(words) | Expr::Sequence(words) => words.iter().map(|e| count_sites(e, name)).sum(),
+        Expr::Function(..) => 0,
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => count_sites(inner, name),
+        Expr::Assert(lhs, rhs) => count_sites(lhs, name) + count_sites(rhs, name),
+    }
+}
+
+/// 람다 밖의 `name`을 `value`로 바꿈. 람다 안으로는 들어가지 않으므로 변수가 잡힐 일이 없음
+fn replace_sites(expr: &Expr, name: &str, value: &Expr) -> Expr {
+    match expr {
+        Expr::Word(w) if w == name => value.clone(),
+        Expr::Function(..) => expr.clone(),
+        _ => expr.map_children(|e| replace_sites(e, name, value)),
+    }
+}
+
+/// 여러 번 나오는 닫힌 식을 최상위 정의 `cse1`, `cse2`, …로 빼고 나오는 자리를 그 이름으로 바꾼다.
+/// 이름만 다른 식도 같은 식으로 봄. 큰 식부터 빼므로 뺀 정의 안에서 다시 나오는 작은 식도 뺄 수 있음.
+/// 람다는 값이라 어디서 빼도 되지만, 적용은 람다 밖에서만 나올 때 뺌. 람다 안의 적용을 미리 평가하면
+/// 불리지 않던 무한 루프를 돌 수 있기 때문. 최상위 정의의 본문 전체는 이름만 하나 더 생기므로 빼지 않음
+pub fn cse(program: &Expr) -> (Expr, String) {
+    let mut statements = match program {
+        Expr::Sequence(exprs) => exprs.clone(),
+        other => vec![other.clone()],
+    };
+    let before = program.size();
+    let mut taken = HashSet::new();
+    for statement in &statements {
+        collect_names(statement, &mut taken);
+    }
+    let mut shared = 0;
+    loop {
+        let mut seen: HashMap<Expr, Occurrences> = HashMap::new();
+        for (i, statement) in statements.iter().enumerate() {
+            let (root, top) = match statement {
+                Expr::Define(_, _, body) => (&**body, true),
+                other => (other, false),
+            };
+            closed_terms(root, i, top, false, &mut seen);
+        }
+        let best = seen.into_iter()
+            .filter(|(_, o)| o.count > 1 && (o.value || !o.in_lambda))
+            // 크기와 처음 나온 문장이 같으면 해시 순서가 아니라 글로 보아 앞선 것
+            .max_by_key(|(key, o)| (key.size(), std::cmp::Reverse(o.first), std::cmp::Reverse(key.to_string())));
+        let Some((key, occurrences)) = best else {
+            break;
+        };
+        let name = (1..)
+            .map(|n| format!("cse{}", n))
+            .find(|name| !taken.contains(name))
+            .unwrap();
+        taken.insert(name.clone());
+        let mut term = None;
+        for statement in &mut statements {
+            *statement = replace_term(statement, &key, &name, &mut term, true);
+        }
+        let term = term.expect("occurrence to replace");
+        statements.insert(occurrences.first, Expr::Define(name, None, Box::new(term)));
+        shared += 1;
+    }
+    let program = match statements.len() {
+        1 => statements.remove(0),
+        _ => Expr::Sequence(statements),
+    };
+    let stats = format!("cse: {} shared terms, {} -> {} nodes", shared, before, program.size());
+    (program, stats)
+}
+
+struct Occurrences {
+    count: usize,
+    // 처음 나온 문장. 뺀 정의는 그 앞에 둠
+    first: usize,
+    // 람다인지. 아니면 적용
+    value: bool,
+    // 한 번이라도 람다 안에서 나왔는지
+    in_lambda: bool,
+}
+
+/// 식의 자유 변수를 돌려주면서, 크기가 충분한 닫힌 부분식을 `Expr` 모양별로 셈. `top`이면 그 식 자체는 세지 않음
+fn closed_terms(
+    expr: &Expr,
+    statement: usize,
+    top: bool,
+    in_lambda: bool,
+    seen: &mut HashMap<Expr, Occurrences>,
+) -> HashSet<String> {
+    let free = match expr {
+        Expr::Word(w) => HashSet::from([w.clone()]),
+        Expr::Words(words) | Expr::Sequence(words) => words.iter()
+            .flat_map(|e| closed_terms(e, statement, false, in_lambda, seen))
+            .collect(),
+        Expr::Function(params, body) => {
+            let mut free = closed_terms(body, statement, false, true, seen);
+            for param in params {
+                free.remove(param);
+            }
+            free
+        }
+        Expr::Paren(inner) | Expr::Located(_, inner) => return closed_terms(inner, statement, top, in_lambda, seen),
+        Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _) => closed_terms(inner, statement, false, in_lambda, seen),
+        Expr::Assert(lhs, rhs) => {
+            let mut free = closed_terms(lhs, statement, false, in_lambda, seen);
+            free.extend(closed_terms(rhs, statement, false, in_lambda, seen));
+            free
+        }
+    };
+    let candidate = matches!(expr, Expr::Function(..) | Expr::Words(_)) && expr.size() >= MIN_SHARED_SIZE;
+    if free.is_empty() && candidate && !top {
+        let occurrences = seen.entry(shape(expr)).or_insert(Occurrences {
+            count: 0,
+            first: statement,
+            value: matches!(expr, Expr::Function(..)),
+            in_lambda: false,
+        });
+        occurrences.count += 1;
+        occurrences.in_lambda |= in_lambda;
+    }
+    free
+}
+
+/// 같은 식인지 비교할 모양: 자리와 바깥 괄호를 떼고 묶인 변수를 v0, v1, …로 바꿈
+fn shape(expr: &Expr) -> Expr {
+    normalize::canonical(&bare(expr))
+}
+
+fn bare(expr: &Expr) -> Expr {
+    strip(expr).rewrite_bottom_up(|e| match e {
+        Expr::Located(_, inner) => *inner,
+        e => e,
+    })
+}
+
+/// 모양이 `key`인 부분식을 `name`으로 바꿈. 처음 바꾼 식을 `term`에 남겨 새 정의의 본문으로 씀.
+/// `top`이면 최상위 정의의 본문 전체는 바꾸지 않음
+fn replace_term(expr: &Expr, key: &Expr, name: &str, term: &mut Option<Expr>, top: bool) -> Expr {
+    if !top && matches!(expr.unlocated(), Expr::Function(..) | Expr::Words(_) | Expr::Paren(_)) && shape(expr) == *key {
+        term.get_or_insert_with(|| bare(expr));
+        return match expr {
+            Expr::Located(span, _) => Expr::Located(*span, Box::new(Expr::Word(name.to_string()))),
+            _ => Expr::Word(name.to_string()),
+        };
+    }
+    let top = match expr {
+        Expr::Sequence(_) | Expr::Define(..) => true,
+        Expr::Located(..) => top,
+        _ => false,
+    };
+    expr.map_children(|e| replace_term(e, key, name, term, top))
+}
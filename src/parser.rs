@@ -1,144 +1,1125 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
-use crate::ast::Expr;
+use crate::ast::{Expr, Span, Type};
+use crate::cps::collect_names;
+use crate::encode;
+use crate::highlight::{self, Class};
+use crate::log::{self, Target};
+use crate::messages::{self, Msg};
+use crate::normalize;
+
+/// 값 호출에서도 멈추는 고정점 조합자 Z
+fn z_combinator() -> Expr {
+    crate::expr!(L f. (L x. f (L v. x x v)) (L x. f (L v. x x v)))
+}
+
+/// `if`, `match` 식 안에서 앞 식을 끝내는 낱말. 변수 이름으로 쓸 수 없음
+const KEYWORDS: [&str; 5] = ["then", "else", "with", "of", "where"];
+
+/// 문법에 쓰는 낱말과 앞으로 쓰려고 남겨 둔 낱말. 정의하거나 매개변수로 묶을 수 없음
+pub const RESERVED: [&str; 15] = [
+    "let", "in", "rec", "data", "import", "where", "if", "then", "else",
+    "match", "with", "case", "of", "assert", "forall",
+];
+
+/// 새로 묶는 이름이 예약어가 아닌지 확인
+fn check_binder(name: &str) -> Result<(), String> {
+    match RESERVED.contains(&name) {
+        true => Err(messages::text(Msg::ReservedKeyword, &[&name])),
+        false => Ok(()),
+    }
+}
+
+/// 이름에 쓸 수 있는 글자: 어느 문자 체계든 글자나 숫자, `_`, 앞 글자에 얹히는 결합 문자.
+/// `→`나 `…`, 이모지 같은 기호는 ASCII 밖이라도 이름이 아님
+pub fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || is_combining(ch)
+}
+
+/// 이름의 첫 글자. 앞 글자에 얹히는 결합 문자로는 시작할 수 없음
+pub fn is_name_start(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// 글자로 치지 않지만 앞 글자에 얹혀 이름을 잇는 글자: 결합 부호, 인도계 문자의 비라마와 누크타, ZWNJ/ZWJ
+fn is_combining(ch: char) -> bool {
+    matches!(ch,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{093C}' | '\u{094D}' | '\u{09BC}' | '\u{09CD}' | '\u{0A3C}' | '\u{0A4D}' | '\u{0ABC}' | '\u{0ACD}'
+        | '\u{0B3C}' | '\u{0B4D}' | '\u{0BCD}' | '\u{0C4D}' | '\u{0CBC}' | '\u{0CCD}' | '\u{0D4D}' | '\u{0DCA}'
+        | '\u{0E3A}' | '\u{1039}' | '\u{17D2}'
+        | '\u{200C}'
+        | '\u{200D}')
+}
+
+/// 식 뒤에 붙는 연산자. 나란히 쓰는 함수 적용도 연산자 하나로 본다.
+#[derive(Clone, Copy)]
+enum Operator {
+    /// `x = e`, `x : T = e`
+    Define,
+    /// ``x `f` y`` = `f x y`
+    Infix,
+    /// `f a`
+    Apply,
+    /// `f [T]`
+    TypeApply,
+    /// `r.a`
+    Project,
+}
+
+impl Operator {
+    /// (왼쪽, 오른쪽) 결합력. 오른쪽이 더 크면 왼쪽 결합
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            Operator::Define => (1, PRIMARY),
+            Operator::Infix => (10, 11),
+            Operator::Apply | Operator::TypeApply => (20, 21),
+            Operator::Project => (30, 31),
+        }
+    }
+}
+
+/// 정의를 뺀 연산자만 묶는 결합력. 정의의 본문과 `assert`의 양쪽은 이만큼만 읽음
+const PRIMARY: u8 = 2;
+
+enum Pattern {
+    True,
+    False,
+    Pair(String, String),
+    None,
+    Some(String),
+}
+
+/// 남은 원문. 지금 위치의 바이트 오프셋을 바로 알 수 있도록 `Peekable<Chars>` 대신 씀.
+/// 파서는 따로 토큰 목록을 만들지 않고 이 위에서 글자를 필요한 만큼만 읽음
+#[derive(Clone)]
+struct Input<'a> {
+    rest: &'a str,
+    current: Option<char>,
+}
+
+impl<'a> Input<'a> {
+    fn new(rest: &'a str) -> Self {
+        Input { rest, current: rest.chars().next() }
+    }
+
+    fn peek(&self) -> Option<&char> {
+        self.current.as_ref()
+    }
+}
+
+impl Iterator for Input<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.current?;
+        self.rest = &self.rest[ch.len_utf8()..];
+        self.current = self.rest.chars().next();
+        Some(ch)
+    }
+}
+
+/// 앞 문장들이 남겨서 뒤 문장을 읽는 방법을 바꾸는 정보
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    // `data` 선언에서 본 생성자 이름과 그 타입의 생성자 목록(이름, 필드 수)
+    constructors: HashMap<String, Vec<(String, usize)>>,
+    // 지금까지 나온 레코드 리터럴의 필드 목록(이름순)
+    records: Vec<Vec<String>>,
+}
+
+/// 최상위 문장 하나와 원문에서의 바이트 범위. 서로 부르는 `rec` 정의들은 한 문장으로 침
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub range: Range<usize>,
+    pub exprs: Vec<Expr>,
+}
 
 pub struct Parser<'a> {
-    input: Peekable<Chars<'a>>,
+    input: Input<'a>,
+    source: &'a str,
+    context: Context,
+    // 켜면 `f a b`를 Words 하나 대신 `Words([Words([f, a]), b])`로 만듦. 뜻은 같음
+    binary_application: bool,
+    // 켜면 낱말과 적용을 `Expr::Located`로 감쌈
+    spans: bool,
+    // 줄마다 시작하는 바이트 오프셋
+    line_starts: Vec<usize>,
+    // `parse_all`에서 켬. 오류를 모으고 동기화 지점까지 건너뛴 뒤 계속 읽음
+    recover: bool,
+    errors: Vec<String>,
+    // 문장을 하나씩 꺼내 쓸 때 오류 뒤로는 더 읽지 않음
+    failed: bool,
+    // 있으면 식이 이보다 깊게 겹치면 오류. 스택이 넘치지 않도록 `serve`에서 켬
+    max_depth: Option<usize>,
+    depth: usize,
+}
+
+/// 최상위 문장을 필요할 때마다 하나씩 읽음. 오류가 나면 그 오류를 마지막으로 끝남
+impl Iterator for Parser<'_> {
+    type Item = Result<Item, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = self.parse_item().transpose();
+        self.failed = matches!(item, Some(Err(_)));
+        item
+    }
 }
 
 impl<'a> Parser<'a> {
     pub fn new(source: &'a str) -> Self {
+        // 스크립트로 실행할 때의 `#!` 줄은 `#` 주석으로 건너뜀
         Parser {
-            input: source.chars().peekable(),
+            input: Input::new(source),
+            source,
+            context: Context::default(),
+            binary_application: false,
+            spans: false,
+            line_starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            recover: false,
+            errors: Vec::new(),
+            failed: false,
+            max_depth: None,
+            depth: 0,
+        }
+    }
+
+    /// 앞 문장들을 이미 읽었다고 보고 `source`의 `offset` 바이트부터 읽음
+    pub fn resume(source: &'a str, offset: usize, context: Context) -> Self {
+        let mut parser = Parser::new(source);
+        if offset > 0 {
+            parser.input = Input::new(&source[offset..]);
+        }
+        parser.context = context;
+        parser
+    }
+
+    /// 지금까지 읽은 바이트 수
+    pub fn offset(&self) -> usize {
+        self.source.len() - self.input.rest.len()
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// 앞서 읽은 파일이나 입력이 남긴 `data` 선언과 레코드 모양을 이어받아 읽는다.
+    /// 여러 파일을 한 프로그램으로 읽을 때 앞 파일의 생성자와 필드를 뒤 파일에서 쓸 수 있음
+    pub fn with_context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// 적용을 언제나 두 항짜리로 만들어 왼쪽 결합이 AST에 그대로 드러나게 한다.
+    pub fn binary_application(mut self, on: bool) -> Self {
+        self.binary_application = on;
+        self
+    }
+
+    /// 평가 중 오류가 난 곳을 알릴 수 있도록 낱말과 적용마다 원문의 자리를 붙인다.
+    /// 이렇게 읽은 식은 자리만 빼면 끄고 읽은 식과 같음
+    pub fn spans(mut self, on: bool) -> Self {
+        self.spans = on;
+        self
+    }
+
+    /// 괄호, 람다, 연산자로 식이 `depth`보다 깊게 겹치면 읽지 않고 오류를 낸다.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// 첫 오류에서 멈추지 않고 오류를 모두 모아 돌려준다. 오류가 난 문장은 다음 `;`까지,
+    /// 괄호 안의 오류는 짝이 맞는 `)`까지 건너뛰고 이어서 읽는다. 오류마다 `line L, column C: `가 붙음
+    pub fn parse_all(&mut self) -> Result<Expr, Vec<String>> {
+        self.recover = true;
+        let result = self.parse();
+        let mut errors = std::mem::take(&mut self.errors);
+        match result {
+            Ok(expr) if errors.is_empty() => Ok(expr),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e);
+                Err(errors)
+            }
         }
     }
 
     pub fn parse(&mut self) -> Result<Expr, String> {
         let mut exprs = Vec::new();
         loop {
+            match self.parse_item() {
+                Ok(Some(item)) => exprs.extend(item.exprs),
+                Ok(None) => break,
+                Err(e) if self.recover => {
+                    log::debug(Target::Parser, || format!("recovering from: {}", e));
+                    self.report(e);
+                    self.skip_to_sync(false);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        log::debug(Target::Parser, || format!("read {} statements from {} bytes", exprs.len(), self.offset()));
+        if exprs.len() == 1 {
+            Ok(exprs.into_iter().next().unwrap())
+        } else {
+            Ok(Expr::Sequence(exprs))
+        }
+    }
+
+    /// 다음 최상위 문장을 `;`까지 읽음. `rec` 정의가 이어지면 묶음이 끝날 때까지 읽고, 입력이 끝났으면 `None`.
+    /// 범위를 함께 돌려주므로 바뀌지 않은 문장은 다시 읽지 않고 쓸 수 있음
+    pub fn parse_item(&mut self) -> Result<Option<Item>, String> {
+        self.skip_whitespace();
+        if self.input.peek().is_none() {
+            return Ok(None);
+        }
+        let start = self.offset();
+        let mut exprs = Vec::new();
+        let mut group = Vec::new();
+        loop {
+            self.parse_statement(&mut exprs, &mut group)?;
+            self.skip_whitespace();
+            let separated = self.consume(';');
             self.skip_whitespace();
-            if self.input.peek().is_none() {
+            if !separated && self.input.peek().is_some() {
+                return Err(self.expected(&["';'", &messages::text(Msg::EndOfInput, &[])]));
+            }
+            if group.is_empty() || self.input.peek().is_none() {
                 break;
             }
+        }
+        exprs.extend(desugar_definitions(group)?);
+        Ok(Some(Item { range: start..self.offset(), exprs }))
+    }
+
+    /// 최상위 문장 하나. 정의는 `rec` 묶음이 끝날 때까지 `group`에 모아 둠
+    fn parse_statement(&mut self, exprs: &mut Vec<Expr>, group: &mut Vec<Definition>) -> Result<(), String> {
+        if let Some(expr) = self.parse_assert()? {
+            exprs.extend(desugar_definitions(std::mem::take(group))?);
+            exprs.push(expr);
+        } else if let Some(constructors) = self.parse_data()? {
+            exprs.extend(desugar_definitions(std::mem::take(group))?);
+            exprs.extend(constructors);
+        } else if let Some(def) = self.parse_top_definition()? {
+            let local = self.parse_where()?;
+            // 연달아 나오는 `rec` 정의는 서로를 부를 수 있는 한 묶음
+            if !def.recursive || !local.is_empty() {
+                exprs.extend(desugar_definitions(std::mem::take(group))?);
+            }
+            exprs.extend(desugar_definitions(local)?);
+            group.push(def);
+            if !group.last().unwrap().recursive {
+                exprs.extend(desugar_definitions(std::mem::take(group))?);
+            }
+        } else {
+            exprs.extend(desugar_definitions(std::mem::take(group))?);
             let expr = self.parse_expression()?;
+            exprs.extend(desugar_definitions(self.parse_where()?)?);
             exprs.push(expr);
-            self.skip_whitespace();
-            if self.consume(';') {
-                continue;
-            } else {
-                break;
+        }
+        Ok(())
+    }
+
+    /// 지금 위치를 붙여 오류를 모음
+    fn report(&mut self, message: String) {
+        let (line, column) = self.line_column(self.offset());
+        self.errors.push(messages::text(Msg::LineColumn, &[&line, &column, &message]));
+    }
+
+    /// 바이트 오프셋의 (줄, 칸). 둘 다 1부터
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let column = self.source[self.line_starts[line - 1]..offset].chars().count() + 1;
+        (line, column)
+    }
+
+    /// `spans`가 켜져 있으면 `start`부터 지금까지의 자리를 붙임
+    fn located(&self, start: usize, expr: Expr) -> Expr {
+        if !self.spans {
+            return expr;
+        }
+        let (line, column) = self.line_column(start);
+        let (end_line, end_column) = self.line_column(self.offset());
+        Expr::Located(Span { line, column, end_line, end_column }, Box::new(expr))
+    }
+
+    /// 오류가 난 곳에서 다음 동기화 지점까지 건너뜀. 건너뛰는 동안에도 괄호 짝과 문자열은 따짐.
+    /// `close`이면 열린 괄호를 닫는 `)`까지, 아니면 다음 `;`까지 먹음.
+    /// `close`여도 괄호 밖의 `;`에서는 그 문장을 끝낼 수 있도록 남겨 두고 멈춤
+    fn skip_to_sync(&mut self, close: bool) {
+        let mut depth = 0;
+        while let Some(&ch) = self.input.peek() {
+            if ch == ';' && depth == 0 && close {
+                return;
+            }
+            self.input.next();
+            match ch {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                ')' if close => return,
+                ';' if depth == 0 => return,
+                '"' => {
+                    while let Some(ch) = self.input.next() {
+                        match ch {
+                            '\\' => {
+                                self.input.next();
+                            }
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
             }
         }
-        if exprs.len() == 1 {
-            Ok(exprs.into_iter().next().unwrap())
-        } else {
-            Ok(Expr::Sequence(exprs))
+    }
+
+    /// `parse_expression`과 같은 순서로, 람다가 아닐 때만 정의를 시도함
+    fn parse_top_definition(&mut self) -> Result<Option<Definition>, String> {
+        self.skip_whitespace();
+        if self.peek_is('L') || self.peek_is('/') {
+            return Ok(None);
         }
+        self.parse_definition()
     }
 
     fn parse_expression(&mut self) -> Result<Expr, String> {
+        self.parse_binding_power(0)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        self.parse_binding_power(PRIMARY)
+    }
+
+    /// 결합력이 `min_bp` 이상인 연산자까지 묶어서 읽는다 (Pratt 파싱).
+    fn parse_binding_power(&mut self, min_bp: u8) -> Result<Expr, String> {
+        if let Some(max) = self.max_depth.filter(|max| self.depth >= *max) {
+            return Err(messages::text(Msg::NestedTooDeep, &[&max]));
+        }
+        self.depth += 1;
+        let expr = self.parse_bounded(min_bp);
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_bounded(&mut self, min_bp: u8) -> Result<Expr, String> {
         self.skip_whitespace();
-        if self.peek_is('L') {
-            self.parse_function()
-        } else if self.peek_is('(') {
-            self.parse_paren()
-        } else if let Some(expr) = self.parse_define()? {
-            Ok(expr)
+        let start = self.offset();
+        // 람다와 `if` 같은 꼴은 본문을 갈 수 있는 데까지 읽으므로 뒤에 연산자가 올 수 없음
+        let expr = if self.peek_is('L') {
+            self.parse_function()?
+        } else if self.peek_is('/') {
+            self.parse_type_function()?
+        } else if let Some(expr) = self.parse_sugar()? {
+            expr
+        } else if min_bp <= Operator::Define.binding_power().0 && self.peek_word() == "rec" {
+            match self.parse_definition()? {
+                Some(def) => desugar_definitions(vec![def])?.pop().unwrap(),
+                None => {
+                    let atom = self.parse_atom()?;
+                    self.parse_operators(atom, start, min_bp)?
+                }
+            }
         } else {
-            self.parse_words()
+            let atom = self.parse_atom()?;
+            self.parse_operators(atom, start, min_bp)?
+        };
+        self.skip_whitespace();
+        Ok(expr)
+    }
+
+    /// `start`는 `lhs`가 시작한 바이트 오프셋
+    fn parse_operators(&mut self, mut lhs: Expr, start: usize, min_bp: u8) -> Result<Expr, String> {
+        while let Some(op) = self.peek_operator() {
+            let (left, right) = op.binding_power();
+            if left < min_bp {
+                break;
+            }
+            lhs = match op {
+                Operator::Project => {
+                    self.expect('.')?;
+                    let field = self.parse_word()?;
+                    let projected = Expr::Words(vec![lhs, self.selector(&field)?]);
+                    Expr::Paren(Box::new(self.located(start, projected)))
+                }
+                Operator::Apply => {
+                    let arg_start = self.offset();
+                    let atom = self.parse_atom()?;
+                    let arg = self.parse_operators(atom, arg_start, right)?;
+                    // 이어 붙인 적용에는 같은 시작으로 자리를 다시 붙임
+                    let lhs = match lhs {
+                        Expr::Located(_, inner) if matches!(*inner, Expr::Words(_)) && !self.binary_application => *inner,
+                        lhs => lhs,
+                    };
+                    // `f a b`는 `(f a) b`. 보통은 Words 하나로 담음
+                    let applied = match lhs {
+                        Expr::Words(mut words) if !self.binary_application => {
+                            words.push(arg);
+                            Expr::Words(words)
+                        }
+                        lhs => Expr::Words(vec![lhs, arg]),
+                    };
+                    self.located(start, applied)
+                }
+                Operator::TypeApply => {
+                    // `f a [T] b` = `((f a) [T]) b`
+                    self.expect('[')?;
+                    let ty = self.parse_type()?;
+                    self.skip_whitespace();
+                    self.expect(']')?;
+                    Expr::TypeApply(Box::new(lhs), ty)
+                }
+                Operator::Infix => {
+                    self.expect('`')?;
+                    let function = self.parse_word()?;
+                    self.expect('`')?;
+                    let rhs = self.parse_binding_power(right)?;
+                    let operand = |e: Expr| match e.unlocated() {
+                        Expr::Word(_) | Expr::Paren(_) => e,
+                        _ => Expr::Paren(Box::new(e)),
+                    };
+                    self.located(start, Expr::Words(vec![Expr::Word(function), operand(lhs), operand(rhs)]))
+                }
+                Operator::Define => {
+                    let name = match lhs.unlocated() {
+                        Expr::Word(name) => name.clone(),
+                        other => return Err(messages::text(Msg::NotDefinable, &[&other])),
+                    };
+                    let def = self.parse_definition_tail(name, false)?;
+                    desugar_definitions(vec![def])?.pop().unwrap()
+                }
+            };
         }
+        Ok(lhs)
     }
 
+    /// 다음 연산자. 없으면 공백도 건너뛰지 않음
+    fn peek_operator(&mut self) -> Option<Operator> {
+        // 필드 꺼내기는 점 앞뒤에 공백이 없을 때만
+        let mut ahead = self.input.clone();
+        if ahead.next() == Some('.') && ahead.next().is_some_and(is_name_start) {
+            return Some(Operator::Project);
+        }
+        let saved_input = self.input.clone();
+        self.skip_whitespace();
+        let op = match self.input.peek().copied() {
+            Some('[') => Some(Operator::TypeApply),
+            Some('`') => Some(Operator::Infix),
+            Some(':') => Some(Operator::Define),
+            Some('=') if !self.peek_second_is('=') => Some(Operator::Define),
+            Some(ch) if (is_name_start(ch) || matches!(ch, '(' | '"' | '{')) && !self.peek_keyword() => {
+                Some(Operator::Apply)
+            }
+            _ => None,
+        };
+        if op.is_none() {
+            self.input = saved_input;
+        }
+        op
+    }
+
+    /// `L x y. e` = `L x. L y. e`. 같은 이름을 두 번 쓰면 뒤의 매개변수가 앞의 것을 가리므로
+    /// `L x x. x`는 둘째 인자를 돌려줌. `lint --duplicates`가 이런 람다를 알려 줌
     fn parse_function(&mut self) -> Result<Expr, String> {
         self.expect('L')?;
         self.skip_whitespace();
-        let mut params = vec![self.parse_word()?];
+        let mut params = Vec::new();
+        let mut types = Vec::new();
         while let Some(&ch) = self.input.peek() {
-            if ch.is_alphanumeric() || ch == '_' {
-                params.push(self.parse_word()?);
+            if is_name_start(ch) {
+                let param = self.parse_word()?;
+                check_binder(&param)?;
+                params.push(param);
+                types.push(None);
+            } else if ch == '(' {
+                let (param, ty) = self.parse_typed_param()?;
+                params.push(param);
+                types.push(Some(ty));
             } else if ch == ' ' {
                 self.input.next();
             } else {
                 break;
             }
         }
-        self.expect('.')?;
+        if params.is_empty() {
+            return Err(self.expected(&[&messages::text(Msg::ParameterName, &[]), "'('"]));
+        }
+        if !self.consume('.') {
+            return Err(self.expected(&[&messages::text(Msg::ParameterName, &[]), "'('", "'.'"]));
+        }
         let body = self.parse_expression()?;
-        Ok(Expr::Function(params, Box::new(body)))
+        let function = Expr::Function(params, Box::new(body));
+        if types.iter().any(Option::is_some) {
+            Ok(Expr::Annotated(types, Box::new(function)))
+        } else {
+            Ok(function)
+        }
     }
 
-    fn parse_define(&mut self) -> Result<Option<Expr>, String> {
-        let saved_input = self.input.clone();
+    /// `(x : T)`
+    fn parse_typed_param(&mut self) -> Result<(String, Type), String> {
+        self.expect('(')?;
         self.skip_whitespace();
-        if let Ok(name) = self.parse_word() {
-            self.skip_whitespace();
-            if self.consume('=') {
+        let param = self.parse_word()?;
+        check_binder(&param)?;
+        self.skip_whitespace();
+        self.expect(':')?;
+        let ty = self.parse_type()?;
+        self.skip_whitespace();
+        self.expect(')')?;
+        Ok((param, ty))
+    }
+
+    /// 화살표는 오른쪽으로 결합: `a -> b -> c` = `a -> (b -> c)`.
+    /// `forall a b. T`는 오른쪽 끝까지 이어진다.
+    fn parse_type(&mut self) -> Result<Type, String> {
+        self.skip_whitespace();
+        let saved_input = self.input.clone();
+        if let Ok(word) = self.parse_word() {
+            if word == "forall" && self.peek_is(' ') {
+                let mut vars = Vec::new();
                 self.skip_whitespace();
-                let body = self.parse_primary()?; // !!! 여기 수정: parse_primary()로 딱 하나만 읽기
-                return Ok(Some(Expr::Define(name, Box::new(body))));
+                while !self.peek_is('.') {
+                    vars.push(self.parse_word()?);
+                    self.skip_whitespace();
+                }
+                self.expect('.')?;
+                let body = self.parse_type()?;
+                return Ok(vars.into_iter().rev()
+                    .fold(body, |ty, var| Type::Forall(var, Box::new(ty))));
             }
         }
         self.input = saved_input;
-        Ok(None)
+
+        let from = if self.consume('(') {
+            let ty = self.parse_type()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            ty
+        } else if self.input.peek().is_some_and(|&ch| is_name_start(ch)) {
+            Type::Base(self.parse_word()?)
+        } else {
+            return Err(self.expected(&[&messages::text(Msg::TypeName, &[]), "'forall'", "'('"]));
+        };
+        self.skip_whitespace();
+        if self.peek_is('-') {
+            self.input.next();
+            self.expect('>')?;
+            let to = self.parse_type()?;
+            Ok(Type::Arrow(Box::new(from), Box::new(to)))
+        } else {
+            Ok(from)
+        }
     }
 
-    fn parse_words(&mut self) -> Result<Expr, String> {
-        let mut words = vec![Expr::Word(self.parse_word()?)];
+    /// `assert e1 == e2`
+    fn parse_assert(&mut self) -> Result<Option<Expr>, String> {
+        let saved_input = self.input.clone();
+        match self.parse_word() {
+            Ok(word) if word == "assert"
+                && matches!(self.input.peek(), Some(' ' | '\t' | '\n' | '\r' | '(')) => {}
+            _ => {
+                self.input = saved_input;
+                return Ok(None);
+            }
+        }
+        let lhs = self.parse_primary()?;
         self.skip_whitespace();
-        while let Some(&ch) = self.input.peek() {
-            if ch.is_alphanumeric() || ch == '_' {
-                words.push(Expr::Word(self.parse_word()?));
+        if !(self.peek_is('=') && self.peek_second_is('=')) {
+            return Err(self.expected(&["'=='"]));
+        }
+        self.input.next();
+        self.input.next();
+        let rhs = self.parse_primary()?;
+        Ok(Some(Expr::Assert(Box::new(lhs), Box::new(rhs))))
+    }
+
+    /// `data Maybe = Nothing | Just x` 선언을 Scott 부호화된 생성자 정의들로 바꾼다.
+    /// 생성자는 필드를 받은 뒤 생성자마다 갈래 하나씩을 받아 자기 갈래에 필드를 넘긴다:
+    /// `Nothing = L Nothing Just. Nothing`, `Just = L x. L Nothing Just. Just x`.
+    fn parse_data(&mut self) -> Result<Option<Vec<Expr>>, String> {
+        let saved_input = self.input.clone();
+        match self.parse_word() {
+            Ok(word) if word == "data" && matches!(self.input.peek(), Some(' ' | '\t' | '\n' | '\r')) => {}
+            _ => {
+                self.input = saved_input;
+                return Ok(None);
+            }
+        }
+        self.skip_whitespace();
+        let type_name = self.parse_word()?;
+        check_binder(&type_name)?;
+        self.skip_whitespace();
+        self.expect('=')?;
+
+        let mut variants = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_word()?;
+            check_binder(&name)?;
+            self.skip_whitespace();
+            let mut fields = Vec::new();
+            while !self.peek_is('|') && !self.peek_is(';') && self.input.peek().is_some() {
+                let field = self.parse_word()?;
+                check_binder(&field)?;
+                fields.push(field);
                 self.skip_whitespace();
-            } else {
+            }
+            if variants.iter().any(|(other, _): &(String, Vec<String>)| *other == name) {
+                return Err(messages::text(Msg::DuplicateConstructor, &[&name, &type_name]));
+            }
+            variants.push((name, fields));
+            if !self.consume('|') {
                 break;
             }
         }
+
+        let signature = variants.iter()
+            .map(|(name, fields)| (name.clone(), fields.len()))
+            .collect::<Vec<_>>();
+        let mut defines = Vec::new();
+        for (name, fields) in &variants {
+            self.context.constructors.insert(name.clone(), signature.clone());
+            let taken = fields.iter().cloned().collect::<HashSet<_>>();
+            let handlers = variants.iter()
+                .map(|(other, _)| unused(other, &taken))
+                .collect::<Vec<_>>();
+            let handler = &handlers[signature.iter().position(|(other, _)| other == name).unwrap()];
+            let body = match fields.is_empty() {
+                true => Expr::Word(handler.clone()),
+                false => Expr::Words(std::iter::once(handler.clone())
+                    .chain(fields.iter().cloned())
+                    .map(Expr::Word)
+                    .collect()),
+            };
+            let mut value = Expr::Function(handlers, Box::new(body));
+            if !fields.is_empty() {
+                value = Expr::Function(fields.clone(), Box::new(value));
+            }
+            defines.push(Expr::Define(name.clone(), None, Box::new(value)));
+        }
+        Ok(Some(defines))
+    }
+
+    /// `name = body`, `name : T = body`, `rec name = body`
+    fn parse_definition(&mut self) -> Result<Option<Definition>, String> {
+        let saved_input = self.input.clone();
+        self.skip_whitespace();
+        if let Ok(mut name) = self.parse_word() {
+            // `rec name = body`: 본문에서 자기 자신을 부를 수 있음
+            let mut recursive = false;
+            if name == "rec" && matches!(self.input.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+                self.skip_whitespace();
+                if let Ok(rec_name) = self.parse_word() {
+                    name = rec_name;
+                    recursive = true;
+                }
+            }
+            self.skip_whitespace();
+            if self.peek_is(':') || (self.peek_is('=') && !self.peek_second_is('=')) {
+                check_binder(&name)?;
+                return self.parse_definition_tail(name, recursive).map(Some);
+            }
+        }
+        self.input = saved_input;
+        Ok(None)
+    }
+
+    /// 이름 뒤의 `= body` 또는 `: T = body`
+    fn parse_definition_tail(&mut self, name: String, recursive: bool) -> Result<Definition, String> {
+        self.skip_whitespace();
+        let ty = if self.consume(':') {
+            let ty = self.parse_type()?;
+            self.skip_whitespace();
+            Some(ty)
+        } else {
+            None
+        };
+        if !self.peek_is('=') || self.peek_second_is('=') {
+            return Err(self.expected(&["'='"]));
+        }
+        self.input.next();
+        let body = self.parse_primary()?; // !!! 여기 수정: parse_primary()로 딱 하나만 읽기
+        Ok(Definition { name, ty, body, recursive })
+    }
+
+    fn collapse(words: Vec<Expr>) -> Expr {
         if words.len() == 1 {
-            Ok(words.into_iter().next().unwrap())
+            words.into_iter().next().unwrap()
         } else {
-            Ok(Expr::Words(words))
+            Expr::Words(words)
+        }
+    }
+
+    /// `/\a b. e`
+    fn parse_type_function(&mut self) -> Result<Expr, String> {
+        self.expect('/')?;
+        self.expect('\\')?;
+        self.skip_whitespace();
+        let mut vars = vec![self.parse_word()?];
+        self.skip_whitespace();
+        while !self.peek_is('.') {
+            vars.push(self.parse_word()?);
+            self.skip_whitespace();
+        }
+        self.expect('.')?;
+        let body = self.parse_expression()?;
+        Ok(Expr::TypeFunction(vars, Box::new(body)))
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, String> {
+        if self.peek_is('(') {
+            self.parse_paren()
+        } else if self.peek_is('"') {
+            self.parse_string()
+        } else if self.peek_is('{') {
+            self.parse_record()
+        } else if self.input.peek().is_some_and(|&ch| is_name_start(ch)) {
+            let start = self.offset();
+            let word = Expr::Word(self.parse_word()?);
+            Ok(self.located(start, word))
+        } else {
+            Err(self.expected(&[&messages::text(Msg::Identifier, &[]), "'L'", "'('", "'\"'", "'{'"]))
+        }
+    }
+
+    /// `{ a = e1, b = e2 }`는 필드를 이름순으로 담은 튜플 `L s. s e1 e2`
+    fn parse_record(&mut self) -> Result<Expr, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        while !self.consume('}') {
+            let name = self.parse_word()?;
+            self.skip_whitespace();
+            self.expect('=')?;
+            let value = self.parse_primary()?;
+            if fields.iter().any(|(other, _)| *other == name) {
+                return Err(messages::text(Msg::DuplicateField, &[&name]));
+            }
+            fields.push((name, value));
+            self.skip_whitespace();
+            if !self.consume(',') {
+                self.expect('}')?;
+                break;
+            }
+            self.skip_whitespace();
+        }
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let names = fields.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        if !self.context.records.contains(&names) {
+            self.context.records.push(names);
+        }
+        let mut taken = HashSet::new();
+        for (_, value) in &fields {
+            collect_names(value, &mut taken);
         }
+        let s = unused("s", &taken);
+        let tuple = std::iter::once(Expr::Word(s.clone()))
+            .chain(fields.into_iter().map(|(_, value)| Expr::Paren(Box::new(value))))
+            .collect::<Vec<_>>();
+        Ok(Expr::Paren(Box::new(Expr::Function(vec![s], Box::new(Self::collapse(tuple))))))
+    }
+
+    /// 레코드는 자기 필드 목록을 모르므로, 이 필드를 가진 레코드 모양이 하나뿐일 때만 꺼낼 수 있음
+    fn selector(&self, field: &str) -> Result<Expr, String> {
+        let mut shapes = self.context.records.iter().filter(|names| names.iter().any(|name| name == field));
+        let shape = shapes.next()
+            .ok_or_else(|| messages::text(Msg::UnknownField, &[&field]))?;
+        if let Some(other) = shapes.next() {
+            let (shape, other) = (format!("{{{}}}", shape.join(", ")), format!("{{{}}}", other.join(", ")));
+            return Err(messages::text(Msg::AmbiguousField, &[&field, &shape, &other]));
+        }
+        Ok(Expr::Paren(Box::new(Expr::Function(shape.clone(), Box::new(Expr::Word(field.to_string()))))))
+    }
+
+    fn parse_string(&mut self) -> Result<Expr, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.input.next() {
+                Some('"') => break,
+                Some('\\') => match self.input.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('0') => s.push('\0'),
+                    Some(ch @ ('\\' | '"')) => s.push(ch),
+                    Some(ch) => return Err(messages::text(Msg::UnknownEscape, &[&format!("\\{}", ch)])),
+                    None => return Err(messages::text(Msg::UnterminatedString, &[])),
+                },
+                Some(ch) => s.push(ch),
+                None => return Err(messages::text(Msg::UnterminatedString, &[])),
+            }
+        }
+        // 괄호로 감싸 두면 출력했다가 다시 파싱해도 같은 식이 됨
+        Ok(Expr::Paren(Box::new(encode::church_string(&s))))
     }
 
     fn parse_paren(&mut self) -> Result<Expr, String> {
         self.expect('(')?;
-        let expr = self.parse_expression()?;
+        match self.parse_paren_body() {
+            Err(e) if self.recover => {
+                // 괄호 밖은 계속 읽도록, 오류가 난 괄호 하나를 아무 낱말로 대신함
+                self.report(e);
+                self.skip_to_sync(true);
+                Ok(Expr::Paren(Box::new(Expr::Word("_".to_string()))))
+            }
+            result => result,
+        }
+    }
+
+    fn parse_paren_body(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_expression()?;
+        // 괄호 안에서는 정의를 둘 수 없으므로 `(L x. e) e1`로 차례로 묶음
+        for define in desugar_definitions(self.parse_where()?)?.into_iter().rev() {
+            if let Expr::Define(name, _, body) = define {
+                expr = Expr::Words(vec![
+                    Expr::Paren(Box::new(Expr::Function(vec![name], Box::new(expr)))),
+                    Expr::Paren(body),
+                ]);
+            }
+        }
+        self.skip_whitespace();
         self.expect(')')?;
         Ok(Expr::Paren(Box::new(expr)))
     }
 
-    fn parse_primary(&mut self) -> Result<Expr, String> {
+    /// `e where x = a; y = b`의 정의들. 최상위에서는 `e` 앞에 정의로 놓임.
+    /// 정의가 아닌 문장이 나올 때까지 `;`로 이어진 정의를 모두 가져감
+    fn parse_where(&mut self) -> Result<Vec<Definition>, String> {
         self.skip_whitespace();
-        if self.peek_is('L') {
-            self.parse_function()
-        } else if self.peek_is('(') {
-            self.parse_paren()
-        } else {
-            self.parse_words()
+        let saved_input = self.input.clone();
+        match self.parse_word() {
+            Ok(word) if word == "where" => {}
+            _ => {
+                self.input = saved_input;
+                return Ok(Vec::new());
+            }
+        }
+        let mut defs = vec![self.parse_definition()?.ok_or_else(|| messages::text(Msg::ExpectedWhereDefinition, &[]))?];
+        loop {
+            self.skip_whitespace();
+            let saved_input = self.input.clone();
+            if self.consume(';') {
+                if let Some(def) = self.parse_definition()? {
+                    defs.push(def);
+                    continue;
+                }
+            }
+            self.input = saved_input;
+            break;
+        }
+        Ok(defs)
+    }
+
+    /// 부호화된 데이터에 대한 `if`와 `match`. 갈래는 값 호출에서도 고른 쪽만 계산되도록
+    /// 인자 하나를 받는 함수로 감싼 뒤 마지막에 아무 값이나 넘긴다:
+    /// `if c then a else b` = `c (L _. a) (L _. b) (L _. _)`.
+    fn parse_sugar(&mut self) -> Result<Option<Expr>, String> {
+        let saved_input = self.input.clone();
+        let keyword = match self.parse_word() {
+            Ok(word) if (word == "if" || word == "match" || word == "case")
+                && matches!(self.input.peek(), Some(' ' | '\t' | '\n' | '\r' | '(')) => word,
+            _ => {
+                self.input = saved_input;
+                return Ok(None);
+            }
+        };
+        self.skip_whitespace();
+        let scrutinee = self.parse_primary()?;
+        if keyword == "if" {
+            self.expect_keyword("then")?;
+            let then = self.parse_primary()?;
+            self.expect_keyword("else")?;
+            let otherwise = self.parse_primary()?;
+            return Ok(Some(lazy_cases(scrutinee, vec![(Vec::new(), then), (Vec::new(), otherwise)])));
+        }
+
+        if keyword == "case" {
+            self.expect_keyword("of")?;
+            return self.parse_case(scrutinee).map(Some);
+        }
+
+        self.expect_keyword("with")?;
+        let mut cases = Vec::new();
+        loop {
+            self.skip_whitespace();
+            self.consume('|');
+            self.skip_whitespace();
+            let pattern = self.parse_pattern()?;
+            self.skip_whitespace();
+            if !(self.peek_is('-') && self.peek_second_is('>')) {
+                return Err(self.expected(&["'->'"]));
+            }
+            self.input.next();
+            self.input.next();
+            cases.push((pattern, self.parse_primary()?));
+            self.skip_whitespace();
+            if !self.peek_is('|') {
+                break;
+            }
+        }
+        match_cases(scrutinee, cases).map(Some)
+    }
+
+    /// `case e of | Nothing -> a | Just x -> b`. 갈래 순서는 상관없지만 모든 생성자가 한 번씩 있어야 함
+    fn parse_case(&mut self, scrutinee: Expr) -> Result<Expr, String> {
+        // 갈래마다 생성자 이름이 시작하는 자리. 생성자를 확인하다 실패하면 오류를 이 자리에 알림
+        let mut cases: Vec<(String, Vec<String>, Expr, Input<'a>)> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            self.consume('|');
+            self.skip_whitespace();
+            let at = self.input.clone();
+            let name = self.parse_word()?;
+            self.skip_whitespace();
+            let mut fields = Vec::new();
+            while !self.peek_is('-') {
+                let field = self.parse_word()?;
+                check_binder(&field)?;
+                fields.push(field);
+                self.skip_whitespace();
+            }
+            self.expect('-')?;
+            self.expect('>')?;
+            cases.push((name, fields, self.parse_primary()?, at));
+            self.skip_whitespace();
+            if !self.peek_is('|') {
+                break;
+            }
+        }
+
+        let first = cases[0].0.clone();
+        let Some(signature) = self.context.constructors.get(&first).cloned() else {
+            self.input = cases.swap_remove(0).3;
+            return Err(messages::text(Msg::UnknownConstructor, &[&first]));
+        };
+        let mut ordered = vec![None; signature.len()];
+        for (name, fields, body, at) in cases {
+            let Some(index) = signature.iter().position(|(other, _)| *other == name) else {
+                self.input = at;
+                return Err(messages::text(Msg::ForeignConstructor, &[&name, &first]));
+            };
+            if fields.len() != signature[index].1 {
+                self.input = at;
+                return Err(messages::text(Msg::PatternArity, &[&name, &signature[index].1, &fields.len()]));
+            }
+            if ordered[index].replace((fields, body)).is_some() {
+                self.input = at;
+                return Err(messages::text(Msg::DuplicateCase, &[&name]));
+            }
+        }
+        let mut sorted = Vec::new();
+        for (case, (name, _)) in ordered.into_iter().zip(&signature) {
+            sorted.push(case.ok_or_else(|| messages::text(Msg::MissingCase, &[&name]))?);
+        }
+        Ok(lazy_cases(scrutinee, sorted))
+    }
+
+    /// `true`, `false`, `(x, y)`, `none`, `some x`
+    fn parse_pattern(&mut self) -> Result<Pattern, String> {
+        if self.consume('(') {
+            self.skip_whitespace();
+            let first = self.parse_word()?;
+            self.skip_whitespace();
+            self.expect(',')?;
+            self.skip_whitespace();
+            let second = self.parse_word()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(Pattern::Pair(first, second));
+        }
+        match self.parse_word()?.as_str() {
+            "true" => Ok(Pattern::True),
+            "false" => Ok(Pattern::False),
+            "none" => Ok(Pattern::None),
+            "some" => {
+                self.skip_whitespace();
+                Ok(Pattern::Some(self.parse_word()?))
+            }
+            other => Err(messages::text(Msg::UnknownPattern, &[&other])),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek_word() != keyword {
+            return Err(self.expected(&[&format!("'{}'", keyword)]));
+        }
+        self.parse_word().map(|_| ())
+    }
+
+    /// 지금 자리에 올 수 있었던 것을 나열한 오류: `Expected A, B, or C after 'x', found 'y'`
+    fn expected(&self, alternatives: &[&str]) -> String {
+        let list = messages::either(alternatives);
+        let rest = self.source[self.offset()..].trim_start();
+        let found = match rest.chars().next() {
+            None => messages::text(Msg::EndOfInput, &[]),
+            Some(ch) if is_name_start(ch) => format!("'{}'", rest.split(|ch| !is_name_char(ch)).next().unwrap_or_default()),
+            Some(ch) => format!("'{}'", ch),
+        };
+        match self.previous_token() {
+            Some(token) => messages::text(Msg::ExpectedAfter, &[&list, &format!("'{}'", token), &found]),
+            None => messages::text(Msg::Expected, &[&list, &found]),
+        }
+    }
+
+    /// 지금 자리 바로 앞의 낱말이나 기호. 사이의 주석은 건너뜀
+    fn previous_token(&self) -> Option<&str> {
+        let before = &self.source[..self.offset()];
+        let (range, _) = highlight::tokens(before).into_iter().rev()
+            .find(|(_, class)| *class != Class::Space && *class != Class::Comment)?;
+        Some(&before[range])
+    }
+
+    fn peek_keyword(&self) -> bool {
+        KEYWORDS.contains(&self.peek_word().as_str())
+    }
+
+    fn peek_word(&self) -> String {
+        let ahead = self.input.clone();
+        match ahead.peek() {
+            Some(&ch) if is_name_start(ch) => ahead.take_while(|&ch| is_name_char(ch)).collect(),
+            _ => String::new(),
         }
     }
 
     fn parse_word(&mut self) -> Result<String, String> {
         let mut word = String::new();
+        if !self.input.peek().is_some_and(|&ch| is_name_start(ch)) {
+            return Err(self.expected(&[&messages::text(Msg::Identifier, &[])]));
+        }
         while let Some(&ch) = self.input.peek() {
-            if ch.is_alphanumeric() || ch == '_' {
+            if is_name_char(ch) {
                 word.push(ch);
                 self.input.next();
             } else {
                 break;
             }
         }
-        if word.is_empty() {
-            Err("Expected word".to_string())
-        } else {
-            Ok(word)
-        }
+        Ok(word)
     }
 
+    /// 공백과 `#`부터 줄 끝까지의 주석을 건너뜀
     fn skip_whitespace(&mut self) {
         while let Some(&ch) = self.input.peek() {
-            if ch == ' ' || ch == '\n' || ch == '\t' || ch == '\r' {
+            if ch == '#' {
+                while self.input.peek().is_some_and(|&ch| ch != '\n') {
+                    self.input.next();
+                }
+            } else if ch == ' ' || ch == '\n' || ch == '\t' || ch == '\r' {
                 self.input.next();
             } else {
                 break;
@@ -156,14 +1137,251 @@ impl<'a> Parser<'a> {
     }
 
     fn expect(&mut self, expected: char) -> Result<(), String> {
-        if self.input.next() == Some(expected) {
+        if self.consume(expected) {
             Ok(())
         } else {
-            Err(format!("Expected '{}'", expected))
+            Err(self.expected(&[&format!("'{}'", expected)]))
         }
     }
 
     fn peek_is(&mut self, expected: char) -> bool {
         self.input.peek() == Some(&expected)
     }
-}
\ No newline at end of file
+
+    fn peek_second_is(&self, expected: char) -> bool {
+        let mut ahead = self.input.clone();
+        ahead.next();
+        ahead.next() == Some(expected)
+    }
+}
+struct Definition {
+    name: String,
+    ty: Option<Type>,
+    body: Expr,
+    recursive: bool,
+}
+
+/// `rec` 정의를 고정점으로 풀어 쓴다.
+/// 하나면 `f = Z (L f. body)`, 여러 개가 연달아 있으면 정의들의 튜플에 대한 고정점을 만들어 나눠 갖는다:
+/// `rec_f_g = Z (L self. L s. s body_f body_g); f = rec_f_g (L f g. f); g = rec_f_g (L f g. g)`.
+/// 본문 안에서 다른 정의를 부르는 `f`는 `self (L f g. f)`로 바뀐다.
+fn desugar_definitions(group: Vec<Definition>) -> Result<Vec<Expr>, String> {
+    // `where` 뒤처럼 일반 정의와 `rec` 정의가 섞여 있으면 연달아 있는 `rec` 정의끼리만 묶음
+    if group.len() > 1 && group.iter().any(|def| !def.recursive) {
+        let mut exprs = Vec::new();
+        let mut run = Vec::new();
+        for def in group {
+            if def.recursive {
+                run.push(def);
+            } else {
+                exprs.extend(desugar_definitions(std::mem::take(&mut run))?);
+                exprs.extend(desugar_definitions(vec![def])?);
+            }
+        }
+        exprs.extend(desugar_definitions(run)?);
+        return Ok(exprs);
+    }
+    let names = group.iter()
+        .filter(|def| def.recursive)
+        .map(|def| def.name.clone())
+        .collect::<Vec<_>>();
+    // 값 호출에서는 함수 밖에서 부르는 순간 아직 정의가 끝나지 않았으므로 끝나지 않음
+    for def in group.iter().filter(|def| def.recursive) {
+        if let Some(used) = direct_reference(&def.body, &names) {
+            return Err(messages::text(Msg::UnresolvedRecursion, &[&def.name, &used]));
+        }
+    }
+    if names.is_empty() {
+        return Ok(group.into_iter()
+            .map(|def| Expr::Define(def.name, def.ty, Box::new(def.body)))
+            .collect());
+    }
+    let z = z_combinator();
+    let fix = |function: Expr| crate::expr!(#(z.clone()) #function);
+
+    if let [def] = group.as_slice() {
+        let function = Expr::Function(vec![def.name.clone()], Box::new(def.body.clone()));
+        return Ok(vec![Expr::Define(def.name.clone(), def.ty.clone(), Box::new(fix(function)))]);
+    }
+
+    let mut taken = HashSet::new();
+    for def in &group {
+        taken.insert(def.name.clone());
+        collect_names(&def.body, &mut taken);
+    }
+    let tuple_name = unused(&format!("rec_{}", names.join("_")), &taken);
+    let self_name = unused("self", &taken);
+    let s = unused("s", &taken);
+    let selector = |i: usize| Expr::Paren(Box::new(Expr::Function(
+        names.clone(),
+        Box::new(Expr::Word(names[i].clone())),
+    )));
+    let mapping = (0..names.len())
+        .map(|i| (names[i].clone(), Expr::Words(vec![Expr::Word(self_name.clone()), selector(i)])))
+        .collect::<HashMap<_, _>>();
+
+    let bodies = group.iter()
+        .map(|def| Expr::Paren(Box::new(normalize::substitute(&def.body, &mapping))));
+    let tuple = Expr::Function(
+        vec![s.clone()],
+        Box::new(Expr::Words(std::iter::once(Expr::Word(s)).chain(bodies).collect())),
+    );
+    let function = Expr::Function(vec![self_name], Box::new(tuple));
+
+    let mut result = vec![Expr::Define(tuple_name.clone(), None, Box::new(fix(function)))];
+    for (i, def) in group.into_iter().enumerate() {
+        let member = Expr::Words(vec![Expr::Word(tuple_name.clone()), selector(i)]);
+        result.push(Expr::Define(def.name, def.ty, Box::new(member)));
+    }
+    Ok(result)
+}
+
+/// 함수 안이 아닌 곳에서 쓰인 이름
+fn direct_reference<'a>(expr: &Expr, names: &'a [String]) -> Option<&'a String> {
+    match expr {
+        Expr::Word(w) => names.iter().find(|n| *n == w),
+        Expr::Function(..) => None,
+        Expr::Words(words) | Expr::Sequence(words) => {
+            words.iter().find_map(|e| direct_reference(e, names))
+        }
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => direct_reference(inner, names),
+        Expr::Assert(lhs, rhs) => direct_reference(lhs, names).or_else(|| direct_reference(rhs, names)),
+    }
+}
+
+/// `match`의 갈래들을 부호화에 맞는 순서로 늘어놓는다.
+/// 불리언은 `true`, `false`, 옵션은 `none`, `some` 순이고 쌍은 `p (L x y. body)`가 된다.
+fn match_cases(scrutinee: Expr, cases: Vec<(Pattern, Expr)>) -> Result<Expr, String> {
+    let mut cases = cases.into_iter();
+    let (first, first_body) = cases.next().ok_or_else(|| messages::text(Msg::EmptyMatch, &[]))?;
+    if let Pattern::Pair(x, y) = first {
+        if cases.next().is_some() {
+            return Err(messages::text(Msg::PairMatchCases, &[]));
+        }
+        let function = Expr::Function(vec![x, y], Box::new(first_body));
+        return Ok(apply(scrutinee, vec![Expr::Paren(Box::new(function))]));
+    }
+
+    let (mut yes, mut no, mut none, mut some) = (None, None, None, None);
+    for (pattern, body) in std::iter::once((first, first_body)).chain(cases) {
+        let (slot, value, name) = match pattern {
+            Pattern::True => (&mut yes, (Vec::new(), body), "true"),
+            Pattern::False => (&mut no, (Vec::new(), body), "false"),
+            Pattern::None => (&mut none, (Vec::new(), body), "none"),
+            Pattern::Some(x) => (&mut some, (vec![x], body), "some"),
+            Pattern::Pair(..) => return Err(messages::text(Msg::PairMatchCases, &[])),
+        };
+        if slot.replace(value).is_some() {
+            return Err(messages::text(Msg::DuplicateMatchCase, &[&name]));
+        }
+    }
+    match (yes, no, none, some) {
+        (Some(yes), Some(no), None, None) => Ok(lazy_cases(scrutinee, vec![yes, no])),
+        (None, None, Some(none), Some(some)) => Ok(lazy_cases(scrutinee, vec![none, some])),
+        (None, None, _, _) => Err(messages::text(Msg::OptionCases, &[])),
+        (_, _, None, None) => Err(messages::text(Msg::BooleanCases, &[])),
+        _ => Err(messages::text(Msg::MixedCases, &[])),
+    }
+}
+
+/// 갈래마다 묶을 이름 뒤에 쓰이지 않는 이름 하나를 더 받게 해서, 고른 갈래만 마지막에 계산되게 함
+fn lazy_cases(scrutinee: Expr, cases: Vec<(Vec<String>, Expr)>) -> Expr {
+    let mut taken = HashSet::new();
+    collect_names(&scrutinee, &mut taken);
+    for (params, body) in &cases {
+        taken.extend(params.iter().cloned());
+        collect_names(body, &mut taken);
+    }
+    let dummy = unused("_", &taken);
+    let mut args = cases.into_iter()
+        .map(|(mut params, body)| {
+            params.push(dummy.clone());
+            Expr::Paren(Box::new(Expr::Function(params, Box::new(body))))
+        })
+        .collect::<Vec<_>>();
+    args.push(Expr::Paren(Box::new(Expr::Function(vec![dummy.clone()], Box::new(Expr::Word(dummy))))));
+    apply(scrutinee, args)
+}
+
+/// 머리가 이미 적용이면 이어 붙여야 출력했다가 다시 파싱해도 같은 식이 됨
+fn apply(head: Expr, args: Vec<Expr>) -> Expr {
+    match head {
+        Expr::Words(mut words) => {
+            words.extend(args);
+            Expr::Words(words)
+        }
+        head => Expr::Words(std::iter::once(head).chain(args).collect()),
+    }
+}
+
+fn unused(base: &str, taken: &HashSet<String>) -> String {
+    let mut name = base.to_string();
+    while taken.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(source: &str) -> Result<Expr, String> {
+        Parser::new(source).parse()
+    }
+
+    #[test]
+    fn reserved_keywords_cannot_be_bound() {
+        let e = parsed("L let. let").unwrap_err();
+        assert!(e.contains("'let' is a reserved keyword"), "{}", e);
+        let e = parsed("where = L x. x; where").unwrap_err();
+        assert!(e.contains("'where' is a reserved keyword"), "{}", e);
+    }
+
+    #[test]
+    fn says_what_was_expected_after_which_token() {
+        let e = parsed("f = L x . ; f").unwrap_err();
+        assert_eq!(e, "Expected identifier, 'L', '(', '\"', or '{' after '.', found ';'");
+        let e = parsed("(L x. x").unwrap_err();
+        assert!(e.starts_with("Expected "), "{}", e);
+        assert!(e.ends_with("found end of input"), "{}", e);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_instead_of_a_stack_overflow() {
+        let source = format!("{}x{}", "(".repeat(100), ")".repeat(100));
+        let e = Parser::new(&source).max_depth(50).parse().unwrap_err();
+        assert!(e.contains("nested deeper than 50 levels"), "{}", e);
+        assert!(Parser::new(&source).max_depth(200).parse().is_ok());
+    }
+
+    #[test]
+    fn unknown_constructors_are_reported_where_they_appear() {
+        let e = Parser::new("data Maybe = Just x;\nm = Just (L a. a);\ncase m of Nothing -> m | Just v -> v").parse_all().unwrap_err();
+        assert!(e[0].starts_with("line 3, column 11: Unknown constructor 'Nothing'"), "{}", e[0]);
+    }
+
+    #[test]
+    fn comments_run_to_the_end_of_the_line() {
+        let commented = parsed("#!/usr/bin/env lintre\nid = L x. x; # identity\n#expect: (λa . a)\nid # the end").unwrap();
+        assert_eq!(commented, parsed("id = L x. x;\nid").unwrap());
+        let e = parsed("f = L x . # body\n ;").unwrap_err();
+        assert!(e.contains("after '.'"), "{}", e);
+    }
+
+    #[test]
+    fn names_may_use_letters_of_any_script_but_not_symbols() {
+        assert!(parsed("값 = L x. x; 값").is_ok());
+        assert!(parsed("λόγος = L x. x; λόγος").is_ok());
+        assert!(parsed("नमस्ते = L x. x; नमस्ते").is_ok());
+        for symbol in ["→", "…", "😀"] {
+            assert!(parsed(&format!("{} = L x. x; {}", symbol, symbol)).is_err(), "{}", symbol);
+            assert!(parsed(&format!("f{} = L x. x; f{}", symbol, symbol)).is_err(), "{}", symbol);
+        }
+    }
+}
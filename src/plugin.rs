@@ -0,0 +1,204 @@
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::sync::OnceLock;
+
+use crate::ast::Expr;
+use crate::encode;
+use crate::error::Error;
+use crate::interpreter::{Decoded, Interpreter, Value};
+use crate::messages::{self, Msg};
+use crate::normalize;
+
+/// 플러그인과 주고받는 값. `kind`가
+/// - `NAT`이면 `nat`이 Church 숫자의 값
+/// - `BOOL`이면 `nat`이 0(거짓)이나 1(참)
+/// - `STRING`이면 `text`가 문자열
+/// - `TERM`이면 `text`가 그 밖의 값을 λ식 소스로 적은 것. 돌려줄 때도 소스로 씀
+///
+/// `text`는 NUL로 끝나는 UTF-8이고, 인자의 `text`는 호출이 끝날 때까지만 유효함
+#[repr(C)]
+pub struct RawValue {
+    pub kind: u32,
+    pub nat: u64,
+    pub text: *const c_char,
+}
+
+pub const NAT: u32 = 0;
+pub const BOOL: u32 = 1;
+pub const STRING: u32 = 2;
+pub const TERM: u32 = 3;
+
+/// 플러그인의 내장 함수. `args`에 인자가 arity개 있고, 결과를 `result`에 쓰고 0을 돌려줌.
+/// 실패하면 0이 아닌 값을 돌려주고 `result.text`에 메시지를 쓸 수 있음(NULL이어도 됨).
+/// 돌려준 `text`는 플러그인의 것이고 같은 함수를 다시 부를 때까지 유효해야 함.
+/// `serve`와 `daemon`에서는 여러 스레드가 동시에 부를 수 있음
+pub type Call = unsafe extern "C" fn(args: *const RawValue, result: *mut RawValue) -> c_int;
+
+/// 플러그인이 `define(host, name, arity, call)`을 불러 내장 함수를 하나씩 등록함
+pub type Define = unsafe extern "C" fn(host: *mut c_void, name: *const c_char, arity: usize, call: Call);
+
+/// 공유 라이브러리가 내보내야 하는 등록 함수의 이름. C로 쓰면
+/// `void lintre_register(void *host, void (*define)(void *, const char *, size_t, lintre_call));`
+const REGISTER: &str = "lintre_register";
+
+type Register = unsafe extern "C" fn(host: *mut c_void, define: Define);
+
+struct Entry {
+    name: &'static str,
+    arity: usize,
+    call: Call,
+}
+
+// 불러온 내장 함수. 프로그램을 시작할 때 한 번만 정하고, 라이브러리는 닫지 않음
+static PLUGINS: OnceLock<Vec<Entry>> = OnceLock::new();
+
+/// `--plugin`으로 받은 라이브러리를 모두 불러와 등록한 내장 함수를 쓸 수 있게 함
+pub fn load(paths: &[String]) -> Result<(), String> {
+    let mut host = Host { entries: Vec::new(), error: None };
+    for path in paths {
+        let register = open(path)?;
+        // SAFETY: 등록 함수가 이 ABI를 따른다고 믿는 수밖에 없음. `host`는 호출이 끝날 때까지 살아 있음
+        unsafe {
+            register(&mut host as *mut Host as *mut c_void, define);
+        }
+        if let Some(e) = host.error.take() {
+            return Err(messages::text(Msg::PluginError, &[&path, &e]));
+        }
+    }
+    PLUGINS.set(host.entries).map_err(|_| messages::text(Msg::PluginsLoaded, &[]))
+}
+
+/// 이름으로 찾은 플러그인 내장 함수의 번호
+pub fn find(name: &str) -> Option<usize> {
+    PLUGINS.get()?.iter().position(|entry| entry.name == name)
+}
+
+pub fn name(index: usize) -> &'static str {
+    PLUGINS.get().map_or("#plugin", |entries| entries[index].name)
+}
+
+pub fn arity(index: usize) -> usize {
+    PLUGINS.get().map_or(0, |entries| entries[index].arity)
+}
+
+pub fn names() -> Vec<&'static str> {
+    PLUGINS.get().map_or_else(Vec::new, |entries| entries.iter().map(|entry| entry.name).collect())
+}
+
+struct Host {
+    entries: Vec<Entry>,
+    // 등록하다 처음 난 오류. 콜백에서 바로 돌려줄 수 없으므로 모아 둠
+    error: Option<String>,
+}
+
+unsafe extern "C" fn define(host: *mut c_void, name: *const c_char, arity: usize, call: Call) {
+    // SAFETY: `host`는 `load`가 넘긴 `Host`이고, `name`은 NUL로 끝나야 함
+    let host = unsafe { &mut *(host as *mut Host) };
+    if host.error.is_some() {
+        return;
+    }
+    let name = match unsafe { CStr::from_ptr(name) }.to_str() {
+        Ok(name) => name,
+        Err(_) => {
+            host.error = Some(messages::text(Msg::PluginNameUtf8, &[]));
+            return;
+        }
+    };
+    let valid = name.chars().next().is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+        && name.chars().all(|ch| ch.is_alphanumeric() || ch == '_' || ch == '\'');
+    if !valid {
+        host.error = Some(messages::text(Msg::PluginNameInvalid, &[&name]));
+    } else if crate::builtins::Builtin::from_name(name).is_some() || host.entries.iter().any(|entry| entry.name == name) {
+        host.error = Some(messages::text(Msg::PluginDuplicate, &[&name]));
+    } else {
+        host.entries.push(Entry { name: Box::leak(name.to_string().into_boxed_str()), arity, call });
+    }
+}
+
+#[cfg(unix)]
+fn open(path: &str) -> Result<Register, String> {
+    #[link(name = "dl")]
+    extern "C" {
+        fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlerror() -> *const c_char;
+    }
+    const RTLD_NOW: c_int = 2;
+
+    let last_error = || {
+        // SAFETY: `dlerror`는 NULL이나 NUL로 끝나는 메시지를 돌려줌
+        let message = unsafe { dlerror() };
+        match message.is_null() {
+            true => messages::text(Msg::UnknownError, &[]),
+            false => unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned(),
+        }
+    };
+    let filename = CString::new(path).map_err(|_| messages::text(Msg::PluginPath, &[&path]))?;
+    let symbol = CString::new(REGISTER).unwrap_or_default();
+    // SAFETY: 둘 다 NUL로 끝나는 문자열. 라이브러리를 여는 것만으로 그 초기화 코드가 돎
+    let handle = unsafe { dlopen(filename.as_ptr(), RTLD_NOW) };
+    if handle.is_null() {
+        return Err(messages::text(Msg::PluginLoad, &[&path, &last_error()]));
+    }
+    let register = unsafe { dlsym(handle, symbol.as_ptr()) };
+    if register.is_null() {
+        return Err(messages::text(Msg::PluginExport, &[&path, &REGISTER, &last_error()]));
+    }
+    // SAFETY: 내보낸 심볼이 `Register`의 꼴이라고 약속한 것으로 봄
+    Ok(unsafe { std::mem::transmute::<*mut c_void, Register>(register) })
+}
+
+#[cfg(not(unix))]
+fn open(path: &str) -> Result<Register, String> {
+    Err(messages::text(Msg::PluginLoad, &[&path, &messages::text(Msg::PluginsUnix, &[])]))
+}
+
+impl Interpreter {
+    pub(crate) fn call_plugin(&mut self, index: usize, args: Vec<Value>) -> Result<Value, Error> {
+        let entry = &PLUGINS.get().ok_or_else(|| messages::text(Msg::NoPlugins, &[]))?[index];
+        // 인자의 `text`가 가리키는 문자열은 호출이 끝날 때까지 여기 둠
+        let mut texts = Vec::new();
+        let mut raw = Vec::new();
+        for arg in &args {
+            let (kind, nat, text) = match self.decode(arg) {
+                Some(Decoded::Nat(n)) => (NAT, n as u64, None),
+                Some(Decoded::Bool(b)) => (BOOL, b as u64, None),
+                Some(Decoded::Str(s)) => (STRING, 0, Some(s)),
+                None => (TERM, 0, Some(normalize::canonical(&normalize::readback(arg)).to_string())),
+            };
+            let text = text
+                .map(|text| CString::new(text)
+                    .map_err(|_| messages::text(Msg::PluginNul, &[&entry.name])))
+                .transpose()?;
+            raw.push(RawValue { kind, nat, text: text.as_ref().map_or(std::ptr::null(), |text| text.as_ptr()) });
+            texts.push(text);
+        }
+        let mut result = RawValue { kind: TERM, nat: 0, text: std::ptr::null() };
+        // SAFETY: `raw`에 arity개의 인자가 있고, `result`는 쓸 수 있는 칸
+        let status = unsafe { (entry.call)(raw.as_ptr(), &mut result) };
+        let text = || match result.text.is_null() {
+            true => None,
+            // SAFETY: 플러그인이 NUL로 끝나는 문자열을 돌려주기로 함
+            false => Some(unsafe { CStr::from_ptr(result.text) }.to_string_lossy().into_owned()),
+        };
+        if status != 0 {
+            return Err(match text() {
+                Some(message) => format!("{}: {}", entry.name, message),
+                None => messages::text(Msg::PluginStatus, &[&entry.name, &status]),
+            }.into());
+        }
+        let name = entry.name;
+        let expr = match result.kind {
+            NAT => encode::church_numeral(result.nat as usize),
+            BOOL => {
+                let chosen = if result.nat != 0 { "a" } else { "b" };
+                Expr::Function(vec!["a".to_string(), "b".to_string()], Box::new(Expr::Word(chosen.to_string())))
+            }
+            STRING => encode::church_string(&text().unwrap_or_default()),
+            TERM => text().ok_or_else(|| messages::text(Msg::PluginNoTerm, &[&name]))?
+                .parse()
+                .map_err(|e| messages::text(Msg::PluginBadTerm, &[&name, &e]))?,
+            kind => return Err(messages::text(Msg::PluginValueKind, &[&name, &kind]).into()),
+        };
+        self.eval(expr)
+    }
+}
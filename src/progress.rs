@@ -0,0 +1,36 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::style::{self, Stream};
+
+// 이보다 오래 걸리면 알리기 시작하고, 그 뒤로는 INTERVAL마다 한 줄씩
+const THRESHOLD: Duration = Duration::from_secs(1);
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// 오래 걸리는 평가가 멈춘 것이 아니라 진행 중임을 stderr로 알림
+#[derive(Debug)]
+pub struct Progress {
+    started: Instant,
+    next: Cell<Duration>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Progress { started: Instant::now(), next: Cell::new(THRESHOLD) }
+    }
+
+    /// β-축약마다 부름. 지금 줄이는 식의 크기는 출력할 때만 계산하도록 함수로 받음
+    pub fn tick(&self, steps: usize, size: impl FnOnce() -> usize) {
+        let elapsed = self.started.elapsed();
+        if elapsed < self.next.get() {
+            return;
+        }
+        self.next.set(elapsed + INTERVAL);
+        let rate = steps as f64 / elapsed.as_secs_f64();
+        let line = format!(
+            "... {} β-reductions in {:.1} s ({:.0}/s), term size {}",
+            steps, elapsed.as_secs_f64(), rate, size(),
+        );
+        eprintln!("{}", style::dim(Stream::Stderr, &line));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use crate::builtins::Builtin;
+use crate::editor::Editor;
+use crate::interpreter::{Config, Interpreter};
+use crate::interrupt;
+use crate::log::{self, Target};
+use crate::messages::{self, Msg};
+use crate::parser::{Context, Parser};
+use crate::plugin;
+use crate::style::{self, Stream};
+
+const PROMPT: &str = "λ> ";
+const COMMANDS: [&str; 3] = [":quit", ":q", ":record"];
+
+/// 한 줄씩 읽어 같은 환경에서 평가한다. 앞에서 정의한 이름은 다음 줄에서도 쓸 수 있다.
+/// `:quit`(`:q`)이나 Ctrl-D로 끝낸다.
+/// `:record <file>` 뒤로는 평가에 성공한 입력을 파일에 덧붙여서 `run`으로 다시 실행할 수 있게 하고,
+/// 인자 없는 `:record`로 기록을 멈춘다.
+/// 평가 중에 Ctrl-C를 누르면 그 평가만 멈추고 프롬프트로 돌아온다.
+pub fn run(config: Config) {
+    interrupt::install();
+    let mut interpreter = Interpreter::new(config);
+    let mut editor = Editor::new();
+    let mut recording: Option<(String, File)> = None;
+    // 앞 입력의 `data` 선언과 레코드를 다음 입력에서도 쓸 수 있도록 이어받음
+    let mut context = Context::default();
+
+    loop {
+        // Tab 완성 후보: 지금까지 정의한 이름, 내장 함수, REPL 명령
+        let mut completions = interpreter.names();
+        completions.extend(Builtin::NAMED.iter().map(|b| b.name().to_string()));
+        completions.extend(plugin::names().into_iter().map(String::from));
+        completions.extend(COMMANDS.iter().map(|c| c.to_string()));
+        let line = match editor.read_line(PROMPT, &completions) {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Failed to read input: {}", e);
+                break;
+            }
+        };
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        editor.add_history(&line);
+
+        if let Some(command) = input.strip_prefix(':') {
+            log::debug(Target::Repl, || format!("command :{}", command));
+            let mut parts = command.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("quit" | "q"), None) => break,
+                (Some("record"), Some(path)) => match OpenOptions::new().create(true).append(true).open(path) {
+                    Ok(file) => {
+                        println!("Recording to {}", path);
+                        recording = Some((path.to_string(), file));
+                    }
+                    Err(e) => eprintln!("Failed to open '{}': {}", path, e),
+                },
+                (Some("record"), None) => match recording.take() {
+                    Some((path, _)) => println!("Stopped recording to {}", path),
+                    None => eprintln!("Not recording."),
+                },
+                _ => eprintln!("Unknown command '{}'. Commands: {}", input, COMMANDS.join(", ")),
+            }
+            continue;
+        }
+
+        let mut parser = Parser::new(input).spans(true).with_context(context.clone());
+        let ast = match parser.parse() {
+            Ok(ast) => {
+                context = parser.context().clone();
+                ast
+            }
+            Err(e) => {
+                eprintln!("{}", style::error(Stream::Stderr, &messages::text(Msg::ParseError, &[&e])));
+                continue;
+            }
+        };
+        interrupt::clear();
+        log::debug(Target::Repl, || format!("evaluating {}", input));
+        match interpreter.eval(ast) {
+            Ok(value) => {
+                interpreter.emit_result(&interpreter.format_result(&value));
+                // 이어 붙인 파일이 그대로 하나의 프로그램이 되도록 문장마다 `;`로 끝냄
+                if let Some((path, file)) = &mut recording {
+                    if let Err(e) = writeln!(file, "{};", input.trim_end_matches(';')) {
+                        eprintln!("Failed to write '{}': {}", path, e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", style::error(Stream::Stderr, &messages::text(Msg::Error, &[&e]))),
+        }
+    }
+}
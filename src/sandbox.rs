@@ -0,0 +1,164 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, ErrorKind};
+use crate::messages::{self, Msg};
+
+// 시간과 항 크기는 β-축약 이만큼마다 한 번씩 확인함. 항 크기를 재는 데 드는 시간을 줄이기 위함
+const CHECK_INTERVAL: usize = 64;
+
+/// `serve`에서 요청 하나의 평가에 거는 제한
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    // `--max-steps N`: β-축약 수
+    pub steps: usize,
+    // `--timeout-ms N`: 평가에 쓸 수 있는 시간
+    pub time: Duration,
+    // `--max-output N`: `print`로 낼 수 있는 바이트 수
+    pub output: usize,
+    // `--max-term-size N`: 줄이는 식의 노드 수. 메모리 사용량 대신 셈
+    pub term_size: usize,
+    // `--max-depth N`: 식을 평가하다 겹쳐 들어간 깊이. 넘으면 스택이 넘치기 전에 멈춤
+    pub depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            steps: 1_000_000,
+            time: Duration::from_secs(5),
+            output: 64 * 1024,
+            term_size: 100_000,
+            depth: 10_000,
+        }
+    }
+}
+
+/// 평가 하나가 지금까지 쓴 양. 하위 인터프리터도 설정을 복제해 쓰므로 모두 같은 칸을 셈
+#[derive(Debug)]
+pub struct Sandbox {
+    limits: Limits,
+    started: Instant,
+    steps: Cell<usize>,
+    output: Cell<usize>,
+    depth: Cell<usize>,
+    // 걸린 제한의 이름(`steps`, `time`, …). 오류 메시지만으로는 다른 오류와 구별하기 어려움
+    exceeded: Cell<Option<&'static str>>,
+}
+
+impl Sandbox {
+    pub fn new(limits: Limits) -> Self {
+        Sandbox {
+            limits,
+            started: Instant::now(),
+            steps: Cell::new(0),
+            output: Cell::new(0),
+            depth: Cell::new(0),
+            exceeded: Cell::new(None),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// β-축약마다 부름. 지금 줄이는 식의 크기는 확인할 때만 계산하도록 함수로 받음
+    pub fn step(&self, size: impl FnOnce() -> usize) -> Result<(), Error> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if steps > self.limits.steps {
+            return self.exceed("steps", messages::text(Msg::EvaluationSteps, &[&self.limits.steps]));
+        }
+        if !steps.is_multiple_of(CHECK_INTERVAL) {
+            return Ok(());
+        }
+        if self.started.elapsed() > self.limits.time {
+            return self.exceed("time", messages::text(Msg::EvaluationTime, &[&self.limits.time.as_millis()]));
+        }
+        let size = size();
+        if size > self.limits.term_size {
+            return self.exceed("term_size", messages::text(Msg::TermSize, &[&size, &self.limits.term_size]));
+        }
+        Ok(())
+    }
+
+    /// `print`가 `bytes`만큼 내기 전에 부름
+    pub fn output(&self, bytes: usize) -> Result<(), Error> {
+        let total = self.output.get() + bytes;
+        if total > self.limits.output {
+            return self.exceed("output", messages::text(Msg::OutputSize, &[&self.limits.output]));
+        }
+        self.output.set(total);
+        Ok(())
+    }
+
+    /// 식 하나를 평가하기 전에 부르고, 다 평가하면 실패했더라도 `leave`를 부름
+    pub fn enter(&self) -> Result<(), Error> {
+        if self.depth.get() >= self.limits.depth {
+            return self.exceed("depth", messages::text(Msg::EvaluationDepth, &[&self.limits.depth]));
+        }
+        self.depth.set(self.depth.get() + 1);
+        Ok(())
+    }
+
+    pub fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
+    /// 걸린 제한의 이름: `steps`, `time`, `output`, `term_size`, `depth`
+    pub fn exceeded(&self) -> Option<&'static str> {
+        self.exceeded.get()
+    }
+
+    fn exceed(&self, limit: &'static str, message: String) -> Result<(), Error> {
+        self.exceeded.set(Some(limit));
+        // 출력은 평가가 끝나지 않아서 걸리는 제한이 아님
+        let kind = match limit {
+            "output" => ErrorKind::Other,
+            _ => ErrorKind::StepLimit,
+        };
+        Err(Error::new(kind, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_within_the_limit_are_allowed() {
+        let sandbox = Sandbox::new(Limits { steps: 3, ..Limits::default() });
+        for _ in 0..3 {
+            assert!(sandbox.step(|| 1).is_ok());
+        }
+        assert_eq!(sandbox.step(|| 1).unwrap_err().kind, ErrorKind::StepLimit);
+        assert_eq!(sandbox.exceeded(), Some("steps"));
+    }
+
+    #[test]
+    fn term_size_is_checked_every_interval() {
+        let sandbox = Sandbox::new(Limits { term_size: 10, ..Limits::default() });
+        for _ in 1..CHECK_INTERVAL {
+            assert!(sandbox.step(|| 100).is_ok());
+        }
+        assert!(sandbox.step(|| 100).is_err());
+        assert_eq!(sandbox.exceeded(), Some("term_size"));
+    }
+
+    #[test]
+    fn output_over_the_limit_is_not_a_step_limit() {
+        let sandbox = Sandbox::new(Limits { output: 4, ..Limits::default() });
+        assert!(sandbox.output(4).is_ok());
+        assert_eq!(sandbox.output(1).unwrap_err().kind, ErrorKind::Other);
+        assert_eq!(sandbox.exceeded(), Some("output"));
+    }
+
+    #[test]
+    fn depth_is_released_on_leave() {
+        let sandbox = Sandbox::new(Limits { depth: 1, ..Limits::default() });
+        assert!(sandbox.enter().is_ok());
+        assert!(sandbox.enter().is_err());
+        sandbox.leave();
+        assert!(sandbox.enter().is_ok());
+    }
+}
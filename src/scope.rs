@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use crate::ast::{Expr, Span};
+use crate::builtins::Builtin;
+use crate::normalize;
+
+/// 이름을 찾지 못한 낱말 하나. 자리를 붙여 읽은 식이면 `span`이 있음
+pub struct Unresolved {
+    pub name: String,
+    pub span: Option<Span>,
+    // 정의는 있지만 그 정의를 평가하기 전에 쓰임
+    pub early: bool,
+}
+
+/// 평가하지 않고 모든 낱말이 무엇을 가리키는지 확인한다. 람다 매개변수, 앞에서 평가한 정의,
+/// 내장 함수가 아닌 낱말을 모두 돌려줌. 여러 파일은 `run`처럼 하나의 프로그램으로 봄.
+/// 뒤에 나올 정의는 람다 안에서만 쓸 수 있음. 람다는 불릴 때 이름을 찾기 때문
+pub fn unresolved(programs: &[Expr]) -> Vec<Vec<Unresolved>> {
+    let defined = programs.iter()
+        .flat_map(statements)
+        .filter_map(|s| match s {
+            Expr::Define(name, _, _) => Some(name.clone()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let mut scope = Scope { defined, evaluated: HashSet::new(), found: Vec::new() };
+    let mut found = Vec::new();
+    for program in programs {
+        let statements = statements(program);
+        // `Sequence`의 마지막 식은 모든 정의를 평가한 뒤에 평가함
+        let last = statements.iter().rposition(|e| !matches!(e, Expr::Define(..) | Expr::Assert(..)));
+        let mut last_expr = None;
+        for (i, statement) in statements.iter().enumerate() {
+            match statement {
+                Expr::Define(name, _, body) => {
+                    scope.walk(body, &mut Vec::new(), None, false);
+                    scope.evaluated.insert(name.clone());
+                }
+                _ if Some(i) == last => last_expr = Some(statement),
+                other => scope.walk(other, &mut Vec::new(), None, false),
+            }
+        }
+        if let Some(e) = last_expr {
+            scope.walk(e, &mut Vec::new(), None, false);
+        }
+        found.push(std::mem::take(&mut scope.found));
+    }
+    found
+}
+
+/// 최상위 정의마다 본문의 자유 변수. 정의 순서대로, 변수는 이름 순서로 돌려줌
+pub fn free_variables(program: &Expr) -> Vec<(String, Vec<String>)> {
+    statements(program).iter()
+        .filter_map(|s| match s {
+            Expr::Define(name, _, body) => {
+                let mut free = normalize::free_vars(body).into_iter().collect::<Vec<_>>();
+                free.sort();
+                Some((name.clone(), free))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// 최상위 정의마다 그 정의가 가져다 쓰는 다른 최상위 정의. 같은 이름을 다시 정의하면 하나로 합침
+pub fn dependencies(program: &Expr) -> Vec<(String, Vec<String>)> {
+    let table = free_variables(program);
+    let defined = table.iter().map(|(name, _)| name.clone()).collect::<HashSet<_>>();
+    let mut deps: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, free) in table {
+        let uses = free.into_iter()
+            .filter(|v| defined.contains(v) && *v != name)
+            .collect::<Vec<_>>();
+        match deps.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                existing.extend(uses);
+                existing.sort();
+                existing.dedup();
+            }
+            None => deps.push((name, uses)),
+        }
+    }
+    deps
+}
+
+/// `dependencies`를 Graphviz DOT 그래프로. 화살표는 쓰는 정의에서 쓰이는 정의로
+pub fn dot(deps: &[(String, Vec<String>)]) -> String {
+    let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut out = String::from("digraph deps {\n");
+    for (name, _) in deps {
+        out.push_str(&format!("  {};\n", quote(name)));
+    }
+    for (name, uses) in deps {
+        for used in uses {
+            out.push_str(&format!("  {} -> {};\n", quote(name), quote(used)));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn statements(program: &Expr) -> &[Expr] {
+    match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    }
+}
+
+struct Scope {
+    defined: HashSet<String>,
+    evaluated: HashSet<String>,
+    found: Vec<Unresolved>,
+}
+
+impl Scope {
+    fn walk(&mut self, expr: &Expr, bound: &mut Vec<String>, span: Option<Span>, deferred: bool) {
+        match expr {
+            Expr::Word(name) => {
+                if bound.contains(name) || self.evaluated.contains(name) || Builtin::from_name(name).is_some() {
+                    return;
+                }
+                let later = self.defined.contains(name);
+                if !later || !deferred {
+                    self.found.push(Unresolved { name: name.clone(), span, early: later });
+                }
+            }
+            Expr::Words(words) | Expr::Sequence(words) => {
+                for e in words {
+                    self.walk(e, bound, span, deferred);
+                }
+            }
+            Expr::Function(params, body) => {
+                let depth = bound.len();
+                bound.extend(params.iter().cloned());
+                self.walk(body, bound, span, true);
+                bound.truncate(depth);
+            }
+            Expr::Located(span, inner) => self.walk(inner, bound, Some(*span), deferred),
+            Expr::Paren(inner)
+            | Expr::Define(_, _, inner)
+            | Expr::Annotated(_, inner)
+            | Expr::TypeFunction(_, inner)
+            | Expr::TypeApply(inner, _) => self.walk(inner, bound, span, deferred),
+            Expr::Assert(lhs, rhs) => {
+                self.walk(lhs, bound, span, deferred);
+                self.walk(rhs, bound, span, deferred);
+            }
+        }
+    }
+}
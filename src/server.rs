@@ -0,0 +1,349 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::interpreter::{Config, Interpreter, Output};
+use crate::json;
+use crate::log::{self, Target};
+use crate::messages::{self, Msg};
+use crate::parser::{Context, Parser};
+use crate::sandbox::{Limits, Sandbox};
+
+// 요청 본문(소스 코드)의 최대 크기
+const MAX_BODY: usize = 1 << 20;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const JSON: &str = "application/json";
+// Prometheus가 읽는 글 형식
+const EXPOSITION: &str = "text/plain; version=0.0.4";
+
+/// `POST /session/:id/eval`로 만든 세션. 앞의 요청에서 정의한 이름을 다음 요청에서도 씀
+struct Session {
+    interpreter: Interpreter,
+    // 앞 요청의 `data` 선언과 레코드를 다음 요청에서도 쓸 수 있도록 이어받음
+    context: Context,
+    output: Rc<RefCell<String>>,
+    last_used: Instant,
+}
+
+/// 평가 하나가 어떻게 끝났는지
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Error,
+    // 걸린 제한의 이름
+    Limit(&'static str),
+    Panic,
+}
+
+/// `GET /metrics`로 보여 줄 누적값. 서버를 켠 뒤부터 셈
+#[derive(Debug, Default)]
+struct Metrics {
+    // 결과(`ok`, `error`, `limit`, `panic`)마다 평가한 수
+    evaluations: BTreeMap<&'static str, u64>,
+    // 제한마다 걸린 수. `time`이 시간 초과
+    limits: BTreeMap<&'static str, u64>,
+    // 평가 요청마다 걸린 시간의 합
+    latency: Duration,
+}
+
+impl Metrics {
+    fn record(&mut self, outcome: Outcome, latency: Duration) {
+        let label = match outcome {
+            Outcome::Ok => "ok",
+            Outcome::Error => "error",
+            Outcome::Limit(limit) => {
+                *self.limits.entry(limit).or_default() += 1;
+                "limit"
+            }
+            Outcome::Panic => "panic",
+        };
+        *self.evaluations.entry(label).or_default() += 1;
+        self.latency += latency;
+    }
+
+    /// Prometheus 글 형식. β-축약 수와 세션 수는 서버가 들고 있으므로 받아서 씀
+    fn exposition(&self, steps: usize, sessions: usize) -> String {
+        let count = self.evaluations.values().sum::<u64>();
+        let average = match count {
+            0 => 0.0,
+            n => self.latency.as_secs_f64() / n as f64,
+        };
+        let mut out = String::new();
+        out.push_str("# HELP lintre_evaluations_total Evaluations served, by outcome.\n");
+        out.push_str("# TYPE lintre_evaluations_total counter\n");
+        for outcome in ["ok", "error", "limit", "panic"] {
+            let n = self.evaluations.get(outcome).copied().unwrap_or(0);
+            out.push_str(&format!("lintre_evaluations_total{{outcome=\"{}\"}} {}\n", outcome, n));
+        }
+        out.push_str("# HELP lintre_beta_reductions_total β-reductions over all evaluations.\n");
+        out.push_str("# TYPE lintre_beta_reductions_total counter\n");
+        out.push_str(&format!("lintre_beta_reductions_total {}\n", steps));
+        out.push_str("# HELP lintre_limit_hits_total Evaluations stopped by a limit; limit=\"time\" counts timeouts.\n");
+        out.push_str("# TYPE lintre_limit_hits_total counter\n");
+        for limit in ["steps", "time", "output", "term_size", "depth"] {
+            let n = self.limits.get(limit).copied().unwrap_or(0);
+            out.push_str(&format!("lintre_limit_hits_total{{limit=\"{}\"}} {}\n", limit, n));
+        }
+        out.push_str("# HELP lintre_eval_latency_seconds Time spent on evaluation requests.\n");
+        out.push_str("# TYPE lintre_eval_latency_seconds summary\n");
+        out.push_str(&format!("lintre_eval_latency_seconds_sum {:.6}\n", self.latency.as_secs_f64()));
+        out.push_str(&format!("lintre_eval_latency_seconds_count {}\n", count));
+        out.push_str("# HELP lintre_eval_latency_average_seconds Average time per evaluation request.\n");
+        out.push_str("# TYPE lintre_eval_latency_average_seconds gauge\n");
+        out.push_str(&format!("lintre_eval_latency_average_seconds {:.6}\n", average));
+        out.push_str("# HELP lintre_sessions Live sessions.\n");
+        out.push_str("# TYPE lintre_sessions gauge\n");
+        out.push_str(&format!("lintre_sessions {}\n", sessions));
+        out
+    }
+}
+
+/// 작은 HTTP/1.1 서버. 요청을 하나씩 차례로 처리하고 응답마다 연결을 닫음.
+/// - `POST /eval`: 본문을 새 환경에서 평가
+/// - `POST /session/:id/eval`: 세션 `id`의 환경에서 평가. 없으면 만듦
+/// - `DELETE /session/:id`: 세션을 지움
+/// - `GET /metrics`: 평가 수, β-축약 수, 제한에 걸린 수, 평균 지연을 Prometheus 글 형식으로
+///
+/// `ttl` 동안 쓰지 않은 세션은 다음 요청이 올 때 지움. `/metrics` 밖의 응답은 모두 JSON 객체 한 줄.
+/// 평가마다 `limits`를 새로 걸고, 평가 중에 패닉이 나면 그 요청만 실패시키고 세션을 버림
+pub struct Server {
+    config: Config,
+    ttl: Duration,
+    limits: Limits,
+    sessions: HashMap<String, Session>,
+    metrics: Metrics,
+}
+
+impl Server {
+    pub fn new(config: Config, ttl: Duration, limits: Limits) -> Self {
+        Server { config, ttl, limits, sessions: HashMap::new(), metrics: Metrics::default() }
+    }
+
+    pub fn serve(&mut self, address: &str) -> Result<(), String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+        eprintln!("Listening on http://{}", address);
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => self.handle(stream),
+                Err(e) => log::warn(Target::Server, || format!("Failed to accept a connection: {}", e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, mut stream: TcpStream) {
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let started = Instant::now();
+        let (status, content_type, body) = match read_request(&mut stream) {
+            Ok((method, path, body)) => {
+                self.expire();
+                let (status, body) = self.route(&method, &path, &body);
+                let content_type = match path.trim_matches('/') == "metrics" && status == "200 OK" {
+                    true => EXPOSITION,
+                    false => JSON,
+                };
+                log::info(Target::Server, || format!(
+                    "{} {} -> {} in {:.1} ms",
+                    method,
+                    path,
+                    status,
+                    started.elapsed().as_secs_f64() * 1000.0,
+                ));
+                (status, content_type, body)
+            }
+            Err((status, message)) => {
+                log::info(Target::Server, || format!("rejected a request: {} ({})", status, message));
+                (status, JSON, error(&message))
+            }
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body,
+        );
+        let _ = stream.write_all(response.as_bytes());
+    }
+
+    fn expire(&mut self) {
+        let ttl = self.ttl;
+        self.sessions.retain(|id, session| {
+            let alive = session.last_used.elapsed() < ttl;
+            if !alive {
+                log::debug(Target::Server, || format!("session '{}' expired", id));
+            }
+            alive
+        });
+    }
+
+    fn route(&mut self, method: &str, path: &str, body: &str) -> (&'static str, String) {
+        let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        let (allowed, id) = match segments.as_slice() {
+            ["metrics"] => ("GET", None),
+            ["eval"] => ("POST", None),
+            ["session", id, "eval"] => ("POST", Some(*id)),
+            ["session", id] => ("DELETE", Some(*id)),
+            _ => return ("404 Not Found", error(&format!("No route for {} {}", method, path))),
+        };
+        if method != allowed {
+            return ("405 Method Not Allowed", error(&format!("Only {} is allowed for {}", allowed, path)));
+        }
+        let started = Instant::now();
+        match id {
+            None if method == "GET" => ("200 OK", self.metrics.exposition(self.config.steps.get(), self.sessions.len())),
+            None => {
+                let mut session = self.session();
+                let (outcome, response) = eval(&mut session, None, body, self.limits);
+                self.metrics.record(outcome, started.elapsed());
+                response
+            }
+            Some(id) if !valid_id(id) => {
+                ("400 Bad Request", error("Session ids are 1 to 64 letters, digits, '-' or '_'"))
+            }
+            Some(id) if method == "DELETE" => match self.sessions.remove(id) {
+                Some(_) => ("200 OK", format!("{{\"session\":{},\"deleted\":true}}", json::string(id))),
+                None => ("404 Not Found", error(&format!("No session '{}'", id))),
+            },
+            Some(id) => {
+                let mut session = self.sessions.remove(id).unwrap_or_else(|| self.session());
+                let (outcome, response) = eval(&mut session, Some(id), body, self.limits);
+                self.metrics.record(outcome, started.elapsed());
+                if outcome != Outcome::Panic {
+                    session.last_used = Instant::now();
+                    self.sessions.insert(id.to_string(), session);
+                }
+                response
+            }
+        }
+    }
+
+    fn session(&self) -> Session {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut config = self.config.clone();
+        config.output = Output::Capture(output.clone());
+        Session { interpreter: Interpreter::new(config), context: Context::default(), output, last_used: Instant::now() }
+    }
+}
+
+/// `{"session":…,"result":…,"output":…,"error":…}`. `result`는 `json::result`의 객체이고, 실패하면 `null`.
+/// 제한에 걸리면 `422`, 패닉이 나면 `500`. 패닉이 나면 세션을 더 쓸 수 없음
+fn eval(session: &mut Session, id: Option<&str>, source: &str, limits: Limits) -> (Outcome, (&'static str, String)) {
+    let sandbox = Rc::new(Sandbox::new(limits));
+    session.interpreter.set_sandbox(Some(sandbox.clone()));
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| evaluate(&mut session.interpreter, &mut session.context, source, &sandbox)));
+    session.interpreter.set_sandbox(None);
+    let output = std::mem::take(&mut *session.output.borrow_mut());
+    let (kind, status, result, error) = match outcome {
+        Ok(Ok(result)) => (Outcome::Ok, "200 OK", result, "null".to_string()),
+        Ok(Err(e)) => match sandbox.exceeded() {
+            Some(limit) => {
+                log::info(Target::Server, || format!("limit reached: {}", e));
+                (Outcome::Limit(limit), "422 Unprocessable Entity", "null".to_string(), json::string(&e))
+            }
+            None => (Outcome::Error, "400 Bad Request", "null".to_string(), json::string(&e)),
+        },
+        Err(_) => {
+            log::error(Target::Server, || format!("an evaluation panicked; dropped session {}", id.unwrap_or("(none)")));
+            (Outcome::Panic, "500 Internal Server Error", "null".to_string(), json::string("The evaluation panicked."))
+        }
+    };
+    let id = id.map_or("null".to_string(), json::string);
+    (kind, (status, format!(
+        "{{\"session\":{},\"result\":{},\"output\":{},\"error\":{}}}",
+        id,
+        result,
+        json::string(&output),
+        error,
+    )))
+}
+
+/// 소스를 `context`에 이어 읽고 평가해서 결과를 `json::result`로. Church 인코딩으로 해석해 보는 동안에도 제한이 걸림
+pub(crate) fn evaluate(interpreter: &mut Interpreter, context: &mut Context, source: &str, sandbox: &Sandbox) -> Result<String, String> {
+    let mut parser = Parser::new(source).max_depth(sandbox.limits().depth).with_context(context.clone());
+    let ast = parser.parse().map_err(|e| messages::text(Msg::ParseError, &[&e]))?;
+    *context = parser.context().clone();
+    let value = interpreter.eval(ast).map_err(|e| e.to_string())?;
+    Ok(json::result(interpreter, &value))
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\":{}}}", json::string(message))
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 요청 줄과 머리, `Content-Length`만큼의 본문을 읽음. 실패하면 응답할 상태와 까닭
+fn read_request(stream: &mut TcpStream) -> Result<(String, String, String), (&'static str, String)> {
+    let bad = |message: &str| ("400 Bad Request", message.to_string());
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| bad(&e.to_string()))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad("Malformed request line"));
+    };
+    let (method, path) = (method.to_string(), target.split('?').next().unwrap_or(target).to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        reader.read_line(&mut line).map_err(|e| bad(&e.to_string()))?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().map_err(|_| bad("Invalid Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(("413 Payload Too Large", format!("The body is larger than {} bytes", MAX_BODY)));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| bad(&e.to_string()))?;
+    let body = String::from_utf8(body).map_err(|_| bad("The body is not valid UTF-8"))?;
+    Ok((method, path, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        Server::new(Config::default(), Duration::from_secs(60), Limits::default())
+    }
+
+    #[test]
+    fn sessions_keep_definitions_between_requests() {
+        let mut server = server();
+        let (status, _) = server.route("POST", "/session/a/eval", "id = L x. x;");
+        assert_eq!(status, "200 OK");
+        let (status, body) = server.route("POST", "/session/a/eval", "id id");
+        assert_eq!(status, "200 OK");
+        assert!(body.starts_with("{\"session\":\"a\",\"result\":{\"kind\":\"closure\""), "{}", body);
+
+        // 다른 세션에서는 `id`가 묶이지 않은 이름
+        let (_, body) = server.route("POST", "/session/b/eval", "id id");
+        assert!(!body.contains("closure"), "{}", body);
+        assert_eq!(server.route("DELETE", "/session/a", "").0, "200 OK");
+        assert_eq!(server.route("DELETE", "/session/a", "").0, "404 Not Found");
+    }
+
+    #[test]
+    fn bad_requests_are_rejected() {
+        let mut server = server();
+        assert_eq!(server.route("POST", "/eval", "L x.").0, "400 Bad Request");
+        assert_eq!(server.route("POST", "/session/a b/eval", "x").0, "400 Bad Request");
+        assert_eq!(server.route("GET", "/eval", "").0, "405 Method Not Allowed");
+        assert_eq!(server.route("POST", "/nowhere", "").0, "404 Not Found");
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::builtins::Builtin;
+use crate::error::Error;
+use crate::explore::{core, sugar};
+use crate::interrupt;
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::sandbox::Sandbox;
+use crate::style::{self, Stream};
+
+const MAX_STEPS: usize = 100_000;
+// 단계마다 항을 복사하므로 자라기만 하는 항은 단계 수보다 먼저 이 크기에서 멈춤. 깊이는 `normalize::MAX_DEPTH`까지
+const MAX_TERM_SIZE: usize = 10_000;
+
+/// de Bruijn 번호로 적은 항. 대입도 항의 한 가지(`Closure`)라서 한 단계씩 줄여 가며 볼 수 있음
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    // 1부터 셈. 1은 가장 안쪽 람다가 묶은 변수
+    Var(usize),
+    // 묶이지 않은 이름. 정의가 있으면 그 본문으로 펼침
+    Free(String),
+    // 매개변수 이름은 결과를 이름 있는 식으로 되돌릴 때만 씀
+    Lam(String, Box<Term>),
+    App(Box<Term>, Box<Term>),
+    // `a[s]`: `a`에 대입 `s`를 아직 하지 않은 것
+    Closure(Box<Term>, Box<Subst>),
+}
+
+/// 명시적 대입
+#[derive(Debug, Clone, PartialEq)]
+enum Subst {
+    // `id`: 아무것도 바꾸지 않음
+    Id,
+    // `↑`: 모든 번호를 하나씩 올림
+    Shift,
+    // `a·s`: 1을 `a`로, n+1을 `n[s]`로
+    Cons(Box<Term>, Box<Subst>),
+    // `s∘t`: `s`를 한 뒤 `t`를 함
+    Compose(Box<Subst>, Box<Subst>),
+}
+
+/// 한 단계에서 쓴 규칙. 이름은 λσ의 관례를 따름
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    // `(λ.a) b → a[b·id]`
+    Beta,
+    // 정의 이름을 본문으로 펼침
+    Delta,
+    // `(a b)[s] → a[s] b[s]`
+    App,
+    // `(λ.a)[s] → λ.a[1·(s∘↑)]`
+    Abs,
+    // `1[a·s] → a`
+    VarCons,
+    // `n+1[a·s] → n[s]`
+    VarShift,
+    // `n[↑] → n+1`
+    Shift,
+    // `a[id] → a`
+    Id,
+    // `n[s∘t] → n[s][t]`
+    VarComp,
+    // `x[s] → x`: 묶이지 않은 이름은 대입의 영향을 받지 않음
+    Free,
+}
+
+impl Rule {
+    fn name(self) -> &'static str {
+        match self {
+            Rule::Beta => "Beta",
+            Rule::Delta => "Delta",
+            Rule::App => "App",
+            Rule::Abs => "Abs",
+            Rule::VarCons => "VarCons",
+            Rule::VarShift => "VarShift",
+            Rule::Shift => "Shift",
+            Rule::Id => "Id",
+            Rule::VarComp => "VarComp",
+            Rule::Free => "Free",
+        }
+    }
+}
+
+/// λσ 방식의 명시적 대입 기계. β-축약은 대입을 만들기만 하고, 그 대입은 규칙 하나씩 항 안으로 밀려 들어감.
+/// 정규 순서(가장 왼쪽 바깥의 자리부터)로 마지막 식을 정규형까지 줄이며,
+/// `-b`이면 단계마다 쓴 규칙과 그 뒤의 항을 보여 줌
+pub struct Machine {
+    defined: HashMap<String, Term>,
+    // 정의한 순서. 결과에 붙일 이름을 찾을 때 나중 정의부터 봄
+    order: Vec<String>,
+    trace: bool,
+    // 있으면 단계마다 그 제한(단계 수, 시간, 항 크기)도 확인함
+    sandbox: Option<Rc<Sandbox>>,
+}
+
+impl Machine {
+    pub fn new(trace: bool) -> Self {
+        Machine { defined: HashMap::new(), order: Vec::new(), trace, sandbox: None }
+    }
+
+    pub fn sandbox(mut self, sandbox: Option<Rc<Sandbox>>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// 결과를 정의 이름이나 다시 파싱할 수 있는 정규형으로 돌려준다.
+    pub fn run(&mut self, program: &Expr) -> Result<String, Error> {
+        let statements = match program {
+            Expr::Sequence(exprs) => exprs.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let mut result = None;
+        for statement in statements {
+            match statement.unlocated() {
+                Expr::Define(name, _, body) => {
+                    let body = self.term(body)?;
+                    self.defined.insert(name.clone(), body);
+                    self.order.push(name.clone());
+                }
+                Expr::Assert(lhs, rhs) => {
+                    let left = self.normal_form(lhs, false)?;
+                    let right = self.normal_form(rhs, false)?;
+                    if !normalize::alpha_eq(&left, &right) {
+                        return Err(format!(
+                            "{}\n  {:<6} {}\n  {:<6} {}",
+                            messages::text(Msg::AssertionFailed, &[]),
+                            messages::text(Msg::AssertionLeft, &[]),
+                            normalize::canonical(&left),
+                            messages::text(Msg::AssertionRight, &[]),
+                            normalize::canonical(&right),
+                        ).into());
+                    }
+                }
+                other => result = Some(other),
+            }
+        }
+        match result {
+            Some(expr) => {
+                let normal = self.normal_form(expr, self.trace)?;
+                Ok(self.name_of(&normal).unwrap_or_else(|| normalize::canonical(&normal).to_string()))
+            }
+            None => Ok("()".to_string()),
+        }
+    }
+
+    /// 결과와 α-동치인 정의 이름. 본문이 이미 정규형인 정의만 봄
+    fn name_of(&self, normal: &Expr) -> Option<String> {
+        self.order.iter().rev()
+            .find(|name| {
+                let body = &self.defined[*name];
+                self.step(body).is_none() && normalize::alpha_eq(&to_expr(body), normal)
+            })
+            .cloned()
+    }
+
+    fn term(&self, expr: &Expr) -> Result<Term, String> {
+        let expr = core(expr)?;
+        for name in free_names(&expr, &mut Vec::new()) {
+            if Builtin::from_name(&name).is_some() {
+                return Err(messages::text(Msg::SigmaUnsupported, &[&name]));
+            }
+        }
+        Ok(from_expr(&expr, &mut Vec::new()))
+    }
+
+    fn normal_form(&self, expr: &Expr, trace: bool) -> Result<Expr, Error> {
+        let mut term = self.term(expr)?;
+        if trace {
+            println!("{} {}", style::dim(Stream::Stdout, &format!("{:>5} {:<8}", 0, "")), term);
+        }
+        let max_size = self.sandbox.as_ref().map_or(MAX_TERM_SIZE, |s| s.limits().term_size.min(MAX_TERM_SIZE));
+        for steps in 1..=MAX_STEPS {
+            interrupt::check(steps, || term.to_string())?;
+            let Some((next, rule)) = self.step(&term) else {
+                return Ok(to_expr(&term));
+            };
+            term = next;
+            let (size, depth) = measure(&term);
+            if let Some(sandbox) = &self.sandbox {
+                sandbox.step(|| size)?;
+            }
+            if depth > normalize::MAX_DEPTH {
+                return Err(Error::step_limit(messages::text(Msg::SubstitutionNesting, &[&normalize::MAX_DEPTH])));
+            }
+            if size > max_size {
+                return Err(Error::step_limit(messages::text(Msg::SubstitutionSize, &[&max_size])));
+            }
+            if trace {
+                println!("{} {}", style::dim(Stream::Stdout, &format!("{:>5} {:<8}", steps, rule.name())), term);
+            }
+        }
+        Err(Error::step_limit(messages::text(Msg::SubstitutionSteps, &[&MAX_STEPS])))
+    }
+
+    /// 가장 왼쪽 바깥의 자리 하나를 줄인다. 정규형이면 `None`.
+    /// 대입이 남은 항은 정규형이 아니므로 `Closure`는 언제나 줄어듦
+    fn step(&self, term: &Term) -> Option<(Term, Rule)> {
+        match term {
+            Term::Var(_) => None,
+            Term::Free(name) => self.defined.get(name).map(|body| (body.clone(), Rule::Delta)),
+            Term::Lam(param, body) => self.step(body)
+                .map(|(body, rule)| (Term::Lam(param.clone(), Box::new(body)), rule)),
+            Term::App(func, arg) => {
+                if let Term::Lam(_, body) = &**func {
+                    let subst = Subst::Cons(arg.clone(), Box::new(Subst::Id));
+                    return Some((Term::Closure(body.clone(), Box::new(subst)), Rule::Beta));
+                }
+                if let Some((func, rule)) = self.step(func) {
+                    return Some((Term::App(Box::new(func), arg.clone()), rule));
+                }
+                self.step(arg).map(|(arg, rule)| (Term::App(func.clone(), Box::new(arg)), rule))
+            }
+            Term::Closure(inner, subst) => Some(self.push(inner, subst)),
+        }
+    }
+
+    /// `inner[subst]`를 한 단계 줄인다
+    fn push(&self, inner: &Term, subst: &Subst) -> (Term, Rule) {
+        let closure = |term: Term, subst: Subst| Term::Closure(Box::new(term), Box::new(subst));
+        match (inner, subst) {
+            (_, Subst::Id) => (inner.clone(), Rule::Id),
+            (Term::Free(_), _) => (inner.clone(), Rule::Free),
+            (Term::Var(n), Subst::Shift) => (Term::Var(n + 1), Rule::Shift),
+            (Term::Var(1), Subst::Cons(head, _)) => ((**head).clone(), Rule::VarCons),
+            (Term::Var(n), Subst::Cons(_, rest)) => (closure(Term::Var(n - 1), (**rest).clone()), Rule::VarShift),
+            (Term::Var(n), Subst::Compose(first, second)) => {
+                (closure(closure(Term::Var(*n), (**first).clone()), (**second).clone()), Rule::VarComp)
+            }
+            (Term::App(func, arg), _) => (
+                Term::App(
+                    Box::new(closure((**func).clone(), subst.clone())),
+                    Box::new(closure((**arg).clone(), subst.clone())),
+                ),
+                Rule::App,
+            ),
+            (Term::Lam(param, body), _) => {
+                let lifted = Subst::Cons(
+                    Box::new(Term::Var(1)),
+                    Box::new(Subst::Compose(Box::new(subst.clone()), Box::new(Subst::Shift))),
+                );
+                (Term::Lam(param.clone(), Box::new(closure((**body).clone(), lifted))), Rule::Abs)
+            }
+            // 안쪽 대입부터 끝냄. `a[s][t] → a[s∘t]`를 쓰지 않으므로 대입끼리의 규칙은 필요 없음
+            (Term::Closure(..), _) => {
+                let (inner, rule) = self.step(inner).expect("a closure always reduces");
+                (closure(inner, subst.clone()), rule)
+            }
+        }
+    }
+}
+
+/// 항과 그 안의 대입을 합친 (노드 수, 깊이)
+fn measure(term: &Term) -> (usize, usize) {
+    let (size, depth) = match term {
+        Term::Var(_) | Term::Free(_) => (0, 0),
+        Term::Lam(_, body) => measure(body),
+        Term::App(func, arg) => sum(measure(func), measure(arg)),
+        Term::Closure(inner, subst) => sum(measure(inner), measure_subst(subst)),
+    };
+    (size + 1, depth + 1)
+}
+
+fn measure_subst(subst: &Subst) -> (usize, usize) {
+    let (size, depth) = match subst {
+        Subst::Id | Subst::Shift => (0, 0),
+        Subst::Cons(head, rest) => sum(measure(head), measure_subst(rest)),
+        Subst::Compose(first, second) => sum(measure_subst(first), measure_subst(second)),
+    };
+    (size + 1, depth + 1)
+}
+
+fn sum((size, depth): (usize, usize), (other_size, other_depth): (usize, usize)) -> (usize, usize) {
+    (size + other_size, depth.max(other_depth))
+}
+
+/// 코어 식(매개변수 하나짜리 람다와 두 항의 적용)을 de Bruijn 번호로 바꿈
+fn from_expr(expr: &Expr, bound: &mut Vec<String>) -> Term {
+    match expr {
+        Expr::Word(name) => match bound.iter().rev().position(|b| b == name) {
+            Some(i) => Term::Var(i + 1),
+            None => Term::Free(name.clone()),
+        },
+        Expr::Function(params, body) => {
+            bound.push(params[0].clone());
+            let body = from_expr(body, bound);
+            bound.pop();
+            Term::Lam(params[0].clone(), Box::new(body))
+        }
+        Expr::Words(pair) => Term::App(Box::new(from_expr(&pair[0], bound)), Box::new(from_expr(&pair[1], bound))),
+        _ => Term::Free(expr.to_string()),
+    }
+}
+
+fn free_names(expr: &Expr, bound: &mut Vec<String>) -> HashSet<String> {
+    match expr {
+        Expr::Word(name) if !bound.contains(name) => HashSet::from([name.clone()]),
+        Expr::Function(params, body) => {
+            bound.push(params[0].clone());
+            let names = free_names(body, bound);
+            bound.pop();
+            names
+        }
+        Expr::Words(pair) => pair.iter().flat_map(|e| free_names(e, bound)).collect(),
+        _ => HashSet::new(),
+    }
+}
+
+/// 대입이 남지 않은 항을 이름 있는 식으로 되돌림. 바깥 이름을 가리지 않도록 겹치면 `'`를 붙임
+fn to_expr(term: &Term) -> Expr {
+    fn go(term: &Term, names: &mut Vec<String>, free: &HashSet<String>) -> Expr {
+        match term {
+            Term::Var(n) => Expr::Word(names[names.len() - n].clone()),
+            Term::Free(name) => Expr::Word(name.clone()),
+            Term::Lam(param, body) => {
+                let mut name = param.clone();
+                while names.contains(&name) || free.contains(&name) {
+                    name.push('\'');
+                }
+                names.push(name.clone());
+                let body = go(body, names, free);
+                names.pop();
+                Expr::Function(vec![name], Box::new(body))
+            }
+            Term::App(func, arg) => Expr::Words(vec![go(func, names, free), go(arg, names, free)]),
+            Term::Closure(..) => unreachable!("normal forms have no closures"),
+        }
+    }
+    let mut free = HashSet::new();
+    collect_free(term, &mut free);
+    sugar(&go(term, &mut Vec::new(), &free))
+}
+
+fn collect_free(term: &Term, free: &mut HashSet<String>) {
+    match term {
+        Term::Free(name) => {
+            free.insert(name.clone());
+        }
+        Term::Lam(_, body) => collect_free(body, free),
+        Term::App(func, arg) => {
+            collect_free(func, free);
+            collect_free(arg, free);
+        }
+        Term::Var(_) | Term::Closure(..) => {}
+    }
+}
+
+/// `λ. 1 2[↑]`처럼 de Bruijn 번호로 적음. 대입은 적용보다 단단히 붙음
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::Var(n) => write!(f, "{}", n),
+            Term::Free(name) => write!(f, "{}", name),
+            Term::Lam(_, body) => write!(f, "λ. {}", body),
+            Term::App(func, arg) => {
+                match **func {
+                    Term::Lam(..) => write!(f, "({})", func)?,
+                    _ => write!(f, "{}", func)?,
+                }
+                match **arg {
+                    Term::Lam(..) | Term::App(..) => write!(f, " ({})", arg),
+                    _ => write!(f, " {}", arg),
+                }
+            }
+            Term::Closure(inner, subst) => match **inner {
+                Term::Lam(..) | Term::App(..) => write!(f, "({})[{}]", inner, subst),
+                _ => write!(f, "{}[{}]", inner, subst),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Subst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Subst::Id => write!(f, "id"),
+            Subst::Shift => write!(f, "↑"),
+            Subst::Cons(head, rest) => {
+                match **head {
+                    Term::Lam(..) | Term::App(..) => write!(f, "({})·", head)?,
+                    _ => write!(f, "{}·", head)?,
+                }
+                match **rest {
+                    Subst::Compose(..) => write!(f, "({})", rest),
+                    _ => write!(f, "{}", rest),
+                }
+            }
+            Subst::Compose(first, second) => {
+                for (i, part) in [first, second].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "∘")?;
+                    }
+                    match **part {
+                        Subst::Cons(..) | Subst::Compose(..) => write!(f, "({})", part)?,
+                        _ => write!(f, "{}", part)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::parser::Parser;
+    use crate::sandbox::Limits;
+    use std::time::Duration;
+
+    const GROWING: &str = "d = L x. x x x x; d (L y. y y y y)";
+
+    fn run(machine: Machine, source: &str) -> Result<String, Error> {
+        let mut machine = machine;
+        machine.run(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn reduces_to_the_normal_form() {
+        assert_eq!(run(Machine::new(false), "k = L x y. x; k (L a. a) (L b. b b)").unwrap(), "L v0. v0");
+    }
+
+    #[test]
+    fn growing_terms_stop_at_the_term_size_limit() {
+        let e = run(Machine::new(false), GROWING).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::StepLimit);
+        assert!(e.message.contains("did not finish within 10000 nodes"), "{}", e);
+    }
+
+    #[test]
+    fn honors_the_sandbox_time_limit() {
+        let sandbox = Rc::new(Sandbox::new(Limits { time: Duration::ZERO, ..Limits::default() }));
+        let e = run(Machine::new(false).sandbox(Some(sandbox)), GROWING).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::StepLimit);
+        assert!(e.message.contains("did not finish within 0 ms"), "{}", e);
+    }
+}
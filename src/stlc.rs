@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Expr, Type};
+use crate::messages::{self, Msg};
+
+/// 추론 중의 타입. 표기가 없는 매개변수는 타입 변수로 시작한다.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Con(String),
+    Var(usize),
+    Arrow(Box<Ty>, Box<Ty>),
+}
+
+#[derive(Debug, Clone)]
+struct Scheme {
+    vars: Vec<usize>,
+    ty: Ty,
+}
+
+/// 단순 타입 람다 계산법 검사기.
+/// 표기 `L (x : a -> a). x`의 `a` 같은 이름은 기본 타입이고, 표기하지 않은 부분은 단일화로 추론한다.
+/// 최상위 정의는 쓰일 때마다 새로 인스턴스화되므로 Church 인코딩을 여러 타입에서 쓸 수 있다.
+pub struct Checker {
+    subst: HashMap<usize, Ty>,
+    next_var: usize,
+    globals: HashMap<String, Scheme>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            subst: HashMap::new(),
+            next_var: 0,
+            globals: HashMap::new(),
+        }
+    }
+
+    /// 프로그램을 검사하고 마지막 식의 타입을 돌려준다.
+    pub fn check(&mut self, program: &Expr) -> Result<Option<Ty>, String> {
+        let statements = match program {
+            Expr::Sequence(exprs) => exprs.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let mut last = None;
+        for statement in statements {
+            match statement {
+                Expr::Define(name, annotation, body) => {
+                    let ty = self.infer(body, &HashMap::new())
+                        .map_err(|e| messages::text(Msg::InDefinition, &[&e, name]))?;
+                    if let Some(annotation) = annotation {
+                        self.unify(&ty, &from_annotation(annotation)?)
+                            .map_err(|e| messages::text(Msg::InDefinition, &[&e, name]))?;
+                    }
+                    let scheme = self.generalize(&ty);
+                    self.globals.insert(name.clone(), scheme);
+                }
+                Expr::Assert(lhs, rhs) => {
+                    let left = self.infer(lhs, &HashMap::new())?;
+                    let right = self.infer(rhs, &HashMap::new())?;
+                    self.unify(&left, &right)
+                        .map_err(|e| messages::text(Msg::InAssert, &[&e]))?;
+                }
+                _ => {
+                    let ty = self.infer(statement, &HashMap::new())?;
+                    last = Some(self.resolve(&ty));
+                }
+            }
+        }
+        Ok(last)
+    }
+
+    fn infer(&mut self, expr: &Expr, locals: &HashMap<String, Ty>) -> Result<Ty, String> {
+        match expr {
+            Expr::Word(name) => {
+                if let Some(ty) = locals.get(name) {
+                    Ok(ty.clone())
+                } else if let Some(scheme) = self.globals.get(name).cloned() {
+                    Ok(self.instantiate(&scheme))
+                } else if let Some(scheme) = builtin_scheme(name) {
+                    Ok(self.instantiate(&scheme))
+                } else {
+                    Err(messages::text(Msg::UnboundVariable, &[name]))
+                }
+            }
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                let mut func = self.infer(head, locals)?;
+                for arg in args {
+                    let arg_ty = self.infer(arg, locals)?;
+                    let result = self.fresh();
+                    let expected = Ty::Arrow(Box::new(arg_ty), Box::new(result.clone()));
+                    self.unify(&func, &expected)
+                        .map_err(|e| messages::text(Msg::InExpression, &[&e, expr]))?;
+                    func = result;
+                }
+                Ok(func)
+            }
+            Expr::Function(params, body) => self.infer_function(params, &[], body, locals),
+            Expr::Annotated(types, inner) => match &**inner {
+                Expr::Function(params, body) => self.infer_function(params, types, body, locals),
+                other => self.infer(other, locals),
+            },
+            Expr::Paren(inner) | Expr::Located(_, inner) => self.infer(inner, locals),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err(messages::text(Msg::TopLevelOnly, &[]))
+            }
+            Expr::TypeFunction(..) | Expr::TypeApply(..) => {
+                Err(messages::text(Msg::TypeAbstractionNeedsSystemF, &[]))
+            }
+        }
+    }
+
+    fn infer_function(
+        &mut self,
+        params: &[String],
+        types: &[Option<Type>],
+        body: &Expr,
+        locals: &HashMap<String, Ty>,
+    ) -> Result<Ty, String> {
+        let mut locals = locals.clone();
+        let mut param_types = Vec::new();
+        for (i, param) in params.iter().enumerate() {
+            let ty = match types.get(i) {
+                Some(Some(annotation)) => from_annotation(annotation)?,
+                _ => self.fresh(),
+            };
+            locals.insert(param.clone(), ty.clone());
+            param_types.push(ty);
+        }
+        let mut ty = self.infer(body, &locals)?;
+        for param_ty in param_types.into_iter().rev() {
+            ty = Ty::Arrow(Box::new(param_ty), Box::new(ty));
+        }
+        Ok(ty)
+    }
+
+    fn fresh(&mut self) -> Ty {
+        self.next_var += 1;
+        Ty::Var(self.next_var - 1)
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Ty {
+        let mapping = scheme.vars.iter()
+            .map(|&v| (v, self.fresh()))
+            .collect::<HashMap<_, _>>();
+        replace_vars(&scheme.ty, &mapping)
+    }
+
+    /// 최상위 환경은 모두 닫힌 스킴이므로 남은 타입 변수를 전부 일반화
+    fn generalize(&self, ty: &Ty) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut vars = Vec::new();
+        collect_vars(&ty, &mut vars);
+        Scheme { vars, ty }
+    }
+
+    pub fn resolve(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(v) => match self.subst.get(v) {
+                Some(bound) => self.resolve(bound),
+                None => ty.clone(),
+            },
+            Ty::Arrow(from, to) => Ty::Arrow(Box::new(self.resolve(from)), Box::new(self.resolve(to))),
+            Ty::Con(_) => ty.clone(),
+        }
+    }
+
+    fn unify(&mut self, a: &Ty, b: &Ty) -> Result<(), String> {
+        let a = self.resolve(a);
+        let b = self.resolve(b);
+        match (&a, &b) {
+            (Ty::Var(x), Ty::Var(y)) if x == y => Ok(()),
+            (Ty::Var(v), other) | (other, Ty::Var(v)) => {
+                if occurs(*v, other) {
+                    return Err(messages::text(Msg::InfiniteType, &[&Ty::Var(*v), other]));
+                }
+                self.subst.insert(*v, other.clone());
+                Ok(())
+            }
+            (Ty::Con(x), Ty::Con(y)) if x == y => Ok(()),
+            (Ty::Arrow(a1, r1), Ty::Arrow(a2, r2)) => {
+                self.unify(a1, a2)?;
+                self.unify(r1, r2)
+            }
+            _ => {
+                // 두 타입에 같은 번호가 붙도록 함께 다시 번호 매김
+                match rename_vars(&Ty::Arrow(Box::new(a.clone()), Box::new(b.clone()))) {
+                    Ty::Arrow(a, b) => Err(messages::text(Msg::TypeMismatch, &[&a, &b])),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+fn from_annotation(ty: &Type) -> Result<Ty, String> {
+    match ty {
+        Type::Base(name) => Ok(Ty::Con(name.clone())),
+        Type::Arrow(from, to) => Ok(Ty::Arrow(
+            Box::new(from_annotation(from)?),
+            Box::new(from_annotation(to)?),
+        )),
+        Type::Forall(..) => Err(messages::text(Msg::PolymorphicNeedsSystemF, &[ty])),
+    }
+}
+
+fn builtin_scheme(name: &str) -> Option<Scheme> {
+    let v = |i| Box::new(Ty::Var(i));
+    let arrow = |a: Box<Ty>, b: Box<Ty>| Box::new(Ty::Arrow(a, b));
+    // Church 숫자: (s -> s) -> s -> s
+    let nat = |s| arrow(arrow(v(s), v(s)), arrow(v(s), v(s)));
+    let ty = match name {
+        "print" => arrow(v(0), v(0)),
+        "trace" => arrow(v(0), arrow(v(1), v(1))),
+        "eq" => arrow(v(0), arrow(v(0), arrow(v(1), arrow(v(1), v(1))))),
+        "readNat" => nat(0),
+        // 문자 코드의 Church 리스트: (nat -> r -> r) -> r -> r
+        "readLine" => arrow(arrow(nat(0), arrow(v(1), v(1))), arrow(v(1), v(1))),
+        _ => return None,
+    };
+    let mut vars = Vec::new();
+    collect_vars(&ty, &mut vars);
+    Some(Scheme { vars, ty: *ty })
+}
+
+fn replace_vars(ty: &Ty, mapping: &HashMap<usize, Ty>) -> Ty {
+    match ty {
+        Ty::Var(v) => mapping.get(v).cloned().unwrap_or_else(|| ty.clone()),
+        Ty::Arrow(from, to) => Ty::Arrow(
+            Box::new(replace_vars(from, mapping)),
+            Box::new(replace_vars(to, mapping)),
+        ),
+        Ty::Con(_) => ty.clone(),
+    }
+}
+
+fn collect_vars(ty: &Ty, vars: &mut Vec<usize>) {
+    match ty {
+        Ty::Var(v) => {
+            if !vars.contains(v) {
+                vars.push(*v);
+            }
+        }
+        Ty::Arrow(from, to) => {
+            collect_vars(from, vars);
+            collect_vars(to, vars);
+        }
+        Ty::Con(_) => {}
+    }
+}
+
+fn occurs(var: usize, ty: &Ty) -> bool {
+    match ty {
+        Ty::Var(v) => *v == var,
+        Ty::Arrow(from, to) => occurs(var, from) || occurs(var, to),
+        Ty::Con(_) => false,
+    }
+}
+
+/// 출력용으로 타입 변수를 등장 순서대로 t0, t1, …로 다시 번호 매김
+pub fn rename_vars(ty: &Ty) -> Ty {
+    let mut vars = Vec::new();
+    collect_vars(ty, &mut vars);
+    let mapping = vars.iter()
+        .enumerate()
+        .map(|(i, &v)| (v, Ty::Var(i)))
+        .collect::<HashMap<_, _>>();
+    replace_vars(ty, &mapping)
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Con(name) => write!(f, "{}", name),
+            Ty::Var(v) => write!(f, "t{}", v),
+            Ty::Arrow(from, to) => match **from {
+                Ty::Arrow(..) => write!(f, "({}) -> {}", from, to),
+                _ => write!(f, "{} -> {}", from, to),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Result<Option<Ty>, String> {
+        Checker::new().check(&source.parse().unwrap())
+    }
+
+    #[test]
+    fn definitions_are_instantiated_at_each_use() {
+        let ty = check("id = L x. x; k = L x y. x; k id (id k)").unwrap().unwrap();
+        assert_eq!(rename_vars(&ty).to_string(), "t0 -> t0");
+        let ty = check("L (f : Nat -> Nat) x. f x").unwrap().unwrap();
+        assert_eq!(rename_vars(&ty).to_string(), "(Nat -> Nat) -> Nat -> Nat");
+    }
+
+    #[test]
+    fn self_application_and_mismatched_annotations_are_rejected() {
+        assert!(check("L x. x x").is_err());
+        assert!(check("L (x : Nat). x; (L (f : Nat -> Nat). f) (L (b : Bool). b)").is_err());
+        assert!(check("id : Nat -> Bool = L x. x").is_err());
+    }
+}
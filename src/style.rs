@@ -0,0 +1,79 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// `--color always|never|auto`. `auto`는 출력이 터미널이고 `NO_COLOR`가 없을 때만 색을 씀
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+}
+
+// 모든 출력이 같은 설정을 봐야 하므로 프로그램 전체에 하나
+static CHOICE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_choice(choice: ColorChoice) {
+    CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+fn enabled(stream: Stream) -> bool {
+    match CHOICE.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => {
+            let terminal = match stream {
+                Stream::Stdout => io::stdout().is_terminal(),
+                Stream::Stderr => io::stderr().is_terminal(),
+            };
+            terminal && env::var_os("NO_COLOR").is_none()
+        }
+    }
+}
+
+fn paint(stream: Stream, code: &str, text: &str) -> String {
+    match enabled(stream) {
+        true => format!("\x1b[{}m{}\x1b[0m", code, text),
+        false => text.to_string(),
+    }
+}
+
+/// 평가 결과나 통과한 테스트
+pub fn result(stream: Stream, text: &str) -> String {
+    paint(stream, "1;32", text)
+}
+
+pub fn error(stream: Stream, text: &str) -> String {
+    paint(stream, "1;31", text)
+}
+
+pub fn warning(stream: Stream, text: &str) -> String {
+    paint(stream, "33", text)
+}
+
+/// 추적 출력에서 지금 줄이는 식
+pub fn redex(stream: Stream, text: &str) -> String {
+    paint(stream, "35", text)
+}
+
+/// 추적 출력의 단계 구분 줄이나 파일 이름처럼 덜 중요한 부분
+pub fn dim(stream: Stream, text: &str) -> String {
+    paint(stream, "2", text)
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Expr, Type};
+use crate::messages::{self, Msg};
+
+/// System F 검사기. 모든 람다 매개변수에 타입을 적어야 하고, 다형성은
+/// `/\a. e`와 `e [T]`로 드러낸다. 어떤 `forall`/`/\`에도 묶이지 않은 타입 이름은 기본 타입이다.
+pub struct Checker {
+    globals: HashMap<String, Type>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker { globals: HashMap::new() }
+    }
+
+    /// 프로그램을 검사하고 마지막 식의 타입을 돌려준다.
+    pub fn check(&mut self, program: &Expr) -> Result<Option<Type>, String> {
+        let statements = match program {
+            Expr::Sequence(exprs) => exprs.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let mut last = None;
+        for statement in statements {
+            match statement {
+                Expr::Define(name, annotation, body) => {
+                    let ty = self.infer(body, &HashMap::new())
+                        .map_err(|e| format!("{} (in definition '{}')", e, name))?;
+                    if let Some(annotation) = annotation.as_ref().filter(|a| !alpha_eq(a, &ty)) {
+                        return Err(messages::text(Msg::DeclaredType, &[name, annotation, &ty]));
+                    }
+                    self.globals.insert(name.clone(), ty);
+                }
+                Expr::Assert(lhs, rhs) => {
+                    let left = self.infer(lhs, &HashMap::new())?;
+                    let right = self.infer(rhs, &HashMap::new())?;
+                    if !alpha_eq(&left, &right) {
+                        return Err(messages::text(Msg::AssertTypeMismatch, &[&left, &right]));
+                    }
+                }
+                _ => last = Some(self.infer(statement, &HashMap::new())?),
+            }
+        }
+        Ok(last)
+    }
+
+    fn infer(&self, expr: &Expr, locals: &HashMap<String, Type>) -> Result<Type, String> {
+        match expr {
+            Expr::Word(name) => locals.get(name)
+                .or_else(|| self.globals.get(name))
+                .cloned()
+                .or_else(|| builtin_type(name))
+                .ok_or_else(|| messages::text(Msg::UnboundVariable, &[name])),
+            Expr::Words(words) => {
+                let (head, args) = words.split_first().ok_or_else(|| messages::text(Msg::EmptyWords, &[]))?;
+                let mut func = self.infer(head, locals)?;
+                for arg in args {
+                    let arg_ty = self.infer(arg, locals)?;
+                    func = match func {
+                        Type::Arrow(from, to) if alpha_eq(&from, &arg_ty) => *to,
+                        Type::Arrow(from, _) => {
+                            return Err(messages::text(Msg::ArgumentType, &[arg, &arg_ty, &from, expr]));
+                        }
+                        Type::Forall(..) => {
+                            return Err(messages::text(Msg::NeedsTypeArgument, &[head, expr]));
+                        }
+                        other => return Err(messages::text(Msg::ApplyNonFunction, &[&other, expr])),
+                    };
+                }
+                Ok(func)
+            }
+            Expr::Function(params, _) => {
+                Err(messages::text(Msg::ParameterNeedsType, &[&params[0]]))
+            }
+            Expr::Annotated(types, inner) => match &**inner {
+                Expr::Function(params, body) => {
+                    let mut locals = locals.clone();
+                    let mut param_types = Vec::new();
+                    for (param, ty) in params.iter().zip(types) {
+                        let ty = ty.clone()
+                            .ok_or_else(|| messages::text(Msg::ParameterNeedsType, &[param]))?;
+                        locals.insert(param.clone(), ty.clone());
+                        param_types.push(ty);
+                    }
+                    let body_ty = self.infer(body, &locals)?;
+                    Ok(param_types.into_iter().rev()
+                        .fold(body_ty, |ty, param_ty| Type::Arrow(Box::new(param_ty), Box::new(ty))))
+                }
+                other => self.infer(other, locals),
+            },
+            Expr::TypeFunction(vars, body) => {
+                // 밖에서 쓰이는 항 변수의 타입이 이 타입 변수를 잡지 않도록 확인
+                for var in vars {
+                    if let Some((name, _)) = locals.iter().find(|(_, ty)| free_type_vars(ty).contains(var)) {
+                        return Err(messages::text(Msg::TypeVariableEscapes, &[var, name]));
+                    }
+                }
+                let body_ty = self.infer(body, locals)?;
+                Ok(vars.iter().rev()
+                    .fold(body_ty, |ty, var| Type::Forall(var.clone(), Box::new(ty))))
+            }
+            Expr::TypeApply(inner, arg) => match self.infer(inner, locals)? {
+                Type::Forall(var, body) => Ok(substitute(&body, &var, arg)),
+                other => Err(messages::text(Msg::NoTypeArgument, &[inner, &other])),
+            },
+            Expr::Paren(inner) | Expr::Located(_, inner) => self.infer(inner, locals),
+            Expr::Define(..) | Expr::Sequence(_) | Expr::Assert(..) => {
+                Err(messages::text(Msg::TopLevelOnly, &[]))
+            }
+        }
+    }
+}
+
+fn builtin_type(name: &str) -> Option<Type> {
+    let v = |name: &str| Box::new(Type::Base(name.to_string()));
+    let arrow = |a: Box<Type>, b: Box<Type>| Box::new(Type::Arrow(a, b));
+    let forall = |name: &str, body: Box<Type>| Box::new(Type::Forall(name.to_string(), body));
+    // Church 숫자: forall s. (s -> s) -> s -> s
+    let nat = || forall("s", arrow(arrow(v("s"), v("s")), arrow(v("s"), v("s"))));
+    let ty = match name {
+        "print" => forall("a", arrow(v("a"), v("a"))),
+        "trace" => forall("a", forall("b", arrow(v("a"), arrow(v("b"), v("b"))))),
+        "eq" => forall("a", arrow(v("a"), arrow(v("a"), forall("r", arrow(v("r"), arrow(v("r"), v("r"))))))),
+        "readNat" => nat(),
+        // 문자 코드의 Church 리스트: forall r. (nat -> r -> r) -> r -> r
+        "readLine" => forall("r", arrow(arrow(nat(), arrow(v("r"), v("r"))), arrow(v("r"), v("r")))),
+        _ => return None,
+    };
+    Some(*ty)
+}
+
+/// 타입 안의 자유 타입 이름을 대입한다. `forall`에 묶인 이름과 겹치면 바꿔서 포획을 피함
+pub fn substitute(ty: &Type, var: &str, replacement: &Type) -> Type {
+    match ty {
+        Type::Base(name) if name == var => replacement.clone(),
+        Type::Base(_) => ty.clone(),
+        Type::Arrow(from, to) => Type::Arrow(
+            Box::new(substitute(from, var, replacement)),
+            Box::new(substitute(to, var, replacement)),
+        ),
+        Type::Forall(bound, _) if bound == var => ty.clone(),
+        Type::Forall(bound, body) => {
+            let incoming = free_type_vars(replacement);
+            if incoming.contains(bound) {
+                let mut taken = incoming;
+                taken.extend(free_type_vars(body));
+                let fresh = (1..)
+                    .map(|n| format!("{}{}", bound, n))
+                    .find(|name| !taken.contains(name))
+                    .unwrap();
+                let renamed = substitute(body, bound, &Type::Base(fresh.clone()));
+                Type::Forall(fresh, Box::new(substitute(&renamed, var, replacement)))
+            } else {
+                Type::Forall(bound.clone(), Box::new(substitute(body, var, replacement)))
+            }
+        }
+    }
+}
+
+fn free_type_vars(ty: &Type) -> HashSet<String> {
+    match ty {
+        Type::Base(name) => HashSet::from([name.clone()]),
+        Type::Arrow(from, to) => {
+            let mut vars = free_type_vars(from);
+            vars.extend(free_type_vars(to));
+            vars
+        }
+        Type::Forall(bound, body) => {
+            let mut vars = free_type_vars(body);
+            vars.remove(bound);
+            vars
+        }
+    }
+}
+
+/// `forall`에 묶인 이름만 다른 두 타입을 같다고 본다.
+pub fn alpha_eq(a: &Type, b: &Type) -> bool {
+    fn go<'a>(a: &'a Type, b: &'a Type, left: &mut Vec<&'a str>, right: &mut Vec<&'a str>) -> bool {
+        match (a, b) {
+            (Type::Base(x), Type::Base(y)) => {
+                let i = left.iter().rposition(|v| v == x);
+                let j = right.iter().rposition(|v| v == y);
+                match (i, j) {
+                    (Some(i), Some(j)) => left.len() - i == right.len() - j,
+                    (None, None) => x == y,
+                    _ => false,
+                }
+            }
+            (Type::Arrow(a1, r1), Type::Arrow(a2, r2)) => {
+                go(a1, a2, left, right) && go(r1, r2, left, right)
+            }
+            (Type::Forall(x, body1), Type::Forall(y, body2)) => {
+                left.push(x);
+                right.push(y);
+                let equal = go(body1, body2, left, right);
+                left.pop();
+                right.pop();
+                equal
+            }
+            _ => false,
+        }
+    }
+    go(a, b, &mut Vec::new(), &mut Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Result<Option<Type>, String> {
+        Checker::new().check(&source.parse().unwrap())
+    }
+
+    #[test]
+    fn type_application_instantiates_a_forall() {
+        let ty = check("id : forall a. a -> a = /\\a. L (x : a). x; id [Nat -> Nat]").unwrap().unwrap();
+        assert_eq!(ty.to_string(), "(Nat -> Nat) -> Nat -> Nat");
+        let ty = check("/\\a. L (x : a). x").unwrap().unwrap();
+        let expected = Type::Forall("b".to_string(), Box::new(Type::Arrow(
+            Box::new(Type::Base("b".to_string())),
+            Box::new(Type::Base("b".to_string())),
+        )));
+        assert!(alpha_eq(&ty, &expected), "{}", ty);
+    }
+
+    #[test]
+    fn unannotated_and_ill_typed_terms_are_rejected() {
+        assert!(check("L x. x").is_err());
+        assert!(check("L (x : Nat). x x").is_err());
+        assert!(check("id : forall a. a -> a = L (x : Nat). x").is_err());
+        assert!(check("(L (x : Nat). x) [Nat]").is_err());
+    }
+}
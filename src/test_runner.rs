@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::bytecode;
+use crate::diff;
+use crate::interpreter::{AssertLog, Config, Decoded, Interpreter, Output};
+use crate::messages::{self, Msg};
+use crate::parser::Parser;
+use crate::style::{self, Stream};
+
+const EXPECT_PREFIX: &str = "#expect:";
+
+/// 각 파일의 `assert e1 == e2`, 최상위 `assert_*` 정의와 `#expect:` 주석을 테스트로 실행하고,
+/// 모두 통과했는지 돌려준다.
+pub fn run_tests(files: &[String], config: Config) -> bool {
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for file in files {
+        for (name, outcome) in run_file(file, config.clone()) {
+            match outcome {
+                Ok(()) => {
+                    println!("test {}::{} ... {}", file, name, style::result(Stream::Stdout, "ok"));
+                    passed += 1;
+                }
+                Err(message) => {
+                    println!("test {}::{} ... {}", file, name, style::error(Stream::Stdout, "FAILED"));
+                    for line in message.lines() {
+                        println!("    {}", line);
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    let status = match failed {
+        0 => style::result(Stream::Stdout, "ok"),
+        _ => style::error(Stream::Stdout, "FAILED"),
+    };
+    println!();
+    println!("test result: {}. {} passed; {} failed", status, passed, failed);
+    failed == 0
+}
+
+fn run_file(file: &str, mut config: Config) -> Vec<(String, Result<(), String>)> {
+    let bytes = match fs::read(file) {
+        Ok(bytes) => bytes,
+        Err(e) => return vec![("<file>".to_string(), Err(format!("Failed to read: {}", e)))],
+    };
+    // `compile -o`로 만든 파일은 파싱하지 않고 바로 읽음. 주석이 없으므로 `#expect:`도 없음
+    let (code, parsed) = match bytecode::read(&bytes) {
+        Some(program) => (String::new(), program),
+        None => match String::from_utf8(bytes) {
+            Ok(code) => {
+                let parsed = Parser::new(&code).spans(true).parse();
+                (code, parsed)
+            }
+            Err(_) => return vec![("<file>".to_string(), Err("Failed to read: not valid UTF-8".to_string()))],
+        },
+    };
+
+    // `#expect:` 줄은 파서에게는 주석이므로 기대 출력만 모아 둠
+    let mut expected = None::<String>;
+    for text in code.lines().filter_map(|line| line.trim_start().strip_prefix(EXPECT_PREFIX)) {
+        let expected = expected.get_or_insert_with(String::new);
+        expected.push_str(text.strip_prefix(' ').unwrap_or(text));
+        expected.push('\n');
+    }
+
+    let ast = match parsed {
+        Ok(ast) => ast,
+        Err(e) => return vec![("<parse>".to_string(), Err(messages::text(Msg::ParseError, &[&e])))],
+    };
+    let asserts = assert_names(&ast);
+
+    let captured = Rc::new(RefCell::new(String::new()));
+    let log = AssertLog::default();
+    config.output = Output::Capture(captured.clone());
+    config.assertions = Some(log.clone());
+    let mut interpreter = Interpreter::new(config);
+    let result = interpreter.eval(ast);
+
+    let mut outcomes = log.take();
+    if let Err(e) = &result {
+        outcomes.push(("<eval>".to_string(), Err(messages::text(Msg::Error, &[&e]))));
+    }
+    for name in asserts {
+        let outcome = match interpreter.lookup(&name) {
+            Some(value) => match interpreter.decode(value) {
+                Some(Decoded::Bool(true)) => Ok(()),
+                _ => Err(format!("expected true, got {}", interpreter.display_value(value))),
+            },
+            None => Err("not evaluated".to_string()),
+        };
+        outcomes.push((name, outcome));
+    }
+    if let Some(expected) = expected {
+        let outcome = match &result {
+            Ok(value) => {
+                let mut actual = captured.borrow().clone();
+                actual.push_str(&interpreter.format_result(value));
+                actual.push('\n');
+                if actual == expected {
+                    Ok(())
+                } else {
+                    Err(diff::line_diff(&expected, &actual))
+                }
+            }
+            Err(_) => Err("evaluation failed".to_string()),
+        };
+        outcomes.push((EXPECT_PREFIX.trim_end_matches(':').to_string(), outcome));
+    }
+    outcomes
+}
+
+fn assert_names(ast: &Expr) -> Vec<String> {
+    match ast {
+        Expr::Define(name, _, _) if name.starts_with("assert_") => vec![name.clone()],
+        Expr::Sequence(exprs) => exprs.iter().flat_map(assert_names).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn source_file(name: &str, code: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lintre-{}-{}.lint", name, std::process::id()));
+        fs::write(&path, code).unwrap();
+        path
+    }
+
+    #[test]
+    fn expect_names_the_first_definition_of_a_shared_value() {
+        let path = source_file("expect-name", "true = L a b. a;\nassert_t = true;\ntrue\n#expect: true\n");
+        // 해시 순서에 따라 `assert_t`가 나오던 실패는 몇 번에 한 번씩만 드러났으므로 여러 번 돌림
+        for _ in 0..20 {
+            let outcomes = run_file(path.to_str().unwrap(), Config::default());
+            assert!(outcomes.iter().all(|(_, outcome)| outcome.is_ok()), "{:?}", outcomes);
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
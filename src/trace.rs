@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::explore::{core, sugar};
+use crate::interrupt;
+use crate::messages::{self, Msg};
+use crate::normalize;
+use crate::sandbox::Sandbox;
+
+const MAX_STEPS: usize = 1_000;
+// 정규형이 없는 항은 줄일수록 커지므로 단계 수보다 먼저 이 크기에 걸릴 수 있음. 깊이는 `normalize::MAX_DEPTH`까지
+const MAX_TERM_SIZE: usize = 100_000;
+// 정규형에 붙일 이름을 찾을 때 정의 하나를 줄이는 β-축약 수
+const NAME_STEPS: usize = 10_000;
+// `--explain`에서 람다와 정의를 문장에 넣을 때 보여 줄 노드 수
+const EXPLAIN_NODES: usize = 6;
+
+/// `--trace-format`: 축약 과정을 어떤 모양으로 내보낼지
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// 번호를 붙인 단계마다 규칙과 코드 블록 하나. 강의 노트에 바로 붙일 수 있음
+    Markdown,
+    /// Mermaid 순서도. 항마다 노드, 규칙은 화살표 이름. 정규형은 둥근 노드로
+    Mermaid,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<TraceFormat> {
+        match name {
+            "markdown" => Some(TraceFormat::Markdown),
+            "mermaid" => Some(TraceFormat::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+/// 한 단계에서 쓴 규칙
+pub enum Rule {
+    /// 정의 이름을 그 본문으로 바꿈
+    Delta { name: String, body: Expr },
+    /// `(L x. body) arg`에서 본문의 `x` 자리에 `arg`를 대입함. `uses`는 본문에 `x`가 나온 횟수
+    Beta { function: Expr, param: String, arg: Expr, uses: usize },
+}
+
+/// 정규 순서로 줄여 가며 거친 항들. 첫 항은 규칙이 없고, 나머지는 그 항을 만든 규칙이 붙음
+pub struct Derivation {
+    pub steps: Vec<(Option<Rule>, Expr)>,
+    // 정해진 단계 안에 정규형에 닿았는지
+    pub complete: bool,
+    // 정규형과 α-동치인 정의 이름
+    pub name: Option<String>,
+}
+
+/// 프로그램의 마지막 식을 정규 순서(가장 왼쪽 바깥의 축약 자리부터)로 한 단계씩 줄인다.
+/// 정의 이름은 그 자리가 가장 왼쪽 바깥이 될 때 본문으로 펼침(δ)
+pub fn derivation(program: &Expr) -> Result<Derivation, Error> {
+    derivation_within(program, None)
+}
+
+/// `derivation`과 같지만 `sandbox`가 있으면 단계마다 그 제한(단계 수, 시간, 항 크기, 깊이)도 확인함
+pub fn derivation_within(program: &Expr, sandbox: Option<&Sandbox>) -> Result<Derivation, Error> {
+    let statements = match program {
+        Expr::Sequence(exprs) => exprs.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let mut defined = HashMap::new();
+    // 정의마다 앞의 정의를 모두 대입한 본문. 정규형의 이름을 찾을 때 씀
+    let mut unfolded = Vec::new();
+    let mut term = None;
+    for statement in statements {
+        match statement.unlocated() {
+            Expr::Define(name, _, body) => {
+                defined.insert(name.clone(), core(body)?);
+                let known = unfolded.iter().cloned().collect::<HashMap<_, _>>();
+                unfolded.push((name.clone(), normalize::substitute(body, &known)));
+            }
+            Expr::Assert(..) => {}
+            other => term = Some(core(other)?),
+        }
+    }
+    let mut term = term.ok_or("No expression to trace.")?;
+
+    let max_depth = sandbox.map_or(normalize::MAX_DEPTH, |s| s.limits().depth.min(normalize::MAX_DEPTH));
+    let max_size = sandbox.map_or(MAX_TERM_SIZE, |s| s.limits().term_size.min(MAX_TERM_SIZE));
+    let mut steps = vec![(None, shown(&term))];
+    let mut complete = false;
+    while steps.len() <= MAX_STEPS {
+        interrupt::check(steps.len(), || shown(&term).to_string())?;
+        let Some((next, rule)) = step(&term, &defined, &mut Vec::new())? else {
+            complete = true;
+            break;
+        };
+        term = next;
+        if let Some(sandbox) = sandbox {
+            sandbox.step(|| term.size())?;
+        }
+        // 항을 돌며 보여 줄 모양을 만드는 함수들도 모두 재귀이므로 보여 주기 전에 확인함
+        if depth(&term) > max_depth {
+            return Err(Error::step_limit(format!("Trace did not finish within {} levels of nesting.", max_depth)));
+        }
+        if term.size() > max_size {
+            return Err(Error::step_limit(format!("Trace did not finish within {} nodes of term size.", max_size)));
+        }
+        steps.push((Some(rule), shown(&term)));
+    }
+    let name = match complete {
+        true => unfolded.iter().rev()
+            .find(|(_, body)| normalize::normalize_within(body, NAME_STEPS).and_then(|normal| Ok(core(&normal)?))
+                .is_ok_and(|normal| normalize::alpha_eq(&normal, &term)))
+            .map(|(name, _)| name.clone()),
+        false => None,
+    };
+    Ok(Derivation { steps, complete, name })
+}
+
+/// 가장 왼쪽 바깥의 축약 자리 하나를 줄인다. 정규형이면 `None`. `bound`는 지금 자리를 묶는 매개변수
+fn step(term: &Expr, defined: &HashMap<String, Expr>, bound: &mut Vec<String>) -> Result<Option<(Expr, Rule)>, String> {
+    // 적용은 두 항씩 왼쪽으로 겹쳐 있으므로 머리까지는 되풀이로 내려감.
+    // 재귀로 내려가면 줄일 때마다 적용이 늘어나는 항에서 스택이 넘침
+    let mut head = term;
+    let mut args = Vec::new();
+    while let Expr::Words(pair) = head {
+        let [func, arg] = pair.as_slice() else {
+            return Err(messages::text(Msg::EmptyWords, &[]));
+        };
+        args.push(arg);
+        head = func;
+    }
+    args.reverse();
+    let Some((first, rest)) = args.split_first() else {
+        return Ok(match term {
+            Expr::Word(name) if !bound.contains(name) => defined.get(name)
+                .map(|body| (body.clone(), Rule::Delta { name: name.clone(), body: shown(body) })),
+            Expr::Function(params, body) => {
+                bound.push(params[0].clone());
+                let reduced = step(body, defined, bound)?;
+                bound.pop();
+                reduced.map(|(body, rule)| (Expr::Function(params.clone(), Box::new(body)), rule))
+            }
+            _ => None,
+        });
+    };
+    let cloned = |args: &[&Expr]| args.iter().map(|arg| (*arg).clone()).collect::<Vec<_>>();
+    if let Expr::Function(params, body) = head {
+        let mapping = HashMap::from([(params[0].clone(), (*first).clone())]);
+        let rule = Rule::Beta {
+            function: shown(head),
+            param: params[0].clone(),
+            arg: shown(first),
+            uses: uses(body, &params[0]),
+        };
+        // 대입하면서 붙은 괄호를 다시 떼어 냄
+        let reduced = core(&normalize::substitute(body, &mapping))?;
+        return Ok(Some((applied(reduced, cloned(rest)), rule)));
+    }
+    if let Some((head, rule)) = step(head, defined, bound)? {
+        return Ok(Some((applied(head, cloned(&args)), rule)));
+    }
+    for (i, arg) in args.iter().enumerate() {
+        if let Some((arg, rule)) = step(arg, defined, bound)? {
+            let mut args = cloned(&args);
+            args[i] = arg;
+            return Ok(Some((applied(head.clone(), args), rule)));
+        }
+    }
+    Ok(None)
+}
+
+/// `head`에 인자를 하나씩 적용한 `core` 모양의 항
+fn applied(head: Expr, args: Vec<Expr>) -> Expr {
+    args.into_iter().fold(head, |func, arg| Expr::Words(vec![func, arg]))
+}
+
+/// `core`로 펼친 항이 겹친 깊이. 적용의 머리 쪽으로 내려가는 것도 한 단계로 셈
+fn depth(term: &Expr) -> usize {
+    match term {
+        Expr::Function(_, body) => 1 + depth(body),
+        Expr::Words(pair) => 1 + pair.iter().map(depth).max().unwrap_or(0),
+        _ => 1,
+    }
+}
+
+/// `name`이 묶이지 않고 나오는 횟수
+fn uses(term: &Expr, name: &str) -> usize {
+    match term {
+        Expr::Word(w) => usize::from(w == name),
+        Expr::Function(params, _) if params[0] == name => 0,
+        Expr::Function(_, body) => uses(body, name),
+        Expr::Words(pair) => pair.iter().map(|e| uses(e, name)).sum(),
+        _ => 0,
+    }
+}
+
+/// 보여 줄 모양: 펼친 람다와 적용을 다시 모으고, 축약하면서 붙은 `x$1` 같은 이름은 되도록 원래 이름으로
+fn shown(term: &Expr) -> Expr {
+    normalize::plain_names(&sugar(term))
+}
+
+impl Rule {
+    /// 규칙 한 줄 요약. `δ: unfold `not`` 또는 `β: substitute `true` for `p``
+    pub fn summary(&self) -> String {
+        match self {
+            Rule::Delta { name, .. } => format!("δ: unfold `{}`", name),
+            Rule::Beta { param, arg, .. } => format!("β: substitute `{}` for `{}`", arg, param),
+        }
+    }
+
+    /// `--explain`: 처음 배우는 사람을 위해 축약 자리의 모양에서 만든 한 문장
+    pub fn explanation(&self) -> String {
+        match self {
+            Rule::Delta { name, body } => format!(
+                "replacing the name `{}` with its definition `{}`",
+                name,
+                body.elide(EXPLAIN_NODES),
+            ),
+            Rule::Beta { function, param, arg, uses } => {
+                let mut sentence = format!(
+                    "applying the function `{}` to the argument `{}`; substituting `{}` for `{}` in the body",
+                    function.elide(EXPLAIN_NODES),
+                    arg,
+                    arg,
+                    param,
+                );
+                match uses {
+                    0 => sentence.push_str(&format!(" (`{}` does not occur, so the argument is dropped)", param)),
+                    1 => {}
+                    n => sentence.push_str(&format!(" (`{}` occurs {} times, so the argument is copied)", param, n)),
+                }
+                sentence
+            }
+        }
+    }
+}
+
+/// `--explain`의 글 모양: 항 한 줄, 그 아래에 번호와 설명 한 줄, 다음 항 한 줄, …
+pub fn explained(derivation: &Derivation) -> String {
+    let mut out = String::new();
+    for (i, (rule, term)) in derivation.steps.iter().enumerate() {
+        if let Some(rule) = rule {
+            out.push_str(&format!("{}. {}\n", i, rule.explanation()));
+        }
+        out.push_str(&format!("   {}\n", term));
+    }
+    out.push_str(&conclusion(derivation));
+    out
+}
+
+/// Mermaid `flowchart`. Markdown의 ```` ```mermaid ```` 블록에 그대로 넣을 수 있음
+pub fn mermaid(derivation: &Derivation, explain: bool) -> String {
+    // 따옴표 안에서도 `"`와 `#`은 Mermaid가 해석하므로 문자 참조로 바꿈
+    let label = |text: &str| format!("\"{}\"", text.replace('#', "#35;").replace('"', "#quot;"));
+    let mut out = String::from("flowchart TD\n");
+    let last = derivation.steps.len() - 1;
+    for (i, (rule, term)) in derivation.steps.iter().enumerate() {
+        let term = match (i == last, &derivation.name) {
+            (true, Some(name)) if derivation.complete => format!("{} = {}", term, name),
+            _ => term.to_string(),
+        };
+        match i == last && derivation.complete {
+            true => out.push_str(&format!("  s{}([{}])\n", i, label(&term))),
+            false => out.push_str(&format!("  s{}[{}]\n", i, label(&term))),
+        }
+        if let Some(rule) = rule {
+            let text = match explain {
+                true => rule.explanation(),
+                false => rule.summary(),
+            };
+            out.push_str(&format!("  s{} -->|{}| s{}\n", i - 1, label(&text.replace('`', "")), i));
+        }
+    }
+    if !derivation.complete {
+        out.push_str(&format!("  s{} -.-> more[{}]\n", last, label(&format!("… stopped after {} steps", last))));
+    }
+    out
+}
+
+fn conclusion(derivation: &Derivation) -> String {
+    let steps = derivation.steps.len() - 1;
+    match (derivation.complete, &derivation.name) {
+        (true, Some(name)) => format!("Normal form after {} steps, which is `{}`.\n", steps, name),
+        (true, None) => format!("Normal form after {} steps.\n", steps),
+        (false, _) => format!("Stopped after {} steps without reaching a normal form.\n", steps),
+    }
+}
+
+/// 0부터 번호를 붙인 목록. 단계마다 규칙과 그 결과 항을 코드 블록으로. `explain`이면 규칙 대신 설명 문장
+pub fn markdown(derivation: &Derivation, explain: bool) -> String {
+    let Some((_, start)) = derivation.steps.first() else {
+        return String::new();
+    };
+    let mut out = format!("### Reduction of `{}`\n\n", start);
+    for (i, (rule, term)) in derivation.steps.iter().enumerate() {
+        let rule = match rule {
+            Some(rule) if explain => rule.explanation(),
+            Some(rule) => rule.summary(),
+            None => "start".to_string(),
+        };
+        out.push_str(&format!("{}. {}\n   ```\n   {}\n   ```\n", i, rule, term));
+    }
+    out.push('\n');
+    out.push_str(&conclusion(derivation));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::parser::Parser;
+
+    fn traced(source: &str) -> Result<Derivation, Error> {
+        derivation(&Parser::new(source).parse().unwrap())
+    }
+
+    #[test]
+    fn reaches_the_normal_form_and_names_it() {
+        let found = traced("id = L x. x; k = L x y. x; k id id").unwrap();
+        assert!(found.complete);
+        assert_eq!(found.name.as_deref(), Some("id"));
+    }
+
+    #[test]
+    fn growing_terms_stop_before_the_stack_overflows() {
+        let e = traced("d = L x. x x x x; d (L y. y y y y)").err().unwrap();
+        assert_eq!(e.kind, ErrorKind::StepLimit, "{}", e);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::ast::Expr;
+
+/// 식을 읽기만 하며 내려가는 순회. 필요한 메서드만 덮어쓰고, 덮어쓰지 않은 메서드는 자식 식을 모두 방문함
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk(self, expr)
+    }
+
+    fn visit_word(&mut self, _word: &str) {}
+
+    fn visit_function(&mut self, _params: &[String], body: &Expr) {
+        self.visit_expr(body)
+    }
+}
+
+/// `Visitor::visit_expr`의 기본 동작: 낱말과 람다는 그 메서드로 넘기고, 나머지는 자식 식마다 `visit_expr`
+pub fn walk<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Word(w) => visitor.visit_word(w),
+        Expr::Function(params, body) => visitor.visit_function(params, body),
+        Expr::Words(exprs) | Expr::Sequence(exprs) => {
+            for e in exprs {
+                visitor.visit_expr(e);
+            }
+        }
+        Expr::Paren(inner)
+        | Expr::Define(_, _, inner)
+        | Expr::Annotated(_, inner)
+        | Expr::TypeFunction(_, inner)
+        | Expr::TypeApply(inner, _)
+        | Expr::Located(_, inner) => visitor.visit_expr(inner),
+        Expr::Assert(lhs, rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+    }
+}
+
+/// 식에서 새 식을 만드는 순회. 덮어쓰지 않은 메서드는 자식 식을 바꾼 뒤 같은 모양으로 다시 조립함.
+/// 실패할 수 있는 변환은 `Error`로 오류를 돌려주고, 실패하지 않는 변환은 `Infallible`로 둠
+pub trait Folder {
+    type Error;
+
+    fn fold_expr(&mut self, expr: &Expr) -> Result<Expr, Self::Error> {
+        fold_children(self, expr)
+    }
+
+    fn fold_word(&mut self, word: &str) -> Result<Expr, Self::Error> {
+        Ok(Expr::Word(word.to_string()))
+    }
+
+    fn fold_words(&mut self, words: &[Expr]) -> Result<Expr, Self::Error> {
+        let words = words.iter()
+            .map(|e| self.fold_expr(e))
+            .collect::<Result<_, _>>()?;
+        Ok(Expr::Words(words))
+    }
+
+    fn fold_function(&mut self, params: &[String], body: &Expr) -> Result<Expr, Self::Error> {
+        Ok(Expr::Function(params.to_vec(), Box::new(self.fold_expr(body)?)))
+    }
+}
+
+/// `Folder::fold_expr`의 기본 동작: 낱말, 적용, 람다는 그 메서드로 넘기고, 나머지는 자식 식마다 `fold_expr`
+pub fn fold_children<F: Folder + ?Sized>(folder: &mut F, expr: &Expr) -> Result<Expr, F::Error> {
+    let mut fold = |e: &Expr| folder.fold_expr(e).map(Box::new);
+    Ok(match expr {
+        Expr::Word(w) => return folder.fold_word(w),
+        Expr::Words(words) => return folder.fold_words(words),
+        Expr::Function(params, body) => return folder.fold_function(params, body),
+        Expr::Sequence(exprs) => Expr::Sequence(exprs.iter()
+            .map(|e| folder.fold_expr(e))
+            .collect::<Result<_, _>>()?),
+        Expr::Paren(inner) => Expr::Paren(fold(inner)?),
+        Expr::Located(span, inner) => Expr::Located(*span, fold(inner)?),
+        Expr::Define(name, ty, body) => Expr::Define(name.clone(), ty.clone(), fold(body)?),
+        Expr::Annotated(types, inner) => Expr::Annotated(types.clone(), fold(inner)?),
+        Expr::TypeFunction(vars, inner) => Expr::TypeFunction(vars.clone(), fold(inner)?),
+        Expr::TypeApply(inner, ty) => Expr::TypeApply(fold(inner)?, ty.clone()),
+        Expr::Assert(lhs, rhs) => Expr::Assert(fold(lhs)?, fold(rhs)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    struct Words(Vec<String>);
+
+    impl Visitor for Words {
+        fn visit_word(&mut self, word: &str) {
+            self.0.push(word.to_string());
+        }
+    }
+
+    /// 이름마다 뒤에 `_`를 붙이고, `bad`를 만나면 실패함
+    struct Suffix;
+
+    impl Folder for Suffix {
+        type Error = String;
+
+        fn fold_word(&mut self, word: &str) -> Result<Expr, String> {
+            match word {
+                "bad" => Err(word.to_string()),
+                _ => Ok(Expr::Word(format!("{}_", word))),
+            }
+        }
+    }
+
+    #[test]
+    fn visitor_reaches_every_word() {
+        let program = Parser::new("id = L x. x; assert id a == (b c); id [Nat] d").parse().unwrap();
+        let mut words = Words(Vec::new());
+        words.visit_expr(&program);
+        assert_eq!(words.0, ["x", "id", "a", "b", "c", "id", "d"]);
+    }
+
+    #[test]
+    fn folder_rebuilds_the_same_shape() {
+        let program = Parser::new("id = L x. x; id (y z)").parse().unwrap();
+        let folded = Suffix.fold_expr(&program).unwrap();
+        assert_eq!(folded, Parser::new("id = L x. x_; id_ (y_ z_)").parse().unwrap());
+        assert_eq!(Suffix.fold_expr(&Parser::new("f (g bad)").parse().unwrap()), Err("bad".to_string()));
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::ast::Expr;
+use crate::incremental::Document;
+use crate::interpreter::{Config, Interpreter};
+use crate::messages::{self, Msg};
+use crate::parser::{Context, Parser};
+use crate::style::{self, Stream};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// 파일이 바뀔 때마다 다시 파싱하고 평가해서 결과와 최상위 정의의 변화를 출력
+pub fn watch(files: &[String], config: Config) -> ! {
+    let mut last_modified = Vec::new();
+    let mut last_defines: Option<BTreeMap<String, Expr>> = None;
+    // 저장할 때마다 바뀐 문장만 다시 읽도록 파일마다 지난 파싱 결과를 둠
+    let mut documents = BTreeMap::new();
+
+    loop {
+        let modified = files.iter()
+            .map(|f| fs::metadata(f).and_then(|m| m.modified()).ok())
+            .collect::<Vec<Option<SystemTime>>>();
+        if modified == last_modified {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        last_modified = modified;
+
+        println!("--- {} ---", files.join(" "));
+        match evaluate(files, config.clone(), &mut documents) {
+            Ok((result, defines)) => {
+                if let Some(previous) = &last_defines {
+                    print_diff(previous, &defines);
+                }
+                println!("{}", style::result(Stream::Stdout, &result));
+                last_defines = Some(defines);
+            }
+            Err(e) => eprintln!("{}", style::error(Stream::Stderr, &e)),
+        }
+    }
+}
+
+fn evaluate(
+    files: &[String],
+    config: Config,
+    documents: &mut BTreeMap<String, Document>,
+) -> Result<(String, BTreeMap<String, Expr>), String> {
+    let mut interpreter = Interpreter::new(config);
+    let mut defines = BTreeMap::new();
+    let mut result = None;
+    // 앞 파일의 `data` 선언과 레코드를 뒤 파일에서 쓸 수 있도록 이어받음
+    let mut context = Context::default();
+
+    for file in files {
+        let code = fs::read_to_string(file)
+            .map_err(|e| messages::text(Msg::FailedToRead, &[file, &e]))?;
+        let document = documents.entry(file.clone()).or_insert_with(|| Document::new(String::new()));
+        document.rebase(&context);
+        document.update(&code);
+        // 편집하는 동안에는 오류가 여럿일 수 있으므로 오류가 있으면 처음부터 읽어 한꺼번에 보여 줌
+        let ast = document.program().or_else(|_| Parser::new(&code).with_context(context.clone()).parse_all()
+            .map_err(|errors| errors.iter()
+                .map(|e| messages::text(Msg::ParseErrorIn, &[file, e]))
+                .collect::<Vec<_>>()
+                .join("\n")))?;
+        context = document.context().clone();
+        collect_defines(&ast, &mut defines);
+        let value = interpreter.eval(ast)
+            .map_err(|e| messages::text(Msg::ErrorIn, &[file, &e]))?;
+        result = Some(value);
+    }
+
+    let result = result.map(|v| interpreter.format_result(&v)).unwrap_or_default();
+    Ok((result, defines))
+}
+
+fn collect_defines(expr: &Expr, defines: &mut BTreeMap<String, Expr>) {
+    match expr {
+        Expr::Define(name, _, body) => {
+            defines.insert(name.clone(), (**body).clone());
+        }
+        Expr::Sequence(exprs) => {
+            for e in exprs {
+                collect_defines(e, defines);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn print_diff(previous: &BTreeMap<String, Expr>, current: &BTreeMap<String, Expr>) {
+    for (name, body) in current {
+        match previous.get(name) {
+            None => println!("+ {}", name),
+            Some(old) if old != body => println!("~ {}", name),
+            _ => {}
+        }
+    }
+    for name in previous.keys() {
+        if !current.contains_key(name) {
+            println!("- {}", name);
+        }
+    }
+}
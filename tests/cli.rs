@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// 정규형이 없고 줄일 때마다 적용이 세 겹씩 늘어나는 프로그램
+const GROWING: &str = "d = L x. x x x x;\nd (L y. y y y y)\n";
+
+/// 테스트마다 다른 이름의 임시 소스 파일
+fn source_file(name: &str, code: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("lintre-cli-{}-{}.lint", name, std::process::id()));
+    fs::write(&path, code).unwrap();
+    path
+}
+
+fn lintre(args: &[&str], file: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lintre"))
+        .args(args)
+        .arg(file)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn markdown_trace_of_a_growing_term_stops_with_the_step_limit_code() {
+    let file = source_file("trace-markdown", GROWING);
+    let output = lintre(&["--trace-format", "markdown"], &file);
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn explained_trace_of_a_growing_term_stops_with_the_step_limit_code() {
+    let file = source_file("trace-explain", GROWING);
+    let output = lintre(&["--explain"], &file);
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn mermaid_trace_of_a_growing_term_stops_with_the_step_limit_code() {
+    let file = source_file("trace-mermaid", GROWING);
+    let output = lintre(&["--trace-format", "mermaid"], &file);
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn sigma_machine_on_a_growing_term_stops_with_the_step_limit_code() {
+    let file = source_file("sigma", GROWING);
+    let output = lintre(&["--machine", "sigma"], &file);
+    assert_eq!(output.status.code(), Some(4), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn localized_messages_keep_user_text_as_written() {
+    // 목록의 말과 같은 입력도 옮기지 않고 그대로 보여 줌
+    let file = source_file("lang-input", "readNat\n");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lintre"))
+        .args(["--lang", "ko", "--allow-io"])
+        .arg(&file)
+        .stdin(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(child.stdin.as_mut().unwrap(), b"end of input\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("readNat: 자연수가 와야 하는데 'end of input'이(가) 들어왔습니다."), "{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn localized_messages_keep_the_exit_code() {
+    let file = source_file("lang-unbound", "x = y;\ny = L a. a;\nx\n");
+    let output = lintre(&["--lang", "ko"], &file);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'y'이(가) 정의를 평가하기 전에 쓰였습니다."), "{}", stderr);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn localized_messages_cover_type_errors() {
+    let file = source_file("lang-type", "L x. x x\n");
+    let output = lintre(&["--lang", "ko", "check", "--typed", "stlc"], &file);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("의 타입 오류: 무한 타입: t0 = t0 -> t1 ('x x' 안)"), "{}", stderr);
+}
+
+/// 소스마다 종료 코드. 스크립트가 `$?`로 실패 종류를 구별하므로 바뀌면 안 됨
+fn exit_code(name: &str, code: &str, args: &[&str]) -> Option<i32> {
+    let file = source_file(name, code);
+    let output = lintre(args, &file);
+    fs::remove_file(file).unwrap();
+    output.status.code()
+}
+
+#[test]
+fn successful_runs_exit_with_zero() {
+    assert_eq!(exit_code("exit-ok", "id = L x. x;\nid id\n", &[]), Some(0));
+}
+
+#[test]
+fn failed_assertions_exit_with_one() {
+    assert_eq!(exit_code("exit-assert", "id = L x. x;\nassert id == L a b. a;\nid\n", &[]), Some(1));
+}
+
+#[test]
+fn parse_errors_exit_with_two() {
+    assert_eq!(exit_code("exit-parse", "f = L x . ;\nf\n", &[]), Some(2));
+}
+
+#[test]
+fn names_used_before_their_definition_exit_with_three() {
+    assert_eq!(exit_code("exit-unbound", "x = y;\ny = L a. a;\nx\n", &[]), Some(3));
+}
+
+#[test]
+fn loops_and_limits_exit_with_four() {
+    assert_eq!(exit_code("exit-loop", "(L x. x x) (L x. x x)\n", &[]), Some(4));
+    assert_eq!(exit_code("exit-explore", GROWING, &["explore"]), Some(4));
+}
+
+#[test]
+fn io_without_allow_io_exits_with_five() {
+    assert_eq!(exit_code("exit-io", "print \"hi\"\n", &[]), Some(5));
+}
+
+#[test]
+fn missing_files_exit_with_five() {
+    let output = lintre(&[], &PathBuf::from("/nonexistent/lintre-missing.lint"));
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn data_declarations_carry_over_to_later_files() {
+    let declaration = source_file("data-declaration", "data Maybe = Nothing | Just x;\n");
+    let usage = source_file("data-usage", "m = Just (L a. a);\ncase m of Nothing -> L z. z | Just v -> v\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_lintre")).arg("run").arg(&declaration).arg(&usage).output().unwrap();
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("λa$1 . a$1"));
+}
+
+#[test]
+fn record_shapes_carry_over_to_later_files() {
+    let literal = source_file("record-literal", "r = { a = L x. x, b = L y z. z };\n");
+    let projection = source_file("record-projection", "r.b\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_lintre")).arg("run").arg(&literal).arg(&projection).output().unwrap();
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("z$1$1 . z$1$1"));
+}
+
+#[test]
+fn expect_annotations_are_comments_for_run() {
+    let file = source_file("expect-run", "id = L x. x; # identity\nid\n#expect: id\n");
+    let output = lintre(&["run"], &file);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = lintre(&["test"], &file);
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn bad_arguments_are_not_io_failures() {
+    let run = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_lintre")).args(args).output().unwrap().status.code();
+    assert_eq!(run(&["gen", "--size", "0"]), Some(1));
+    assert_eq!(run(&["run", "/nonexistent/lintre-*.ltr"]), Some(1));
+}
+
+#[test]
+fn stdin_statements_run_before_the_input_ends() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lintre"))
+        .args(["run", "--allow-io", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    std::io::Write::write_all(&mut stdin, b"rec f = L x. g x;\nrec g = L x. x;\nprint \"early\";\n").unwrap();
+    // 입력이 아직 열려 있는 동안 앞 문장의 출력이 나와야 함
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    std::io::BufRead::read_line(&mut stdout, &mut line).unwrap();
+    assert_eq!(line, "early\n");
+    std::io::Write::write_all(&mut stdin, b"f\n").unwrap();
+    drop(stdin);
+    line.clear();
+    std::io::BufRead::read_line(&mut stdout, &mut line).unwrap();
+    assert_eq!(line, "f\n");
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+}
+
+#[test]
+fn test_runs_compiled_bytecode() {
+    let source = source_file("test-bytecode", "id = L x. x;\nassert id id == id;\nid\n");
+    let compiled = source.with_extension("lbc");
+    let status = Command::new(env!("CARGO_BIN_EXE_lintre")).arg("compile").arg("-o").arg(&compiled).arg(&source).status().unwrap();
+    assert!(status.success());
+    let output = lintre(&["test"], &compiled);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("assert id id == id ... ok"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn plugin_builtins_can_be_called() {
+    let dir = std::env::temp_dir().join(format!("lintre-cli-plugin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let library = dir.join("libinc.so");
+    fs::write(dir.join("inc.c"), r#"
+#include <stddef.h>
+#include <stdint.h>
+typedef struct { uint32_t kind; uint64_t nat; const char *text; } value;
+typedef int (*call)(const value *, value *);
+static int inc(const value *args, value *result) {
+    if (args[0].kind != 0) { result->text = "expected a number"; return 1; }
+    result->kind = 0;
+    result->nat = args[0].nat + 1;
+    return 0;
+}
+void lintre_register(void *host, void (*define)(void *, const char *, size_t, call)) {
+    define(host, "inc", 1, inc);
+}
+"#).unwrap();
+    let status = Command::new("cc").args(["-shared", "-fPIC", "-o"]).arg(&library).arg(dir.join("inc.c")).status().unwrap();
+    assert!(status.success());
+
+    let file = source_file("plugin", "two = L f x. f (f x);\nassert inc two == (L f x. f (f (f x)));\nassert inc (L f x. f x) == two;\n");
+    let output = lintre(&["--plugin", library.to_str().unwrap()], &file);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let file = source_file("plugin-error", "inc (L x. x x)\n");
+    let output = lintre(&["--plugin", library.to_str().unwrap()], &file);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("inc: expected a number"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_plugins_are_reported() {
+    let file = source_file("plugin-missing", "L x. x\n");
+    let output = lintre(&["--plugin", "/nonexistent/libnothing.so"], &file);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("/nonexistent/libnothing.so"));
+}